@@ -2,19 +2,16 @@ use crate::yaml::Yaml;
 use std::fmt;
 
 #[allow(dead_code)]
-#[derive(Copy, Clone, Eq, PartialEq)]
+// Named after GitHub runner labels
+#[allow(clippy::enum_variant_names)]
+#[derive(Copy, Clone, Eq, PartialEq, Default)]
 pub enum Env {
     WindowsLatest,
+    #[default]
     UbuntuLatest,
     MacosLatest,
 }
 
-impl Default for Env {
-    fn default() -> Self {
-        Env::UbuntuLatest
-    }
-}
-
 impl fmt::Display for Env {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -46,9 +43,9 @@ impl Step {
     }
 }
 
-impl Into<Yaml> for Step {
-    fn into(self) -> Yaml {
-        self.0
+impl From<Step> for Yaml {
+    fn from(step: Step) -> Self {
+        step.0
     }
 }
 
@@ -61,16 +58,16 @@ pub struct Job {
     pub env: Vec<(String, String)>,
 }
 
-impl Into<(String, Yaml)> for Job {
-    fn into(self) -> (String, Yaml) {
+impl From<Job> for (String, Yaml) {
+    fn from(job: Job) -> Self {
         let mut entries = vec![
-            ("name", Yaml::string(self.name)),
-            ("runs-on", Yaml::string(format!("{}", self.runs_on))),
+            ("name", Yaml::string(job.name)),
+            ("runs-on", Yaml::string(format!("{}", job.runs_on))),
         ];
-        if !self.env.is_empty() {
-            entries.push(("env", Yaml::map(self.env)));
+        if !job.env.is_empty() {
+            entries.push(("env", Yaml::map(job.env)));
         }
-        entries.push(("steps", Yaml::list(self.steps)));
-        (self.id, Yaml::map(entries))
+        entries.push(("steps", Yaml::list(job.steps)));
+        (job.id, Yaml::map(entries))
     }
 }
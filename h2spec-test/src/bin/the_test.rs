@@ -13,7 +13,7 @@ fn main() {
     let _server = server.build().expect("server.build()");
 
     let mut h2spec = process::Command::new("h2spec")
-        .args(&["-p", "8888", "-v"])
+        .args(["-p", "8888", "-v"])
         .args(env::args().skip(1))
         .stdin(process::Stdio::null())
         .stdout(process::Stdio::inherit())
@@ -91,7 +91,7 @@ fn inf_impl(client: Client, still_alive: Arc<AtomicBool>, path: &str, size: usiz
 
         let mut resp = resp.filter_data();
 
-        let exp = vec![17; size as usize];
+        let exp = vec![17; size];
 
         let iterations = 1_000_000_000 / size;
 
@@ -99,7 +99,7 @@ fn inf_impl(client: Client, still_alive: Arc<AtomicBool>, path: &str, size: usiz
             still_alive.store(true, Ordering::SeqCst);
 
             let mut v = Vec::new();
-            while v.len() < size as usize {
+            while v.len() < size {
                 still_alive.store(true, Ordering::SeqCst);
                 v.extend(rt.block_on(resp.next()).unwrap().unwrap());
             }
@@ -123,7 +123,7 @@ fn pkcs12_to_pem(pkcs12: &[u8], passin: &str) -> Vec<u8> {
     let command = t!(Command::new("openssl")
         .arg("pkcs12")
         .arg("-passin")
-        .arg(&format!("pass:{}", passin))
+        .arg(format!("pass:{}", passin))
         .arg("-nodes")
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
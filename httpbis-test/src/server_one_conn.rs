@@ -8,7 +8,6 @@ use futures::future;
 use futures::future::FutureExt;
 use futures::future::TryFutureExt;
 
-use httpbis;
 use httpbis::for_test::*;
 use httpbis::*;
 
@@ -89,7 +88,7 @@ impl ServerOneConn {
                         service,
                     );
                     *conn_for_thread.lock().unwrap() = Some(conn);
-                    future
+                    future.map_err(httpbis::Error::from)
                 });
 
                 let shutdown_rx = shutdown_rx.then(|_| future::ready(()));
@@ -132,7 +131,7 @@ impl ServerOneConn {
 
 impl Drop for ServerOneConn {
     fn drop(&mut self) {
-        drop(self.shutdown_tx.take().unwrap().send(()));
+        let _ = self.shutdown_tx.take().unwrap().send(());
         self.join_handle.take().unwrap().join().ok();
     }
 }
@@ -4,7 +4,6 @@ use std::sync::Arc;
 
 use bytes::Bytes;
 
-use httpbis;
 use httpbis::Headers;
 use httpbis::Server;
 use httpbis::ServerBuilder;
@@ -66,6 +65,12 @@ impl ServerHandler for Echo {
     }
 }
 
+impl Default for ServerTest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ServerTest {
     pub fn new() -> ServerTest {
         let mut server = ServerBuilder::new_plain();
@@ -74,10 +79,7 @@ impl ServerTest {
         server.service.set_service("/echo", Arc::new(Echo {}));
         let server = server.build().expect("server");
         let port = server.local_addr().port().unwrap();
-        ServerTest {
-            server: server,
-            port: port,
-        }
+        ServerTest { server, port }
     }
 
     #[cfg(unix)]
@@ -88,9 +90,6 @@ impl ServerTest {
         server.service.set_service("/blocks", Arc::new(Blocks {}));
         server.service.set_service("/echo", Arc::new(Echo {}));
         let server = server.build().expect("server");
-        ServerTest {
-            server: server,
-            port: 0,
-        }
+        ServerTest { server, port: 0 }
     }
 }
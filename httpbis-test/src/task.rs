@@ -15,6 +15,12 @@ fn new_raw_waker() -> RawWaker {
     RawWaker::new(ptr::null(), &VTABLE)
 }
 
+impl Default for NopRuntime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl NopRuntime {
     pub fn new() -> NopRuntime {
         NopRuntime {
@@ -13,20 +13,24 @@ use bytes::Bytes;
 
 use httpbis::for_test;
 use httpbis::for_test::hpack;
-use httpbis::for_test::solicit::frame::ContinuationFlag;
-use httpbis::for_test::solicit::frame::ContinuationFrame;
-use httpbis::for_test::solicit::frame::DataFlag;
-use httpbis::for_test::solicit::frame::DataFrame;
-use httpbis::for_test::solicit::frame::FrameIR;
-use httpbis::for_test::solicit::frame::GoawayFrame;
-use httpbis::for_test::solicit::frame::HeadersFlag;
-use httpbis::for_test::solicit::frame::HeadersFrame;
-use httpbis::for_test::solicit::frame::HttpFrame;
-use httpbis::for_test::solicit::frame::RawFrame;
-use httpbis::for_test::solicit::frame::RstStreamFrame;
-use httpbis::for_test::solicit::frame::SettingsFrame;
-use httpbis::for_test::solicit::frame::WindowUpdateFrame;
 use httpbis::for_test::solicit::header::*;
+use httpbis::frame::ContinuationFlag;
+use httpbis::frame::ContinuationFrame;
+use httpbis::frame::DataFlag;
+use httpbis::frame::DataFrame;
+use httpbis::frame::Flags;
+use httpbis::frame::FrameIR;
+use httpbis::frame::GoawayFrame;
+use httpbis::frame::HeadersFlag;
+use httpbis::frame::HeadersFrame;
+use httpbis::frame::HttpFrame;
+use httpbis::frame::HttpSetting;
+use httpbis::frame::PushPromiseFlag;
+use httpbis::frame::PushPromiseFrame;
+use httpbis::frame::RawFrame;
+use httpbis::frame::RstStreamFrame;
+use httpbis::frame::SettingsFrame;
+use httpbis::frame::WindowUpdateFrame;
 use httpbis::Client;
 use httpbis::ErrorCode;
 use httpbis::SimpleHttpMessage;
@@ -34,12 +38,18 @@ use httpbis::StreamId;
 
 use super::BIND_HOST;
 use crate::bytes_ext::BytesExt;
-use httpbis::for_test::HttpSettings;
+use httpbis::for_test::Settings;
 use httpbis::for_test::WindowSize;
 use httpbis::for_test::DEFAULT_SETTINGS;
 
 pub struct HttpServerTester(net::TcpListener);
 
+impl Default for HttpServerTester {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl HttpServerTester {
     pub fn on_port(port: u16) -> HttpServerTester {
         let socket = net::TcpListener::bind((BIND_HOST, port)).expect("bind");
@@ -83,7 +93,7 @@ impl HttpServerTester {
     }
 }
 
-static PREFACE: &'static [u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+static PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
 
 pub struct HttpConnTester {
     tcp: net::TcpStream,
@@ -92,15 +102,19 @@ pub struct HttpConnTester {
     pub decoder: hpack::Decoder,
     pub encoder: hpack::Encoder,
     /// Last known peer settings
-    pub peer_settings: HttpSettings,
+    pub peer_settings: Settings,
+    /// Peer advertised `SETTINGS_ENABLE_CONNECT_PROTOCOL`
+    pub peer_enable_connect_protocol: bool,
     /// Last our settings acknowledged
-    pub our_settings_ack: HttpSettings,
+    pub our_settings_ack: Settings,
     /// Last our settings sent
-    pub our_settings_sent: Option<HttpSettings>,
+    pub our_settings_sent: Option<Settings>,
 }
 
 impl HttpConnTester {
     pub fn with_tcp(tcp: net::TcpStream) -> HttpConnTester {
+        // tests write many small frames and wait for reply
+        tcp.set_nodelay(true).expect("set_nodelay");
         HttpConnTester {
             tcp,
             encoder: hpack::Encoder::new(),
@@ -108,6 +122,7 @@ impl HttpConnTester {
             in_window_size: WindowSize::new(DEFAULT_SETTINGS.initial_window_size as i32),
             out_window_size: WindowSize::new(DEFAULT_SETTINGS.initial_window_size as i32),
             peer_settings: DEFAULT_SETTINGS,
+            peer_enable_connect_protocol: false,
             our_settings_ack: DEFAULT_SETTINGS,
             our_settings_sent: None,
         }
@@ -133,8 +148,7 @@ impl HttpConnTester {
     }
 
     pub fn recv_preface(&mut self) {
-        let mut preface = Vec::new();
-        preface.resize(PREFACE.len(), 0);
+        let mut preface = vec![0; PREFACE.len()];
         self.tcp.read_exact(&mut preface).unwrap();
         assert_eq!(PREFACE, &preface[..]);
     }
@@ -154,17 +168,37 @@ impl HttpConnTester {
         info!("EOF received");
     }
 
+    /// Skip any frames until connection is closed.
+    pub fn recv_eof_skip_frames(&mut self) {
+        while let Ok(frame) =
+            for_test::recv_raw_frame_sync(&mut self.tcp, self.our_settings_ack.max_frame_size)
+        {
+            debug!("skipping frame {}", frame.frame_type());
+        }
+        info!("EOF received");
+    }
+
     pub fn send_preface(&mut self) {
-        self.tcp.write(PREFACE).expect("send");
+        self.tcp.write_all(PREFACE).expect("send");
     }
 
     pub fn send_frame<F: FrameIR>(&mut self, frame: F) {
         info!("sending {:?}", frame);
         self.tcp
-            .write(&frame.serialize_into_vec())
+            .write_all(&frame.serialize_into_vec())
             .expect("send_frame");
     }
 
+    /// Send frames with single write.
+    pub fn send_frames<F: FrameIR>(&mut self, frames: Vec<F>) {
+        info!("sending {:?}", frames);
+        let mut buf = Vec::new();
+        for frame in frames {
+            buf.extend(frame.serialize_into_vec());
+        }
+        self.tcp.write_all(&buf).expect("send_frames");
+    }
+
     pub fn send_window_update_conn(&mut self, increment: u32) {
         self.in_window_size.try_increase(increment).unwrap();
         self.send_frame(WindowUpdateFrame::for_connection(increment));
@@ -193,6 +227,26 @@ impl HttpConnTester {
         self.send_frame(headers_frame);
     }
 
+    pub fn send_push_promise(
+        &mut self,
+        stream_id: StreamId,
+        promised_stream_id: StreamId,
+        headers: Headers,
+    ) {
+        let fragment = self
+            .encoder
+            .encode_for_test(headers.iter().map(|h| (h.name().as_bytes(), h.value())));
+        let mut flags = Flags::new(0);
+        flags.set(PushPromiseFlag::EndHeaders);
+        self.send_frame(PushPromiseFrame {
+            flags,
+            stream_id,
+            promised_stream_id,
+            header_fragment: Bytes::from(fragment),
+            padding_len: 0,
+        });
+    }
+
     pub fn send_get(&mut self, stream_id: StreamId, path: &str) {
         let mut headers = Headers::new();
         headers.add(":method", "GET");
@@ -233,7 +287,7 @@ impl HttpConnTester {
         let frame = self.fn_recv_frame_no_check_ack();
         if let HttpFrame::Settings(ref f) = frame {
             if self.our_settings_sent.is_some() && f.is_ack() {
-                self.process_peer_settings_ack(&f);
+                self.process_peer_settings_ack(f);
                 return None;
             }
         }
@@ -269,6 +323,11 @@ impl HttpConnTester {
         let settings = self.recv_frame_settings();
         assert!(!settings.is_ack());
         self.peer_settings.apply_from_frame(&settings);
+        for setting in &settings.settings {
+            if let HttpSetting::EnableConnectProtocol(enabled) = *setting {
+                self.peer_enable_connect_protocol = enabled;
+            }
+        }
         settings
     }
 
@@ -398,6 +457,24 @@ impl HttpConnTester {
         headers
     }
 
+    /// Receive `PUSH_PROMISE`, return promised stream id and request headers.
+    pub fn recv_frame_push_promise_check(&mut self, stream_id: StreamId) -> (StreamId, Headers) {
+        let frame = match self.recv_frame() {
+            HttpFrame::PushPromise(frame) => frame,
+            f => panic!("expecting PUSH_PROMISE, got: {:?}", f),
+        };
+        assert_eq!(stream_id, frame.stream_id);
+        assert!(frame.flags.is_set(PushPromiseFlag::EndHeaders));
+        let headers = self.decoder.decode(frame.header_fragment).expect("decode");
+        let headers = Headers::from_vec(
+            headers
+                .into_iter()
+                .map(|(n, v)| Header::new(n, v))
+                .collect(),
+        );
+        (frame.promised_stream_id, headers)
+    }
+
     pub fn recv_frame_data(&mut self) -> DataFrame {
         match self.recv_frame() {
             HttpFrame::Data(data) => data,
@@ -409,7 +486,7 @@ impl HttpConnTester {
         let data = self.recv_frame_data();
         assert_eq!(stream_id, data.stream_id);
         assert_eq!(end, data.is_end_of_stream());
-        (&data.data[..]).to_vec()
+        data.data[..].to_vec()
     }
 
     pub fn recv_frame_data_check_empty_end(&mut self, stream_id: StreamId) {
@@ -441,7 +518,7 @@ impl HttpConnTester {
         let frame = self.recv_frame_data();
         assert_eq!(stream_id, frame.stream_id);
 
-        let data = (&frame.data).to_vec();
+        let data = frame.data.to_vec();
 
         if frame.is_end_of_stream() {
             return data;
@@ -1,6 +1,13 @@
 //! Tests for client.
 
+use std::cmp;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
 use std::sync::mpsc;
+use std::sync::Arc;
+use std::sync::Mutex;
 use std::thread;
 use std::time::Duration;
 
@@ -9,16 +16,28 @@ use httpbis_test::*;
 use bytes::Bytes;
 
 use futures::channel::oneshot;
+use futures::stream;
 use futures::stream::StreamExt;
+use futures::stream::TryStreamExt;
 
 use futures::future;
+use futures::future::FutureExt;
 use futures::future::TryFutureExt;
 
 use httpbis::for_test::solicit::DEFAULT_SETTINGS;
 use httpbis::for_test::*;
+use httpbis::frame::ContinuationFrame;
+use httpbis::frame::HeadersFrame;
+use httpbis::frame::HttpFrame;
+use httpbis::frame::HttpSetting;
+use httpbis::frame::PingFrame;
+use httpbis::frame::PriorityFrame;
+use httpbis::frame::RawFrame;
+use httpbis::frame::SettingsFrame;
 use httpbis::ErrorCode;
 use httpbis::*;
 use std::task::Poll;
+use tokio::io::AsyncReadExt;
 use tokio::runtime::Runtime;
 
 #[test]
@@ -55,6 +74,1512 @@ fn stream_count() {
     assert_eq!(0, state.streams.len(), "{:?}", state);
 }
 
+#[test]
+fn ping() {
+    init_logger();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+
+    // unsolicited PING is acknowledged
+    server_tester.send_frame(PingFrame::with_data(0x1122));
+    match server_tester.recv_frame() {
+        HttpFrame::Ping(f) => {
+            assert!(f.is_ack());
+            assert_eq!(0x1122, f.opaque_data());
+        }
+        f => panic!("expecting PING, got: {:?}", f),
+    }
+
+    let ping = client.ping();
+
+    let opaque_data = match server_tester.recv_frame() {
+        HttpFrame::Ping(f) => {
+            assert!(!f.is_ack());
+            f.opaque_data()
+        }
+        f => panic!("expecting PING, got: {:?}", f),
+    };
+    server_tester.send_frame(PingFrame::new_ack(opaque_data));
+
+    let mut rt = Runtime::new().unwrap();
+    rt.block_on(ping).expect("ping");
+}
+
+#[test]
+fn ping_ack_mismatch() {
+    init_logger();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+
+    // ACK without outstanding PING is ignored
+    server_tester.send_frame(PingFrame::new_ack(0x1122));
+    server_tester.send_frame(PingFrame::with_data(1));
+    match server_tester.recv_frame() {
+        HttpFrame::Ping(f) => assert!(f.is_ack()),
+        f => panic!("expecting PING, got: {:?}", f),
+    }
+
+    let mut ping = client.ping();
+
+    let opaque_data = match server_tester.recv_frame() {
+        HttpFrame::Ping(f) => f.opaque_data(),
+        f => panic!("expecting PING, got: {:?}", f),
+    };
+
+    // ACK not matching outstanding PING is ignored too
+    server_tester.send_frame(PingFrame::new_ack(opaque_data + 1));
+    server_tester.send_frame(PingFrame::with_data(2));
+    match server_tester.recv_frame() {
+        HttpFrame::Ping(f) => assert!(f.is_ack()),
+        f => panic!("expecting PING, got: {:?}", f),
+    }
+    assert!((&mut ping).now_or_never().is_none());
+
+    server_tester.send_frame(PingFrame::new_ack(opaque_data));
+
+    let mut rt = Runtime::new().unwrap();
+    rt.block_on(ping).expect("ping");
+}
+
+#[test]
+fn max_concurrent_streams() {
+    init_logger();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+
+    let limit = 2;
+
+    let mut settings = SettingsFrame::new();
+    settings
+        .settings
+        .push(HttpSetting::MaxConcurrentStreams(limit));
+    server_tester.send_recv_settings(settings);
+
+    let reqs: Vec<_> = (0..limit + 5)
+        .map(|_| client.start_get("/fgfg", "localhost").collect())
+        .collect();
+
+    server_tester.recv_frame_headers_check(1, true);
+    server_tester.recv_frame_headers_check(3, true);
+    assert_eq!(limit as usize, client.conn_state().streams.len());
+
+    // each completed stream lets one queued request start
+    for i in 0..limit + 5 {
+        let stream_id = 1 + i * 2;
+        server_tester.send_headers(stream_id, Headers::ok_200(), true);
+        if i + limit < limit + 5 {
+            server_tester.recv_frame_headers_check(stream_id + limit * 2, true);
+        }
+    }
+
+    let mut rt = Runtime::new().unwrap();
+    for req in reqs {
+        assert_eq!(200, rt.block_on(req).expect("r").headers.status());
+    }
+}
+
+#[test]
+fn negotiated_settings() {
+    init_logger();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client();
+    server_tester.recv_preface();
+
+    let mut settings = SettingsFrame::new();
+    settings.settings.push(HttpSetting::MaxFrameSize(20000));
+    settings
+        .settings
+        .push(HttpSetting::MaxConcurrentStreams(10));
+    server_tester.send_settings(settings);
+    server_tester.recv_frame_settings_set();
+    server_tester.send_frame(SettingsFrame::new_ack());
+
+    let mut rt = Runtime::new().unwrap();
+    rt.block_on(client.settings_acked()).expect("acked");
+
+    assert_eq!(server_tester.peer_settings, client.local_settings());
+    assert_eq!(20000, client.peer_settings().max_frame_size);
+    assert_eq!(10, client.peer_settings().max_concurrent_streams);
+
+    server_tester.recv_frame_settings_ack();
+
+    // Updated when peer changes settings
+    let mut settings = SettingsFrame::new();
+    settings.settings.push(HttpSetting::MaxConcurrentStreams(5));
+    server_tester.send_recv_settings(settings);
+    assert_eq!(5, client.peer_settings().max_concurrent_streams);
+    assert_eq!(20000, client.peer_settings().max_frame_size);
+
+    // Resolves immediately after first ACK
+    rt.block_on(client.settings_acked()).expect("acked");
+}
+
+#[test]
+fn response_content_length_mismatch() {
+    init_logger();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let mut headers = Headers::ok_200();
+    headers.add("content-length", "3");
+
+    // Fewer bytes than advertised
+    let req = client.start_get("/short", "localhost").collect();
+    server_tester.recv_message(1);
+    server_tester.send_headers(1, headers.clone(), false);
+    server_tester.send_data(1, b"ab", true);
+    server_tester.recv_rst_frame_check(1, ErrorCode::ProtocolError);
+    assert!(rt.block_on(req).is_err());
+
+    // More bytes than advertised
+    let req = client.start_get("/long", "localhost").collect();
+    server_tester.recv_message(3);
+    server_tester.send_headers(3, headers.clone(), false);
+    server_tester.send_data(3, b"abcd", true);
+    server_tester.recv_rst_frame_check(3, ErrorCode::ProtocolError);
+    assert!(rt.block_on(req).is_err());
+
+    // Response to HEAD has no body
+    let mut head = Headers::new();
+    head.add(":method", "HEAD");
+    head.add(":path", "/");
+    head.add(":authority", "localhost");
+    head.add(":scheme", "http");
+    let req = client.start_request_end_stream(head, None, None).collect();
+    server_tester.recv_message(5);
+    server_tester.send_headers(5, headers, true);
+    assert_eq!(200, rt.block_on(req).expect("head").headers.status());
+}
+
+#[test]
+fn connected() {
+    init_logger();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client();
+
+    let mut connected = Box::pin(client.connected());
+
+    server_tester.recv_preface();
+    server_tester.send_settings(SettingsFrame::new());
+    server_tester.recv_frame_settings_set();
+    server_tester.recv_frame_settings_ack();
+
+    let mut rt = Runtime::new().unwrap();
+    // Not resolved before peer acknowledged our settings
+    assert!(rt
+        .block_on(future::poll_fn(|cx| Poll::Ready(
+            connected.as_mut().poll(cx)
+        )))
+        .is_pending());
+
+    server_tester.send_frame(SettingsFrame::new_ack());
+    rt.block_on(connected).expect("connected");
+}
+
+#[test]
+fn connected_refused() {
+    init_logger();
+
+    let port = {
+        let listener = std::net::TcpListener::bind((BIND_HOST, 0)).unwrap();
+        listener.local_addr().unwrap().port()
+    };
+
+    let client = Client::new_plain(BIND_HOST, port, ClientConf::new()).expect("client");
+
+    let mut rt = Runtime::new().unwrap();
+    assert!(rt.block_on(client.connected()).is_err());
+}
+
+#[test]
+fn connected_conn_died_before_ack() {
+    init_logger();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client();
+    server_tester.recv_preface();
+    server_tester.recv_frame_settings_set();
+    drop(server_tester);
+
+    let mut rt = Runtime::new().unwrap();
+    assert!(rt.block_on(client.connected()).is_err());
+}
+
+#[test]
+fn continuation_flood() {
+    init_logger();
+
+    let server = HttpServerTester::new();
+
+    let mut conf = ClientConf::new();
+    conf.common.max_continuation_frames = Some(4);
+    let client = Client::new_plain(BIND_HOST, server.port(), conf).expect("client");
+
+    let mut tester = server.accept_xchg();
+
+    let req = client.start_get("/fgfg", "localhost").collect();
+    tester.recv_frame_headers_check(1, true);
+
+    let fragment = tester
+        .encoder
+        .encode_for_test(vec![(&b":status"[..], &b"200"[..])]);
+    tester.send_frame(HeadersFrame::new_conv(fragment, 1));
+    // Fifth frame exceeds the limit
+    for _ in 0..5 {
+        tester.send_frame(ContinuationFrame::new(Bytes::new(), 1));
+    }
+
+    tester.recv_goaway_frame_check(ErrorCode::EnhanceYourCalm);
+
+    let mut rt = Runtime::new().unwrap();
+    assert!(rt.block_on(req).is_err());
+}
+
+/// Receive request with body, return number of CONTINUATION frames and max DATA frame size.
+fn recv_request_frame_sizes(
+    tester: &mut HttpConnTester,
+    stream_id: u32,
+    len: usize,
+) -> (u32, usize) {
+    let (headers, cont_count) = tester.recv_frame_headers_continuation();
+    assert_eq!(stream_id, headers.stream_id);
+    let mut max_len = 0;
+    let mut total = 0;
+    loop {
+        let data = tester.recv_frame_data();
+        max_len = cmp::max(max_len, data.data.len());
+        total += data.data.len();
+        if data.is_end_of_stream() {
+            break;
+        }
+    }
+    assert_eq!(len, total);
+    (cont_count, max_len)
+}
+
+#[test]
+fn frames_fit_peer_max_frame_size() {
+    init_logger();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+    assert_eq!(16384, server_tester.our_settings_ack.max_frame_size);
+    server_tester.send_recv_settings(SettingsFrame::from_settings(vec![
+        HttpSetting::InitialWindowSize(1 << 20),
+    ]));
+    server_tester.send_window_update_conn(1 << 22);
+
+    let len = 100 << 10;
+    let post = |path: &str| {
+        let mut headers = Headers::new_post(path);
+        headers.add(":authority", "localhost");
+        headers.add(":scheme", "http");
+        // Header block is larger than a frame even when Huffman encoded
+        headers.add("x-large", "0123456789".repeat(5000));
+        client
+            .start_request_end_stream(headers, Some(Bytes::from(vec![1; len])), None)
+            .collect()
+    };
+
+    let mut rt = Runtime::new().unwrap();
+
+    // Frame size is checked by tester on receive
+    let req = post("/a");
+    let (cont_count, max_len) = recv_request_frame_sizes(&mut server_tester, 1, len);
+    assert!(cont_count > 0);
+    assert_eq!(16384, max_len);
+    server_tester.send_headers(1, Headers::ok_200(), true);
+    rt.block_on(req).expect("resp");
+
+    // Frames are split by current setting
+    server_tester.send_recv_settings(SettingsFrame::from_settings(vec![
+        HttpSetting::MaxFrameSize(32768),
+    ]));
+    let req = post("/b");
+    let (_, max_len) = recv_request_frame_sizes(&mut server_tester, 3, len);
+    assert_eq!(32768, max_len);
+    server_tester.send_headers(3, Headers::ok_200(), true);
+    rt.block_on(req).expect("resp");
+}
+
+#[test]
+fn window_available() {
+    init_logger();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client();
+    server_tester.recv_preface();
+    server_tester.send_settings(SettingsFrame::from_settings(vec![
+        HttpSetting::InitialWindowSize(0),
+    ]));
+    server_tester.recv_frame_settings_set();
+    server_tester.send_frame(SettingsFrame::new_ack());
+    server_tester.recv_frame_settings_ack();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let mut headers = Headers::new_post("/");
+    headers.add(":authority", "localhost");
+    headers.add(":scheme", "http");
+    let (mut sender, _resp) = rt
+        .block_on(client.start_request(headers, None, None, false))
+        .expect("start");
+    server_tester.recv_frame_headers_check(1, false);
+
+    {
+        let mut available = Box::pin(sender.window_available());
+        assert!(rt
+            .block_on(future::poll_fn(|cx| Poll::Ready(
+                available.as_mut().poll(cx)
+            )))
+            .is_pending());
+
+        server_tester.send_window_update_stream(1, 3);
+        rt.block_on(available).expect("window");
+    }
+
+    sender
+        .send_data(Bytes::from_static(b"abc"))
+        .expect("send_data");
+    assert_eq!(b"abc", &server_tester.recv_frame_data_check(1, false)[..]);
+}
+
+fn extended_connect_headers() -> Headers {
+    let mut headers = Headers::new();
+    headers.add(":method", "CONNECT");
+    headers.add(":protocol", "websocket");
+    headers.add(":scheme", "http");
+    headers.add(":path", "/chat");
+    headers.add(":authority", "localhost");
+    headers
+}
+
+#[test]
+fn connect_protocol_enabled() {
+    init_logger();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client();
+    server_tester.recv_preface();
+
+    let mut settings = SettingsFrame::new();
+    settings
+        .settings
+        .push(HttpSetting::EnableConnectProtocol(true));
+    server_tester.send_settings(settings);
+    server_tester.recv_frame_settings_set();
+    server_tester.send_frame(SettingsFrame::new_ack());
+
+    let mut rt = Runtime::new().unwrap();
+    rt.block_on(client.connected()).expect("connected");
+    assert!(client.connect_protocol_enabled());
+
+    server_tester.recv_frame_settings_ack();
+
+    let req = client
+        .start_request_end_stream(extended_connect_headers(), None, None)
+        .collect();
+    let headers = server_tester.recv_frame_headers_check(1, true);
+    assert_eq!(Some("websocket"), headers.get_opt(":protocol"));
+    server_tester.send_headers(1, Headers::ok_200(), true);
+    assert_eq!(200, rt.block_on(req).expect("resp").headers.status());
+
+    // Setting cannot be disabled once enabled
+    let mut settings = SettingsFrame::new();
+    settings
+        .settings
+        .push(HttpSetting::EnableConnectProtocol(false));
+    server_tester.send_settings(settings);
+    server_tester.recv_goaway_frame_check(ErrorCode::ProtocolError);
+}
+
+#[test]
+fn connect_protocol_disabled() {
+    init_logger();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+    assert!(!client.connect_protocol_enabled());
+
+    let mut rt = Runtime::new().unwrap();
+
+    let req = client
+        .start_request_end_stream(extended_connect_headers(), None, None)
+        .collect();
+    match rt.block_on(req) {
+        Err(Error::ConnectProtocolDisabled) => {}
+        Err(e) => panic!("{:?}", e),
+        Ok(_) => panic!("expecting error"),
+    }
+
+    // Nothing is sent for the refused request
+    let req = client.start_get("/", "localhost").collect();
+    server_tester.recv_message(3);
+    server_tester.send_headers(3, Headers::ok_200(), true);
+    assert_eq!(200, rt.block_on(req).expect("resp").headers.status());
+}
+
+#[test]
+fn connect_protocol_waits_for_settings() {
+    init_logger();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client();
+    server_tester.recv_preface();
+    server_tester.recv_frame_settings_set();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let req = client
+        .start_request_end_stream(extended_connect_headers(), None, None)
+        .collect();
+
+    // Request is queued until server settings are received
+    let state = rt.block_on(client.dump_state()).expect("state");
+    assert!(state.streams.is_empty());
+
+    let mut settings = SettingsFrame::new();
+    settings
+        .settings
+        .push(HttpSetting::EnableConnectProtocol(true));
+    server_tester.send_settings(settings);
+    server_tester.recv_frame_settings_ack();
+
+    let headers = server_tester.recv_frame_headers_check(1, true);
+    assert_eq!(Some("websocket"), headers.get_opt(":protocol"));
+    server_tester.send_headers(1, Headers::ok_200(), true);
+    assert_eq!(200, rt.block_on(req).expect("resp").headers.status());
+}
+
+#[test]
+fn initial_settings() {
+    init_logger();
+
+    let server = HttpServerTester::new();
+
+    let mut conf = ClientConf::new();
+    conf.common.initial_settings = Some(Settings {
+        header_table_size: 0,
+        enable_push: true,
+        max_concurrent_streams: 100,
+        initial_window_size: 1 << 20,
+        max_frame_size: 1 << 20,
+        max_header_list_size: 8192,
+    });
+    let _client = Client::new_plain(BIND_HOST, server.port(), conf).expect("client");
+
+    let mut tester = server.accept();
+    tester.recv_preface();
+
+    // Push is advertised as disabled, because there is no push handler
+    #[rustfmt::skip]
+    let expected = [
+        0, 0, 36, 0x4, 0, 0, 0, 0, 0,
+        0, 1, 0, 0, 0, 0,
+        0, 2, 0, 0, 0, 0,
+        0, 3, 0, 0, 0, 100,
+        0, 4, 0, 0x10, 0, 0,
+        0, 5, 0, 0x10, 0, 0,
+        0, 6, 0, 0, 0x20, 0,
+    ];
+    assert_eq!(&expected[..], &tester.recv_raw_frame().raw_content[..]);
+}
+
+#[test]
+fn initial_settings_invalid() {
+    init_logger();
+
+    let mut settings = DEFAULT_SETTINGS;
+    settings.max_frame_size = 100;
+
+    let mut conf = ClientConf::new();
+    conf.common.initial_settings = Some(settings);
+    match Client::new_plain(BIND_HOST, 1, conf) {
+        Err(Error::InvalidSettings(..)) => {}
+        r => panic!("expecting InvalidSettings, got: {:?}", r),
+    }
+}
+
+#[test]
+fn window_update_fraction_invalid() {
+    init_logger();
+
+    for &fraction in &[0.0, 1.0, 1.5] {
+        let mut conf = ClientConf::new();
+        conf.common.window_update_fraction = Some(fraction);
+        match Client::new_plain(BIND_HOST, 1, conf) {
+            Err(Error::InvalidWindowUpdateFraction(f)) => assert_eq!(fraction, f),
+            r => panic!("expecting InvalidWindowUpdateFraction, got: {:?}", r),
+        }
+    }
+}
+
+#[test]
+fn initial_window_size_decrease_applied_to_streams() {
+    init_logger();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+
+    let _req = client
+        .start_post("/foobar", "localhost", Bytes::from(vec![1; 1000]))
+        .collect();
+
+    server_tester.recv_frame_headers_check(1, false);
+    assert_eq!(1000, server_tester.recv_frame_data_check(1, true).len());
+
+    let old_size = DEFAULT_SETTINGS.initial_window_size as i32;
+    let new_size = 100;
+
+    let state = client.conn_state();
+    assert_eq!(old_size - 1000, state.streams[&1].out_window_size);
+
+    let mut settings = SettingsFrame::new();
+    settings
+        .settings
+        .push(HttpSetting::InitialWindowSize(new_size as u32));
+    server_tester.send_recv_settings(settings);
+
+    let state = client.conn_state();
+    // stream window is decreased by exactly the delta, becoming negative
+    assert_eq!(
+        old_size - 1000 + (new_size - old_size),
+        state.streams[&1].out_window_size
+    );
+    assert_eq!(-900, state.streams[&1].out_window_size);
+    // connection window is not affected
+    assert_eq!(old_size - 1000, state.out_window_size);
+}
+
+#[test]
+fn stats() {
+    init_logger();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+
+    let mut settings = SettingsFrame::new();
+    settings.settings.push(HttpSetting::InitialWindowSize(0));
+    server_tester.send_recv_settings(settings);
+
+    let _req = client
+        .start_post("/foobar", "localhost", Bytes::from(vec![1; 1000]))
+        .collect();
+    server_tester.recv_frame_headers_check(1, false);
+
+    thread::sleep(Duration::from_millis(100));
+
+    let mut rt = Runtime::new().unwrap();
+    let stats = rt.block_on(client.stats()).expect("stats");
+    assert_eq!(1, stats.active_streams);
+    assert_eq!(1, stats.streams_opened);
+    assert_eq!(1, stats.frames_sent.headers);
+    assert_eq!(0, stats.frames_sent.data);
+    assert!(stats.frames_received.settings >= 2);
+    assert!(stats.bytes_sent > 0);
+    assert!(stats.bytes_received > 0);
+    assert_eq!(1000, stats.streams[&1].queued_out_data_size);
+    assert_eq!(0, stats.streams[&1].out_window_size);
+    assert!(
+        stats.flow_control_blocked_stream_time >= Duration::from_millis(100),
+        "{:?}",
+        stats.flow_control_blocked_stream_time
+    );
+
+    server_tester.send_rst(1, ErrorCode::Cancel);
+    server_tester.send_frame(PingFrame::with_data(1));
+    server_tester.recv_frame();
+
+    let stats = rt.block_on(client.stats()).expect("stats");
+    assert_eq!(0, stats.active_streams);
+    assert_eq!(1, stats.frames_received.rst_stream);
+}
+
+fn client_with_stats_callback(
+    server: &HttpServerTester,
+    interval: Duration,
+) -> (Client, mpsc::Receiver<ConnectionStats>) {
+    let (tx, rx) = mpsc::channel();
+    let tx = Mutex::new(tx);
+    let mut conf = ClientConf::new();
+    conf.common.stats_interval = Some(interval);
+    conf.common.on_stats = Some(Arc::new(move |stats: &ConnectionStats| {
+        // ignore error: test may be already finished
+        drop(tx.lock().unwrap().send(stats.clone()));
+    }));
+    let client = Client::new_plain(BIND_HOST, server.port(), conf).expect("client");
+    (client, rx)
+}
+
+#[test]
+fn stats_callback_periodic() {
+    init_logger();
+
+    let server = HttpServerTester::new();
+    let (client, rx) = client_with_stats_callback(&server, Duration::from_millis(10));
+    let mut server_tester = server.accept_xchg();
+
+    let mut rt = Runtime::new().unwrap();
+    let req = client.start_get("/", "localhost").collect();
+    server_tester.recv_message(1);
+    server_tester.send_headers(1, Headers::ok_200(), true);
+    assert_eq!(200, rt.block_on(req).expect("r").headers.status());
+
+    loop {
+        let stats = rx.recv_timeout(Duration::from_secs(5)).expect("stats");
+        if stats.streams_opened == 1 && stats.active_streams == 0 {
+            break;
+        }
+    }
+}
+
+#[test]
+fn stats_callback_on_close() {
+    init_logger();
+
+    let server = HttpServerTester::new();
+    let (client, rx) = client_with_stats_callback(&server, Duration::from_secs(3600));
+    let mut server_tester = server.accept_xchg();
+
+    let mut rt = Runtime::new().unwrap();
+    let req = client.start_get("/", "localhost").collect();
+    server_tester.recv_message(1);
+    server_tester.send_headers(1, Headers::ok_200(), true);
+    assert_eq!(200, rt.block_on(req).expect("r").headers.status());
+
+    // The only snapshot is reported when connection is closed
+    drop(server_tester);
+    let stats = rx.recv_timeout(Duration::from_secs(5)).expect("stats");
+    assert_eq!(1, stats.streams_opened);
+    assert_eq!(1, stats.frames_received.headers);
+}
+
+#[test]
+fn frame_observer() {
+    init_logger();
+
+    let server = HttpServerTester::new();
+
+    let observed = Arc::new(Mutex::new(Vec::new()));
+
+    let mut conf = ClientConf::new();
+    let observed_copy = observed.clone();
+    conf.common.frame_observer = Some(Arc::new(move |direction, header: &FrameHeader| {
+        observed_copy
+            .lock()
+            .unwrap()
+            .push((direction, header.frame_type, header.stream_id));
+    }));
+    let client = Client::new_plain(BIND_HOST, server.port(), conf).expect("client");
+
+    let mut server_tester = server.accept_xchg();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let req = client.start_get("/", "localhost").collect();
+    server_tester.recv_message(1);
+    server_tester.send_headers(1, Headers::ok_200(), false);
+    server_tester.send_data(1, b"ab", true);
+    assert_eq!(200, rt.block_on(req).expect("r").headers.status());
+
+    let observed = observed.lock().unwrap();
+    // SETTINGS frames from both sides
+    assert!(observed.contains(&(FrameDirection::Outbound, 0x4, 0)));
+    assert!(observed.contains(&(FrameDirection::Inbound, 0x4, 0)));
+    let stream: Vec<_> = observed.iter().filter(|(_, _, s)| *s == 1).collect();
+    assert_eq!(
+        vec![
+            &(FrameDirection::Outbound, 0x1, 1),
+            &(FrameDirection::Inbound, 0x1, 1),
+            &(FrameDirection::Inbound, 0x0, 1),
+        ],
+        stream
+    );
+}
+
+#[test]
+fn unknown_frame() {
+    init_logger();
+
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let received_copy = received.clone();
+
+    let server = HttpServerTester::new();
+    let mut conf = ClientConf::new();
+    conf.common.on_unknown_frame = Some(Arc::new(move |frame_type, stream_id, payload| {
+        received_copy
+            .lock()
+            .unwrap()
+            .push((frame_type, stream_id, payload.to_vec()));
+    }));
+    let client = Client::new_plain(BIND_HOST, server.port(), conf).expect("client");
+
+    let mut tester = server.accept();
+    tester.recv_preface();
+    tester.settings_xchg();
+
+    tester.send_frame(RawFrame::from(&[0, 0, 3, 0xbb, 0, 0, 0, 0, 0, 1, 2, 3][..]));
+    tester.send_frame(PingFrame::with_data(1));
+    match tester.recv_frame() {
+        HttpFrame::Ping(f) => assert!(f.is_ack()),
+        f => panic!("expecting PING, got: {:?}", f),
+    }
+    assert_eq!(vec![(0xbb, 0, vec![1, 2, 3])], *received.lock().unwrap());
+
+    // ORIGIN is an extension frame too
+    let mut origin = vec![0, 0, 11, 0xc, 0, 0, 0, 0, 0, 0, 9];
+    origin.extend_from_slice(b"https://a");
+    tester.send_frame(RawFrame::from(origin));
+    tester.send_frame(PingFrame::with_data(2));
+    match tester.recv_frame() {
+        HttpFrame::Ping(f) => assert!(f.is_ack()),
+        f => panic!("expecting PING, got: {:?}", f),
+    }
+    assert_eq!(
+        (0xc, 0, b"\0\x09https://a".to_vec()),
+        received.lock().unwrap()[1]
+    );
+    assert_eq!(Some(vec!["https://a".to_owned()]), client.origin_set());
+
+    client.send_raw_frame(0xbc, 0x1, 0, b"xy").expect("send");
+    assert_eq!(
+        &[0, 0, 2, 0xbc, 0x1, 0, 0, 0, 0, b'x', b'y'][..],
+        &tester.recv_raw_frame().raw_content[..]
+    );
+
+    match client.send_raw_frame(0x0, 0, 1, b"data") {
+        Err(Error::InvalidFrame(..)) => {}
+        r => panic!("expecting InvalidFrame, got: {:?}", r),
+    }
+}
+
+#[test]
+fn origin_set() {
+    init_logger();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+
+    assert_eq!(None, client.origin_set());
+
+    let origin = |stream_id| {
+        let mut raw = vec![0, 0, 11, 0xc, 0, 0, 0, 0, stream_id, 0, 9];
+        raw.extend_from_slice(b"https://a");
+        RawFrame::from(raw)
+    };
+    // Ignored on non-zero stream
+    server_tester.send_frame(origin(1));
+    server_tester.send_frame(origin(0));
+    server_tester.send_frame(PingFrame::with_data(1));
+    match server_tester.recv_frame() {
+        HttpFrame::Ping(f) => assert!(f.is_ack()),
+        f => panic!("expecting PING, got: {:?}", f),
+    }
+
+    assert_eq!(Some(vec!["https://a".to_owned()]), client.origin_set());
+}
+
+#[test]
+fn request_stream_id() {
+    init_logger();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+
+    let mut rt = Runtime::new().unwrap();
+    let (req1, _resp1) = rt
+        .block_on(client.start_post_sink("/a", "localhost"))
+        .expect("start");
+    let (req2, _resp2) = rt
+        .block_on(client.start_post_sink("/b", "localhost"))
+        .expect("start");
+    assert_eq!(1, req1.stream_id());
+    assert_eq!(3, req2.stream_id());
+
+    assert_eq!(
+        "/a",
+        server_tester
+            .recv_frame_headers_check(1, false)
+            .get(":path")
+    );
+    assert_eq!(
+        "/b",
+        server_tester
+            .recv_frame_headers_check(3, false)
+            .get(":path")
+    );
+}
+
+#[test]
+fn drop_response_mid_body_sends_cancel() {
+    init_logger();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let resp = client.start_get("/a", "localhost");
+    server_tester.recv_frame_headers_check(1, true);
+    server_tester.send_headers(1, Headers::ok_200(), false);
+    server_tester.send_data(1, b"abc", false);
+
+    let (headers, mut body) = rt.block_on(resp.0).expect("headers");
+    assert_eq!(200, headers.status());
+    assert!(rt.block_on(body.next()).is_some());
+    assert_eq!(1, client.conn_state().streams.len());
+
+    drop(body);
+
+    server_tester.recv_rst_frame_check(1, ErrorCode::Cancel);
+    assert_eq!(0, client.conn_state().streams.len());
+
+    // Response fully received, nothing is sent on drop
+    let resp = client.start_get("/b", "localhost");
+    server_tester.recv_frame_headers_check(3, true);
+    server_tester.send_headers(3, Headers::ok_200(), true);
+    drop(rt.block_on(resp.collect()).expect("resp"));
+
+    server_tester.send_frame(PingFrame::with_data(2));
+    match server_tester.recv_frame() {
+        HttpFrame::Ping(f) => assert!(f.is_ack()),
+        f => panic!("expecting PING, got: {:?}", f),
+    }
+}
+
+#[test]
+fn connection_close_peer_closed() {
+    init_logger();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let req = client.start_get("/", "localhost").collect();
+    server_tester.recv_message(1);
+    drop(server_tester);
+
+    match rt.block_on(req) {
+        Err(Error::ConnectionClosed(ConnectionClose::PeerClosed)) => {}
+        r => panic!("expecting peer closed, got: {:?}", r.map(|m| m.headers)),
+    }
+}
+
+#[test]
+fn goaway_twice() {
+    init_logger();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let req1 = client.start_get("/1", "localhost").collect();
+    server_tester.recv_message(1);
+    let req3 = client.start_get("/3", "localhost").collect();
+    server_tester.recv_message(3);
+
+    // Graceful shutdown: GOAWAY with max stream id, then with last processed
+    server_tester.send_goaway(0x7fffffff);
+    server_tester.send_goaway(1);
+
+    assert!(rt.block_on(req3).is_err());
+
+    server_tester.send_headers(1, Headers::ok_200(), true);
+    assert_eq!(200, rt.block_on(req1).expect("ok").headers.status());
+}
+
+#[test]
+fn connection_close_protocol_error() {
+    init_logger();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let req = client.start_get("/", "localhost").collect();
+    server_tester.recv_message(1);
+    server_tester.send_goaway(1);
+    // Last stream id must not increase
+    server_tester.send_goaway(3);
+
+    match rt.block_on(req) {
+        Err(Error::ConnectionClosed(ConnectionClose::ProtocolError(e))) => {
+            assert!(matches!(*e, Error::GoawayAfterGoaway), "{:?}", e);
+        }
+        r => panic!("expecting protocol error, got: {:?}", r.map(|m| m.headers)),
+    }
+}
+
+#[test]
+fn rst_stream_refused_is_retryable() {
+    init_logger();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let req = client.start_get("/", "localhost").collect();
+    server_tester.recv_message(1);
+    server_tester.send_rst(1, ErrorCode::RefusedStream);
+
+    match rt.block_on(req) {
+        Err(Error::RstStreamReceived(error_code)) => {
+            assert_eq!(ErrorCode::RefusedStream, error_code);
+            assert!(error_code.is_retryable());
+        }
+        r => panic!("expecting RST_STREAM, got: {:?}", r.map(|m| m.headers)),
+    }
+
+    let req = client.start_get("/", "localhost").collect();
+    server_tester.recv_message(3);
+    server_tester.send_rst(3, ErrorCode::Cancel);
+
+    match rt.block_on(req) {
+        Err(Error::RstStreamReceived(error_code)) => assert!(!error_code.is_retryable()),
+        r => panic!("expecting RST_STREAM, got: {:?}", r.map(|m| m.headers)),
+    }
+}
+
+#[test]
+fn scoped_request_stream_error() {
+    init_logger();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let req = client.start_get("/", "localhost").collect_scoped();
+    server_tester.recv_message(1);
+    server_tester.send_rst(1, ErrorCode::RefusedStream);
+
+    match rt.block_on(req) {
+        Err(StreamError::Stream {
+            error_code: Some(ErrorCode::RefusedStream),
+            error: Error::RstStreamReceived(ErrorCode::RefusedStream),
+        }) => {}
+        r => panic!("expecting stream error, got: {:?}", r.map(|m| m.headers)),
+    }
+
+    // Connection survives
+    let req = client.start_get("/", "localhost").collect_scoped();
+    server_tester.recv_message(3);
+    server_tester.send_headers(3, Headers::ok_200(), true);
+    assert_eq!(200, rt.block_on(req).expect("ok").headers.status());
+}
+
+#[test]
+fn scoped_request_connection_error() {
+    init_logger();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let req = client.start_get("/", "localhost").collect_scoped();
+    server_tester.recv_message(1);
+    server_tester.send_goaway(1);
+    // Last stream id must not increase
+    server_tester.send_goaway(3);
+
+    match rt.block_on(req) {
+        Err(StreamError::Connection(e)) => {
+            assert_eq!(Some(ErrorCode::ProtocolError), e.error_code);
+            assert!(matches!(
+                e.close(),
+                Some(ConnectionClose::ProtocolError(..))
+            ));
+        }
+        r => panic!(
+            "expecting connection error, got: {:?}",
+            r.map(|m| m.headers)
+        ),
+    }
+}
+
+#[test]
+fn scoped_request_peer_closed() {
+    init_logger();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let req = client.start_get("/", "localhost").collect_scoped();
+    server_tester.recv_message(1);
+    drop(server_tester);
+
+    // Peer closed without `GOAWAY`, so there is no error code
+    match rt.block_on(req) {
+        Err(StreamError::Connection(e)) => assert_eq!(None, e.error_code),
+        r => panic!(
+            "expecting connection error, got: {:?}",
+            r.map(|m| m.headers)
+        ),
+    }
+}
+
+#[test]
+fn request_timeout() {
+    init_logger();
+
+    let server = HttpServerTester::new();
+
+    let mut conf = ClientConf::new();
+    conf.request_timeout = Some(Duration::from_millis(100));
+    let client = Client::new_plain(BIND_HOST, server.port(), conf).expect("client");
+
+    let mut server_tester = server.accept_xchg();
+
+    let mut rt = Runtime::new().unwrap();
+
+    // slow responder: never replies to the first request
+    let slow = client.start_get("/slow", "localhost").collect();
+    server_tester.recv_message(1);
+
+    server_tester.recv_rst_frame_check(1, ErrorCode::Cancel);
+    match rt.block_on(slow) {
+        Err(Error::RequestTimeout) => {}
+        r => panic!("expecting timeout, got: {:?}", r.map(|m| m.headers)),
+    }
+
+    // connection is still usable
+    let req = client.start_get("/fast", "localhost").collect();
+    server_tester.recv_message(3);
+    server_tester.send_headers(3, Headers::ok_200(), true);
+    assert_eq!(200, rt.block_on(req).expect("r").headers.status());
+
+    assert_eq!(0, client.conn_state().streams.len());
+}
+
+#[test]
+fn trailers() {
+    init_logger();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let resp = client.start_get("/grpc", "localhost");
+    server_tester.recv_message(1);
+
+    server_tester.send_headers(1, Headers::ok_200(), false);
+    server_tester.send_data(1, b"abc", false);
+    let mut trailers = Headers::new();
+    trailers.add("grpc-status", "0");
+    server_tester.send_headers(1, trailers, true);
+
+    let (headers, stream) = rt.block_on(resp).expect("resp");
+    assert_eq!(200, headers.status());
+
+    let parts: Vec<DataOrTrailers> = rt.block_on(stream.try_collect()).expect("stream");
+    assert_eq!(2, parts.len());
+    match &parts[0] {
+        DataOrTrailers::Data(data, _) => assert_eq!(&b"abc"[..], &data[..]),
+        DataOrTrailers::Trailers(..) => panic!("expecting DATA"),
+    }
+    match &parts[1] {
+        DataOrTrailers::Trailers(trailers) => {
+            assert_eq!(Some("0"), trailers.get_opt("grpc-status"))
+        }
+        DataOrTrailers::Data(..) => panic!("expecting trailers"),
+    }
+
+    // Trailers always end the stream, so DATA after them is on half-closed stream
+    server_tester.send_data(1, b"def", false);
+    server_tester.recv_goaway_frame_check(ErrorCode::StreamClosed);
+}
+
+#[test]
+fn trailers_without_end_stream() {
+    init_logger();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let resp = client.start_get("/grpc", "localhost");
+    server_tester.recv_message(1);
+
+    server_tester.send_headers(1, Headers::ok_200(), false);
+    server_tester.send_data(1, b"abc", false);
+    let mut trailers = Headers::new();
+    trailers.add("grpc-status", "0");
+    server_tester.send_headers(1, trailers, false);
+
+    server_tester.recv_rst_frame_check(1, ErrorCode::ProtocolError);
+
+    let (_, stream) = rt.block_on(resp).expect("resp");
+    assert!(rt.block_on(stream.try_collect::<Vec<_>>()).is_err());
+}
+
+#[test]
+fn response_body_async_read() {
+    init_logger();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let resp = client.start_get("/big", "localhost");
+    server_tester.recv_message(1);
+
+    server_tester.send_headers(1, Headers::ok_200(), false);
+    let (_, body) = rt.block_on(resp.0).expect("headers");
+    let mut read = body.into_async_read();
+
+    let w = DEFAULT_SETTINGS.initial_window_size as usize;
+    for _ in 0..3 {
+        server_tester.send_data(1, &vec![17; 16000], false);
+    }
+
+    let mut buf = vec![0; 48000];
+    rt.block_on(read.read_exact(&mut buf)).expect("read");
+    assert!(buf.iter().all(|&b| b == 17));
+
+    // consumed data is returned to the peer
+    assert!(client.stream_state(1).in_window_size as usize > w - 48000);
+
+    let mut trailers = Headers::new();
+    trailers.add("grpc-status", "0");
+    server_tester.send_headers(1, trailers, true);
+
+    let mut rem = Vec::new();
+    rt.block_on(read.read_to_end(&mut rem)).expect("read");
+    assert!(rem.is_empty());
+    assert_eq!(Some("0"), read.trailers().unwrap().get_opt("grpc-status"));
+}
+
+#[test]
+fn request_body_stream_backpressure() {
+    init_logger();
+
+    const CHUNK: usize = 1024;
+    const TOTAL: usize = 100 * 1024 * 1024;
+    const WINDOW: u32 = 4096;
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client();
+    server_tester.recv_preface();
+
+    let mut settings = SettingsFrame::new();
+    settings
+        .settings
+        .push(HttpSetting::InitialWindowSize(WINDOW));
+    server_tester.send_settings(settings);
+    server_tester.recv_frame_settings_set();
+    server_tester.send_frame(SettingsFrame::new_ack());
+    server_tester.recv_frame_settings_ack();
+
+    let produced = Arc::new(AtomicUsize::new(0));
+    let produced_copy = produced.clone();
+    let body = stream::iter(0..TOTAL / CHUNK).map(move |_| {
+        produced_copy.fetch_add(CHUNK, Ordering::SeqCst);
+        Ok(Bytes::from(vec![17; CHUNK]))
+    });
+
+    let headers = Headers::from_vec(vec![
+        Header::new(":method", "POST"),
+        Header::new(":path", "/upload"),
+        Header::new(":scheme", "http"),
+    ]);
+    let resp = client.start_request_body_stream(headers, body).collect();
+
+    server_tester.recv_frame_headers_check(1, false);
+
+    let window = WINDOW as usize;
+
+    let mut received = 0;
+    let mut not_acked = 0;
+    loop {
+        let data = server_tester.recv_frame_data();
+        assert_eq!(1, data.stream_id);
+        received += data.data.len();
+        not_acked += data.data.len();
+
+        // body is not read ahead of what peer allowed to send
+        assert!(produced.load(Ordering::SeqCst) <= received + window + CHUNK);
+
+        if data.is_end_of_stream() {
+            break;
+        }
+
+        if not_acked >= window / 2 {
+            server_tester.send_window_update_conn(not_acked as u32);
+            server_tester.send_window_update_stream(1, not_acked as u32);
+            not_acked = 0;
+        }
+    }
+    assert_eq!(TOTAL, received);
+
+    server_tester.send_headers(1, Headers::ok_200(), true);
+
+    let mut rt = Runtime::new().unwrap();
+    assert_eq!(200, rt.block_on(resp).expect("r").headers.status());
+}
+
+/// Reader producing `total` bytes in short reads, then optionally failing.
+struct GeneratedReader {
+    total: usize,
+    produced: Arc<AtomicUsize>,
+    fail_at_end: bool,
+}
+
+impl tokio::io::AsyncRead for GeneratedReader {
+    fn poll_read(
+        self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let produced = self.produced.load(Ordering::SeqCst);
+        if produced == self.total && self.fail_at_end {
+            return Poll::Ready(Err(std::io::Error::other("disk error")));
+        }
+        // reads are shorter than buffer and of varying length
+        let len = (self.total - produced)
+            .min(buf.len())
+            .min(1000 + produced % 7000);
+        for b in &mut buf[..len] {
+            *b = 17;
+        }
+        self.produced.fetch_add(len, Ordering::SeqCst);
+        Poll::Ready(Ok(len))
+    }
+}
+
+#[test]
+fn request_body_reader_backpressure() {
+    init_logger();
+
+    const TOTAL: usize = 10 * 1024 * 1024;
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+
+    let produced = Arc::new(AtomicUsize::new(0));
+    let body = GeneratedReader {
+        total: TOTAL,
+        produced: produced.clone(),
+        fail_at_end: false,
+    };
+
+    let headers = Headers::from_vec(vec![
+        Header::new(":method", "POST"),
+        Header::new(":path", "/upload"),
+        Header::new(":scheme", "http"),
+    ]);
+    let resp = client.start_request_body_reader(headers, body).collect();
+
+    server_tester.recv_frame_headers_check(1, false);
+
+    let window = DEFAULT_SETTINGS.initial_window_size as usize;
+
+    let mut received = 0;
+    let mut not_acked = 0;
+    loop {
+        let data = server_tester.recv_frame_data();
+        assert_eq!(1, data.stream_id);
+        assert!(data.data.iter().all(|&b| b == 17));
+        received += data.data.len();
+        not_acked += data.data.len();
+
+        // reader is not read ahead of what peer allowed to send
+        assert!(produced.load(Ordering::SeqCst) <= received + window + 16 * 1024);
+
+        if data.is_end_of_stream() {
+            break;
+        }
+
+        if not_acked >= window / 2 {
+            server_tester.send_window_update_conn(not_acked as u32);
+            server_tester.send_window_update_stream(1, not_acked as u32);
+            not_acked = 0;
+        }
+    }
+    assert_eq!(TOTAL, received);
+
+    server_tester.send_headers(1, Headers::ok_200(), true);
+
+    let mut rt = Runtime::new().unwrap();
+    assert_eq!(200, rt.block_on(resp).expect("r").headers.status());
+}
+
+#[test]
+fn request_body_reader_error() {
+    init_logger();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+
+    let body = GeneratedReader {
+        total: 10,
+        produced: Arc::new(AtomicUsize::new(0)),
+        fail_at_end: true,
+    };
+
+    let headers = Headers::from_vec(vec![
+        Header::new(":method", "POST"),
+        Header::new(":path", "/upload"),
+        Header::new(":scheme", "http"),
+    ]);
+    let _resp = client.start_request_body_reader(headers, body);
+
+    server_tester.recv_frame_headers_check(1, false);
+    assert_eq!(10, server_tester.recv_frame_data_check(1, false).len());
+    server_tester.recv_rst_frame_check(1, ErrorCode::InternalError);
+}
+
+fn pushed_request(path: &str) -> Headers {
+    let mut headers = Headers::new();
+    headers.add(":method", "GET");
+    headers.add(":path", path);
+    headers.add(":scheme", "http");
+    headers.add(":authority", "localhost");
+    headers
+}
+
+struct RefuseCssPush(mpsc::SyncSender<(Headers, Response)>);
+
+impl ClientPushHandler for RefuseCssPush {
+    fn push_promise(&self, _associated_stream_id: StreamId, request: &Headers) -> bool {
+        !request.path().ends_with(".css")
+    }
+
+    fn pushed_response(&self, request: Headers, response: Response) {
+        drop(self.0.send((request, response)));
+    }
+}
+
+#[test]
+fn keep_alive_timeout() {
+    init_logger();
+
+    let server = HttpServerTester::new();
+
+    let mut conf = ClientConf::new();
+    conf.common.keep_alive_timeout = Some(Duration::from_millis(100));
+    conf.common.keep_alive_ping_timeout = Some(Duration::from_millis(100));
+    let client = Client::new_plain(BIND_HOST, server.port(), conf).expect("client");
+
+    let mut server_tester = server.accept();
+    server_tester.recv_preface();
+    server_tester.settings_xchg();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let resp = client.start_get("/", "localhost").collect();
+    server_tester.recv_message(1);
+
+    // Server is silent, client probes it with PING
+    match server_tester.recv_frame() {
+        HttpFrame::Ping(ping) => assert!(!ping.is_ack()),
+        f => panic!("expecting PING, got: {:?}", f),
+    }
+
+    // And closes connection when PING is not answered
+    assert!(rt.block_on(resp).is_err());
+    server_tester.recv_eof();
+}
+
+#[test]
+fn push_promise_accept_and_refuse() {
+    init_logger();
+
+    let server = HttpServerTester::new();
+
+    let (tx, rx) = mpsc::sync_channel(1);
+    let mut conf = ClientConf::new();
+    conf.push_handler = Some(Arc::new(RefuseCssPush(tx)));
+    let client = Client::new_plain(BIND_HOST, server.port(), conf).expect("client");
+
+    let mut server_tester = server.accept();
+    server_tester.recv_preface();
+    server_tester.send_settings(SettingsFrame::new());
+    server_tester.recv_frame_settings_set();
+    assert!(server_tester.peer_settings.enable_push);
+    server_tester.send_frame(SettingsFrame::new_ack());
+    server_tester.recv_frame_settings_ack();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let resp = client.start_get("/", "localhost").collect();
+    server_tester.recv_message(1);
+
+    server_tester.send_push_promise(1, 2, pushed_request("/style.css"));
+    server_tester.recv_rst_frame_check(2, ErrorCode::Cancel);
+
+    server_tester.send_push_promise(1, 4, pushed_request("/script.js"));
+    server_tester.send_headers(4, Headers::ok_200(), false);
+    server_tester.send_data(4, b"js", true);
+    server_tester.send_headers(1, Headers::ok_200(), true);
+
+    assert_eq!(200, rt.block_on(resp).expect("resp").headers.status());
+
+    let (request, pushed) = rx.recv_timeout(Duration::from_secs(10)).expect("pushed");
+    assert_eq!("/script.js", request.path());
+    let pushed = rt.block_on(pushed.collect()).expect("pushed");
+    assert_eq!(&b"js"[..], &pushed.body.get_bytes()[..]);
+
+    assert_eq!(0, client.conn_state().streams.len());
+}
+
+#[test]
+fn max_concurrent_streams_pushed_stream_not_counted() {
+    init_logger();
+
+    let server = HttpServerTester::new();
+
+    let (tx, _rx) = mpsc::sync_channel(1);
+    let mut conf = ClientConf::new();
+    conf.push_handler = Some(Arc::new(RefuseCssPush(tx)));
+    let client = Client::new_plain(BIND_HOST, server.port(), conf).expect("client");
+
+    let mut server_tester = server.accept();
+    server_tester.recv_preface();
+    let mut settings = SettingsFrame::new();
+    settings.settings.push(HttpSetting::MaxConcurrentStreams(1));
+    server_tester.send_settings(settings);
+    server_tester.recv_frame_settings_set();
+    server_tester.send_frame(SettingsFrame::new_ack());
+    server_tester.recv_frame_settings_ack();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let resp = client.start_get("/", "localhost").collect();
+    server_tester.recv_message(1);
+
+    // Pushed stream stays open after associated stream is complete
+    server_tester.send_push_promise(1, 2, pushed_request("/script.js"));
+    server_tester.send_headers(2, Headers::ok_200(), false);
+    server_tester.send_headers(1, Headers::ok_200(), true);
+    assert_eq!(200, rt.block_on(resp).expect("resp").headers.status());
+
+    let _resp = client.start_get("/next", "localhost").collect();
+    server_tester.recv_frame_headers_check(3, true);
+}
+
+#[test]
+fn push_promise_on_unknown_stream() {
+    init_logger();
+
+    let server = HttpServerTester::new();
+
+    let (tx, _rx) = mpsc::sync_channel(1);
+    let mut conf = ClientConf::new();
+    conf.push_handler = Some(Arc::new(RefuseCssPush(tx)));
+    let client = Client::new_plain(BIND_HOST, server.port(), conf).expect("client");
+
+    let mut server_tester = server.accept_xchg();
+
+    let _resp = client.start_get("/", "localhost");
+    server_tester.recv_message(1);
+
+    // Stream 3 is not opened by client
+    server_tester.send_push_promise(3, 2, pushed_request("/script.js"));
+    server_tester.recv_goaway_frame_check(ErrorCode::ProtocolError);
+}
+
+#[test]
+fn push_promise_when_push_disabled() {
+    init_logger();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+    assert!(!server_tester.peer_settings.enable_push);
+
+    let _resp = client.start_get("/", "localhost");
+    server_tester.recv_message(1);
+
+    server_tester.send_push_promise(1, 2, pushed_request("/style.css"));
+    server_tester.recv_goaway_frame_check(ErrorCode::ProtocolError);
+}
+
 #[test]
 fn rst_is_error() {
     init_logger();
@@ -107,6 +1632,26 @@ fn handle_1xx_headers() {
     assert_eq!(0, state.streams.len(), "{:?}", state);
 }
 
+#[test]
+fn invalid_status() {
+    init_logger();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+
+    let req = client.start_get("/fgfg", "localhost").collect();
+
+    server_tester.recv_message(1);
+
+    let mut headers = Headers::new();
+    headers.add(":status", "99");
+    server_tester.send_headers(1, headers, false);
+
+    server_tester.recv_rst_frame_check(1, ErrorCode::ProtocolError);
+
+    let mut rt = Runtime::new().unwrap();
+    assert!(rt.block_on(req).is_err());
+}
+
 #[test]
 fn client_call_dropped() {
     init_logger();
@@ -120,6 +1665,9 @@ fn client_call_dropped() {
 
         drop(req);
 
+        server_tester.recv_rst_frame_check(1, ErrorCode::Cancel);
+
+        // Response sent before server received RST_STREAM is ignored
         server_tester.send_headers(1, Headers::ok_200(), true);
     }
 
@@ -159,7 +1707,7 @@ fn reconnect_on_disconnect() {
     drop(server_tester);
 
     // waiting for client connection to die
-    while let Ok(_) = rt.block_on(client.dump_state()) {
+    while rt.block_on(client.dump_state()).is_ok() {
         thread::sleep(Duration::from_millis(1));
     }
 
@@ -215,6 +1763,197 @@ fn reconnect_on_goaway() {
     }
 }
 
+#[test]
+fn settings_reset_on_reconnect() {
+    init_logger();
+
+    let (server, client) = HttpServerTester::new_with_client();
+
+    let mut rt = Runtime::new().unwrap();
+
+    {
+        let mut server_tester = server.accept();
+        server_tester.recv_preface();
+        let mut settings = SettingsFrame::new();
+        settings.settings.push(HttpSetting::MaxFrameSize(20000));
+        settings
+            .settings
+            .push(HttpSetting::EnableConnectProtocol(true));
+        server_tester.send_settings(settings);
+        server_tester.recv_frame_settings_set();
+        server_tester.send_frame(SettingsFrame::new_ack());
+        server_tester.recv_frame_settings_ack();
+
+        rt.block_on(client.connected()).expect("connected");
+        assert_eq!(20000, client.peer_settings().max_frame_size);
+        assert!(client.connect_protocol_enabled());
+    }
+
+    // waiting for client connection to die
+    while rt.block_on(client.dump_state()).is_ok() {
+        thread::sleep(Duration::from_millis(1));
+    }
+
+    let mut connected = Box::pin(client.connected());
+
+    let mut server_tester = server.accept();
+    server_tester.recv_preface();
+    server_tester.recv_frame_settings_set();
+
+    // Nothing is known about the new connection yet
+    assert_eq!(DEFAULT_SETTINGS, client.peer_settings());
+    assert!(!client.connect_protocol_enabled());
+    let pending = rt.block_on(future::poll_fn(|cx| {
+        Poll::Ready(connected.as_mut().poll(cx).is_pending())
+    }));
+    assert!(pending);
+
+    server_tester.send_settings(SettingsFrame::new());
+    server_tester.send_frame(SettingsFrame::new_ack());
+    server_tester.recv_frame_settings_ack();
+
+    rt.block_on(connected).expect("connected");
+    assert_eq!(DEFAULT_SETTINGS, client.peer_settings());
+    assert_eq!(server_tester.peer_settings, client.local_settings());
+}
+
+fn pool_get(authority: &str) -> Headers {
+    Headers::from_vec(vec![
+        Header::new(":method", "GET"),
+        Header::new(":path", "/"),
+        Header::new(":authority", authority.to_owned()),
+        Header::new(":scheme", "http"),
+    ])
+}
+
+fn pool_accept(server: &HttpServerTester) -> HttpConnTester {
+    let mut conn = server.accept();
+    conn.recv_preface();
+    // request HEADERS may come before ACK of our SETTINGS
+    conn.settings_xchg_but_ack();
+    conn
+}
+
+#[test]
+fn pool_opens_conn_when_streams_exhausted() {
+    init_logger();
+
+    let server = HttpServerTester::new();
+    let authority = format!("{}:{}", BIND_HOST, server.port());
+
+    let mut conf = ClientPoolConf::new();
+    conf.max_streams_per_conn = Some(1);
+    let pool = ClientPool::new(conf);
+
+    let mut rt = Runtime::new().unwrap();
+
+    let req1 = pool
+        .request(&authority, pool_get(&authority), None)
+        .collect();
+    let mut conn1 = pool_accept(&server);
+    conn1.recv_message(1);
+
+    let req2 = pool
+        .request(&authority, pool_get(&authority), None)
+        .collect();
+    let mut conn2 = pool_accept(&server);
+    conn2.recv_message(1);
+
+    assert_eq!(2, pool.conn_count(&authority));
+
+    conn1.send_headers(1, Headers::ok_200(), true);
+    conn2.send_headers(1, Headers::ok_200(), true);
+    assert_eq!(200, rt.block_on(req1).expect("r").headers.status());
+    assert_eq!(200, rt.block_on(req2).expect("r").headers.status());
+
+    // completed requests free the first connection
+    let req3 = pool
+        .request(&authority, pool_get(&authority), None)
+        .collect();
+    conn1.recv_message(3);
+    conn1.send_headers(3, Headers::ok_200(), true);
+    assert_eq!(200, rt.block_on(req3).expect("r").headers.status());
+
+    assert_eq!(2, pool.conn_count(&authority));
+}
+
+#[test]
+fn pool_evicts_idle_conn() {
+    init_logger();
+
+    let server = HttpServerTester::new();
+    let authority = format!("{}:{}", BIND_HOST, server.port());
+
+    let mut conf = ClientPoolConf::new();
+    conf.idle_timeout = Some(Duration::from_millis(10));
+    let pool = ClientPool::new(conf);
+
+    let mut rt = Runtime::new().unwrap();
+
+    let req = pool
+        .request(&authority, pool_get(&authority), None)
+        .collect();
+    let mut conn1 = pool_accept(&server);
+    conn1.recv_message(1);
+    conn1.send_headers(1, Headers::ok_200(), true);
+    assert_eq!(200, rt.block_on(req).expect("r").headers.status());
+
+    // idle connection is closed without waiting for the next request
+    while pool.conn_count(&authority) != 0 {
+        thread::sleep(Duration::from_millis(5));
+    }
+    conn1.recv_eof_skip_frames();
+
+    let req = pool
+        .request(&authority, pool_get(&authority), None)
+        .collect();
+
+    let mut conn2 = pool_accept(&server);
+    conn2.recv_message(1);
+    conn2.send_headers(1, Headers::ok_200(), true);
+    assert_eq!(200, rt.block_on(req).expect("r").headers.status());
+
+    assert_eq!(1, pool.conn_count(&authority));
+}
+
+#[test]
+fn pool_skips_conn_after_goaway() {
+    init_logger();
+
+    let server = HttpServerTester::new();
+    let authority = format!("{}:{}", BIND_HOST, server.port());
+
+    let pool = ClientPool::new(ClientPoolConf::new());
+
+    let mut rt = Runtime::new().unwrap();
+
+    let req1 = pool
+        .request(&authority, pool_get(&authority), None)
+        .collect();
+    let mut conn1 = pool_accept(&server);
+    conn1.recv_message(1);
+
+    conn1.send_goaway(1);
+    // PING ACK is sent after `GOAWAY` is processed
+    conn1.send_frame(PingFrame::new());
+    match conn1.recv_frame() {
+        HttpFrame::Ping(f) => assert!(f.is_ack()),
+        f => panic!("unexpected frame: {:?}", f),
+    }
+
+    conn1.send_headers(1, Headers::ok_200(), true);
+    assert_eq!(200, rt.block_on(req1).expect("r").headers.status());
+
+    // new request goes to a new connection
+    let req2 = pool
+        .request(&authority, pool_get(&authority), None)
+        .collect();
+    let mut conn2 = pool_accept(&server);
+    conn2.recv_message(1);
+    conn2.send_headers(1, Headers::ok_200(), true);
+    assert_eq!(200, rt.block_on(req2).expect("r").headers.status());
+}
+
 #[test]
 pub fn issue_89() {
     init_logger();
@@ -430,7 +2169,7 @@ fn sink_reset_by_peer() {
 
     server_tester.send_rst(1, ErrorCode::Cancel);
 
-    while client.conn_state().streams.len() != 0 {
+    while !client.conn_state().streams.is_empty() {
         // spin-wait
     }
 
@@ -449,3 +2188,237 @@ fn sink_reset_by_peer() {
     assert_eq!(0, client.conn_state().out_window_size);
     assert_eq!(0, client.conn_state().pump_out_window_size);
 }
+
+fn ping_burst_socket_writes(flush_strategy: FlushStrategy) -> u64 {
+    let server = HttpServerTester::new();
+
+    let mut conf = ClientConf::new();
+    conf.common.flush_strategy = Some(flush_strategy);
+    let client = Client::new_plain(BIND_HOST, server.port(), conf).expect("client");
+
+    let mut tester = server.accept_xchg();
+
+    let mut rt = Runtime::new().unwrap();
+    let before = rt.block_on(client.stats()).expect("stats").socket_writes;
+
+    tester.send_frames((1..=10).map(PingFrame::with_data).collect());
+    for i in 1..=10 {
+        match tester.recv_frame() {
+            HttpFrame::Ping(f) if f.is_ack() => assert_eq!(i, f.opaque_data()),
+            f => panic!("unexpected frame: {:?}", f),
+        }
+    }
+
+    rt.block_on(client.stats()).expect("stats").socket_writes - before
+}
+
+#[test]
+fn flush_on_idle_coalesces_burst() {
+    init_logger();
+
+    assert_eq!(1, ping_burst_socket_writes(FlushStrategy::OnIdle));
+    assert_eq!(10, ping_burst_socket_writes(FlushStrategy::Immediate));
+}
+
+#[test]
+fn flush_interval() {
+    init_logger();
+
+    let interval = Duration::from_millis(50);
+    assert_eq!(
+        1,
+        ping_burst_socket_writes(FlushStrategy::Interval(interval))
+    );
+}
+
+#[test]
+fn fair_scheduling() {
+    init_logger();
+
+    let server = HttpServerTester::new();
+
+    let mut conf = ClientConf::new();
+    conf.common.scheduling_policy = Some(SchedulingPolicy::Fair);
+    let client = Client::new_plain(BIND_HOST, server.port(), conf).expect("client");
+
+    let mut tester = server.accept_xchg();
+
+    let w = DEFAULT_SETTINGS.initial_window_size;
+
+    let _large = client
+        .start_post("/large", "localhost", Bytes::from(vec![1; w as usize * 3]))
+        .collect();
+    tester.recv_frame_headers_check(1, false);
+
+    // Large stream drains connection window
+    let mut received = 0;
+    while received < w as usize {
+        received += tester.recv_frame_data_check(1, false).len();
+    }
+
+    let _small: Vec<_> = (0..3)
+        .map(|_| {
+            client
+                .start_post("/small", "localhost", Bytes::from(vec![2; 1000]))
+                .collect()
+        })
+        .collect();
+    for &stream_id in &[3, 5, 7] {
+        tester.recv_frame_headers_check(stream_id, false);
+    }
+
+    tester.send_window_update_stream(1, w * 2);
+    tester.send_window_update_conn(4000);
+    tester.send_frame(PingFrame::new());
+
+    let mut received = HashMap::new();
+    loop {
+        match tester.recv_frame() {
+            HttpFrame::Ping(f) if f.is_ack() => break,
+            HttpFrame::Data(f) => *received.entry(f.stream_id).or_insert(0) += f.data.len(),
+            f => panic!("unexpected frame: {:?}", f),
+        }
+    }
+
+    // Window is shared equally, large stream does not take it all
+    assert_eq!(Some(&1000), received.get(&1));
+    assert_eq!(Some(&1000), received.get(&3));
+    assert_eq!(Some(&1000), received.get(&5));
+    assert_eq!(Some(&1000), received.get(&7));
+}
+
+/// Send `increment` of connection window and count DATA bytes sent per stream.
+fn recv_data_after_window_update_conn(
+    tester: &mut HttpConnTester,
+    increment: u32,
+) -> HashMap<StreamId, usize> {
+    tester.send_window_update_conn(increment);
+    tester.send_frame(PingFrame::new());
+
+    let mut received = HashMap::new();
+    loop {
+        match tester.recv_frame() {
+            HttpFrame::Ping(f) if f.is_ack() => return received,
+            HttpFrame::Data(f) => *received.entry(f.stream_id).or_insert(0) += f.data.len(),
+            f => panic!("unexpected frame: {:?}", f),
+        }
+    }
+}
+
+#[test]
+fn weighted_fair_scheduling() {
+    init_logger();
+
+    let server = HttpServerTester::new();
+
+    let mut conf = ClientConf::new();
+    conf.common.scheduling_policy = Some(SchedulingPolicy::WeightedFair);
+    let client = Client::new_plain(BIND_HOST, server.port(), conf).expect("client");
+
+    let mut tester = server.accept_xchg();
+
+    let w = DEFAULT_SETTINGS.initial_window_size;
+
+    let _heavy = client
+        .start_post("/heavy", "localhost", Bytes::from(vec![1; w as usize * 3]))
+        .collect();
+    tester.recv_frame_headers_check(1, false);
+
+    // First stream drains connection window
+    let mut received = 0;
+    while received < w as usize {
+        received += tester.recv_frame_data_check(1, false).len();
+    }
+
+    let _light = client
+        .start_post("/light", "localhost", Bytes::from(vec![2; w as usize * 3]))
+        .collect();
+    tester.recv_frame_headers_check(3, false);
+
+    // Weights 256 and 1
+    tester.send_frame(PriorityFrame::new(1, 0, false, 255));
+    tester.send_frame(PriorityFrame::new(3, 0, false, 0));
+    tester.send_window_update_stream(1, w * 2);
+
+    // Both streams share the connection window
+    let received = recv_data_after_window_update_conn(&mut tester, 257 * 100);
+    assert_eq!(Some(&25600), received.get(&1));
+    assert_eq!(Some(&100), received.get(&3));
+
+    // Stream 1 depends on stream 3, then stream 3 is made dependent on stream 1:
+    // stream 1 is moved to the root first (RFC 7540 section 5.3.3)
+    tester.send_frame(PriorityFrame::new(1, 3, false, 255));
+    tester.send_frame(PriorityFrame::new(3, 1, false, 0));
+
+    let received = recv_data_after_window_update_conn(&mut tester, 1000);
+    assert_eq!(Some(&1000), received.get(&1));
+    assert_eq!(None, received.get(&3));
+}
+
+#[test]
+fn weighted_fair_scheduling_exclusive() {
+    init_logger();
+
+    let server = HttpServerTester::new();
+
+    let mut conf = ClientConf::new();
+    conf.common.scheduling_policy = Some(SchedulingPolicy::WeightedFair);
+    let client = Client::new_plain(BIND_HOST, server.port(), conf).expect("client");
+
+    let mut tester = server.accept_xchg();
+
+    let w = DEFAULT_SETTINGS.initial_window_size;
+
+    let _first = client
+        .start_post("/first", "localhost", Bytes::from(vec![1; w as usize * 3]))
+        .collect();
+    tester.recv_frame_headers_check(1, false);
+
+    // First stream drains connection window
+    let mut received = 0;
+    while received < w as usize {
+        received += tester.recv_frame_data_check(1, false).len();
+    }
+
+    let _second = client
+        .start_post("/second", "localhost", Bytes::from(vec![2; w as usize * 3]))
+        .collect();
+    tester.recv_frame_headers_check(3, false);
+    let _third = client
+        .start_post("/third", "localhost", Bytes::from(vec![3; w as usize * 3]))
+        .collect();
+    tester.recv_frame_headers_check(5, false);
+
+    // Stream 3 becomes dependent on stream 5
+    tester.send_frame(PriorityFrame::new(5, 0, true, 15));
+
+    let received = recv_data_after_window_update_conn(&mut tester, 1000);
+    assert_eq!(None, received.get(&3));
+    assert_eq!(Some(&1000), received.get(&5));
+}
+
+#[test]
+fn request_headers_exceed_peer_max_header_list_size() {
+    init_logger();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+
+    server_tester.send_recv_settings(SettingsFrame::from_settings(vec![
+        HttpSetting::MaxHeaderListSize(200),
+    ]));
+
+    let mut headers = Headers::new_get("/");
+    headers.add(":authority", "localhost");
+    headers.add(":scheme", "http");
+    headers.add("x-large", "x".repeat(200));
+
+    let mut rt = Runtime::new().unwrap();
+    match rt.block_on(client.start_request(headers, None, None, true)) {
+        Err(Error::HeaderListTooLarge(size, 200)) => assert!(size > 200),
+        r => panic!("expecting HeaderListTooLarge, got: {:?}", r.map(|_| ())),
+    }
+
+    // Small request is still sent
+    let _resp = client.start_get("/small", "localhost").collect();
+    server_tester.recv_frame_headers_check(1, true);
+}
@@ -8,6 +8,7 @@ use httpbis_test::*;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::sync::Mutex;
 
 use bytes::Bytes;
 
@@ -18,20 +19,29 @@ use std::thread;
 use futures::stream;
 
 use futures::channel::oneshot;
+use futures::executor;
 use futures::stream::Stream;
 use futures::stream::StreamExt;
 
 use std::task::Poll;
 
-use httpbis::for_test::solicit::frame::HeadersFlag;
-use httpbis::for_test::solicit::frame::HttpSetting;
-use httpbis::for_test::solicit::frame::SettingsFrame;
 use httpbis::for_test::solicit::DEFAULT_SETTINGS;
+use httpbis::for_test::ServerConn;
+use httpbis::frame::HeadersFlag;
+use httpbis::frame::HeadersFrame;
+use httpbis::frame::HttpFrame;
+use httpbis::frame::HttpSetting;
+use httpbis::frame::PingFrame;
+use httpbis::frame::RawFrame;
+use httpbis::frame::SettingsFrame;
+use httpbis::frame::WindowUpdateFrame;
 use httpbis::*;
 
 use std::iter::FromIterator;
 use std::net::TcpStream;
 use std::sync::mpsc;
+use std::time::Duration;
+use std::time::Instant;
 
 use futures::task::Context;
 use httpbis::BytesDeque;
@@ -69,6 +79,26 @@ fn simple_new() {
     assert_eq!(0, server.dump_state().streams.len());
 }
 
+#[test]
+fn peer_tls_info_plain() {
+    init_logger();
+
+    let server = ServerOneConn::new_fn(0, |_, req, mut resp| {
+        assert_eq!(None, req.peer_tls_info());
+        assert_eq!(None, req.alpn_protocol());
+        resp.send_found_200_plain_text("plain")?;
+        Ok(())
+    });
+
+    let mut tester = HttpConnTester::connect(server.port());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    let resp = tester.get(1, "/");
+    assert_eq!(200, resp.headers.status());
+    assert_eq!(&b"plain"[..], resp.body.get_bytes());
+}
+
 #[test]
 fn custom_drop_callback() {
     init_logger();
@@ -196,7 +226,7 @@ fn response_large() {
 
     let mut large_resp = Vec::new();
     while large_resp.len() < 100_000 {
-        if large_resp.len() != 0 {
+        if !large_resp.is_empty() {
             write!(&mut large_resp, ",").unwrap();
         }
         let len = large_resp.len();
@@ -231,6 +261,190 @@ fn response_large() {
     assert_eq!(0, server.dump_state().streams.len());
 }
 
+#[test]
+fn trailers_round_trip() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let server = ServerOneConn::new_fn(0, |_, req, mut resp| {
+        let mut trailers = Headers::new();
+        trailers.add("grpc-status", "0");
+
+        resp.send_headers(Headers::ok_200())?;
+        match req.headers.path() {
+            "/stream" => {
+                resp.pull_from_stream(HttpStreamAfterHeaders::new(stream::iter(vec![
+                    Ok(DataOrTrailers::intermediate_data(Bytes::from("abc"))),
+                    Ok(DataOrTrailers::Trailers(trailers)),
+                ])))?;
+            }
+            "/bad" => {
+                match resp.send_trailers(Headers::new_status(200)) {
+                    Err(SendError::InvalidTrailers(..)) => {}
+                    r => panic!("expecting invalid trailers: {:?}", r),
+                }
+                resp.send_trailers(trailers)?;
+            }
+            _ => {
+                resp.send_data(Bytes::from("abc"))?;
+                resp.send_trailers(trailers)?;
+            }
+        }
+        Ok(())
+    });
+
+    let client = Client::new_plain(BIND_HOST, server.port(), Default::default()).expect("connect");
+
+    for path in &["/send", "/stream", "/bad"] {
+        let (headers, stream) = rt
+            .block_on(client.start_get(path, "localhost"))
+            .expect("resp");
+        assert_eq!(200, headers.status());
+
+        let parts: Vec<DataOrTrailers> = rt
+            .block_on(stream.collect::<Vec<_>>())
+            .into_iter()
+            .map(|p| p.expect("part"))
+            .collect();
+        let trailers = match parts.last() {
+            Some(DataOrTrailers::Trailers(trailers)) => trailers,
+            _ => panic!("expecting trailers"),
+        };
+        assert_eq!(Some("0"), trailers.get_opt("grpc-status"));
+    }
+
+    assert_eq!(0, server.dump_state().streams.len());
+}
+
+fn push_request(method: &str, path: &str) -> Headers {
+    let mut headers = Headers::new();
+    headers.add(":method", method);
+    headers.add(":path", path);
+    headers.add(":scheme", "http");
+    headers.add(":authority", "localhost");
+    headers
+}
+
+fn push_style_css(
+    _: ServerHandlerContext,
+    _req: ServerRequest,
+    mut resp: ServerResponse,
+) -> httpbis::Result<()> {
+    match resp.push_promise(push_request("GET", "/style.css")) {
+        Ok(mut pushed) => {
+            match resp.push_promise(push_request("POST", "/form")) {
+                Err(Error::InvalidPushRequest) => {}
+                r => panic!("expecting invalid push request: {:?}", r.map(|_| ())),
+            }
+            pushed.send_found_200_plain_text("body {}")?;
+            resp.send_found_200_plain_text("pushed")?;
+        }
+        Err(Error::PushDisabled) => resp.send_found_200_plain_text("not pushed")?,
+        Err(e) => return Err(e),
+    }
+    Ok(())
+}
+
+struct CollectPushes(Mutex<mpsc::Sender<(Headers, Response)>>);
+
+impl ClientPushHandler for CollectPushes {
+    fn push_promise(&self, associated_stream_id: StreamId, _request: &Headers) -> bool {
+        assert_eq!(1, associated_stream_id);
+        true
+    }
+
+    fn pushed_response(&self, request: Headers, response: Response) {
+        drop(self.0.lock().unwrap().send((request, response)));
+    }
+}
+
+#[test]
+fn server_push() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let server = ServerOneConn::new_fn(0, push_style_css);
+
+    let (tx, rx) = mpsc::channel();
+    let mut conf = ClientConf::new();
+    conf.push_handler = Some(Arc::new(CollectPushes(Mutex::new(tx))));
+    let client = Client::new_plain(BIND_HOST, server.port(), conf).expect("connect");
+
+    let page = rt
+        .block_on(client.start_get("/", "localhost").collect())
+        .expect("page");
+    assert_eq!(&b"pushed"[..], &page.body.get_bytes()[..]);
+
+    let (request, response) = rx.recv_timeout(Duration::from_secs(10)).expect("pushed");
+    assert_eq!("/style.css", request.path());
+    let pushed = rt.block_on(response.collect()).expect("pushed");
+    assert_eq!(200, pushed.headers.status());
+    assert_eq!(&b"body {}"[..], &pushed.body.get_bytes()[..]);
+
+    assert_eq!(0, server.dump_state().streams.len());
+}
+
+#[test]
+fn server_push_disabled_by_client() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let server = ServerOneConn::new_fn(0, push_style_css);
+
+    // client without push handler sends `SETTINGS_ENABLE_PUSH = 0`
+    let client = Client::new_plain(BIND_HOST, server.port(), Default::default()).expect("connect");
+
+    let page = rt
+        .block_on(client.start_get("/", "localhost").collect())
+        .expect("page");
+    assert_eq!(&b"not pushed"[..], &page.body.get_bytes()[..]);
+}
+
+#[test]
+fn server_push_max_concurrent_streams() {
+    init_logger();
+
+    let server = ServerOneConn::new_fn(0, |_, _req, mut resp| {
+        let mut pushed = resp.push_promise(push_request("GET", "/style.css"))?;
+        pushed.send_headers(Headers::ok_200())?;
+        // Not sent, because the first pushed stream is still open
+        let mut not_pushed = resp.push_promise(push_request("GET", "/script.js"))?;
+        drop(not_pushed.send_found_200_plain_text("js"));
+        resp.send_found_200_plain_text("pushed")?;
+        pushed.send_data_end_of_stream(Bytes::from_static(b"css"))?;
+        Ok(())
+    });
+
+    let mut tester = HttpConnTester::connect(server.port());
+    tester.send_preface();
+    let mut settings = SettingsFrame::new();
+    settings.settings.push(HttpSetting::MaxConcurrentStreams(1));
+    tester.send_settings(settings);
+    tester.recv_frame_settings_set();
+    tester.send_frame(SettingsFrame::new_ack());
+    tester.recv_frame_settings_ack();
+
+    tester.send_get(1, "/");
+
+    let mut promised = Vec::new();
+    let mut ended = Vec::new();
+    while ended.len() < 2 {
+        match tester.recv_frame() {
+            HttpFrame::PushPromise(f) => promised.push(f.promised_stream_id),
+            HttpFrame::Headers(f) if f.is_end_of_stream() => ended.push(f.stream_id),
+            HttpFrame::Data(f) if f.is_end_of_stream() => ended.push(f.stream_id),
+            HttpFrame::Headers(..) | HttpFrame::Data(..) => {}
+            f => panic!("unexpected frame: {:?}", f),
+        }
+    }
+    assert_eq!(vec![2], promised);
+    ended.sort();
+    assert_eq!(vec![1, 2], ended);
+}
+
 #[test]
 fn rst_stream_on_data_without_stream() {
     init_logger();
@@ -241,37 +455,68 @@ fn rst_stream_on_data_without_stream() {
     tester.send_preface();
     tester.settings_xchg();
 
-    // DATA frame without open stream
+    // DATA frame on idle stream
     tester.send_data(11, &[10, 20, 30], false);
 
-    tester.recv_goaway_frame_check(ErrorCode::StreamClosed);
+    tester.recv_goaway_frame_check(ErrorCode::ProtocolError);
 
     tester.recv_eof();
 }
 
 #[test]
-fn exceed_max_frame_size() {
+fn shutdown_graceful() {
     init_logger();
 
+    let mut rt = Runtime::new().unwrap();
+
     let server = ServerTest::new();
 
     let mut tester = HttpConnTester::connect(server.port);
     tester.send_preface();
     tester.settings_xchg();
 
-    tester.send_data(1, &[0; 17_000], false);
+    let mut headers = Headers::new();
+    headers.add(":method", "POST");
+    headers.add(":path", "/echo");
+    headers.add(":scheme", "http");
+    tester.send_headers(1, headers, false);
+    assert_eq!(200, tester.recv_frame_headers_check(1, false).status());
 
-    tester.recv_eof();
+    let shutdown = server.server.shutdown_graceful(Duration::from_secs(30));
 
-    let mut tester = HttpConnTester::connect(server.port);
-    tester.send_preface();
-    tester.settings_xchg();
+    let goaway = tester.recv_goaway_frame();
+    assert_eq!(ErrorCode::NoError, goaway.error_code());
+    assert_eq!(0x7fffffff, goaway.last_stream_id());
 
-    assert_eq!(200, tester.get(1, "/echo").headers.status());
+    // new connections are not served
+    if let Ok(mut tcp) = TcpStream::connect((BIND_HOST, server.port)) {
+        tcp.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+        let mut buf = [0; 1];
+        match tcp.read(&mut buf) {
+            Ok(0) | Err(_) => {}
+            Ok(_) => panic!("new connection served during graceful shutdown"),
+        }
+    }
+
+    // new streams are refused
+    tester.send_get(3, "/echo");
+    tester.recv_rst_frame_check(3, ErrorCode::RefusedStream);
+
+    // stream in flight is completed
+    tester.send_data(1, b"abcd", true);
+    assert_eq!(&b"abcd"[..], &tester.recv_frame_data_check(1, true)[..]);
+
+    let goaway = tester.recv_goaway_frame();
+    assert_eq!(ErrorCode::NoError, goaway.error_code());
+    assert_eq!(1, goaway.last_stream_id());
+
+    tester.recv_eof();
+
+    rt.block_on(shutdown);
 }
 
 #[test]
-fn increase_frame_size() {
+fn rapid_reset() {
     init_logger();
 
     let server = ServerTest::new();
@@ -280,18 +525,44 @@ fn increase_frame_size() {
     tester.send_preface();
     tester.settings_xchg();
 
-    let mut frame = SettingsFrame::new();
-    frame.settings.push(HttpSetting::MaxFrameSize(20000));
-    tester.send_recv_settings(frame);
+    let mut headers = Headers::new();
+    headers.add(":method", "POST");
+    headers.add(":path", "/echo");
+    headers.add(":scheme", "http");
 
-    tester.send_get(1, "/blocks/30000/1");
-    assert_eq!(200, tester.recv_frame_headers_check(1, false).status());
-    assert_eq!(20000, tester.recv_frame_data_check(1, false).len());
-    assert_eq!(10000, tester.recv_frame_data_tail(1).len());
+    // normal cancellation
+    for i in 0..5 {
+        let stream_id = 1 + i * 2;
+        tester.send_headers(stream_id, headers.clone(), false);
+        tester.send_rst(stream_id, ErrorCode::Cancel);
+    }
+    tester.send_get(11, "/echo");
+    loop {
+        match tester.recv_frame() {
+            HttpFrame::Goaway(f) => panic!("unexpected GOAWAY: {:?}", f),
+            HttpFrame::Data(ref f) if f.stream_id == 11 && f.is_end_of_stream() => break,
+            _ => {}
+        }
+    }
+
+    for i in 0..300 {
+        let stream_id = 13 + i * 2;
+        tester.send_headers(stream_id, headers.clone(), false);
+        tester.send_rst(stream_id, ErrorCode::Cancel);
+    }
+
+    loop {
+        if let HttpFrame::Goaway(f) = tester.recv_frame() {
+            assert_eq!(ErrorCode::EnhanceYourCalm, f.error_code());
+            break;
+        }
+    }
+
+    tester.recv_eof();
 }
 
 #[test]
-fn exceed_window_size() {
+fn settings_length_not_multiple_of_six() {
     init_logger();
 
     let server = ServerTest::new();
@@ -300,123 +571,91 @@ fn exceed_window_size() {
     tester.send_preface();
     tester.settings_xchg();
 
-    let mut frame = SettingsFrame::new();
-    frame.settings.push(HttpSetting::MaxFrameSize(
-        tester.peer_settings.initial_window_size + 5,
+    // SETTINGS frame with 5 bytes payload
+    tester.send_frame(RawFrame::from(
+        &[0, 0, 5, 4, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0][..],
     ));
-    tester.send_recv_settings(frame);
-
-    let data = Vec::from_iter((0..tester.peer_settings.initial_window_size + 3).map(|_| 2));
 
-    // Deliberately set wrong out_windows_size so `send_data` wouldn't fail.
-    tester.out_window_size.try_add(10000000).unwrap();
-    tester.send_data(1, &data, false);
+    tester.recv_goaway_frame_check(ErrorCode::FrameSizeError);
     tester.recv_eof();
+}
+
+#[test]
+fn data_on_stream_zero() {
+    init_logger();
+
+    let server = ServerTest::new();
 
     let mut tester = HttpConnTester::connect(server.port);
     tester.send_preface();
     tester.settings_xchg();
 
-    assert_eq!(200, tester.get(1, "/echo").headers.status());
+    tester.send_frame(RawFrame::from(&[0, 0, 3, 0, 0, 0, 0, 0, 0, 10, 20, 30][..]));
+
+    tester.recv_goaway_frame_check(ErrorCode::ProtocolError);
+    tester.recv_eof();
 }
 
 #[test]
-fn stream_window_gt_conn_window() {
+fn settings_on_non_zero_stream() {
     init_logger();
 
-    let mut rt = Runtime::new().unwrap();
-
     let server = ServerTest::new();
 
     let mut tester = HttpConnTester::connect(server.port);
     tester.send_preface();
     tester.settings_xchg();
 
-    let w = DEFAULT_SETTINGS.initial_window_size;
-
-    // May need to be changed if server defaults are changed
-    assert_eq!(w as i32, tester.in_window_size.size());
-
-    tester.send_recv_settings(SettingsFrame::from_settings(vec![
-        HttpSetting::InitialWindowSize(w * 2),
-        HttpSetting::MaxFrameSize(w * 10),
-    ]));
+    tester.send_frame(RawFrame::from(&[0, 0, 0, 4, 0, 0, 0, 0, 1][..]));
 
-    // Now new stream window is gt than conn window
+    tester.recv_goaway_frame_check(ErrorCode::ProtocolError);
+    tester.recv_eof();
+}
 
-    let w = tester.peer_settings.initial_window_size;
-    tester.send_get(1, &format!("/blocks/{}/{}", w, 2));
+#[test]
+fn even_client_stream_id() {
+    init_logger();
 
-    assert_eq!(200, tester.recv_frame_headers_check(1, false).status());
-    assert_eq!(w as usize, tester.recv_frame_data_check(1, false).len());
+    let server = ServerTest::new();
 
-    let server_sn = rt.block_on(server.server.dump_state()).expect("state");
-    assert_eq!(0, server_sn.single_conn().1.out_window_size);
-    assert_eq!(
-        w as i32,
-        server_sn.single_conn().1.single_stream().1.out_window_size
-    );
-    assert_eq!(
-        w as isize,
-        server_sn
-            .single_conn()
-            .1
-            .single_stream()
-            .1
-            .pump_out_window_size
-    );
-    assert_eq!(
-        0,
-        server_sn
-            .single_conn()
-            .1
-            .single_stream()
-            .1
-            .queued_out_data_size
-    );
+    let mut tester = HttpConnTester::connect(server.port);
+    tester.send_preface();
+    tester.settings_xchg();
 
-    tester.send_window_update_conn(w + 1);
-    tester.send_window_update_stream(1, w + 1);
+    tester.send_get(2, "/");
 
-    assert_eq!(w as usize, tester.recv_frame_data_tail(1).len());
+    tester.recv_goaway_frame_check(ErrorCode::ProtocolError);
+    tester.recv_eof();
 }
 
 #[test]
-fn do_not_poll_when_not_enough_window() {
+fn decreasing_client_stream_id() {
     init_logger();
 
-    let polls = Arc::new(AtomicUsize::new(0));
-    let polls_copy = polls.clone();
+    let server = ServerTest::new();
 
-    let server = ServerOneConn::new_fn(0, move |_, _, mut resp| {
-        struct StreamImpl {
-            polls: Arc<AtomicUsize>,
-        }
+    let mut tester = HttpConnTester::connect(server.port);
+    tester.send_preface();
+    tester.settings_xchg();
 
-        impl Stream for StreamImpl {
-            type Item = httpbis::Result<Bytes>;
+    assert_eq!(404, tester.get(5, "/").headers.status());
 
-            fn poll_next(
-                self: Pin<&mut Self>,
-                _cx: &mut Context<'_>,
-            ) -> Poll<Option<httpbis::Result<Bytes>>> {
-                let polls = self.polls.fetch_add(1, Ordering::SeqCst);
-                Poll::Ready(match polls {
-                    0 | 1 | 2 => Some(Ok(Bytes::from(vec![
-                        polls as u8;
-                        DEFAULT_SETTINGS.initial_window_size
-                            as usize
-                    ]))),
-                    _ => None,
-                })
-            }
-        }
+    tester.send_get(3, "/");
 
-        resp.send_headers(Headers::ok_200())?;
-        resp.pull_bytes_from_stream(StreamImpl {
-            polls: polls_copy.clone(),
-        })?;
+    tester.recv_goaway_frame_check(ErrorCode::ProtocolError);
+    tester.recv_eof();
+}
 
+#[test]
+fn data_after_end_stream_half_closed() {
+    init_logger();
+
+    let responses = Arc::new(Mutex::new(Vec::new()));
+    let responses_copy = responses.clone();
+    let server = ServerOneConn::new_fn(0, move |_, _req, mut resp| {
+        resp.send_headers(Headers::ok_200())?;
+        // Keep response open
+        responses_copy.lock().unwrap().push(resp);
         Ok(())
     });
 
@@ -424,26 +663,251 @@ fn do_not_poll_when_not_enough_window() {
     tester.send_preface();
     tester.settings_xchg();
 
-    tester.send_recv_settings(SettingsFrame::from_settings(vec![
-        HttpSetting::MaxFrameSize(DEFAULT_SETTINGS.initial_window_size * 5),
-    ]));
+    tester.send_get(1, "/");
+    tester.recv_frame_headers_check(1, false);
 
-    tester.send_get(1, "/fgfg");
-    assert_eq!(200, tester.recv_frame_headers_check(1, false).status());
-    assert_eq!(
-        DEFAULT_SETTINGS.initial_window_size as usize,
-        tester.recv_frame_data_check(1, false).len()
-    );
+    tester.send_data(1, b"abc", false);
+    tester.recv_rst_frame_check(1, ErrorCode::StreamClosed);
 
-    assert_eq!(1, polls.load(Ordering::SeqCst));
+    // Connection is still usable
+    tester.send_get(3, "/");
+    tester.recv_frame_headers_check(3, false);
+}
 
-    tester.send_window_update_conn(DEFAULT_SETTINGS.initial_window_size);
-    tester.send_window_update_stream(1, DEFAULT_SETTINGS.initial_window_size);
+#[test]
+fn data_after_end_stream_closed() {
+    init_logger();
 
-    assert_eq!(
-        DEFAULT_SETTINGS.initial_window_size as usize,
-        tester.recv_frame_data_check(1, false).len()
-    );
+    let server = ServerTest::new();
+
+    let mut tester = HttpConnTester::connect(server.port);
+    tester.send_preface();
+    tester.settings_xchg();
+
+    assert_eq!(404, tester.get(1, "/").headers.status());
+
+    tester.send_data(1, b"abc", false);
+    tester.recv_goaway_frame_check(ErrorCode::StreamClosed);
+    tester.recv_eof();
+}
+
+#[test]
+fn exceed_max_frame_size() {
+    init_logger();
+
+    let server = ServerTest::new();
+
+    let mut tester = HttpConnTester::connect(server.port);
+    tester.send_preface();
+    tester.settings_xchg();
+
+    tester.send_data(1, &[0; 17_000], false);
+
+    tester.recv_goaway_frame_check(ErrorCode::FrameSizeError);
+    tester.recv_eof();
+
+    let mut tester = HttpConnTester::connect(server.port);
+    tester.send_preface();
+    tester.settings_xchg();
+
+    assert_eq!(200, tester.get(1, "/echo").headers.status());
+}
+
+#[test]
+fn frame_header_exceeds_max_frame_size() {
+    init_logger();
+
+    let server = ServerTest::new();
+
+    let mut tester = HttpConnTester::connect(server.port);
+    tester.send_preface();
+    tester.settings_xchg();
+
+    // DATA frame header with length 2^24-1, payload is never sent
+    tester.send_frame(RawFrame::from(&[0xff, 0xff, 0xff, 0, 0, 0, 0, 0, 1][..]));
+
+    tester.recv_goaway_frame_check(ErrorCode::FrameSizeError);
+    tester.recv_eof();
+}
+
+#[test]
+fn increase_frame_size() {
+    init_logger();
+
+    let server = ServerTest::new();
+
+    let mut tester = HttpConnTester::connect(server.port);
+    tester.send_preface();
+    tester.settings_xchg();
+
+    let mut frame = SettingsFrame::new();
+    frame.settings.push(HttpSetting::MaxFrameSize(20000));
+    tester.send_recv_settings(frame);
+
+    tester.send_get(1, "/blocks/30000/1");
+    assert_eq!(200, tester.recv_frame_headers_check(1, false).status());
+    assert_eq!(20000, tester.recv_frame_data_check(1, false).len());
+    assert_eq!(10000, tester.recv_frame_data_tail(1).len());
+}
+
+#[test]
+fn exceed_window_size() {
+    init_logger();
+
+    let server = ServerTest::new();
+
+    let mut tester = HttpConnTester::connect(server.port);
+    tester.send_preface();
+    tester.settings_xchg();
+
+    let data = Vec::from_iter((0..tester.peer_settings.initial_window_size + 3).map(|_| 2));
+
+    // Deliberately set wrong out_windows_size so `send_data` wouldn't fail.
+    tester.out_window_size.try_add(10000000).unwrap();
+    let mut headers = Headers::new_post("/blocks/1/1");
+    headers.add(":scheme", "http");
+    tester.send_headers(1, headers, false);
+    // Response is complete before window is exceeded
+    tester.recv_frame_headers_check(1, false);
+    assert_eq!(1, tester.recv_frame_data_tail(1).len());
+    // Frames within server max frame size
+    for chunk in data.chunks(tester.peer_settings.max_frame_size as usize) {
+        tester.send_data(1, chunk, false);
+    }
+    tester.recv_goaway_frame_check(ErrorCode::FlowControlError);
+    tester.recv_eof();
+
+    let mut tester = HttpConnTester::connect(server.port);
+    tester.send_preface();
+    tester.settings_xchg();
+
+    assert_eq!(200, tester.get(1, "/echo").headers.status());
+}
+
+#[test]
+fn stream_window_gt_conn_window() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let server = ServerTest::new();
+
+    let mut tester = HttpConnTester::connect(server.port);
+    tester.send_preface();
+    tester.settings_xchg();
+
+    let w = DEFAULT_SETTINGS.initial_window_size;
+
+    // May need to be changed if server defaults are changed
+    assert_eq!(w as i32, tester.in_window_size.size());
+
+    tester.send_recv_settings(SettingsFrame::from_settings(vec![
+        HttpSetting::InitialWindowSize(w * 2),
+        HttpSetting::MaxFrameSize(w * 10),
+    ]));
+
+    // Now new stream window is gt than conn window
+
+    let w = tester.peer_settings.initial_window_size;
+    tester.send_get(1, &format!("/blocks/{}/{}", w, 2));
+
+    assert_eq!(200, tester.recv_frame_headers_check(1, false).status());
+    assert_eq!(w as usize, tester.recv_frame_data_check(1, false).len());
+
+    let server_sn = rt.block_on(server.server.dump_state()).expect("state");
+    assert_eq!(0, server_sn.single_conn().1.out_window_size);
+    assert_eq!(
+        w as i32,
+        server_sn.single_conn().1.single_stream().1.out_window_size
+    );
+    assert_eq!(
+        w as isize,
+        server_sn
+            .single_conn()
+            .1
+            .single_stream()
+            .1
+            .pump_out_window_size
+    );
+    assert_eq!(
+        0,
+        server_sn
+            .single_conn()
+            .1
+            .single_stream()
+            .1
+            .queued_out_data_size
+    );
+
+    tester.send_window_update_conn(w + 1);
+    tester.send_window_update_stream(1, w + 1);
+
+    assert_eq!(w as usize, tester.recv_frame_data_tail(1).len());
+}
+
+#[test]
+fn do_not_poll_when_not_enough_window() {
+    init_logger();
+
+    let polls = Arc::new(AtomicUsize::new(0));
+    let polls_copy = polls.clone();
+
+    let server = ServerOneConn::new_fn(0, move |_, _, mut resp| {
+        struct StreamImpl {
+            polls: Arc<AtomicUsize>,
+        }
+
+        impl Stream for StreamImpl {
+            type Item = httpbis::Result<Bytes>;
+
+            fn poll_next(
+                self: Pin<&mut Self>,
+                _cx: &mut Context<'_>,
+            ) -> Poll<Option<httpbis::Result<Bytes>>> {
+                let polls = self.polls.fetch_add(1, Ordering::SeqCst);
+                Poll::Ready(match polls {
+                    0..=2 => Some(Ok(Bytes::from(vec![
+                        polls as u8;
+                        DEFAULT_SETTINGS.initial_window_size
+                            as usize
+                    ]))),
+                    _ => None,
+                })
+            }
+        }
+
+        resp.send_headers(Headers::ok_200())?;
+        resp.pull_bytes_from_stream(StreamImpl {
+            polls: polls_copy.clone(),
+        })?;
+
+        Ok(())
+    });
+
+    let mut tester = HttpConnTester::connect(server.port());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    tester.send_recv_settings(SettingsFrame::from_settings(vec![
+        HttpSetting::MaxFrameSize(DEFAULT_SETTINGS.initial_window_size * 5),
+    ]));
+
+    tester.send_get(1, "/fgfg");
+    assert_eq!(200, tester.recv_frame_headers_check(1, false).status());
+    assert_eq!(
+        DEFAULT_SETTINGS.initial_window_size as usize,
+        tester.recv_frame_data_check(1, false).len()
+    );
+
+    assert_eq!(1, polls.load(Ordering::SeqCst));
+
+    tester.send_window_update_conn(DEFAULT_SETTINGS.initial_window_size);
+    tester.send_window_update_stream(1, DEFAULT_SETTINGS.initial_window_size);
+
+    assert_eq!(
+        DEFAULT_SETTINGS.initial_window_size as usize,
+        tester.recv_frame_data_check(1, false).len()
+    );
 
     assert_eq!(2, polls.load(Ordering::SeqCst));
 }
@@ -502,6 +966,64 @@ pub fn http_1_1() {
     );
 }
 
+#[test]
+pub fn http_1_1_long_request_line() {
+    init_logger();
+
+    let server = ServerTest::new();
+
+    let mut tcp_stream = TcpStream::connect((BIND_HOST, server.port)).expect("connect");
+
+    let path = "/a".repeat(100);
+    tcp_stream
+        .write_all(format!("GET {} HTTP/1.1\n", path).as_bytes())
+        .expect("write");
+
+    let mut read = Vec::new();
+    tcp_stream.read_to_end(&mut read).expect("read");
+    assert!(
+        &read.starts_with(b"HTTP/1.1 500 Internal Server Error\r\n"),
+        "{:?}",
+        BsDebug(&read)
+    );
+}
+
+#[test]
+pub fn wrong_preface() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+    let mut listener = rt
+        .block_on(tokio::net::TcpListener::bind((BIND_HOST, 0)))
+        .expect("bind");
+    let port = listener.local_addr().unwrap().port();
+
+    let mut tcp_stream = TcpStream::connect((BIND_HOST, port)).expect("connect");
+    tcp_stream.write_all(b"PRX").expect("write");
+
+    let (socket, peer_addr) = rt.block_on(listener.accept()).expect("accept");
+    let (_conn, future) = ServerConn::new_plain_single_thread_fn(
+        rt.handle(),
+        socket,
+        peer_addr,
+        Default::default(),
+        |_, _, _| Ok(()),
+    );
+
+    // Connection is closed without waiting for the whole preface
+    match rt.block_on(future) {
+        Err(e) => match e.error {
+            Error::PrefaceError(e) => assert_eq!(b"PRX", &e.received[..]),
+            e => panic!("expecting PrefaceError, got: {:?}", e),
+        },
+        Ok(close) => panic!("expecting error, got: {}", close),
+    }
+
+    let mut read = Vec::new();
+    tcp_stream.read_to_end(&mut read).expect("read");
+    assert!(read.is_empty(), "{:?}", BsDebug(&read));
+}
+
 #[cfg(unix)]
 #[test]
 pub fn http_1_1_unix() {
@@ -524,16 +1046,247 @@ pub fn http_1_1_unix() {
     );
 }
 
+/// Plain server with `/hello` handler and custom h2c options.
+fn h2c_server(allow_h2c_upgrade: bool, allow_h2c_prior_knowledge: bool) -> Server {
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server.conf.allow_h2c_upgrade = Some(allow_h2c_upgrade);
+    server.conf.allow_h2c_prior_knowledge = Some(allow_h2c_prior_knowledge);
+    server.service.set_service_fn("/hello", |_, _, mut resp| {
+        resp.send_found_200_plain_text("hello world")?;
+        Ok(())
+    });
+    server.build().expect("server")
+}
+
 #[test]
-fn external_event_loop() {
+pub fn h2c_upgrade() {
     init_logger();
 
-    let mut rt = Runtime::new().unwrap();
-
-    let (tx, rx) = mpsc::channel();
-    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server.conf.allow_h2c_upgrade = Some(true);
+    server.service.set_service_fn("/hello", |_, req, mut resp| {
+        assert_eq!(Some("localhost"), req.headers.get_opt(":authority"));
+        resp.send_found_200_plain_text("hello world")?;
+        Ok(())
+    });
+    let server = server.build().expect("server");
+    let port = server.local_addr().port().unwrap();
+
+    let mut tcp_stream = TcpStream::connect((BIND_HOST, port)).expect("connect");
+
+    // Settings is `SETTINGS_INITIAL_WINDOW_SIZE` = 3
+    tcp_stream
+        .write_all(
+            b"GET /hello HTTP/1.1\r\n\
+            Host: localhost\r\n\
+            Connection: Upgrade, HTTP2-Settings\r\n\
+            Upgrade: h2c\r\n\
+            HTTP2-Settings: AAQAAAAD\r\n\
+            \r\n",
+        )
+        .expect("write");
 
-    let t = thread::Builder::new()
+    let mut read = Vec::new();
+    while !read.ends_with(b"\r\n\r\n") {
+        let mut buf = [0];
+        tcp_stream.read_exact(&mut buf).expect("read");
+        read.push(buf[0]);
+    }
+    assert!(
+        read.starts_with(b"HTTP/1.1 101 Switching Protocols\r\n"),
+        "{:?}",
+        BsDebug(&read)
+    );
+
+    let mut tester = HttpConnTester::with_tcp(tcp_stream);
+    tester.send_preface();
+    tester.recv_frame_settings_set();
+
+    let headers = tester.recv_frame_headers_check(1, false);
+    assert_eq!("200", headers.get(":status"));
+    assert_eq!(b"hel", &tester.recv_frame_data_check(1, false)[..]);
+
+    tester.send_settings(SettingsFrame::new());
+    tester.send_frame(SettingsFrame::new_ack());
+    tester.recv_frame_settings_ack();
+
+    tester.send_window_update_stream(1, 100);
+    assert_eq!(b"lo world", &tester.recv_frame_data_tail(1)[..]);
+}
+
+#[test]
+pub fn h2c_prior_knowledge() {
+    init_logger();
+
+    let server = h2c_server(true, true);
+
+    let mut tester = HttpConnTester::connect(server.local_addr().port().unwrap());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    let resp = tester.get(1, "/hello");
+    assert_eq!(200, resp.headers.status());
+    assert_eq!(&b"hello world"[..], resp.body.get_bytes());
+}
+
+#[test]
+pub fn h2c_prior_knowledge_not_allowed() {
+    init_logger();
+
+    let server = h2c_server(true, false);
+
+    let mut tester = HttpConnTester::connect(server.local_addr().port().unwrap());
+    tester.send_preface();
+    tester.recv_eof();
+}
+
+/// Plain server with keep alive timeouts.
+fn keep_alive_server(timeout: Duration, ping_timeout: Option<Duration>) -> Server {
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server.conf.common.keep_alive_timeout = Some(timeout);
+    server.conf.common.keep_alive_ping_timeout = ping_timeout;
+    server.service.set_service_fn("/", |_, _, mut resp| {
+        resp.send_found_200_plain_text("alive")?;
+        Ok(())
+    });
+    server.build().expect("server")
+}
+
+#[test]
+fn keep_alive_timeout() {
+    init_logger();
+
+    let server = keep_alive_server(Duration::from_millis(100), None);
+
+    let mut tester = HttpConnTester::connect(server.local_addr().port().unwrap());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    tester.recv_eof();
+}
+
+#[test]
+fn keep_alive_ping() {
+    init_logger();
+
+    let server = keep_alive_server(Duration::from_millis(100), Some(Duration::from_millis(100)));
+
+    let mut tester = HttpConnTester::connect(server.local_addr().port().unwrap());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    // PING ACK keeps connection alive
+    for _ in 0..2 {
+        match tester.recv_frame() {
+            HttpFrame::Ping(ping) => {
+                assert!(!ping.is_ack());
+                tester.send_frame(PingFrame::new_ack(ping.opaque_data()));
+            }
+            f => panic!("expecting PING, got: {:?}", f),
+        }
+    }
+
+    let resp = tester.get(1, "/");
+    assert_eq!(200, resp.headers.status());
+
+    match tester.recv_frame() {
+        HttpFrame::Ping(ping) => assert!(!ping.is_ack()),
+        f => panic!("expecting PING, got: {:?}", f),
+    }
+    tester.recv_eof();
+}
+
+fn data_coalesce_server(
+    delay: Duration,
+    f: impl Fn(ServerResponse) -> httpbis::Result<()> + Send + Sync + 'static,
+) -> Server {
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server.conf.data_coalesce_max_bytes = Some(4096);
+    server.conf.data_coalesce_delay = Some(delay);
+    server.service.set_service_fn("/", move |_, _, mut resp| {
+        resp.send_headers(Headers::ok_200())?;
+        f(resp)
+    });
+    server.build().expect("server")
+}
+
+#[test]
+fn data_coalesce() {
+    init_logger();
+
+    let server = data_coalesce_server(Duration::from_millis(1), |mut resp| {
+        for _ in 0..1000 {
+            resp.send_data(Bytes::from_static(b"0123456789abcdef"))?;
+        }
+        resp.send_data_end_of_stream(Bytes::new())?;
+        Ok(())
+    });
+
+    let mut tester = HttpConnTester::connect(server.local_addr().port().unwrap());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    tester.send_get(1, "/");
+    tester.recv_frame_headers_check(1, false);
+
+    let mut frames = 0;
+    let mut body = Vec::new();
+    loop {
+        let data = tester.recv_frame_data();
+        assert_eq!(1, data.stream_id);
+        assert!(data.data.len() <= 4096);
+        body.extend_from_slice(&data.data);
+        frames += 1;
+        if data.is_end_of_stream() {
+            break;
+        }
+    }
+    assert_eq!(16000, body.len());
+    assert!(frames <= 20, "{} DATA frames", frames);
+}
+
+#[test]
+fn data_coalesce_flush() {
+    init_logger();
+
+    let held = Arc::new(Mutex::new(None));
+    let held_copy = held.clone();
+    let server = data_coalesce_server(Duration::from_secs(3600), move |mut resp| {
+        resp.send_data(Bytes::from_static(b"ab"))?;
+        resp.flush()?;
+        *held_copy.lock().unwrap() = Some(resp);
+        Ok(())
+    });
+
+    let mut tester = HttpConnTester::connect(server.local_addr().port().unwrap());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    tester.send_get(1, "/");
+    tester.recv_frame_headers_check(1, false);
+    assert_eq!(b"ab", &tester.recv_frame_data_check(1, false)[..]);
+
+    let mut resp: ServerResponse = held.lock().unwrap().take().expect("resp");
+    resp.send_data(Bytes::from_static(b"cd")).unwrap();
+    resp.send_data_end_of_stream(Bytes::from_static(b"ef"))
+        .unwrap();
+    assert_eq!(b"cdef", &tester.recv_frame_data_check(1, true)[..]);
+}
+
+#[test]
+fn external_event_loop() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let (tx, rx) = mpsc::channel();
+    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+
+    let t = thread::Builder::new()
         .name("servers".to_owned())
         .spawn(move || {
             let mut core = Runtime::new().expect("Runtime::new");
@@ -586,3 +1339,902 @@ fn external_event_loop() {
 
     info!("last line of test");
 }
+
+#[test]
+fn max_connections() {
+    init_logger();
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server.conf.max_connections = Some(1);
+    server.service.set_service_fn("/", |_, _, mut resp| {
+        resp.send_found_200_plain_text("hi")?;
+        Ok(())
+    });
+    let server = server.build().expect("server");
+    let port = server.local_addr().port().unwrap();
+
+    let mut tester = HttpConnTester::connect(port);
+    tester.send_preface();
+    tester.settings_xchg();
+    let connection_count = || {
+        executor::block_on(server.stats())
+            .expect("stats")
+            .connection_count
+    };
+    assert_eq!(1, connection_count());
+
+    let mut rejected = HttpConnTester::connect(port);
+    rejected.recv_frame_settings_set();
+    rejected.recv_goaway_frame_check(ErrorCode::EnhanceYourCalm);
+    rejected.recv_eof();
+
+    // First connection is not affected
+    assert_eq!(200, tester.get(1, "/").headers.status());
+
+    drop(tester);
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while connection_count() != 0 {
+        assert!(Instant::now() < deadline, "connection is not closed");
+        thread::sleep(Duration::from_millis(1));
+    }
+
+    let mut tester = HttpConnTester::connect(port);
+    tester.send_preface();
+    tester.settings_xchg();
+    assert_eq!(200, tester.get(1, "/").headers.status());
+}
+
+#[test]
+fn stream_recv_buffer_limit() {
+    init_logger();
+
+    let bodies = Arc::new(Mutex::new(Vec::new()));
+    let bodies_copy = bodies.clone();
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server.conf.stream_recv_buffer_limit = Some(10000);
+    server.service.set_service_fn("/", move |_, req, mut resp| {
+        resp.send_headers(Headers::ok_200())?;
+        // Request body is read by the test
+        bodies_copy.lock().unwrap().push((req.make_stream(), resp));
+        Ok(())
+    });
+    let server = server.build().expect("server");
+
+    let mut tester = HttpConnTester::connect(server.local_addr().port().unwrap());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    let mut headers = Headers::new_post("/");
+    headers.add(":scheme", "http");
+    tester.send_headers(1, headers, false);
+    tester.recv_frame_headers_check(1, false);
+
+    for _ in 0..4 {
+        tester.send_data(1, &[0; 16_000], false);
+    }
+
+    let (mut body, _resp) = bodies.lock().unwrap().pop().expect("request");
+    let mut read = |len: usize| {
+        let mut read = 0;
+        while read < len {
+            match executor::block_on(body.next()) {
+                Some(Ok(DataOrTrailers::Data(data, _))) => read += data.len(),
+                _ => panic!("expecting data"),
+            }
+        }
+    };
+
+    // Stream window would be increased after reading half of it,
+    // but last frame is still buffered
+    read(48_000);
+    tester.send_frame(PingFrame::new());
+    loop {
+        match tester.fn_recv_frame_no_check_ack() {
+            HttpFrame::Ping(f) if f.is_ack() => break,
+            HttpFrame::WindowUpdate(f) => assert_eq!(0, f.stream_id),
+            f => panic!("unexpected frame: {:?}", f),
+        }
+    }
+
+    // Buffer is drained
+    read(16_000);
+    loop {
+        match tester.fn_recv_frame_no_check_ack() {
+            HttpFrame::WindowUpdate(f) if f.stream_id == 0 => {}
+            HttpFrame::WindowUpdate(f) => {
+                assert_eq!(1, f.stream_id);
+                break;
+            }
+            f => panic!("unexpected frame: {:?}", f),
+        }
+    }
+}
+
+#[test]
+fn conn_window_follows_initial_window_size() {
+    init_logger();
+
+    let bodies = Arc::new(Mutex::new(Vec::new()));
+    let bodies_copy = bodies.clone();
+
+    let mut settings = DEFAULT_SETTINGS;
+    settings.initial_window_size = 1 << 20;
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server.conf.common.initial_settings = Some(settings);
+    server.service.set_service_fn("/", move |_, req, mut resp| {
+        resp.send_headers(Headers::ok_200())?;
+        bodies_copy.lock().unwrap().push((req.make_stream(), resp));
+        Ok(())
+    });
+    let server = server.build().expect("server");
+
+    let mut tester = HttpConnTester::connect(server.local_addr().port().unwrap());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    let mut headers = Headers::new_post("/");
+    headers.add(":scheme", "http");
+    tester.send_headers(1, headers, false);
+    tester.recv_frame_headers_check(1, false);
+
+    tester.send_data(1, &[0; 10_000], false);
+    tester.send_frame(PingFrame::new());
+
+    // Connection window is increased up to stream initial window
+    let mut increment = 0;
+    loop {
+        match tester.fn_recv_frame_no_check_ack() {
+            HttpFrame::Ping(f) if f.is_ack() => break,
+            HttpFrame::WindowUpdate(f) if f.stream_id == 0 => increment += f.increment,
+            HttpFrame::WindowUpdate(_) => {}
+            f => panic!("unexpected frame: {:?}", f),
+        }
+    }
+    assert_eq!(
+        (1 << 20) - DEFAULT_SETTINGS.initial_window_size + 10_000,
+        increment
+    );
+}
+
+#[test]
+fn conn_window_auto_tuning() {
+    init_logger();
+
+    let bodies = Arc::new(Mutex::new(Vec::new()));
+    let bodies_copy = bodies.clone();
+
+    let mut settings = DEFAULT_SETTINGS;
+    settings.initial_window_size = 1 << 20;
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server.conf.common.initial_settings = Some(settings);
+    server.conf.common.conn_window_auto_tuning_max = Some(1 << 22);
+    server.service.set_service_fn("/", move |_, req, mut resp| {
+        resp.send_headers(Headers::ok_200())?;
+        bodies_copy.lock().unwrap().push((req.make_stream(), resp));
+        Ok(())
+    });
+    let server = server.build().expect("server");
+
+    let mut tester = HttpConnTester::connect(server.local_addr().port().unwrap());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    for &stream_id in &[1, 3] {
+        let mut headers = Headers::new_post("/");
+        headers.add(":scheme", "http");
+        tester.send_headers(stream_id, headers, false);
+        tester.recv_frame_headers_check(stream_id, false);
+    }
+
+    // Whole window is sent without delay
+    for _ in 0..40 {
+        for &stream_id in &[1, 3] {
+            while tester.out_window_size.size() < 16_000 {
+                if let Some(f) = tester.recv_special_frame_process_special() {
+                    panic!("unexpected frame: {:?}", f);
+                }
+            }
+            tester.send_data(stream_id, &[0; 16_000], false);
+        }
+    }
+    tester.send_frame(PingFrame::new());
+
+    loop {
+        match tester.recv_special_frame_process_special() {
+            None => {}
+            Some(HttpFrame::Ping(f)) if f.is_ack() => break,
+            Some(f) => panic!("unexpected frame: {:?}", f),
+        }
+    }
+
+    // Connection window outgrew configured initial window
+    let window = tester.out_window_size.size();
+    assert!(window > 1 << 20, "{}", window);
+}
+
+#[test]
+fn conn_window_update_coalesced() {
+    init_logger();
+
+    let bodies = Arc::new(Mutex::new(Vec::new()));
+    let bodies_copy = bodies.clone();
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server.service.set_service_fn("/", move |_, req, mut resp| {
+        resp.send_headers(Headers::ok_200())?;
+        bodies_copy.lock().unwrap().push((req.make_stream(), resp));
+        Ok(())
+    });
+    let server = server.build().expect("server");
+
+    let mut tester = HttpConnTester::connect(server.local_addr().port().unwrap());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    let mut headers = Headers::new_post("/");
+    headers.add(":scheme", "http");
+    tester.send_headers(1, headers, false);
+    tester.recv_frame_headers_check(1, false);
+
+    for _ in 0..40 {
+        tester.send_data(1, &[0; 1000], false);
+    }
+    tester.send_frame(PingFrame::new());
+
+    // Single update once half of the window is consumed
+    let mut increments = Vec::new();
+    loop {
+        match tester.fn_recv_frame_no_check_ack() {
+            HttpFrame::Ping(f) if f.is_ack() => break,
+            HttpFrame::WindowUpdate(f) if f.stream_id == 0 => increments.push(f.increment),
+            f => panic!("unexpected frame: {:?}", f),
+        }
+    }
+    assert_eq!(vec![33_000], increments);
+}
+
+#[test]
+fn origin_set() {
+    init_logger();
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server.service.set_service_fn("/", |_, _, mut resp| {
+        resp.send_found_200_plain_text("hi")?;
+        Ok(())
+    });
+    let server = server.build().expect("server");
+    let port = server.local_addr().port().unwrap();
+
+    let mut tester = HttpConnTester::connect(port);
+    tester.send_preface();
+    tester.settings_xchg();
+
+    server
+        .send_origin_set(&["https://a.example"])
+        .expect("origin");
+
+    let origin = [
+        0, 0, 19, 0xc, 0, 0, 0, 0, 0, 0, 17, b'h', b't', b't', b'p', b's', b':', b'/', b'/', b'a',
+        b'.', b'e', b'x', b'a', b'm', b'p', b'l', b'e',
+    ];
+    assert_eq!(&origin[..], &tester.recv_raw_frame().raw_content[..]);
+
+    // Sent to new connections after SETTINGS
+    let mut tester = HttpConnTester::connect(port);
+    tester.send_preface();
+    tester.recv_frame_settings_set();
+    assert_eq!(&origin[..], &tester.recv_raw_frame().raw_content[..]);
+
+    assert!(server.send_origin_set(&["https://\u{e9}.example"]).is_err());
+}
+
+#[test]
+fn response_origin_set() {
+    init_logger();
+
+    let server = ServerOneConn::new_fn(0, |_, _, mut resp| {
+        assert!(resp.send_origin_set(&["https://\u{e9}.example"]).is_err());
+        resp.send_origin_set(&["https://a.example"])?;
+        resp.send_found_200_plain_text("hi")?;
+        Ok(())
+    });
+
+    let mut tester = HttpConnTester::connect(server.port());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    tester.send_get(1, "/");
+    let origin = [
+        0, 0, 19, 0xc, 0, 0, 0, 0, 0, 0, 17, b'h', b't', b't', b'p', b's', b':', b'/', b'/', b'a',
+        b'.', b'e', b'x', b'a', b'm', b'p', b'l', b'e',
+    ];
+    assert_eq!(&origin[..], &tester.recv_raw_frame().raw_content[..]);
+    assert_eq!(200, tester.recv_frame_headers_check(1, false).status());
+    assert_eq!(&b"hi"[..], &tester.recv_frame_data_check(1, true)[..]);
+}
+
+#[test]
+fn send_raw_frame() {
+    init_logger();
+
+    let server = ServerOneConn::new_fn(0, |_, _, mut resp| {
+        match resp.send_raw_frame(0x0, 0, 1, b"data") {
+            Err(Error::InvalidFrame(..)) => {}
+            r => panic!("expecting InvalidFrame, got: {:?}", r),
+        }
+        resp.send_raw_frame(0xbc, 0x1, 0, b"xy")?;
+        resp.send_found_200_plain_text("hi")?;
+        Ok(())
+    });
+
+    let mut tester = HttpConnTester::connect(server.port());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    tester.send_get(1, "/");
+    assert_eq!(
+        &[0, 0, 2, 0xbc, 0x1, 0, 0, 0, 0, b'x', b'y'][..],
+        &tester.recv_raw_frame().raw_content[..]
+    );
+    assert_eq!(200, tester.recv_frame_headers_check(1, false).status());
+    assert_eq!(&b"hi"[..], &tester.recv_frame_data_check(1, true)[..]);
+}
+
+#[test]
+fn request_stream_id() {
+    init_logger();
+
+    let stream_ids = Arc::new(Mutex::new(Vec::new()));
+    let stream_ids_copy = stream_ids.clone();
+
+    let server = ServerOneConn::new_fn(0, move |_, req, mut resp| {
+        assert_eq!(req.stream_id(), resp.stream_id());
+        stream_ids_copy.lock().unwrap().push(req.stream_id());
+        resp.send_found_200_plain_text("hi")?;
+        Ok(())
+    });
+
+    let mut tester = HttpConnTester::connect(server.port());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    assert_eq!(200, tester.get(1, "/").headers.status());
+    assert_eq!(200, tester.get(5, "/").headers.status());
+
+    assert_eq!(vec![1, 5], *stream_ids.lock().unwrap());
+}
+
+fn tunnel_server(enable_connect_protocol: bool) -> Server {
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server.conf.enable_connect_protocol = Some(enable_connect_protocol);
+    server.service.set_service_fn("/", |_, req, mut resp| {
+        assert_eq!("CONNECT", req.headers.method());
+        resp.send_headers(Headers::ok_200())?;
+        // Echo tunnel
+        resp.pull_from_stream(req.make_stream())?;
+        Ok(())
+    });
+    server.build().expect("server")
+}
+
+#[test]
+fn connect_tunnel() {
+    init_logger();
+
+    let server = tunnel_server(false);
+
+    let mut tester = HttpConnTester::connect(server.local_addr().port().unwrap());
+    tester.send_preface();
+    tester.settings_xchg();
+    assert!(!tester.peer_enable_connect_protocol);
+
+    let mut headers = Headers::new();
+    headers.add(":method", "CONNECT");
+    headers.add(":authority", "example.com:443");
+    tester.send_headers(1, headers, false);
+    assert_eq!(200, tester.recv_frame_headers_check(1, false).status());
+
+    tester.send_data(1, b"ping", false);
+    assert_eq!(b"ping", &tester.recv_frame_data_check(1, false)[..]);
+    tester.send_data(1, b"pong", true);
+    assert_eq!(b"pong", &tester.recv_frame_data_check(1, true)[..]);
+
+    // `:protocol` is rejected unless enabled
+    let mut headers = Headers::new();
+    headers.add(":method", "CONNECT");
+    headers.add(":protocol", "websocket");
+    headers.add(":scheme", "http");
+    headers.add(":path", "/chat");
+    headers.add(":authority", "example.com");
+    tester.send_headers(3, headers, false);
+    tester.recv_rst_frame_check(3, ErrorCode::ProtocolError);
+}
+
+#[test]
+fn extended_connect() {
+    init_logger();
+
+    let server = tunnel_server(true);
+
+    let mut tester = HttpConnTester::connect(server.local_addr().port().unwrap());
+    tester.send_preface();
+    tester.settings_xchg();
+    assert!(tester.peer_enable_connect_protocol);
+
+    let mut headers = Headers::new();
+    headers.add(":method", "CONNECT");
+    headers.add(":protocol", "websocket");
+    headers.add(":scheme", "http");
+    headers.add(":path", "/chat");
+    headers.add(":authority", "example.com");
+    tester.send_headers(1, headers, false);
+    assert_eq!(200, tester.recv_frame_headers_check(1, false).status());
+
+    tester.send_data(1, b"frame", true);
+    assert_eq!(b"frame", &tester.recv_frame_data_check(1, true)[..]);
+}
+
+#[test]
+fn grpc_timeout_cancel() {
+    init_logger();
+
+    let deadlines = Arc::new(Mutex::new(Vec::new()));
+    let deadlines_copy = deadlines.clone();
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server.conf.grpc_timeout_cancel = Some(true);
+    server.service.set_service_fn("/", move |_, req, mut resp| {
+        resp.send_headers(Headers::ok_200())?;
+        // Never complete the response
+        deadlines_copy.lock().unwrap().push((req.deadline(), resp));
+        Ok(())
+    });
+    let server = server.build().expect("server");
+
+    let mut tester = HttpConnTester::connect(server.local_addr().port().unwrap());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    let start = Instant::now();
+    let mut headers = Headers::new_get("/");
+    headers.add(":scheme", "http");
+    headers.add("grpc-timeout", "100m");
+    tester.send_headers(1, headers, true);
+    tester.recv_frame_headers_check(1, false);
+
+    tester.recv_rst_frame_check(1, ErrorCode::Cancel);
+    assert!(start.elapsed() >= Duration::from_millis(100));
+
+    let deadline = deadlines.lock().unwrap()[0].0.expect("deadline");
+    assert!(deadline >= start + Duration::from_millis(100));
+}
+
+#[test]
+fn grpc_timeout_huge() {
+    init_logger();
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server.conf.grpc_timeout_cancel = Some(true);
+    server.service.set_service_fn("/", |_, _req, mut resp| {
+        resp.send_found_200_plain_text("hi")?;
+        Ok(())
+    });
+    let server = server.build().expect("server");
+
+    let mut tester = HttpConnTester::connect(server.local_addr().port().unwrap());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    // Both are far above tokio timer limit, timer panic would kill the connection
+    for (stream_id, timeout) in [(1, "99999999S"), (3, "20000H")] {
+        let mut headers = Headers::new_get("/");
+        headers.add(":scheme", "http");
+        headers.add("grpc-timeout", timeout);
+        tester.send_headers(stream_id, headers, true);
+        tester.recv_frame_headers_check(stream_id, false);
+        assert_eq!(
+            &b"hi"[..],
+            &tester.recv_frame_data_check(stream_id, true)[..]
+        );
+    }
+
+    assert_eq!(200, tester.get(5, "/").headers.status());
+}
+
+#[test]
+fn window_update_zero_increment_stream() {
+    init_logger();
+
+    let responses = Arc::new(Mutex::new(Vec::new()));
+    let responses_copy = responses.clone();
+
+    let server = ServerOneConn::new_fn(0, move |_, req, mut resp| {
+        if req.headers.path() == "/open" {
+            resp.send_headers(Headers::ok_200())?;
+            // Keep response open
+            responses_copy.lock().unwrap().push(resp);
+        } else {
+            resp.send_found_200_plain_text("hi")?;
+        }
+        Ok(())
+    });
+
+    let mut tester = HttpConnTester::connect(server.port());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    tester.send_get(1, "/open");
+    tester.recv_frame_headers_check(1, false);
+
+    tester.send_window_update_stream(1, 0);
+    tester.recv_rst_frame_check(1, ErrorCode::ProtocolError);
+
+    // Connection is still alive
+    assert_eq!(200, tester.get(3, "/").headers.status());
+}
+
+#[test]
+fn request_content_length_mismatch() {
+    init_logger();
+
+    let responses = Arc::new(Mutex::new(Vec::new()));
+    let responses_copy = responses.clone();
+
+    let server = ServerOneConn::new_fn(0, move |_, _req, resp| {
+        // Keep response open
+        responses_copy.lock().unwrap().push(resp);
+        Ok(())
+    });
+
+    let mut tester = HttpConnTester::connect(server.port());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    let post = || {
+        let mut headers = Headers::new();
+        headers.add(":method", "POST");
+        headers.add(":path", "/");
+        headers.add(":scheme", "http");
+        headers.add("content-length", "3");
+        headers
+    };
+
+    // More DATA than content-length
+    tester.send_headers(1, post(), false);
+    tester.send_data(1, b"abcd", true);
+    tester.recv_rst_frame_check(1, ErrorCode::ProtocolError);
+
+    // Less DATA than content-length
+    tester.send_headers(3, post(), false);
+    tester.send_data(3, b"ab", true);
+    tester.recv_rst_frame_check(3, ErrorCode::ProtocolError);
+
+    // No DATA at all
+    tester.send_headers(5, post(), true);
+    tester.recv_rst_frame_check(5, ErrorCode::ProtocolError);
+
+    // Trailers before all DATA
+    tester.send_headers(7, post(), false);
+    tester.send_data(7, b"ab", false);
+    let mut trailers = Headers::new();
+    trailers.add("x-trailer", "1");
+    tester.send_headers(7, trailers, true);
+    tester.recv_rst_frame_check(7, ErrorCode::ProtocolError);
+}
+
+#[test]
+fn trailers_after_rst_stream() {
+    init_logger();
+
+    let responses = Arc::new(Mutex::new(Vec::new()));
+    let responses_copy = responses.clone();
+
+    let server = ServerOneConn::new_fn(0, move |_, req, mut resp| {
+        if req.headers.method() == "GET" {
+            resp.send_found_200_plain_text("hi")?;
+        } else {
+            // Keep response open
+            responses_copy.lock().unwrap().push(resp);
+        }
+        Ok(())
+    });
+
+    let mut tester = HttpConnTester::connect(server.port());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    let mut headers = Headers::new();
+    headers.add(":method", "POST");
+    headers.add(":path", "/");
+    headers.add(":scheme", "http");
+    headers.add("content-length", "3");
+    tester.send_headers(1, headers, false);
+    tester.send_data(1, b"abcd", false);
+    tester.recv_rst_frame_check(1, ErrorCode::ProtocolError);
+
+    // Client has not seen RST_STREAM yet
+    tester.send_data(1, b"e", false);
+    let mut trailers = Headers::new();
+    trailers.add("x-trailer", "1");
+    tester.send_headers(1, trailers, true);
+
+    // Frames are ignored, connection is still usable
+    assert_eq!(200, tester.get(3, "/").headers.status());
+}
+
+#[test]
+fn header_value_with_crlf() {
+    init_logger();
+
+    let server = ServerOneConn::new_fn(0, |_, _req, mut resp| {
+        resp.send_found_200_plain_text("hi")?;
+        Ok(())
+    });
+
+    let mut tester = HttpConnTester::connect(server.port());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    let fragment = tester.encoder.encode_for_test(vec![
+        (&b":method"[..], &b"GET"[..]),
+        (&b":path"[..], &b"/"[..]),
+        (&b":scheme"[..], &b"http"[..]),
+        (&b"x-smuggle"[..], &b"a\r\nb"[..]),
+    ]);
+    let mut headers = HeadersFrame::new_conv(fragment, 1);
+    headers.set_flag(HeadersFlag::EndHeaders);
+    headers.set_flag(HeadersFlag::EndStream);
+    tester.send_frame(headers);
+
+    tester.recv_goaway_frame_check(ErrorCode::ProtocolError);
+    tester.recv_eof();
+}
+
+#[test]
+fn invalid_request_pseudo_headers() {
+    init_logger();
+
+    let handled = Arc::new(AtomicUsize::new(0));
+    let handled_copy = handled.clone();
+
+    let server = ServerOneConn::new_fn(0, move |_, _req, mut resp| {
+        handled_copy.fetch_add(1, Ordering::SeqCst);
+        resp.send_found_200_plain_text("hi")?;
+        Ok(())
+    });
+
+    let mut tester = HttpConnTester::connect(server.port());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    let requests: &[&[(&str, &str)]] = &[
+        // empty `:path`
+        &[(":method", "GET"), (":path", ""), (":scheme", "http")],
+        // missing `:scheme`
+        &[(":method", "GET"), (":path", "/")],
+        // `:method` is not a token
+        &[(":method", "GE T"), (":path", "/"), (":scheme", "http")],
+    ];
+
+    let mut stream_id = 1;
+    for request in requests {
+        let mut headers = Headers::new();
+        for &(name, value) in *request {
+            headers.add(name, value);
+        }
+        tester.send_headers(stream_id, headers, true);
+        tester.recv_rst_frame_check(stream_id, ErrorCode::ProtocolError);
+        stream_id += 2;
+    }
+
+    assert_eq!(200, tester.get(stream_id, "/").headers.status());
+    assert_eq!(1, handled.load(Ordering::SeqCst));
+}
+
+#[test]
+fn window_update_zero_increment_conn() {
+    init_logger();
+
+    let server = ServerOneConn::new_fn(0, |_, _, mut resp| {
+        resp.send_found_200_plain_text("hi")?;
+        Ok(())
+    });
+
+    let mut tester = HttpConnTester::connect(server.port());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    tester.send_frame(WindowUpdateFrame::for_connection(0));
+    tester.recv_goaway_frame_check(ErrorCode::ProtocolError);
+}
+
+#[test]
+fn window_update_conn_and_stream_independent() {
+    init_logger();
+
+    let server = ServerTest::new();
+
+    let mut tester = HttpConnTester::connect(server.port);
+    tester.send_preface();
+    tester.settings_xchg();
+
+    let w = DEFAULT_SETTINGS.initial_window_size;
+
+    tester.send_recv_settings(SettingsFrame::from_settings(vec![
+        HttpSetting::MaxFrameSize(w * 10),
+    ]));
+
+    tester.send_get(1, &format!("/blocks/{}/{}", w, 3));
+    assert_eq!(200, tester.recv_frame_headers_check(1, false).status());
+    assert_eq!(w as usize, tester.recv_frame_data_check(1, false).len());
+
+    // Stream window increase does not open connection window
+    tester.send_window_update_stream(1, w);
+    tester.send_frame(PingFrame::new());
+    match tester.fn_recv_frame_no_check_ack() {
+        HttpFrame::Ping(f) => assert!(f.is_ack()),
+        f => panic!("unexpected frame: {:?}", f),
+    }
+
+    // Connection window increase is limited by stream window
+    tester.send_window_update_conn(w * 2);
+    assert_eq!(w as usize, tester.recv_frame_data_check(1, false).len());
+    tester.send_frame(PingFrame::new());
+    match tester.fn_recv_frame_no_check_ack() {
+        HttpFrame::Ping(f) => assert!(f.is_ack()),
+        f => panic!("unexpected frame: {:?}", f),
+    }
+
+    tester.send_window_update_stream(1, w);
+    assert_eq!(w as usize, tester.recv_frame_data_check(1, false).len());
+}
+
+#[test]
+fn request_headers_exceed_max_header_list_size() {
+    init_logger();
+
+    let mut settings = DEFAULT_SETTINGS;
+    settings.max_header_list_size = 200;
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server.conf.common.initial_settings = Some(settings);
+    server.service.set_service_fn("/", |_, _, mut resp| {
+        resp.send_found_200_plain_text("hi")?;
+        Ok(())
+    });
+    let server = server.build().expect("server");
+
+    let mut tester = HttpConnTester::connect(server.local_addr().port().unwrap());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    let mut headers = Headers::new_get("/");
+    headers.add(":scheme", "http");
+    headers.add("x-large", "x".repeat(200));
+    tester.send_headers(1, headers, true);
+
+    tester.recv_goaway_frame_check(ErrorCode::ProtocolError);
+    tester.recv_eof();
+}
+
+#[test]
+fn response_headers_exceed_peer_max_header_list_size() {
+    init_logger();
+
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let errors_copy = errors.clone();
+
+    let server = ServerOneConn::new_fn(0, move |_, _, mut resp| {
+        let mut headers = Headers::ok_200();
+        headers.add("x-large", "x".repeat(200));
+        if let Err(e) = resp.send_headers(headers) {
+            errors_copy.lock().unwrap().push(e);
+        }
+        resp.send_headers_end_of_stream(Headers::new_status(500))?;
+        Ok(())
+    });
+
+    let mut tester = HttpConnTester::connect(server.port());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    tester.send_recv_settings(SettingsFrame::from_settings(vec![
+        HttpSetting::MaxHeaderListSize(200),
+    ]));
+
+    tester.send_get(1, "/");
+    assert_eq!(500, tester.recv_frame_headers_check(1, true).status());
+
+    let errors = errors.lock().unwrap();
+    match &errors[..] {
+        [SendError::HeaderListTooLarge(size, 200)] => assert!(*size > 200),
+        e => panic!("expecting HeaderListTooLarge, got: {:?}", e),
+    }
+}
+
+#[test]
+fn bidi_echo() {
+    init_logger();
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server
+        .service
+        .set_service_bidi("/echo", |context, _headers, body, mut resp| {
+            resp.send_headers(Headers::ok_200())?;
+            context.loop_remote().spawn(async move {
+                let mut body = body.filter_data();
+                while let Some(Ok(message)) = body.next().await {
+                    if !message.is_empty() {
+                        resp.send_data(message).expect("send_data");
+                    }
+                }
+                // Client closed its half
+                let mut trailers = Headers::new();
+                trailers.add("grpc-status", "0");
+                resp.send_trailers(trailers).expect("send_trailers");
+            });
+            Ok(())
+        });
+    let server = server.build().expect("server");
+
+    let mut tester = HttpConnTester::connect(server.local_addr().port().unwrap());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    let mut headers = Headers::new_post("/echo");
+    headers.add(":scheme", "http");
+    tester.send_headers(1, headers, false);
+    assert_eq!(200, tester.recv_frame_headers_check(1, false).status());
+
+    for message in &[&b"ping"[..], &b"pong"[..], &b"done"[..]] {
+        tester.send_data(1, message, false);
+        assert_eq!(*message, &tester.recv_frame_data_check(1, false)[..]);
+    }
+
+    tester.send_data(1, b"", true);
+    let trailers = tester.recv_frame_headers_check(1, true);
+    assert_eq!(Some("0"), trailers.get_opt("grpc-status"));
+}
+
+#[test]
+fn duplicate_response_headers_order() {
+    init_logger();
+
+    let server = ServerOneConn::new_fn(0, |_, _, mut resp| {
+        let mut headers = Headers::ok_200();
+        headers.add("set-cookie", "a=1");
+        headers.add("via", "1.1 proxy");
+        headers.add("set-cookie", "b=2");
+        resp.send_headers_end_of_stream(headers)?;
+        Ok(())
+    });
+
+    let mut tester = HttpConnTester::connect(server.port());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    let headers = tester.get(1, "/").headers;
+    let regular: Vec<_> = headers
+        .iter()
+        .filter(|h| !h.is_preudo_header())
+        .map(|h| h.format())
+        .collect();
+    assert_eq!(
+        vec!["set-cookie: a=1", "via: 1.1 proxy", "set-cookie: b=2"],
+        regular
+    );
+}
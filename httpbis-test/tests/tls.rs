@@ -63,7 +63,7 @@ fn tls() {
     let server = server.build().expect("server");
 
     let socket_addr = match server.local_addr() {
-        &AnySocketAddr::Inet(ref sock_addr) => sock_addr,
+        AnySocketAddr::Inet(sock_addr) => sock_addr,
         _ => panic!("Assumed server was an inet server"),
     };
 
@@ -80,3 +80,51 @@ fn tls() {
     assert_eq!(200, resp.headers.status());
     assert_eq!(&b"hello"[..], resp.body.get_bytes());
 }
+
+#[test]
+fn peer_tls_info() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let mut server = ServerBuilder::new();
+    server.set_addr((BIND_HOST, 0)).expect("set_addr");
+    server.set_tls(test_tls_acceptor());
+    server.service.set_service_fn("/", |_, req, mut resp| {
+        let tls_info = req.peer_tls_info().expect("TLS info");
+        let body = format!(
+            "{} {} {}",
+            tls_info.server_name.as_deref().unwrap_or("-"),
+            tls_info.protocol_version.as_deref().unwrap_or("-"),
+            tls_info.cipher.as_deref().unwrap_or("-"),
+        );
+        resp.send_found_200_plain_text(&body)?;
+        Ok(())
+    });
+    let server = server.build().expect("server");
+
+    let socket_addr = match server.local_addr() {
+        AnySocketAddr::Inet(sock_addr) => sock_addr,
+        _ => panic!("Assumed server was an inet server"),
+    };
+
+    let client: Client = Client::new_expl(
+        socket_addr,
+        ClientTlsOption::Tls("localhost".to_owned(), Arc::new(test_tls_connector())),
+        Default::default(),
+    )
+    .expect("http client");
+
+    // Server name comes from SNI, not from `:authority`
+    let resp: SimpleHttpMessage = rt
+        .block_on(client.start_get("/hi", "other.example").collect())
+        .unwrap();
+    assert_eq!(200, resp.headers.status());
+    let body = String::from_utf8(resp.body.get_bytes().to_vec()).unwrap();
+    let fields: Vec<&str> = body.split(' ').collect();
+    match &fields[..] {
+        ["localhost", "TLSv1.3", cipher] => assert!(cipher.starts_with("TLS_"), "{}", body),
+        ["localhost", "TLSv1.2", cipher] => assert!(cipher.starts_with("TLS_"), "{}", body),
+        _ => panic!("unexpected TLS info: {}", body),
+    }
+}
@@ -0,0 +1,60 @@
+//! Encoding of outgoing `DATA` frames into connection write buffer.
+//!
+//! `reused buffer` is steady-state connection writing: buffer is written out
+//! after each frame, and its vectors are reused for the next frames.
+//! `new buffer` allocates a buffer for each frame.
+
+use bytes::Buf;
+use bytes::Bytes;
+
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::Criterion;
+
+use httpbis::for_test::WriteBuffer;
+use httpbis::frame::DataFrame;
+use httpbis::frame::FrameIR;
+
+const FRAMES: usize = 10_000;
+
+fn data_frame(payload: &Bytes, i: usize) -> DataFrame {
+    let mut frame = DataFrame::with_data(1, payload.clone());
+    // Padding length is written to a vector of write buffer
+    if i.is_multiple_of(2) {
+        frame.set_padding(8);
+    }
+    frame
+}
+
+fn write_out(buf: &mut WriteBuffer) {
+    let remaining = buf.remaining();
+    buf.advance(remaining);
+}
+
+fn encode_data_frames(c: &mut Criterion) {
+    let payload = Bytes::from(vec![0x55; 1000]);
+
+    let mut group = c.benchmark_group("encode 10k DATA frames");
+    group.bench_function("reused buffer", |b| {
+        let mut buf = WriteBuffer::new();
+        b.iter(|| {
+            for i in 0..FRAMES {
+                data_frame(&payload, i).serialize_into(&mut buf);
+                write_out(&mut buf);
+            }
+        })
+    });
+    group.bench_function("new buffer", |b| {
+        b.iter(|| {
+            for i in 0..FRAMES {
+                let mut buf = WriteBuffer::new();
+                data_frame(&payload, i).serialize_into(&mut buf);
+                write_out(&mut buf);
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, encode_data_frames);
+criterion_main!(benches);
@@ -143,9 +143,9 @@ fn ping_pong() {
 fn main() {
     let args: Vec<_> = env::args().collect();
     let args: Vec<&str> = args.iter().map(String::as_str).collect();
-    match &args[1..] {
-        &["request"] => request(),
-        &["ping-pong"] => ping_pong(),
+    match args[1..] {
+        ["request"] => request(),
+        ["ping-pong"] => ping_pong(),
         _ => panic!("usage: {} <mode>", args[0]),
     }
 }
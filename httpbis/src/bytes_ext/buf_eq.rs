@@ -10,7 +10,7 @@ pub fn buf_eq<B1: Buf, B2: Buf>(mut b1: B1, mut b2: B2) -> bool {
         let s1 = b1.bytes();
         let s2 = b2.bytes();
         let min = cmp::min(s1.len(), s2.len());
-        if &s1[..min] != &s2[..min] {
+        if s1[..min] != s2[..min] {
             return false;
         }
         b1.advance(min);
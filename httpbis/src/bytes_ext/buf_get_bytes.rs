@@ -98,7 +98,7 @@ impl<A: BufGetBytes> BufGetBytes for Take<A> {
 
 impl<A: BufGetBytes> BufGetBytes for &mut A {
     fn get_bytes(&mut self, cnt: usize) -> Bytes {
-        (&mut **self).get_bytes(cnt)
+        (**self).get_bytes(cnt)
     }
 }
 
@@ -8,7 +8,9 @@ use std::collections::vec_deque;
 use std::collections::VecDeque;
 use std::io::IoSlice;
 use std::mem;
+#[cfg(test)]
 use std::ops::Deref;
+#[cfg(test)]
 use std::ops::DerefMut;
 
 #[derive(Debug)]
@@ -58,8 +60,27 @@ impl<B: Buf> BufVecDeque<B> {
         }
     }
 
+    /// Advance, passing fully consumed buffers to `consumed`.
+    pub fn advance_with(&mut self, mut cnt: usize, mut consumed: impl FnMut(B)) {
+        assert!(self.len >= cnt);
+        self.len -= cnt;
+
+        while cnt != 0 {
+            let front = self.deque.front_mut().unwrap();
+            let front_remaining = front.remaining();
+            if cnt < front_remaining {
+                front.advance(cnt);
+                break;
+            }
+
+            consumed(self.deque.pop_front().unwrap());
+
+            cnt -= front_remaining;
+        }
+    }
+
     #[cfg(test)]
-    pub fn back_mut(&mut self) -> Option<BufVecDequeBackMut<B>> {
+    pub fn back_mut(&mut self) -> Option<BufVecDequeBackMut<'_, B>> {
         match self.deque.pop_back() {
             Some(back) => Some(BufVecDequeBackMut {
                 deque: self,
@@ -97,22 +118,8 @@ impl<B: Buf> Buf for BufVecDeque<B> {
         n
     }
 
-    fn advance(&mut self, mut cnt: usize) {
-        assert!(self.len >= cnt);
-        self.len -= cnt;
-
-        while cnt != 0 {
-            let front = self.deque.front_mut().unwrap();
-            let front_remaining = front.remaining();
-            if cnt < front_remaining {
-                front.advance(cnt);
-                break;
-            }
-
-            self.deque.pop_front().unwrap();
-
-            cnt -= front_remaining;
-        }
+    fn advance(&mut self, cnt: usize) {
+        self.advance_with(cnt, drop)
     }
 
     fn to_bytes(&mut self) -> Bytes {
@@ -168,12 +175,14 @@ impl<B: Buf> IntoIterator for BufVecDeque<B> {
     }
 }
 
+#[cfg(test)]
 pub struct BufVecDequeBackMut<'a, B: Buf> {
     deque: &'a mut BufVecDeque<B>,
     back: Option<B>,
     remaining: usize,
 }
 
+#[cfg(test)]
 impl<'a, B: Buf> Deref for BufVecDequeBackMut<'a, B> {
     type Target = B;
 
@@ -182,12 +191,14 @@ impl<'a, B: Buf> Deref for BufVecDequeBackMut<'a, B> {
     }
 }
 
+#[cfg(test)]
 impl<'a, B: Buf> DerefMut for BufVecDequeBackMut<'a, B> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         self.back.as_mut().unwrap()
     }
 }
 
+#[cfg(test)]
 impl<'a, B: Buf> Drop for BufVecDequeBackMut<'a, B> {
     fn drop(&mut self) {
         let back = mem::take(&mut self.back).unwrap();
@@ -43,6 +43,11 @@ impl BytesDeque {
         }
     }
 
+    /// Deque contains no bytes.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
     /// Append [`Bytes`] to this deque.
     pub fn extend(&mut self, bytes: Bytes) {
         if bytes.is_empty() {
@@ -121,15 +126,15 @@ impl<'a> From<&'a str> for BytesDeque {
     }
 }
 
-impl Into<Bytes> for BytesDeque {
-    fn into(self) -> Bytes {
-        self.into_bytes()
+impl From<BytesDeque> for Bytes {
+    fn from(deque: BytesDeque) -> Self {
+        deque.into_bytes()
     }
 }
 
-impl Into<Vec<u8>> for BytesDeque {
-    fn into(self) -> Vec<u8> {
-        match self.0 {
+impl From<BytesDeque> for Vec<u8> {
+    fn from(deque: BytesDeque) -> Self {
+        match deque.0 {
             Inner::One(b) => Vec::from(b.as_ref()),
             Inner::Deque(d) => d.into(),
         }
@@ -198,7 +203,7 @@ impl<'a> IntoIterator for &'a BytesDeque {
     fn into_iter(self) -> Self::IntoIter {
         match &self.0 {
             Inner::One(b) => Iter::One(Some(b)),
-            Inner::Deque(d) => Iter::Deque((&d).into_iter()),
+            Inner::Deque(d) => Iter::Deque(d.into_iter()),
         }
     }
 }
@@ -21,16 +21,16 @@ impl<I: Into<VecDeque<Bytes>>> From<I> for BytesVecDeque {
     }
 }
 
-impl Into<Bytes> for BytesVecDeque {
-    fn into(self) -> Bytes {
-        self.into_bytes()
+impl From<BytesVecDeque> for Bytes {
+    fn from(deque: BytesVecDeque) -> Self {
+        deque.into_bytes()
     }
 }
 
-impl Into<Vec<u8>> for BytesVecDeque {
-    fn into(self) -> Vec<u8> {
-        let mut v = Vec::with_capacity(self.remaining());
-        for b in self.deque {
+impl From<BytesVecDeque> for Vec<u8> {
+    fn from(deque: BytesVecDeque) -> Self {
+        let mut v = Vec::with_capacity(deque.remaining());
+        for b in deque.deque {
             v.extend_from_slice(b.as_ref());
         }
         v
@@ -112,7 +112,7 @@ mod test {
         let d = BytesVecDeque::default();
         assert_eq!(&[0u8; 0], Buf::bytes(&d));
         assert_eq!(0, Buf::remaining(&d));
-        assert_eq!(false, Buf::has_remaining(&d));
+        assert!(!Buf::has_remaining(&d));
     }
 
     #[test]
@@ -126,7 +126,7 @@ mod test {
         assert_eq!(b"cde", Buf::bytes(&d));
         Buf::advance(&mut d, 3);
         assert_eq!(0, Buf::remaining(&d));
-        assert_eq!(false, Buf::has_remaining(&d));
+        assert!(!Buf::has_remaining(&d));
     }
 
     #[test]
@@ -142,7 +142,7 @@ mod test {
         assert_eq!(b"e", Buf::bytes(&d));
         Buf::advance(&mut d, 1);
         assert_eq!(0, Buf::remaining(&d));
-        assert_eq!(false, Buf::has_remaining(&d));
+        assert!(!Buf::has_remaining(&d));
     }
 
     #[test]
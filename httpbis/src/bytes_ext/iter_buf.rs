@@ -20,7 +20,7 @@ impl<B: Buf, I: Iterator<Item = B>> IterBuf<B, I> {
 
     fn fill_next(&mut self) {
         loop {
-            if let None = self.next {
+            if self.next.is_none() {
                 self.next = self.iter.next();
             }
             match &mut self.next {
@@ -47,12 +47,13 @@ impl<B: Buf, I: Iterator<Item = B>> Buf for IterBuf<B, I> {
         }
     }
 
-    fn advance(&mut self, cnt: usize) {
+    fn advance(&mut self, mut cnt: usize) {
         while cnt != 0 {
             if let Some(buf) = &mut self.next {
                 let min = cmp::min(cnt, buf.remaining());
                 buf.advance(min);
                 self.rem -= min;
+                cnt -= min;
                 if !buf.has_remaining() {
                     self.next = None;
                 } else {
@@ -68,4 +69,18 @@ impl<B: Buf, I: Iterator<Item = B>> Buf for IterBuf<B, I> {
 }
 
 #[cfg(test)]
-mod test {}
+mod test {
+    use super::*;
+    use bytes::Bytes;
+
+    #[test]
+    fn advance_across_bufs() {
+        let bufs = vec![Bytes::from_static(b"ab"), Bytes::from_static(b"cde")];
+        let mut buf = IterBuf::new(bufs.into_iter(), 5);
+        buf.advance(3);
+        assert_eq!(2, buf.remaining());
+        assert_eq!(b"de", buf.bytes());
+        buf.advance(2);
+        assert_eq!(0, buf.remaining());
+    }
+}
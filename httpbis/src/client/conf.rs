@@ -1,4 +1,6 @@
+use crate::client::push::ClientPushHandler;
 use crate::common::conf::CommonConf;
+use std::sync::Arc;
 use std::time::Duration;
 
 /// Client configuration.
@@ -10,6 +12,18 @@ pub struct ClientConf {
     pub thread_name: Option<String>,
     /// Connection timeout.
     pub connection_timeout: Option<Duration>,
+    /// Max number of concurrent streams, even if server allows more.
+    ///
+    /// Requests above the limit are queued until streams complete.
+    pub max_concurrent_streams_override: Option<u32>,
+    /// Cancel request with `RST_STREAM(CANCEL)` if it is not completed in this time.
+    ///
+    /// Time is measured from the moment the stream is opened.
+    pub request_timeout: Option<Duration>,
+    /// Accept streams pushed by server.
+    ///
+    /// Server push is disabled if not set.
+    pub push_handler: Option<Arc<dyn ClientPushHandler>>,
 
     /// Common client/server conf.
     pub common: CommonConf,
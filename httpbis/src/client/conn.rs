@@ -1,8 +1,11 @@
 //! Single client connection
 
+use std::cmp;
+use std::collections::VecDeque;
 use std::io;
 use std::result::Result as std_Result;
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::error;
 use crate::error::Error;
@@ -10,8 +13,11 @@ use crate::result;
 use crate::AnySocketAddr;
 
 use crate::solicit::end_stream::EndStream;
-use crate::solicit::frame::HttpSetting;
-use crate::solicit::frame::SettingsFrame;
+use crate::solicit::frame::GoawayFrame;
+use crate::solicit::frame::OriginFrame;
+use crate::solicit::frame::PushPromiseDecodedFrame;
+use crate::solicit::frame::RawFrame;
+use crate::solicit::frame::Settings;
 use crate::solicit::header::*;
 use crate::solicit::DEFAULT_SETTINGS;
 
@@ -19,32 +25,36 @@ use std::future::Future;
 
 use tls_api::TlsConnector;
 
-use tls_api;
-
 use crate::solicit_async::*;
 
 use crate::assert_types::assert_send_future;
+use crate::client::push::ClientPushHandler;
 use crate::client::req::ClientRequest;
 
 use crate::client::stream_handler::ClientStreamCreatedHandler;
+use crate::client::tls::check_alpn_protocol;
 use crate::client::types::ClientTypes;
 use crate::client::ClientInterface;
 use crate::client_died_error_holder::SomethingDiedErrorHolder;
 use crate::common::conn::Conn;
-use crate::common::conn::SideSpecific;
 use crate::common::conn::ConnStateSnapshot;
+use crate::common::conn::SideSpecific;
 use crate::common::conn_command_channel::conn_command_channel;
 use crate::common::conn_command_channel::ConnCommandSender;
 use crate::common::conn_read::ConnReadSideCustom;
 use crate::common::conn_write::CommonToWriteMessage;
 use crate::common::conn_write::ConnWriteSideCustom;
+use crate::common::init_where::InitWhere;
 use crate::common::sender::CommonSender;
+use crate::common::stats::ConnectionStats;
 use crate::common::stream::HttpStreamCommon;
 use crate::common::stream::HttpStreamData;
 use crate::common::stream::HttpStreamDataSpecific;
 use crate::common::stream::InMessageStage;
 use crate::common::stream_handler::StreamHandlerInternal;
 use crate::common::stream_map::HttpStreamRef;
+use crate::common::trace::StreamSpan;
+use crate::common::types::Types;
 use crate::data_or_headers::DataOrHeaders;
 use crate::headers_place::HeadersPlace;
 use crate::net::connect::ToClientStream;
@@ -56,6 +66,8 @@ use crate::ClientTlsOption;
 use crate::ErrorCode;
 use bytes::Bytes;
 use futures::channel::oneshot;
+use futures::future;
+use futures::future::AbortHandle;
 use futures::FutureExt;
 use futures::TryFutureExt;
 use std::pin::Pin;
@@ -64,7 +76,20 @@ use crate::client::resp::ClientResponse;
 use tokio::runtime::Handle;
 use tokio::time;
 
-pub struct ClientStreamData {}
+pub struct ClientStreamData {
+    /// Request method is `HEAD`, response `content-length` describes no body.
+    head: bool,
+    /// `request_timeout` timer, aborted when stream is removed.
+    request_timer: Option<AbortHandle>,
+}
+
+impl Drop for ClientStreamData {
+    fn drop(&mut self) {
+        if let Some(request_timer) = self.request_timer.take() {
+            request_timer.abort();
+        }
+    }
+}
 
 impl HttpStreamDataSpecific for ClientStreamData {}
 
@@ -75,7 +100,15 @@ impl HttpStreamData for ClientStream {
 }
 
 pub struct ClientConnData {
-    _callbacks: Box<dyn ClientConnCallbacks>,
+    callbacks: Box<dyn ClientConnCallbacks>,
+    max_concurrent_streams_override: Option<u32>,
+    request_timeout: Option<Duration>,
+    push_handler: Option<Arc<dyn ClientPushHandler>>,
+    /// Requests waiting for peer to allow more concurrent streams
+    /// or for peer settings
+    queued_requests: VecDeque<ClientStartRequestMessage>,
+    /// First `SETTINGS` frame received from peer
+    peer_settings_received: bool,
 }
 
 impl SideSpecific for ClientConnData {}
@@ -102,6 +135,7 @@ pub struct ClientStartRequestMessage {
 pub(crate) enum ClientToWriteMessage {
     Start(ClientStartRequestMessage),
     WaitForHandshake(oneshot::Sender<result::Result<()>>),
+    RequestTimeout(StreamId),
     Common(CommonToWriteMessage),
 }
 
@@ -119,14 +153,30 @@ where
 
     fn process_message(&mut self, message: ClientToWriteMessage) -> result::Result<()> {
         match message {
-            ClientToWriteMessage::Start(start) => self.process_start(start),
+            ClientToWriteMessage::Start(start) => self.process_start_or_queue(start),
             ClientToWriteMessage::Common(common) => self.process_common_message(common),
             ClientToWriteMessage::WaitForHandshake(tx) => {
                 // ignore error
                 drop(tx.send(Ok(())));
                 Ok(())
             }
+            ClientToWriteMessage::RequestTimeout(stream_id) => {
+                self.process_request_timeout(stream_id)
+            }
+        }
+    }
+
+    fn start_queued_streams(&mut self) -> result::Result<bool> {
+        let mut started = false;
+        while let Some(start) = self.specific.queued_requests.front() {
+            if !self.can_start_request(start) {
+                break;
+            }
+            let start = self.specific.queued_requests.pop_front().unwrap();
+            self.process_start(start)?;
+            started = true;
         }
+        Ok(started)
     }
 }
 
@@ -134,6 +184,43 @@ impl<I> Conn<ClientTypes, I>
 where
     I: SocketStream,
 {
+    fn max_concurrent_streams(&self) -> usize {
+        let limit = self.peer_settings.max_concurrent_streams;
+        let limit = match self.specific.max_concurrent_streams_override {
+            Some(o) => cmp::min(limit, o),
+            None => limit,
+        };
+        limit as usize
+    }
+
+    fn can_start_stream(&self) -> bool {
+        // Pushed streams are limited by our setting, not by peer's
+        self.streams.locally_initiated_len() < self.max_concurrent_streams()
+    }
+
+    /// Whether `SETTINGS_ENABLE_CONNECT_PROTOCOL` is known for this request.
+    ///
+    /// A client MUST NOT send `:protocol` before it receives the setting
+    /// (RFC 8441 section 3), so such requests wait for peer settings.
+    fn can_start_request(&self, start: &ClientStartRequestMessage) -> bool {
+        self.can_start_stream()
+            && (self.specific.peer_settings_received
+                || start.start.headers.get_opt(":protocol").is_none())
+    }
+
+    fn process_start_or_queue(&mut self, start: ClientStartRequestMessage) -> result::Result<()> {
+        if !self.specific.queued_requests.is_empty() || !self.can_start_request(&start) {
+            debug!(
+                "max concurrent streams {} reached or peer settings not received, queueing request",
+                self.max_concurrent_streams()
+            );
+            self.specific.queued_requests.push_back(start);
+            return Ok(());
+        }
+
+        self.process_start(start)
+    }
+
     fn process_start(&mut self, start: ClientStartRequestMessage) -> result::Result<()> {
         let ClientStartRequestMessage {
             start:
@@ -149,12 +236,27 @@ where
 
         let stream_id = self.next_local_stream_id();
 
+        debug!(
+            "stream {} opened: {} {}",
+            stream_id,
+            headers.get_opt(":method").unwrap_or(""),
+            headers.get_opt(":path").unwrap_or("")
+        );
+
         {
-            let (_, out_window) = self.new_stream_data(
+            let (mut stream, out_window) = self.new_stream_data(
                 stream_id,
                 None,
                 InMessageStage::Initial,
-                ClientStreamData {},
+                ClientStreamData {
+                    head: headers.method() == "HEAD",
+                    request_timer: None,
+                },
+            );
+            stream.stream().span = StreamSpan::opened(
+                stream_id,
+                headers.get_opt(":method").unwrap_or(""),
+                headers.get_opt(":path").unwrap_or(""),
             );
 
             let in_window_size = self
@@ -169,15 +271,27 @@ where
                 common: if end_stream {
                     CommonSender::new_done(stream_id)
                 } else {
-                    CommonSender::new(stream_id, write_tx, out_window, true)
+                    CommonSender::new(
+                        stream_id,
+                        write_tx,
+                        out_window,
+                        true,
+                        self.peer_max_header_list_size.clone(),
+                    )
                 },
                 drop_callback: None,
             };
 
+            // A client MUST NOT send `:protocol` unless server advertised
+            // SETTINGS_ENABLE_CONNECT_PROTOCOL (RFC 8441 section 3).
+            let connect_protocol_disabled =
+                headers.get_opt(":protocol").is_some() && !self.peer_enable_connect_protocol;
+
             let mut handler = None;
             let resp = ClientResponse {
                 stream_handler: &mut handler,
                 in_window_size,
+                window_update_threshold: self.conf.window_update_threshold(in_window_size),
                 stream_id,
                 to_write_tx: &self.to_write_tx,
             };
@@ -191,6 +305,17 @@ where
                         .unwrap()
                         .close_outgoing(ErrorCode::InternalError);
                 }
+                Ok(()) if connect_protocol_disabled => {
+                    warn!(
+                        "stream {}: extended CONNECT is disabled by server",
+                        stream_id
+                    );
+                    if let Some(handler) = handler {
+                        // ignore error: handler may be already dead
+                        drop(handler.error(error::Error::ConnectProtocolDisabled));
+                    }
+                    self.streams.remove_stream(stream_id);
+                }
                 Ok(()) => {
                     let mut stream = self.streams.get_mut(stream_id).unwrap();
                     stream.stream().peer_tx = handler;
@@ -209,15 +334,154 @@ where
             };
         }
 
+        if let (Some(timeout), Some(mut stream)) = (
+            self.specific.request_timeout,
+            self.streams.get_mut(stream_id),
+        ) {
+            let to_write_tx = self.to_write_tx.clone();
+            let (timer, request_timer) = future::abortable(async move {
+                time::delay_for(timeout).await;
+                // ignore error: connection may be already closed
+                drop(to_write_tx.unbounded_send(ClientToWriteMessage::RequestTimeout(stream_id)));
+            });
+            self.loop_handle.spawn(timer);
+            stream.stream().specific.request_timer = Some(request_timer);
+        }
+
         // Also opens latch if necessary
         self.buffer_outg_conn()?;
         Ok(())
     }
+
+    fn process_request_timeout(&mut self, stream_id: StreamId) -> result::Result<()> {
+        let mut stream = match self.streams.get_mut(stream_id) {
+            Some(stream) => stream,
+            None => return Ok(()),
+        };
+
+        debug!("request timed out: {}", stream_id);
+
+        if let Some(handler) = stream.stream().peer_tx.take() {
+            // it is OK to ignore error: handler may be already dead
+            drop(handler.error(error::Error::RequestTimeout));
+        }
+
+        // Return unsent data to connection pump window
+        let dropped = stream.stream().outgoing.data_size();
+        self.pump_out_window_size.increase(dropped);
+
+        self.send_rst_stream(stream_id, ErrorCode::Cancel)
+    }
+
+    fn process_push_promise_frame(&mut self, frame: PushPromiseDecodedFrame) -> result::Result<()> {
+        let PushPromiseDecodedFrame {
+            stream_id: associated_stream_id,
+            promised_stream_id,
+            headers,
+            ..
+        } = frame;
+
+        let push_handler = match self.specific.push_handler {
+            Some(ref push_handler) => push_handler.clone(),
+            None => {
+                // 8.2: receiving PUSH_PROMISE when push is disabled is a connection error
+                warn!("PUSH_PROMISE received while push is disabled");
+                return self.send_goaway(ErrorCode::ProtocolError);
+            }
+        };
+
+        if ClientTypes::init_where(promised_stream_id) != InitWhere::Peer
+            || promised_stream_id <= self.last_peer_stream_id
+        {
+            warn!("incorrect promised stream id: {}", promised_stream_id);
+            return self.send_goaway(ErrorCode::ProtocolError);
+        }
+
+        self.last_peer_stream_id = promised_stream_id;
+
+        // 8.2.1: push promise must be associated with a client-initiated stream
+        if ClientTypes::init_where(associated_stream_id) != InitWhere::Locally
+            || associated_stream_id > self.last_local_stream_id
+        {
+            warn!(
+                "push promise {} on unknown stream {}",
+                promised_stream_id, associated_stream_id
+            );
+            return self.send_goaway(ErrorCode::ProtocolError);
+        }
+
+        // Stream may be closed by us while promise was in flight
+        if self.streams.get_mut(associated_stream_id).is_none() {
+            debug!(
+                "push promise {} on finished stream {}",
+                promised_stream_id, associated_stream_id
+            );
+            return self.send_rst_stream(promised_stream_id, ErrorCode::Cancel);
+        }
+
+        if let Err(e) = headers.validate(RequestOrResponse::Request, HeadersPlace::Initial) {
+            warn!("invalid push promise headers: {:?}: {:?}", e, headers);
+            return self.send_rst_stream(promised_stream_id, ErrorCode::ProtocolError);
+        }
+        // 8.2: promised requests MUST be cacheable and MUST be safe
+        if !matches!(headers.method(), "GET" | "HEAD") {
+            warn!("push promise with method {}", headers.method());
+            return self.send_rst_stream(promised_stream_id, ErrorCode::ProtocolError);
+        }
+
+        if !push_handler.push_promise(associated_stream_id, &headers) {
+            debug!("push promise {} refused", promised_stream_id);
+            return self.send_rst_stream(promised_stream_id, ErrorCode::Cancel);
+        }
+
+        let (_, _out_window) = self.new_stream_data(
+            promised_stream_id,
+            None,
+            InMessageStage::Initial,
+            ClientStreamData {
+                head: headers.method() == "HEAD",
+                request_timer: None,
+            },
+        );
+
+        let mut stream = self.streams.get_mut(promised_stream_id).unwrap();
+        // Client never sends on pushed stream
+        stream.stream().close_local();
+        let in_window_size = stream.stream().in_window_size.size() as u32;
+
+        let mut handler = None;
+        let response = ClientResponse {
+            stream_handler: &mut handler,
+            in_window_size,
+            window_update_threshold: self.conf.window_update_threshold(in_window_size),
+            stream_id: promised_stream_id,
+            to_write_tx: &self.to_write_tx,
+        }
+        .make_stream();
+
+        self.streams
+            .get_mut(promised_stream_id)
+            .unwrap()
+            .stream()
+            .peer_tx = handler;
+
+        push_handler.pushed_response(headers, response);
+        Ok(())
+    }
 }
 
 pub trait ClientConnCallbacks: Send + 'static {
     // called at most once
     fn goaway(&self, stream_id: StreamId, raw_error_code: u32);
+
+    /// Called each time peer settings are changed.
+    fn peer_settings(&self, _settings: &Settings, _enable_connect_protocol: bool) {}
+
+    /// Called each time peer acknowledged our settings.
+    fn our_settings_acked(&self, _settings: &Settings) {}
+
+    /// Called for each `ORIGIN` frame received from server.
+    fn origin(&self, _origins: &[String]) {}
 }
 
 impl ClientConn {
@@ -240,13 +504,16 @@ impl ClientConn {
             write_tx: to_write_tx.clone(),
         };
 
-        let settings_frame = SettingsFrame::from_settings(vec![HttpSetting::EnablePush(false)]);
+        let settings_frame = conf
+            .common
+            .initial_settings_frame(conf.push_handler.is_some());
         let mut settings = DEFAULT_SETTINGS;
         settings.apply_from_frame(&settings_frame);
 
         let lh_copy = lh.clone();
 
         let conn_died_error_holder_copy = conn_died_error_holder.clone();
+        let conn_died_error_holder_close = conn_died_error_holder.clone();
 
         let future = connect.and_then(move |mut conn| async move {
             client_handshake(&mut conn, settings_frame).await?;
@@ -256,7 +523,12 @@ impl ClientConn {
             let conn_data = Conn::<ClientTypes, _>::new(
                 lh_copy,
                 ClientConnData {
-                    _callbacks: Box::new(callbacks),
+                    callbacks: Box::new(callbacks),
+                    max_concurrent_streams_override: conf.max_concurrent_streams_override,
+                    request_timeout: conf.request_timeout,
+                    push_handler: conf.push_handler,
+                    queued_requests: VecDeque::new(),
+                    peer_settings_received: false,
                 },
                 conf.common,
                 settings,
@@ -266,7 +538,9 @@ impl ClientConn {
                 peer_addr,
                 conn_died_error_holder,
             );
-            conn_data.run().await
+            let close = conn_data.run().await;
+            conn_died_error_holder_close.set_once(error::Error::ConnectionClosed(close));
+            Ok(())
         });
 
         let future = conn_died_error_holder_copy.wrap_future(future);
@@ -367,12 +641,15 @@ impl ClientConn {
                 }
                 socket
             })
-            .map_err(|e| error::Error::from(e));
+            .map_err(error::Error::from);
 
         let connect = assert_send_future(connect);
 
-        let tls_conn = connect
-            .and_then(move |conn| async move { Ok(connector.connect(&domain, conn).await?) });
+        let tls_conn = connect.and_then(move |conn| async move {
+            let tls_conn = connector.connect(&domain, conn).await?;
+            check_alpn_protocol(C::supports_alpn(), tls_conn.get_alpn_protocol())?;
+            Ok(tls_conn)
+        });
 
         let tls_conn = assert_send_future(tls_conn);
 
@@ -413,12 +690,29 @@ impl ClientConn {
 
         self.dump_state_with_resp_sender(tx);
 
-        let rx =
-            rx.map_err(|_| Error::from(io::Error::new(io::ErrorKind::Other, "oneshot canceled")));
+        let rx = rx.map_err(|_| Error::from(io::Error::other("oneshot canceled")));
 
         Box::pin(rx)
     }
 
+    pub fn stats_with_resp_sender(&self, tx: oneshot::Sender<ConnectionStats>) {
+        let message = ClientToWriteMessage::Common(CommonToWriteMessage::Stats(tx));
+        // ignore error, sender is dropped if connection is dead
+        drop(self.write_tx.unbounded_send(message));
+    }
+
+    pub fn send_raw_frame(&self, frame: RawFrame) {
+        let message = ClientToWriteMessage::Common(CommonToWriteMessage::RawFrame(frame));
+        // ignore error, frame is lost if connection is dead
+        drop(self.write_tx.unbounded_send(message));
+    }
+
+    pub fn ping_with_resp_sender(&self, tx: oneshot::Sender<result::Result<Duration>>) {
+        let message = ClientToWriteMessage::Common(CommonToWriteMessage::Ping(tx));
+        // ignore error, sender is dropped if connection is dead
+        drop(self.write_tx.unbounded_send(message));
+    }
+
     pub fn wait_for_connect_with_resp_sender(
         &self,
         tx: oneshot::Sender<result::Result<()>>,
@@ -449,7 +743,7 @@ impl ClientInterface for ClientConn {
             stream_handler,
         };
 
-        if let Err(_) = self.start_request_with_resp_sender(start) {
+        if self.start_request_with_resp_sender(start).is_err() {
             return Err(error::Error::ClientDied(None));
         }
 
@@ -463,12 +757,16 @@ where
 {
     type Types = ClientTypes;
 
+    fn process_push_promise(&mut self, frame: PushPromiseDecodedFrame) -> result::Result<()> {
+        self.process_push_promise_frame(frame)
+    }
+
     fn process_headers(
         &mut self,
         stream_id: StreamId,
         end_stream: EndStream,
         headers: Headers,
-    ) -> result::Result<Option<HttpStreamRef<ClientTypes>>> {
+    ) -> result::Result<Option<HttpStreamRef<'_, ClientTypes>>> {
         let existing_stream = self
             .get_stream_for_headers_maybe_send_error(stream_id)?
             .is_some();
@@ -487,9 +785,9 @@ where
             InMessageStage::Initial => HeadersPlace::Initial,
             InMessageStage::AfterInitialHeaders => HeadersPlace::Trailing,
             InMessageStage::AfterTrailingHeaders => {
-                return Err(error::Error::InternalError(format!(
-                    "closed stream must be handled before"
-                )));
+                return Err(error::Error::InternalError(
+                    "closed stream must be handled before".to_string(),
+                ));
             }
         };
 
@@ -503,7 +801,7 @@ where
             HeadersPlace::Initial => {
                 let status = headers.status();
 
-                let status_1xx = status >= 100 && status <= 199;
+                let status_1xx = (100..=199).contains(&status);
                 if status_1xx && end_stream == EndStream::Yes {
                     warn!("1xx headers and end stream: {}", stream_id);
                     self.send_rst_stream(stream_id, ErrorCode::ProtocolError)?;
@@ -521,11 +819,26 @@ where
             }
         };
 
-        let mut stream = self.streams.get_mut(stream_id).unwrap();
-        if let Some(in_rem_content_length) = headers.content_length() {
-            stream.stream().in_rem_content_length = Some(in_rem_content_length);
+        let content_length_incomplete = {
+            let mut stream = self.streams.get_mut(stream_id).unwrap();
+            // Responses to HEAD and 204, 304 responses have no body regardless of content-length
+            let has_body = !status_1xx
+                && !stream.stream().specific.head
+                && !matches!(headers.get_opt(":status"), Some("204") | Some("304"));
+            if headers_place == HeadersPlace::Initial && has_body {
+                stream.stream().in_rem_content_length = headers.content_length();
+            }
+            stream.stream().in_content_length_incomplete()
+        };
+
+        if end_stream == EndStream::Yes && content_length_incomplete {
+            warn!("stream {} ended without content-length data", stream_id);
+            self.send_rst_stream(stream_id, ErrorCode::ProtocolError)?;
+            return Ok(None);
         }
 
+        let mut stream = self.streams.get_mut(stream_id).unwrap();
+
         stream.stream().in_message_stage = match (headers_place, status_1xx) {
             (HeadersPlace::Initial, false) => InMessageStage::AfterInitialHeaders,
             (HeadersPlace::Initial, true) => InMessageStage::Initial,
@@ -552,4 +865,27 @@ where
 
         Ok(Some(stream))
     }
+
+    fn process_peer_settings_applied(&mut self) {
+        self.specific.peer_settings_received = true;
+        self.specific
+            .callbacks
+            .peer_settings(&self.peer_settings, self.peer_enable_connect_protocol);
+    }
+
+    fn process_our_settings_acked(&mut self) {
+        self.specific
+            .callbacks
+            .our_settings_acked(&self.our_settings_ack);
+    }
+
+    fn process_origin(&mut self, frame: OriginFrame) {
+        self.specific.callbacks.origin(&frame.origins);
+    }
+
+    fn process_goaway_received(&mut self, frame: &GoawayFrame) {
+        self.specific
+            .callbacks
+            .goaway(frame.last_stream_id, frame.raw_error_code());
+    }
 }
@@ -1,6 +1,8 @@
 pub(crate) mod conf;
 pub(crate) mod conn;
 pub(crate) mod increase_in_window;
+pub(crate) mod pool;
+pub(crate) mod push;
 pub(crate) mod req;
 pub(crate) mod resp;
 pub(crate) mod stream_handler;
@@ -10,7 +12,10 @@ pub(crate) mod types;
 use std::net::SocketAddr;
 use std::net::ToSocketAddrs;
 use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
 use std::thread;
+use std::time::Duration;
 
 use bytes::Bytes;
 
@@ -21,20 +26,25 @@ use futures::channel::oneshot;
 use futures::future;
 use futures::future::FutureExt;
 use futures::future::TryFutureExt;
+use futures::stream::Stream;
 use futures::stream::StreamExt;
+use futures::Future;
 
 use tls_api::TlsConnector;
 use tls_api::TlsConnectorBuilder;
-use tls_api_stub;
 
 use crate::futures_misc::*;
 
 use crate::error;
+use crate::error::ConnectionError;
 use crate::error::Error;
 use crate::result::Result;
 
+use crate::solicit::frame::RawFrame;
+use crate::solicit::frame::Settings;
 use crate::solicit::header::*;
 use crate::solicit::HttpScheme;
+use crate::solicit::DEFAULT_SETTINGS;
 
 use crate::solicit_async::*;
 
@@ -54,6 +64,9 @@ pub use crate::client::tls::ClientTlsOption;
 use crate::client_died_error_holder::ClientDiedType;
 use crate::client_died_error_holder::SomethingDiedErrorHolder;
 use crate::common::conn::ConnStateSnapshot;
+use crate::common::conn_write::extension_frame;
+use crate::common::stats::ConnectionStats;
+use crate::http_stream_async_read::async_read_bytes;
 
 use crate::client::resp::ClientResponse;
 use crate::net::unix::SocketAddrUnix;
@@ -61,6 +74,7 @@ use crate::result;
 use crate::solicit::stream_id::StreamId;
 use crate::Response;
 use std::fmt;
+use tokio::io::AsyncRead;
 use tokio::runtime::Handle;
 use tokio::runtime::Runtime;
 
@@ -81,6 +95,15 @@ impl ClientBuilder<tls_api_stub::TlsConnector> {
     }
 }
 
+#[cfg(feature = "rustls")]
+impl ClientBuilder<tls_api_rustls::TlsConnector> {
+    /// New client builder with default rustls TLS implementation,
+    /// TLS is enabled with `set_tls`.
+    pub fn new_rustls() -> ClientBuilder<tls_api_rustls::TlsConnector> {
+        ClientBuilder::new()
+    }
+}
+
 impl<C: TlsConnector> ClientBuilder<C> {
     /// Set the addr client connects to.
     pub fn set_addr<S: ToSocketAddrs>(&mut self, addr: S) -> Result<()> {
@@ -105,6 +128,12 @@ impl<C: TlsConnector> ClientBuilder<C> {
     }
 }
 
+impl<C: TlsConnector> Default for ClientBuilder<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<C: TlsConnector> ClientBuilder<C> {
     pub fn new() -> ClientBuilder<C> {
         ClientBuilder {
@@ -119,7 +148,6 @@ impl<C: TlsConnector> ClientBuilder<C> {
         let mut tls_connector = C::builder()?;
 
         if C::supports_alpn() {
-            // TODO: check negotiated protocol after connect
             tls_connector.set_alpn_protocols(&[b"h2"])?;
         }
 
@@ -131,6 +159,8 @@ impl<C: TlsConnector> ClientBuilder<C> {
     }
 
     pub fn build(self) -> Result<Client> {
+        self.conf.common.validate()?;
+
         let addr = self.addr.expect("addr is not specified");
         let addr_copy = addr.clone();
 
@@ -146,10 +176,12 @@ impl<C: TlsConnector> ClientBuilder<C> {
         let client_died_error_holder = SomethingDiedErrorHolder::new();
         let client_died_error_holder_copy = client_died_error_holder.clone();
 
+        let conn_status = Arc::new(ClientConnStatus::new());
+        let conn_status_copy = conn_status.clone();
+
         let join = if let Some(remote) = self.event_loop {
             let tls = self.tls;
             let conf = self.conf;
-            let controller_tx = controller_tx.clone();
             let handle = remote.clone();
             remote.spawn(future::lazy(move |_cx| {
                 spawn_client_event_loop(
@@ -159,9 +191,9 @@ impl<C: TlsConnector> ClientBuilder<C> {
                     tls,
                     conf,
                     done_tx,
-                    controller_tx,
                     controller_rx,
                     client_died_error_holder_copy,
+                    conn_status_copy,
                 )
             }));
             Completion::Rx(done_rx)
@@ -174,7 +206,6 @@ impl<C: TlsConnector> ClientBuilder<C> {
                 .clone()
                 .unwrap_or_else(|| "http2-client-loop".to_owned())
                 .to_string();
-            let controller_tx = controller_tx.clone();
             let join_handle = thread::Builder::new()
                 .name(thread_name)
                 .spawn(move || {
@@ -188,9 +219,9 @@ impl<C: TlsConnector> ClientBuilder<C> {
                         tls,
                         conf,
                         done_tx,
-                        controller_tx,
                         controller_rx,
                         client_died_error_holder_copy,
+                        conn_status_copy,
                     );
 
                     lp.block_on(done_rx).expect("run");
@@ -206,6 +237,7 @@ impl<C: TlsConnector> ClientBuilder<C> {
             shutdown: shutdown_signal,
             client_died_error_holder,
             addr,
+            conn_status,
         })
     }
 }
@@ -228,6 +260,69 @@ pub struct Client {
     shutdown: ShutdownSignal,
     client_died_error_holder: SomethingDiedErrorHolder<ClientDiedType>,
     addr: AnySocketAddr,
+    conn_status: Arc<ClientConnStatus>,
+}
+
+/// Peer state reported by client connections.
+struct ClientConnStatus {
+    conn: Mutex<ClientConnState>,
+}
+
+/// State of the current connection, reset when client reconnects.
+struct ClientConnState {
+    /// Incremented on each connection, callbacks of previous connections are ignored.
+    conn_id: u64,
+    goaway_received: bool,
+    peer: Settings,
+    peer_enable_connect_protocol: bool,
+    local: Settings,
+    acked: bool,
+    /// Notified when our settings are acknowledged.
+    ack_waiters: Vec<oneshot::Sender<()>>,
+    /// Origins received in `ORIGIN` frames.
+    origin_set: Option<Vec<String>>,
+}
+
+impl ClientConnState {
+    fn new(conn_id: u64) -> ClientConnState {
+        ClientConnState {
+            conn_id,
+            goaway_received: false,
+            peer: DEFAULT_SETTINGS,
+            peer_enable_connect_protocol: false,
+            local: DEFAULT_SETTINGS,
+            acked: false,
+            ack_waiters: Vec::new(),
+            origin_set: None,
+        }
+    }
+}
+
+impl ClientConnStatus {
+    fn new() -> ClientConnStatus {
+        ClientConnStatus {
+            conn: Mutex::new(ClientConnState::new(0)),
+        }
+    }
+
+    /// Start tracking a new connection, return its id.
+    ///
+    /// Waiters for settings `ACK` of the previous connection fail.
+    fn new_conn(&self) -> u64 {
+        let mut conn = self.conn.lock().unwrap();
+        *conn = ClientConnState::new(conn.conn_id + 1);
+        conn.conn_id
+    }
+
+    fn settings_acked(&self) -> HttpFutureSend<()> {
+        let mut conn = self.conn.lock().unwrap();
+        if conn.acked {
+            return Box::pin(future::ok(()));
+        }
+        let (tx, rx) = oneshot::channel();
+        conn.ack_waiters.push(tx);
+        Box::pin(rx.map_err(|_| error::Error::ConnDied))
+    }
 }
 
 impl fmt::Debug for Client {
@@ -282,7 +377,7 @@ impl Client {
         conf: ClientConf,
     ) -> Result<Client> {
         let mut client = ClientBuilder::new();
-        client.addr = Some(AnySocketAddr::Inet(addr.clone()));
+        client.addr = Some(AnySocketAddr::Inet(*addr));
         client.tls = tls;
         client.conf = conf;
         client.build()
@@ -309,7 +404,7 @@ impl Client {
             ) -> result::Result<()> {
                 let tx = self.tx.take().unwrap();
 
-                if let Err(_) = tx.send((req, resp.make_stream())) {
+                if tx.send((req, resp.make_stream())).is_err() {
                     return Err(error::Error::CallerDied);
                 }
 
@@ -345,6 +440,71 @@ impl Client {
         )
     }
 
+    /// Start request with body streamed from `body`.
+    ///
+    /// Body stream is polled only when peer flow control windows allow sending more data.
+    pub fn start_request_body_stream<S>(&self, headers: Headers, body: S) -> Response
+    where
+        S: Stream<Item = result::Result<Bytes>> + Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+
+        struct Impl<S> {
+            body: Option<S>,
+            tx: Option<oneshot::Sender<Response>>,
+        }
+
+        impl<S> ClientStreamCreatedHandler for Impl<S>
+        where
+            S: Stream<Item = result::Result<Bytes>> + Send + 'static,
+        {
+            fn request_created(
+                &mut self,
+                mut req: ClientRequest,
+                resp: ClientResponse,
+            ) -> result::Result<()> {
+                req.pull_bytes_from_stream(self.body.take().unwrap())?;
+
+                let tx = self.tx.take().unwrap();
+                if tx.send(resp.make_stream()).is_err() {
+                    return Err(error::Error::CallerDied);
+                }
+
+                Ok(())
+            }
+        }
+
+        if let Err(e) = self.start_request_low_level(
+            headers,
+            None,
+            None,
+            false,
+            Box::new(Impl {
+                body: Some(body),
+                tx: Some(tx),
+            }),
+        ) {
+            return Response::err(e);
+        }
+
+        let client_error = self.client_died_error_holder.clone();
+        Response::new(
+            rx.map_err(move |oneshot::Canceled| client_error.error())
+                .and_then(|response| response),
+        )
+    }
+
+    /// Start request with body read from `body` until EOF.
+    ///
+    /// Reader is polled only when peer flow control windows allow sending more data,
+    /// read error resets the stream.
+    pub fn start_request_body_reader<R>(&self, headers: Headers, body: R) -> Response
+    where
+        R: AsyncRead + Send + 'static,
+    {
+        self.start_request_body_stream(headers, async_read_bytes(body))
+    }
+
     /// Start HTTP/2 `GET` request.
     pub fn start_get(&self, path: &str, authority: &str) -> Response {
         let headers = Headers::from_vec(vec![
@@ -393,6 +553,95 @@ impl Client {
         Box::pin(rx.map_err(|_| error::Error::ConnDied))
     }
 
+    /// Statistics of current connection.
+    pub fn stats(&self) -> HttpFutureSend<ConnectionStats> {
+        let (tx, rx) = oneshot::channel();
+        // ignore error
+        drop(
+            self.controller_tx
+                .unbounded_send(ControllerCommand::Stats(tx)),
+        );
+        Box::pin(rx.map_err(|_| error::Error::ConnDied))
+    }
+
+    /// Send `PING` on current connection and wait for `ACK`, return round-trip time.
+    pub fn ping(&self) -> HttpFutureSend<Duration> {
+        let (tx, rx) = oneshot::channel();
+        // ignore error
+        drop(
+            self.controller_tx
+                .unbounded_send(ControllerCommand::Ping(tx)),
+        );
+        Box::pin(
+            rx.map_err(|_| error::Error::ConnDied)
+                .and_then(future::ready),
+        )
+    }
+
+    /// Send frame of extension type (RFC 7540 section 5.5) on current connection.
+    ///
+    /// Frames of types defined in RFC 7540 are rejected, because they would
+    /// break connection state.
+    pub fn send_raw_frame(
+        &self,
+        frame_type: u8,
+        flags: u8,
+        stream_id: StreamId,
+        payload: &[u8],
+    ) -> Result<()> {
+        let max_frame_size = self.peer_settings().max_frame_size;
+        let frame = extension_frame(frame_type, flags, stream_id, payload, max_frame_size)?;
+        self.controller_tx
+            .unbounded_send(ControllerCommand::RawFrame(frame))
+            .map_err(|_| Error::ClientControllerDied)
+    }
+
+    /// Max concurrent streams allowed by peer in the last received settings.
+    pub(crate) fn peer_max_concurrent_streams(&self) -> u32 {
+        self.peer_settings().max_concurrent_streams
+    }
+
+    /// Settings last received from peer on the current connection,
+    /// `DEFAULT_SETTINGS` before the first `SETTINGS` frame.
+    pub fn peer_settings(&self) -> Settings {
+        self.conn_status.conn.lock().unwrap().peer
+    }
+
+    /// Server advertised `SETTINGS_ENABLE_CONNECT_PROTOCOL` (RFC 8441),
+    /// so requests may carry `:protocol` pseudo-header.
+    ///
+    /// Tracked separately from `peer_settings`, `Settings` has no field for it.
+    pub fn connect_protocol_enabled(&self) -> bool {
+        self.conn_status
+            .conn
+            .lock()
+            .unwrap()
+            .peer_enable_connect_protocol
+    }
+
+    /// Our settings acknowledged by peer on the current connection,
+    /// `DEFAULT_SETTINGS` before the first `ACK`.
+    pub fn local_settings(&self) -> Settings {
+        self.conn_status.conn.lock().unwrap().local
+    }
+
+    /// Origins advertised by server in `ORIGIN` frames (RFC 8336),
+    /// `None` if server sent no `ORIGIN` frame on the current connection.
+    pub fn origin_set(&self) -> Option<Vec<String>> {
+        self.conn_status.conn.lock().unwrap().origin_set.clone()
+    }
+
+    /// Create a future which resolves when peer acknowledged our initial settings
+    /// on the current connection.
+    pub fn settings_acked(&self) -> HttpFutureSend<()> {
+        self.conn_status.settings_acked()
+    }
+
+    /// Peer sent `GOAWAY` on the current connection of this client.
+    pub(crate) fn goaway_received(&self) -> bool {
+        self.conn_status.conn.lock().unwrap().goaway_received
+    }
+
     /// Create a future which waits for successful connection.
     pub fn wait_for_connect(&self) -> HttpFutureSend<()> {
         let (tx, rx) = oneshot::channel();
@@ -404,9 +653,23 @@ impl Client {
         // TODO: return client death reason
         Box::pin(
             rx.map_err(|_| error::Error::ConnDied)
-                .and_then(|r| future::ready(r)),
+                .and_then(future::ready),
         )
     }
+
+    /// Create a future which resolves when connection is established
+    /// and peer acknowledged our initial settings.
+    ///
+    /// Fails if connection cannot be established or dies before `SETTINGS` ACK.
+    pub fn connected(
+        &self,
+    ) -> impl Future<Output = std::result::Result<(), ConnectionError>> + Send {
+        // Settings of the connection established by `wait_for_connect`
+        let conn_status = self.conn_status.clone();
+        self.wait_for_connect()
+            .and_then(move |()| conn_status.settings_acked())
+            .map_err(ConnectionError::from)
+    }
 }
 
 pub trait ClientInterface {
@@ -430,6 +693,14 @@ impl ClientInterface for Client {
         end_stream: bool,
         stream_handler: Box<dyn ClientStreamCreatedHandler>,
     ) -> result::Result<()> {
+        let max_header_list_size = self.peer_settings().max_header_list_size;
+        if !headers.fits_within(max_header_list_size) {
+            return Err(error::Error::HeaderListTooLarge(
+                headers.list_size(),
+                max_header_list_size,
+            ));
+        }
+
         let start = StartRequestMessage {
             headers,
             body,
@@ -438,9 +709,10 @@ impl ClientInterface for Client {
             stream_handler,
         };
 
-        if let Err(_) = self
+        if self
             .controller_tx
             .unbounded_send(ControllerCommand::StartRequest(start))
+            .is_err()
         {
             // TODO: cause
             return Err(error::Error::ClientControllerDied);
@@ -451,10 +723,12 @@ impl ClientInterface for Client {
 }
 
 enum ControllerCommand {
-    GoAway,
     StartRequest(StartRequestMessage),
     WaitForConnect(oneshot::Sender<Result<()>>),
     DumpState(oneshot::Sender<ConnStateSnapshot>),
+    Ping(oneshot::Sender<Result<Duration>>),
+    Stats(oneshot::Sender<ConnectionStats>),
+    RawFrame(RawFrame),
 }
 
 struct ControllerState<T: ToClientStream, C: TlsConnector> {
@@ -464,7 +738,7 @@ struct ControllerState<T: ToClientStream, C: TlsConnector> {
     conf: ClientConf,
     // current connection
     conn: Arc<ClientConn>,
-    tx: UnboundedSender<ControllerCommand>,
+    conn_status: Arc<ClientConnStatus>,
 }
 
 impl<T: ToClientStream + 'static + Clone, C: TlsConnector> ControllerState<T, C> {
@@ -475,7 +749,8 @@ impl<T: ToClientStream + 'static + Clone, C: TlsConnector> ControllerState<T, C>
             self.tls.clone(),
             self.conf.clone(),
             CallbacksImpl {
-                tx: self.tx.clone(),
+                conn_id: self.conn_status.new_conn(),
+                conn_status: self.conn_status.clone(),
             },
         );
 
@@ -484,9 +759,6 @@ impl<T: ToClientStream + 'static + Clone, C: TlsConnector> ControllerState<T, C>
 
     fn iter(mut self, cmd: ControllerCommand) -> ControllerState<T, C> {
         match cmd {
-            ControllerCommand::GoAway => {
-                self.init_conn();
-            }
             ControllerCommand::StartRequest(start) => {
                 if let Err(start) = self.conn.start_request_with_resp_sender(start) {
                     self.init_conn();
@@ -510,6 +782,15 @@ impl<T: ToClientStream + 'static + Clone, C: TlsConnector> ControllerState<T, C>
             ControllerCommand::DumpState(tx) => {
                 self.conn.dump_state_with_resp_sender(tx);
             }
+            ControllerCommand::Ping(tx) => {
+                self.conn.ping_with_resp_sender(tx);
+            }
+            ControllerCommand::Stats(tx) => {
+                self.conn.stats_with_resp_sender(tx);
+            }
+            ControllerCommand::RawFrame(frame) => {
+                self.conn.send_raw_frame(frame);
+            }
         }
         self
     }
@@ -523,12 +804,66 @@ impl<T: ToClientStream + 'static + Clone, C: TlsConnector> ControllerState<T, C>
 }
 
 struct CallbacksImpl {
-    tx: UnboundedSender<ControllerCommand>,
+    conn_id: u64,
+    conn_status: Arc<ClientConnStatus>,
+}
+
+impl CallbacksImpl {
+    /// State of this connection, `None` if client already reconnected.
+    fn conn(&self) -> Option<MutexGuard<'_, ClientConnState>> {
+        let conn = self.conn_status.conn.lock().unwrap();
+        if conn.conn_id == self.conn_id {
+            Some(conn)
+        } else {
+            None
+        }
+    }
 }
 
 impl ClientConnCallbacks for CallbacksImpl {
     fn goaway(&self, _stream_id: StreamId, _error_code: u32) {
-        drop(self.tx.unbounded_send(ControllerCommand::GoAway));
+        // Client reconnects when the connection is closed,
+        // `ClientPool` stops using the client immediately
+        if let Some(mut conn) = self.conn() {
+            conn.goaway_received = true;
+        }
+    }
+
+    fn peer_settings(&self, settings: &Settings, enable_connect_protocol: bool) {
+        if let Some(mut conn) = self.conn() {
+            conn.peer = *settings;
+            conn.peer_enable_connect_protocol = enable_connect_protocol;
+        }
+    }
+
+    fn origin(&self, origins: &[String]) {
+        if let Some(mut conn) = self.conn() {
+            conn.origin_set
+                .get_or_insert_with(Vec::new)
+                .extend(origins.iter().cloned());
+        }
+    }
+
+    fn our_settings_acked(&self, settings: &Settings) {
+        if let Some(mut conn) = self.conn() {
+            conn.local = *settings;
+            conn.acked = true;
+            for tx in conn.ack_waiters.drain(..) {
+                // ignore error
+                let _ = tx.send(());
+            }
+        }
+    }
+}
+
+impl Drop for CallbacksImpl {
+    fn drop(&mut self) {
+        // Connection died before settings were acked, wake up waiters with error
+        if let Some(mut conn) = self.conn() {
+            if !conn.acked {
+                conn.ack_waiters.clear();
+            }
+        }
     }
 }
 
@@ -540,9 +875,9 @@ fn spawn_client_event_loop<T: ToClientStream + Send + Clone + 'static, C: TlsCon
     tls: ClientTlsOption<C>,
     conf: ClientConf,
     done_tx: oneshot::Sender<()>,
-    controller_tx: UnboundedSender<ControllerCommand>,
     controller_rx: UnboundedReceiver<ControllerCommand>,
     client_died_error_holder: SomethingDiedErrorHolder<ClientDiedType>,
+    conn_status: Arc<ClientConnStatus>,
 ) {
     let http_conn = ClientConn::spawn(
         handle.clone(),
@@ -550,17 +885,18 @@ fn spawn_client_event_loop<T: ToClientStream + Send + Clone + 'static, C: TlsCon
         tls.clone(),
         conf.clone(),
         CallbacksImpl {
-            tx: controller_tx.clone(),
+            conn_id: conn_status.new_conn(),
+            conn_status: conn_status.clone(),
         },
     );
 
     let init = ControllerState {
         handle: handle.clone(),
         socket_addr: socket_addr.clone(),
-        tls: tls,
-        conf: conf,
+        tls,
+        conf,
         conn: Arc::new(http_conn),
-        tx: controller_tx,
+        conn_status,
     };
 
     let controller_future = init.run(controller_rx);
@@ -580,7 +916,7 @@ fn spawn_client_event_loop<T: ToClientStream + Send + Clone + 'static, C: TlsCon
 
     let done = done.then(|r| {
         // OK to ignore error, because rx might be already dead
-        drop(done_tx.send(()));
+        let _ = done_tx.send(());
         future::ready(r)
     });
 
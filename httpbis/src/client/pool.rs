@@ -0,0 +1,214 @@
+//! Pool of multiplexed client connections.
+
+use std::cmp;
+use std::collections::HashMap;
+use std::mem;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use bytes::Bytes;
+
+use futures::future;
+use futures::future::TryFutureExt;
+use futures::stream::StreamExt;
+
+use crate::client::Client;
+use crate::client::ClientBuilder;
+use crate::data_or_trailers::HttpStreamAfterHeaders;
+use crate::result;
+use crate::solicit::header::Headers;
+use crate::ClientConf;
+use crate::Response;
+
+/// Default time after which connection without requests is closed.
+pub const DEFAULT_POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// Client pool configuration.
+#[derive(Default, Debug, Clone)]
+pub struct ClientPoolConf {
+    /// Max number of concurrent streams per connection, even if server allows more.
+    pub max_streams_per_conn: Option<u32>,
+    /// Close connection after it has no requests for this time.
+    ///
+    /// Default is `DEFAULT_POOL_IDLE_TIMEOUT`.
+    pub idle_timeout: Option<Duration>,
+    /// Configuration of pooled connections.
+    pub client: ClientConf,
+}
+
+impl ClientPoolConf {
+    /// Default configuration.
+    pub fn new() -> ClientPoolConf {
+        Default::default()
+    }
+}
+
+struct PooledClient {
+    client: Client,
+    /// Number of requests not yet completed
+    in_flight: Arc<AtomicUsize>,
+    last_used: Instant,
+}
+
+impl PooledClient {
+    fn has_capacity(&self, conf: &ClientPoolConf) -> bool {
+        let limit = cmp::min(
+            self.client.peer_max_concurrent_streams(),
+            conf.max_streams_per_conn.unwrap_or(u32::MAX),
+        );
+        !self.client.goaway_received() && self.in_flight.load(Ordering::SeqCst) < limit as usize
+    }
+
+    fn can_be_evicted(&self, now: Instant, idle_timeout: Duration) -> bool {
+        if self.in_flight.load(Ordering::SeqCst) != 0 {
+            return false;
+        }
+        self.client.goaway_received() || now.duration_since(self.last_used) >= idle_timeout
+    }
+}
+
+/// Decrements in-flight counter when response is completed or dropped.
+struct InFlightGuard(Arc<AtomicUsize>);
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+fn start_request(pooled: &mut PooledClient, headers: Headers, body: Option<Bytes>) -> Response {
+    pooled.last_used = Instant::now();
+    pooled.in_flight.fetch_add(1, Ordering::SeqCst);
+    let guard = InFlightGuard(pooled.in_flight.clone());
+
+    let resp = pooled.client.start_request_end_stream(headers, body, None);
+
+    Response::new(resp.0.map_ok(move |(headers, stream)| {
+        let stream = stream.map(move |part| {
+            let _guard = &guard;
+            part
+        });
+        (headers, HttpStreamAfterHeaders::new(stream))
+    }))
+}
+
+/// Plain text HTTP/2 client which keeps connections per authority.
+///
+/// Requests are multiplexed over existing connection
+/// until it reaches max concurrent streams, then another connection is opened.
+/// Connections which received `GOAWAY` are not used for new requests,
+/// and are closed together with connections idle for `idle_timeout`
+/// by background thread.
+pub struct ClientPool {
+    shared: Arc<ClientPoolShared>,
+    /// Eviction thread stops when sender is dropped.
+    _evict_stop_tx: mpsc::Sender<()>,
+}
+
+struct ClientPoolShared {
+    conf: ClientPoolConf,
+    clients: Mutex<HashMap<String, Vec<PooledClient>>>,
+}
+
+impl ClientPool {
+    /// Create an empty pool.
+    pub fn new(conf: ClientPoolConf) -> ClientPool {
+        let idle_timeout = conf.idle_timeout.unwrap_or(DEFAULT_POOL_IDLE_TIMEOUT);
+        let shared = Arc::new(ClientPoolShared {
+            conf,
+            clients: Mutex::new(HashMap::new()),
+        });
+
+        let (evict_stop_tx, evict_stop_rx) = mpsc::channel::<()>();
+        let shared_copy = shared.clone();
+        thread::Builder::new()
+            .name("http2-client-pool-evict".to_owned())
+            .spawn(move || {
+                while let Err(mpsc::RecvTimeoutError::Timeout) =
+                    evict_stop_rx.recv_timeout(idle_timeout)
+                {
+                    drop(shared_copy.evict());
+                }
+            })
+            .expect("spawn");
+
+        ClientPool {
+            shared,
+            _evict_stop_tx: evict_stop_tx,
+        }
+    }
+
+    /// Number of open connections to given authority.
+    pub fn conn_count(&self, authority: &str) -> usize {
+        let clients = self.shared.clients.lock().unwrap();
+        clients.get(authority).map(|c| c.len()).unwrap_or(0)
+    }
+
+    /// Start a request to `authority` (`host:port`).
+    pub fn request(&self, authority: &str, headers: Headers, body: Option<Bytes>) -> Response {
+        {
+            let mut clients = self.shared.clients.lock().unwrap();
+            if let Some(pooled) = clients
+                .get_mut(authority)
+                .and_then(|conns| conns.iter_mut().find(|c| c.has_capacity(&self.shared.conf)))
+            {
+                return start_request(pooled, headers, body);
+            }
+        }
+
+        // Resolve and connect without holding the lock,
+        // concurrent callers may open extra connections which is fine
+        let mut pooled = match self.shared.connect(authority) {
+            Ok(pooled) => pooled,
+            Err(e) => return Response::new(future::err(e)),
+        };
+        let resp = start_request(&mut pooled, headers, body);
+
+        let mut clients = self.shared.clients.lock().unwrap();
+        clients
+            .entry(authority.to_owned())
+            .or_default()
+            .push(pooled);
+        resp
+    }
+}
+
+impl ClientPoolShared {
+    fn connect(&self, authority: &str) -> result::Result<PooledClient> {
+        let mut builder = ClientBuilder::new_plain();
+        builder.conf = self.conf.client.clone();
+        builder.set_addr(authority)?;
+        Ok(PooledClient {
+            client: builder.build()?,
+            in_flight: Arc::new(AtomicUsize::new(0)),
+            last_used: Instant::now(),
+        })
+    }
+
+    /// Remove connections which may be closed.
+    ///
+    /// Removed clients must be dropped outside of the lock,
+    /// because `Client` destructor waits for event loop to stop.
+    fn evict(&self) -> Vec<PooledClient> {
+        let now = Instant::now();
+        let idle_timeout = self.conf.idle_timeout.unwrap_or(DEFAULT_POOL_IDLE_TIMEOUT);
+
+        let mut clients = self.clients.lock().unwrap();
+        let mut evicted = Vec::new();
+        for conns in clients.values_mut() {
+            let (remove, keep) = mem::take(conns)
+                .into_iter()
+                .partition(|c| c.can_be_evicted(now, idle_timeout));
+            *conns = keep;
+            evicted.extend::<Vec<_>>(remove);
+        }
+        clients.retain(|_, conns| !conns.is_empty());
+        evicted
+    }
+}
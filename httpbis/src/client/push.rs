@@ -0,0 +1,27 @@
+//! Streams pushed by server.
+
+use std::fmt;
+
+use crate::solicit::header::Headers;
+use crate::solicit::stream_id::StreamId;
+use crate::Response;
+
+/// Handler of streams pushed by server.
+///
+/// Client enables push with `SETTINGS_ENABLE_PUSH` only if handler is configured.
+pub trait ClientPushHandler: Send + Sync + 'static {
+    /// Server promised to push response to `request`
+    /// while responding on stream `associated_stream_id`.
+    ///
+    /// Return `false` to refuse the push with `RST_STREAM(CANCEL)`.
+    fn push_promise(&self, associated_stream_id: StreamId, request: &Headers) -> bool;
+
+    /// Response of push accepted by `push_promise`.
+    fn pushed_response(&self, request: Headers, response: Response);
+}
+
+impl fmt::Debug for dyn ClientPushHandler {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("ClientPushHandler")
+    }
+}
@@ -9,10 +9,11 @@ use crate::ErrorCode;
 use crate::Headers;
 use crate::HttpStreamAfterHeaders;
 use crate::SenderState;
+use crate::StreamId;
 use bytes::Bytes;
 use futures::stream::Stream;
 use futures::task::Context;
-use std::mem;
+use futures::Future;
 use std::task::Poll;
 
 /// Reference to outgoing stream on the client side.
@@ -31,7 +32,7 @@ impl Drop for ClientRequest {
                 "sender was not properly finished, state: {:?}, invoking custom callback",
                 self.state()
             );
-            if let Some(mut drop_callback) = mem::replace(&mut self.drop_callback, None) {
+            if let Some(mut drop_callback) = self.drop_callback.take() {
                 if let Err(e) = drop_callback(self) {
                     warn!("custom callback resulted in error: {:?}", e);
                 }
@@ -45,6 +46,11 @@ fn _assert_types() {
 }
 
 impl ClientRequest {
+    /// HTTP/2 stream id.
+    pub fn stream_id(&self) -> StreamId {
+        self.common.stream_id()
+    }
+
     pub fn state(&self) -> SenderState {
         self.common.state()
     }
@@ -65,6 +71,12 @@ impl ClientRequest {
         self.common.poll(cx)
     }
 
+    /// Future which resolves when flow control windows allow sending data,
+    /// pending while peer keeps stream or connection window at zero.
+    pub fn window_available(&mut self) -> impl Future<Output = Result<(), StreamDead>> + '_ {
+        self.common.window_available()
+    }
+
     /// Enqueue data to outgoing stream
     ///
     /// This operation fails if stream is in incorrect state.
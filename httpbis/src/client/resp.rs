@@ -12,17 +12,28 @@ use crate::StreamId;
 pub struct ClientResponse<'a> {
     pub(crate) stream_handler: &'a mut Option<ClientResponseStreamHandlerHolder>,
     pub(crate) in_window_size: u32,
+    pub(crate) window_update_threshold: u32,
     pub(crate) stream_id: StreamId,
     pub(crate) to_write_tx: &'a ConnCommandSender<ClientTypes>,
 }
 
 impl<'a> ClientResponse<'a> {
+    /// HTTP/2 stream id.
+    pub fn stream_id(&self) -> StreamId {
+        self.stream_id
+    }
+
     pub fn make_stream(self) -> Response {
+        let initial_window_size = self.in_window_size;
+        let window_update_threshold = self.window_update_threshold;
         self.register_stream_handler(|increase_in_window| {
             let (inc_tx, inc_rx) = stream_queue_sync();
             let stream_from_network = StreamFromNetwork {
                 rx: inc_rx,
                 increase_in_window: increase_in_window.0,
+                recv_buffer_limit: None,
+                initial_window_size,
+                window_update_threshold,
             };
 
             (inc_tx, Response::from_stream(stream_from_network))
@@ -2,10 +2,14 @@ use std::sync::Arc;
 
 use tls_api::TlsConnector;
 
+use crate::error;
+use crate::result;
 use crate::solicit::HttpScheme;
 
 pub enum ClientTlsOption<C: TlsConnector> {
     Plain,
+    /// Connector must request `h2` protocol if it supports ALPN,
+    /// connection fails if server does not select it.
     Tls(String, Arc<C>), // domain
 }
 
@@ -13,16 +17,46 @@ impl<C: TlsConnector> Clone for ClientTlsOption<C> {
     fn clone(&self) -> Self {
         match self {
             &ClientTlsOption::Plain => ClientTlsOption::Plain,
-            &ClientTlsOption::Tls(ref d, ref c) => ClientTlsOption::Tls(d.clone(), c.clone()),
+            ClientTlsOption::Tls(d, c) => ClientTlsOption::Tls(d.clone(), c.clone()),
         }
     }
 }
 
 impl<C: TlsConnector> ClientTlsOption<C> {
     pub fn http_scheme(&self) -> HttpScheme {
-        match self {
-            &ClientTlsOption::Plain => HttpScheme::Http,
-            &ClientTlsOption::Tls(..) => HttpScheme::Https,
+        match *self {
+            ClientTlsOption::Plain => HttpScheme::Http,
+            ClientTlsOption::Tls(..) => HttpScheme::Https,
+        }
+    }
+}
+
+/// Check protocol negotiated by TLS handshake is `h2`.
+///
+/// 3.3: HTTP/2 over TLS uses the "h2" protocol identifier, so ALPN result
+/// is ignored only when connector cannot negotiate protocol.
+pub(crate) fn check_alpn_protocol(
+    supports_alpn: bool,
+    negotiated: Option<Vec<u8>>,
+) -> result::Result<()> {
+    match negotiated {
+        Some(ref p) if p == b"h2" => Ok(()),
+        None if !supports_alpn => Ok(()),
+        negotiated => Err(error::Error::AlpnProtocolNotAllowed(negotiated)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn alpn_protocol() {
+        for &supports_alpn in &[false, true] {
+            assert!(check_alpn_protocol(supports_alpn, Some(b"h2".to_vec())).is_ok());
+            assert!(check_alpn_protocol(supports_alpn, Some(b"http/1.1".to_vec())).is_err());
         }
+        assert!(check_alpn_protocol(false, None).is_ok());
+        assert!(check_alpn_protocol(true, None).is_err());
     }
 }
@@ -5,7 +5,6 @@ use crate::client::conn::ClientToWriteMessage;
 use crate::client::stream_handler::ClientResponseStreamHandlerHolder;
 use crate::common::client_or_server::ClientOrServer;
 use crate::common::types::Types;
-use crate::req_resp::RequestOrResponse;
 
 #[derive(Clone, Default)]
 pub struct ClientTypes;
@@ -19,6 +18,5 @@ impl Types for ClientTypes {
 
     const CLIENT_OR_SERVER: ClientOrServer = ClientOrServer::Client;
 
-    const OUT_REQUEST_OR_RESPONSE: RequestOrResponse = RequestOrResponse::Request;
     const CONN_NDC: &'static str = "client conn";
 }
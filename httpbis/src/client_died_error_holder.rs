@@ -52,7 +52,7 @@ impl<D: DiedType> SomethingDiedErrorHolder<D> {
         error::Error::ClientDied(self.client_died_error())
     }
 
-    fn set_once(&self, error: error::Error) {
+    pub fn set_once(&self, error: error::Error) {
         let mut lock = self.error.lock().unwrap();
         if (*lock).is_none() {
             *lock = Some(Arc::new(error));
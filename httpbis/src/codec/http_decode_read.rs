@@ -1,12 +1,19 @@
+use crate::codec::http_framed_read::HttpFrameOrGoaway;
 use crate::codec::http_framed_read::HttpFramedJoinContinuationRead;
+use crate::common::conf::FrameObserver;
+use crate::common::stats::FrameCounts;
+use crate::error;
 use crate::hpack;
+use crate::hpack::decoder::DecoderError;
 use crate::result;
 use crate::solicit::frame::HttpFrameDecoded;
+use crate::solicit::frame::PushPromiseDecodedFrame;
 use crate::solicit::frame::{HeadersDecodedFrame, HttpFrame};
 use crate::solicit::stream_id::StreamId;
 use crate::ErrorCode;
 use crate::Header;
 use crate::Headers;
+use bytes::Bytes;
 use futures::task::Context;
 use std::task::Poll;
 use tokio::io::AsyncRead;
@@ -31,48 +38,125 @@ impl<R: AsyncRead + Unpin> HttpDecodeRead<R> {
         }
     }
 
+    /// Set max dynamic table size of HPACK decoder
+    /// after our `SETTINGS_HEADER_TABLE_SIZE` is acknowledged.
+    pub fn set_max_table_size(&mut self, size: u32) {
+        self.decoder.set_max_table_size(size as usize);
+    }
+
+    /// Limit header blocks split into `HEADERS` and `CONTINUATION` frames.
+    pub fn set_header_block_limits(
+        &mut self,
+        max_header_block_size: usize,
+        max_continuation_frames: usize,
+    ) {
+        self.framed_read
+            .set_header_block_limits(max_header_block_size, max_continuation_frames);
+    }
+
+    pub fn set_frame_observer(&mut self, frame_observer: Option<FrameObserver>) {
+        self.framed_read.set_frame_observer(frame_observer);
+    }
+
+    pub fn bytes_received(&self) -> u64 {
+        self.framed_read.bytes_received()
+    }
+
+    /// Frames received by type, before `CONTINUATION` frames are joined.
+    pub fn frames_received(&self) -> &FrameCounts {
+        self.framed_read.frames_received()
+    }
+
+    /// Current size of HPACK decoder dynamic table.
+    pub fn hpack_table_size(&self) -> usize {
+        self.decoder.table_size()
+    }
+
+    /// Decode header block, error is sent with `GOAWAY`.
+    fn decode_headers(
+        &mut self,
+        header_fragment: Bytes,
+        stream_id: StreamId,
+        max_header_list_size: u32,
+    ) -> Result<Headers, ErrorCode> {
+        // Limit is checked while decoding to bound memory used by decoded headers,
+        // decoder state is not needed after error, because connection is closed
+        let headers = match self
+            .decoder
+            .decode_with_sensitivity_limit(header_fragment, max_header_list_size as usize)
+        {
+            Err(DecoderError::HeaderListTooLarge(..)) => {
+                warn!(
+                    "header list size of stream {} exceeds max_header_list_size {}",
+                    stream_id, max_header_list_size
+                );
+                return Err(ErrorCode::ProtocolError);
+            }
+            Err(e) => {
+                warn!("failed to decode headers: {:?}", e);
+                return Err(ErrorCode::CompressionError);
+            }
+            Ok(headers) => headers,
+        };
+
+        let headers = match headers
+            .into_iter()
+            .map(|(n, v, sensitive)| {
+                Header::new_validate(n, v).map(|mut h| {
+                    h.set_sensitive(sensitive);
+                    h
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .and_then(Headers::from_vec_pseudo_first)
+        {
+            Ok(headers) => headers,
+            Err(e) => {
+                // All pseudo-header fields MUST appear in the header block before
+                // regular header fields. Any request or response that contains
+                // a pseudo-header field that appears in a header block after
+                // a regular header field MUST be treated as malformed (Section 8.1.2.6).
+                warn!(
+                    "received incorrect headers in stream {}: {:?}",
+                    stream_id, e
+                );
+                // TODO: close connection, because decoder may be in incorrect state
+                return Err(ErrorCode::ProtocolError);
+            }
+        };
+
+        Ok(headers)
+    }
+
     pub fn poll_http_frame(
         &mut self,
         cx: &mut Context<'_>,
         max_frame_size: u32,
+        max_header_list_size: u32,
     ) -> Poll<result::Result<HttpFrameDecodedOrGoaway>> {
-        let frame = match self.framed_read.poll_http_frame(cx, max_frame_size)? {
-            Poll::Ready(frame) => frame,
+        let frame = match self.framed_read.poll_http_frame(cx, max_frame_size) {
+            Poll::Ready(Ok(HttpFrameOrGoaway::Frame(frame))) => frame,
+            Poll::Ready(Ok(HttpFrameOrGoaway::SendGoaway(error_code))) => {
+                return Poll::Ready(Ok(HttpFrameDecodedOrGoaway::SendGoaway(error_code)));
+            }
+            Poll::Ready(Err(error::Error::ParseFrameError(e))) => {
+                warn!("failed to parse frame: {:?}", e);
+                return Poll::Ready(Ok(HttpFrameDecodedOrGoaway::SendGoaway(e.error_code())));
+            }
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
             Poll::Pending => return Poll::Pending,
         };
         Poll::Ready(Ok(HttpFrameDecodedOrGoaway::Frame(match frame {
             HttpFrame::Data(frame) => HttpFrameDecoded::Data(frame),
             HttpFrame::Headers(frame) => {
-                let headers = match self.decoder.decode(frame.header_fragment) {
-                    Err(e) => {
-                        warn!("failed to decode headers: {:?}", e);
-                        return Poll::Ready(Ok(HttpFrameDecodedOrGoaway::SendGoaway(
-                            ErrorCode::CompressionError,
-                        )));
-                    }
+                let headers = match self.decode_headers(
+                    frame.header_fragment,
+                    frame.stream_id,
+                    max_header_list_size,
+                ) {
                     Ok(headers) => headers,
-                };
-
-                let headers = match headers
-                    .into_iter()
-                    .map(|h| Header::new_validate(h.0, h.1))
-                    .collect::<Result<Vec<_>, _>>()
-                    .and_then(Headers::from_vec_pseudo_first)
-                {
-                    Ok(headers) => headers,
-                    Err(e) => {
-                        // All pseudo-header fields MUST appear in the header block before
-                        // regular header fields. Any request or response that contains
-                        // a pseudo-header field that appears in a header block after
-                        // a regular header field MUST be treated as malformed (Section 8.1.2.6).
-                        warn!(
-                            "received incorrect headers in stream {}: {:?}",
-                            frame.stream_id, e
-                        );
-                        // TODO: close connection, because decoder may be in incorrect state
-                        return Poll::Ready(Ok(HttpFrameDecodedOrGoaway::SendGoaway(
-                            ErrorCode::ProtocolError,
-                        )));
+                    Err(error_code) => {
+                        return Poll::Ready(Ok(HttpFrameDecodedOrGoaway::SendGoaway(error_code)))
                     }
                 };
 
@@ -81,13 +165,30 @@ impl<R: AsyncRead + Unpin> HttpDecodeRead<R> {
                     stream_id: frame.stream_id,
                     headers,
                     stream_dep: frame.stream_dep,
-                    padding_len: frame.padding_len,
                 })
             }
             HttpFrame::Priority(frame) => HttpFrameDecoded::Priority(frame),
             HttpFrame::RstStream(frame) => HttpFrameDecoded::RstStream(frame),
             HttpFrame::Settings(frame) => HttpFrameDecoded::Settings(frame),
-            HttpFrame::PushPromise(frame) => HttpFrameDecoded::PushPromise(frame),
+            HttpFrame::PushPromise(frame) => {
+                // Decoded even if push is disabled to keep decoder state in sync with peer.
+                let headers = match self.decode_headers(
+                    frame.header_fragment,
+                    frame.stream_id,
+                    max_header_list_size,
+                ) {
+                    Ok(headers) => headers,
+                    Err(error_code) => {
+                        return Poll::Ready(Ok(HttpFrameDecodedOrGoaway::SendGoaway(error_code)))
+                    }
+                };
+
+                HttpFrameDecoded::PushPromise(PushPromiseDecodedFrame {
+                    stream_id: frame.stream_id,
+                    promised_stream_id: frame.promised_stream_id,
+                    headers,
+                })
+            }
             HttpFrame::Ping(frame) => HttpFrameDecoded::Ping(frame),
             HttpFrame::Goaway(frame) => HttpFrameDecoded::Goaway(frame),
             HttpFrame::WindowUpdate(frame) => HttpFrameDecoded::WindowUpdate(frame),
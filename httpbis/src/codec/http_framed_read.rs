@@ -1,6 +1,9 @@
 use bytes::Bytes;
 use bytes::BytesMut;
 
+use crate::common::conf::FrameDirection;
+use crate::common::conf::FrameObserver;
+use crate::common::stats::FrameCounts;
 use crate::error;
 use crate::result;
 use crate::solicit::frame::unpack_header_from_slice;
@@ -20,9 +23,16 @@ use std::task::Poll;
 use tokio::io::AsyncRead;
 
 /// Buffered read for reading HTTP/2 frames.
+///
+/// Frame length is checked against max frame size before reading payload,
+/// and reads are done only when a frame is incomplete, so buffer never exceeds
+/// max frame size plus frame header plus one 8192 bytes read.
 pub struct HttpFramedRead<R: AsyncRead + Unpin> {
     read: R,
     buf: BytesMut,
+    bytes_received: u64,
+    frames_received: FrameCounts,
+    frame_observer: Option<FrameObserver>,
 }
 
 impl<R: AsyncRead + Unpin> HttpFramedRead<R> {
@@ -30,6 +40,9 @@ impl<R: AsyncRead + Unpin> HttpFramedRead<R> {
         HttpFramedRead {
             read,
             buf: BytesMut::new(),
+            bytes_received: 0,
+            frames_received: FrameCounts::default(),
+            frame_observer: None,
         }
     }
 
@@ -52,7 +65,7 @@ impl<R: AsyncRead + Unpin> HttpFramedRead<R> {
         at_least: usize,
     ) -> Poll<result::Result<()>> {
         while self.buf.len() < at_least {
-            if let Poll::Pending = Pin::new(&mut *self).fill_buf(cx)? {
+            if Pin::new(&mut *self).fill_buf(cx)?.is_pending() {
                 return Poll::Pending;
             }
         }
@@ -64,7 +77,10 @@ impl<R: AsyncRead + Unpin> HttpFramedRead<R> {
         cx: &mut Context<'_>,
         max_frame_size: u32,
     ) -> Poll<result::Result<RawFrame>> {
-        if let Poll::Pending = self.fill_buff_to_at_least(cx, FRAME_HEADER_LEN)? {
+        if self
+            .fill_buff_to_at_least(cx, FRAME_HEADER_LEN)?
+            .is_pending()
+        {
             return Poll::Pending;
         }
 
@@ -78,15 +94,24 @@ impl<R: AsyncRead + Unpin> HttpFramedRead<R> {
                 "closing conn because peer sent frame with size: {}, max_frame_size: {}",
                 header.payload_len, max_frame_size
             );
-            return Poll::Ready(Err(error::Error::CodeError(ErrorCode::FrameSizeError)));
+            return Poll::Ready(Err(error::Error::PayloadTooLarge(
+                header.payload_len,
+                max_frame_size,
+            )));
         }
 
         let total_len = FRAME_HEADER_LEN + header.payload_len as usize;
 
-        if let Poll::Pending = self.fill_buff_to_at_least(cx, total_len)? {
+        if self.fill_buff_to_at_least(cx, total_len)?.is_pending() {
             return Poll::Pending;
         }
 
+        self.bytes_received += total_len as u64;
+        self.frames_received.inc(header.frame_type);
+        if let Some(frame_observer) = &self.frame_observer {
+            frame_observer(FrameDirection::Inbound, &header);
+        }
+
         Poll::Ready(Ok(RawFrame {
             raw_content: self.buf.split_to(total_len).freeze(),
         }))
@@ -111,6 +136,8 @@ enum ContinuableFrame {
 
 struct Continuable {
     header_fragment: BytesMut,
+    /// Number of `CONTINUATION` frames received
+    continuation_count: usize,
     /// Note frame contatains a header fragment, but it is not used
     frame: ContinuableFrame,
 }
@@ -119,6 +146,7 @@ impl Continuable {
     fn headers(header: HeadersFrame) -> Continuable {
         Continuable {
             header_fragment: BytesMut::from(&header.header_fragment[..]),
+            continuation_count: 0,
             frame: ContinuableFrame::Headers(header),
         }
     }
@@ -126,6 +154,7 @@ impl Continuable {
     fn push_promise(push_promise: PushPromiseFrame) -> Continuable {
         Continuable {
             header_fragment: BytesMut::from(&push_promise.header_fragment[..]),
+            continuation_count: 0,
             frame: ContinuableFrame::PushPromise(push_promise),
         }
     }
@@ -144,6 +173,7 @@ impl Continuable {
 
     fn extend_header_fragment(&mut self, bytes: Bytes) {
         self.header_fragment.extend_from_slice(&bytes[..]);
+        self.continuation_count += 1;
     }
 
     fn set_end_headers(&mut self) {
@@ -165,10 +195,22 @@ impl Continuable {
     }
 }
 
+/// Default limit of accumulated header block fragment size.
+pub const DEFAULT_MAX_HEADER_BLOCK_SIZE: usize = 256 * 1024;
+/// Default limit of `CONTINUATION` frames per header block.
+pub const DEFAULT_MAX_CONTINUATION_FRAMES: usize = 32;
+
+pub enum HttpFrameOrGoaway {
+    Frame(HttpFrame),
+    /// Frame size or header block limits exceeded
+    SendGoaway(ErrorCode),
+}
+
 pub struct HttpFramedJoinContinuationRead<R: AsyncRead + Unpin> {
     framed_read: HttpFramedRead<R>,
-    // TODO: check total size is not exceeded some limit
     header_opt: Option<Continuable>,
+    max_header_block_size: usize,
+    max_continuation_frames: usize,
 }
 
 impl<R: AsyncRead + Unpin> HttpFramedJoinContinuationRead<R> {
@@ -176,29 +218,84 @@ impl<R: AsyncRead + Unpin> HttpFramedJoinContinuationRead<R> {
         HttpFramedJoinContinuationRead {
             framed_read: HttpFramedRead::new(read),
             header_opt: None,
+            max_header_block_size: DEFAULT_MAX_HEADER_BLOCK_SIZE,
+            max_continuation_frames: DEFAULT_MAX_CONTINUATION_FRAMES,
+        }
+    }
+
+    /// Limit header blocks split into `HEADERS` and `CONTINUATION` frames,
+    /// checked before header block is decoded.
+    pub fn set_header_block_limits(
+        &mut self,
+        max_header_block_size: usize,
+        max_continuation_frames: usize,
+    ) {
+        self.max_header_block_size = max_header_block_size;
+        self.max_continuation_frames = max_continuation_frames;
+    }
+
+    pub fn set_frame_observer(&mut self, frame_observer: Option<FrameObserver>) {
+        self.framed_read.frame_observer = frame_observer;
+    }
+
+    pub fn bytes_received(&self) -> u64 {
+        self.framed_read.bytes_received
+    }
+
+    pub fn frames_received(&self) -> &FrameCounts {
+        &self.framed_read.frames_received
+    }
+
+    fn check_limits(&self, h: &Continuable) -> Option<ErrorCode> {
+        if h.header_fragment.len() > self.max_header_block_size {
+            warn!(
+                "header block size {} of stream {} exceeds limit {}",
+                h.header_fragment.len(),
+                h.get_stream_id(),
+                self.max_header_block_size
+            );
+            return Some(ErrorCode::CompressionError);
+        }
+        if h.continuation_count > self.max_continuation_frames {
+            warn!(
+                "too many CONTINUATION frames in stream {}, limit {}",
+                h.get_stream_id(),
+                self.max_continuation_frames
+            );
+            return Some(ErrorCode::EnhanceYourCalm);
         }
+        None
     }
 
     pub fn poll_http_frame(
         &mut self,
         cx: &mut Context<'_>,
         max_frame_size: u32,
-    ) -> Poll<result::Result<HttpFrame>> {
+    ) -> Poll<result::Result<HttpFrameOrGoaway>> {
         loop {
-            let frame = match self.framed_read.poll_http_frame(cx, max_frame_size)? {
+            let frame = match self.framed_read.poll_http_frame(cx, max_frame_size) {
                 Poll::Pending => return Poll::Pending,
-                Poll::Ready(frame) => frame,
+                Poll::Ready(Ok(frame)) => frame,
+                // 4.2: frame exceeding SETTINGS_MAX_FRAME_SIZE is treated as connection error
+                Poll::Ready(Err(error::Error::PayloadTooLarge(..))) => {
+                    return Poll::Ready(Ok(HttpFrameOrGoaway::SendGoaway(
+                        ErrorCode::FrameSizeError,
+                    )));
+                }
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
             };
 
             match frame {
                 HttpFrame::Headers(h) => {
-                    if let Some(_) = self.header_opt {
+                    if self.header_opt.is_some() {
                         return Poll::Ready(Err(error::Error::ExpectingContinuationGot(
                             RawHttpFrameType::HEADERS,
                         )));
                     } else {
                         if h.flags.is_set(HeadersFlag::EndHeaders) {
-                            return Poll::Ready(Ok(HttpFrame::Headers(h)));
+                            return Poll::Ready(Ok(HttpFrameOrGoaway::Frame(HttpFrame::Headers(
+                                h,
+                            ))));
                         } else {
                             self.header_opt = Some(Continuable::headers(h));
                             continue;
@@ -206,13 +303,15 @@ impl<R: AsyncRead + Unpin> HttpFramedJoinContinuationRead<R> {
                     }
                 }
                 HttpFrame::PushPromise(p) => {
-                    if let Some(_) = self.header_opt {
+                    if self.header_opt.is_some() {
                         return Poll::Ready(Err(error::Error::ExpectingContinuationGot(
                             RawHttpFrameType::PUSH_PROMISE,
                         )));
                     } else {
                         if p.flags.is_set(PushPromiseFlag::EndHeaders) {
-                            return Poll::Ready(Ok(HttpFrame::PushPromise(p)));
+                            return Poll::Ready(Ok(HttpFrameOrGoaway::Frame(
+                                HttpFrame::PushPromise(p),
+                            )));
                         } else {
                             self.header_opt = Some(Continuable::push_promise(p));
                             continue;
@@ -231,9 +330,12 @@ impl<R: AsyncRead + Unpin> HttpFramedJoinContinuationRead<R> {
                         } else {
                             let header_end = c.is_headers_end();
                             h.extend_header_fragment(c.header_fragment);
+                            if let Some(error_code) = self.check_limits(&h) {
+                                return Poll::Ready(Ok(HttpFrameOrGoaway::SendGoaway(error_code)));
+                            }
                             if header_end {
                                 h.set_end_headers();
-                                return Poll::Ready(Ok(h.into_frame()));
+                                return Poll::Ready(Ok(HttpFrameOrGoaway::Frame(h.into_frame())));
                             } else {
                                 self.header_opt = Some(h);
                                 continue;
@@ -244,15 +346,69 @@ impl<R: AsyncRead + Unpin> HttpFramedJoinContinuationRead<R> {
                     }
                 }
                 f => {
-                    if let Some(_) = self.header_opt {
+                    if self.header_opt.is_some() {
                         return Poll::Ready(Err(error::Error::ExpectingContinuationGot(
                             f.frame_type(),
                         )));
                     } else {
-                        return Poll::Ready(Ok(f));
+                        return Poll::Ready(Ok(HttpFrameOrGoaway::Frame(f)));
                     }
                 }
             };
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::solicit::frame::ContinuationFrame;
+    use crate::solicit::frame::FrameIR;
+    use futures::executor;
+    use futures::future;
+
+    fn poll_first_frame(input: Vec<u8>) -> HttpFrameOrGoaway {
+        let mut read = HttpFramedJoinContinuationRead::new(&input[..]);
+        executor::block_on(future::poll_fn(|cx| read.poll_http_frame(cx, 16384)))
+            .expect("poll_http_frame")
+    }
+
+    #[test]
+    fn continuation_flood() {
+        let mut input = HeadersFrame::new(Bytes::new(), 1).serialize_into_vec();
+        for _ in 0..10000 {
+            input.extend(ContinuationFrame::new(Bytes::new(), 1).serialize_into_vec());
+        }
+
+        match poll_first_frame(input) {
+            HttpFrameOrGoaway::SendGoaway(ErrorCode::EnhanceYourCalm) => {}
+            _ => panic!("expecting GOAWAY"),
+        }
+    }
+
+    #[test]
+    fn header_block_too_large() {
+        let fragment = Bytes::from(vec![0; 16384]);
+        let mut input = HeadersFrame::new(fragment.clone(), 1).serialize_into_vec();
+        for _ in 0..20 {
+            input.extend(ContinuationFrame::new(fragment.clone(), 1).serialize_into_vec());
+        }
+
+        match poll_first_frame(input) {
+            HttpFrameOrGoaway::SendGoaway(ErrorCode::CompressionError) => {}
+            _ => panic!("expecting GOAWAY"),
+        }
+    }
+
+    #[test]
+    fn frame_too_large() {
+        // Only frame header with max length, payload must not be awaited
+        let input = vec![0xff, 0xff, 0xff, 0, 0, 0, 0, 0, 1];
+
+        match poll_first_frame(input) {
+            HttpFrameOrGoaway::SendGoaway(ErrorCode::FrameSizeError) => {}
+            _ => panic!("expecting GOAWAY"),
+        }
+    }
+}
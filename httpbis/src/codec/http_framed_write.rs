@@ -2,6 +2,8 @@ use crate::result;
 use tokio::io::AsyncWrite;
 
 use crate::codec::write_buffer::WriteBuffer;
+use crate::common::conf::FrameObserver;
+use crate::common::stats::FrameCounts;
 use crate::solicit::frame::FrameIR;
 use bytes::Buf;
 use futures::task::Context;
@@ -11,6 +13,10 @@ use std::task::Poll;
 pub struct HttpFramedWrite<W: AsyncWrite + Unpin> {
     write: W,
     buf: WriteBuffer,
+    /// Total bytes of frames buffered
+    bytes_buffered: u64,
+    /// Total socket write calls
+    writes: u64,
 }
 
 impl<W: AsyncWrite + Unpin> HttpFramedWrite<W> {
@@ -18,6 +24,8 @@ impl<W: AsyncWrite + Unpin> HttpFramedWrite<W> {
         HttpFramedWrite {
             write,
             buf: WriteBuffer::new(),
+            bytes_buffered: 0,
+            writes: 0,
         }
     }
 
@@ -28,7 +36,25 @@ impl<W: AsyncWrite + Unpin> HttpFramedWrite<W> {
     pub fn buffer_frame<F: FrameIR>(&mut self, frame: F) {
         debug!("send {:?}", frame);
 
+        let before = self.buf.remaining();
         frame.serialize_into(&mut self.buf);
+        self.bytes_buffered += (self.buf.remaining() - before) as u64;
+    }
+
+    pub fn set_frame_observer(&mut self, frame_observer: Option<FrameObserver>) {
+        self.buf.set_frame_observer(frame_observer);
+    }
+
+    pub fn bytes_buffered(&self) -> u64 {
+        self.bytes_buffered
+    }
+
+    pub fn frames_buffered(&self) -> &FrameCounts {
+        self.buf.frames_written()
+    }
+
+    pub fn writes(&self) -> u64 {
+        self.writes
     }
 
     pub fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<result::Result<()>> {
@@ -37,9 +63,136 @@ impl<W: AsyncWrite + Unpin> HttpFramedWrite<W> {
                 return Poll::Ready(Ok(()));
             }
 
-            if let Poll::Pending = Pin::new(&mut self.write).poll_write_buf(cx, &mut self.buf)? {
+            if Pin::new(&mut self.write)
+                .poll_write_buf(cx, &mut self.buf)?
+                .is_pending()
+            {
                 return Poll::Pending;
             }
+            self.writes += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::net::socket::SocketStream;
+    use crate::net::socket::VectoredWriteSocket;
+    use crate::solicit::frame::DataFrame;
+    use crate::solicit::frame::PingFrame;
+    use crate::AnySocketAddr;
+    use crate::BufGetBytes;
+    use bytes::Bytes;
+    use futures::executor;
+    use futures::future;
+    use std::io;
+    use std::io::IoSlice;
+    use tokio::io::AsyncRead;
+
+    /// Socket which records each write call.
+    #[derive(Debug, Default)]
+    struct MockSocket {
+        vectored: bool,
+        writes: Vec<Vec<u8>>,
+    }
+
+    impl AsyncRead for MockSocket {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            _buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            Poll::Ready(Ok(0))
+        }
+    }
+
+    impl AsyncWrite for MockSocket {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            self.get_mut().writes.push(buf.to_vec());
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
         }
+
+        fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    impl SocketStream for MockSocket {
+        fn is_tcp(&self) -> bool {
+            false
+        }
+
+        fn set_tcp_nodelay(&self, _no_delay: bool) -> io::Result<()> {
+            Err(io::Error::other("mock"))
+        }
+
+        fn peer_addr(&self) -> io::Result<AnySocketAddr> {
+            Err(io::Error::other("mock"))
+        }
+
+        fn poll_write_buf_dyn(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut dyn Buf,
+        ) -> Poll<io::Result<usize>> {
+            if !self.vectored {
+                return self.poll_write(cx, buf.bytes()).map_ok(|n| {
+                    buf.advance(n);
+                    n
+                });
+            }
+            let mut slices = [IoSlice::new(&[]); 64];
+            let n = buf.bytes_vectored(&mut slices);
+            let data: Vec<u8> = slices[..n].iter().flat_map(|s| s.iter().cloned()).collect();
+            buf.advance(data.len());
+            let len = data.len();
+            self.get_mut().writes.push(data);
+            Poll::Ready(Ok(len))
+        }
+    }
+
+    fn write_frames(vectored: bool) -> Vec<Vec<u8>> {
+        let socket = VectoredWriteSocket(MockSocket {
+            vectored,
+            ..Default::default()
+        });
+        let mut write = HttpFramedWrite::new(socket);
+        write.buffer_frame(PingFrame::with_data(1));
+        write.buffer_frame(DataFrame::with_data(1, Bytes::from_static(b"ab")));
+        write.buffer_frame(PingFrame::with_data(2));
+        executor::block_on(future::poll_fn(|cx| write.poll_flush(cx))).expect("flush");
+        write.write.0.writes
+    }
+
+    fn expected_bytes() -> Vec<u8> {
+        let mut buf = WriteBuffer::new();
+        PingFrame::with_data(1).serialize_into(&mut buf);
+        DataFrame::with_data(1, Bytes::from_static(b"ab")).serialize_into(&mut buf);
+        PingFrame::with_data(2).serialize_into(&mut buf);
+        buf.get_bytes(buf.remaining()).to_vec()
+    }
+
+    #[test]
+    fn vectored_write() {
+        let writes = write_frames(true);
+        assert_eq!(1, writes.len());
+        assert_eq!(expected_bytes(), writes.concat());
+    }
+
+    #[test]
+    fn sequential_write_fallback() {
+        let writes = write_frames(false);
+        assert!(writes.len() > 1);
+        assert_eq!(expected_bytes(), writes.concat());
     }
 }
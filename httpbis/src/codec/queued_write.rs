@@ -1,4 +1,6 @@
 use crate::codec::http_framed_write::HttpFramedWrite;
+use crate::common::conf::FrameObserver;
+use crate::common::stats::FrameCounts;
 use crate::result;
 use crate::solicit::frame::FrameIR;
 use crate::solicit::frame::GoawayFrame;
@@ -24,6 +26,24 @@ impl<W: AsyncWrite + Unpin> QueuedWrite<W> {
         self.framed_write.data_len()
     }
 
+    pub fn set_frame_observer(&mut self, frame_observer: Option<FrameObserver>) {
+        self.framed_write.set_frame_observer(frame_observer);
+    }
+
+    /// Bytes of frames queued since connection start.
+    pub fn bytes_sent(&self) -> u64 {
+        self.framed_write.bytes_buffered()
+    }
+
+    pub fn frames_sent(&self) -> &FrameCounts {
+        self.framed_write.frames_buffered()
+    }
+
+    /// Socket write calls since connection start.
+    pub fn socket_writes(&self) -> u64 {
+        self.framed_write.writes()
+    }
+
     pub fn _queued_empty(&self) -> bool {
         self.queued_bytes_len() == 0
     }
@@ -51,7 +71,7 @@ impl<W: AsyncWrite + Unpin> QueuedWrite<W> {
         self.framed_write.poll_flush(cx)
     }
 
-    pub fn _goaway_queued(&self) -> bool {
+    pub fn goaway_queued(&self) -> bool {
         self.goaway_queued
     }
 
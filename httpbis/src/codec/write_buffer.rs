@@ -1,5 +1,9 @@
 use crate::bytes_ext::buf_vec_deque::BufVecDeque;
 use crate::codec::zeroes::Zeroes;
+use crate::common::conf::FrameDirection;
+use crate::common::conf::FrameObserver;
+use crate::common::stats::FrameCounts;
+use crate::solicit::frame::FrameHeader;
 use crate::solicit::frame::FrameHeaderBuffer;
 use crate::BufGetBytes;
 use bytes::Buf;
@@ -64,9 +68,19 @@ impl BufGetBytes for Item {
     }
 }
 
+/// Max number of written out vectors kept for reuse.
+const MAX_POOLED_VECS: usize = 4;
+/// Larger vectors are freed after write.
+const MAX_POOLED_VEC_CAPACITY: usize = 64 * 1024;
+
 #[derive(Default)]
 pub struct WriteBuffer {
     deque: BufVecDeque<Item>,
+    /// Empty vectors reused by `tail_vec`, so steady-state writing does not allocate.
+    pool: Vec<Vec<u8>>,
+    /// Frames written to the buffer by type
+    frames: FrameCounts,
+    frame_observer: Option<FrameObserver>,
 }
 
 impl Buf for WriteBuffer {
@@ -84,7 +98,16 @@ impl Buf for WriteBuffer {
     }
 
     fn advance(&mut self, cnt: usize) {
-        self.deque.advance(cnt)
+        let pool = &mut self.pool;
+        self.deque.advance_with(cnt, |item| {
+            if let Item::Vec(cursor) = item {
+                let mut vec = cursor.into_inner();
+                if pool.len() < MAX_POOLED_VECS && vec.capacity() <= MAX_POOLED_VEC_CAPACITY {
+                    vec.clear();
+                    pool.push(vec);
+                }
+            }
+        })
     }
 }
 
@@ -110,6 +133,22 @@ impl WriteBuffer {
         self.deque.push_back(Item::Bytes(data));
     }
 
+    pub fn set_frame_observer(&mut self, frame_observer: Option<FrameObserver>) {
+        self.frame_observer = frame_observer;
+    }
+
+    /// Count and observe frame written to this buffer.
+    pub fn frame_written(&mut self, header: &FrameHeader) {
+        self.frames.inc(header.frame_type);
+        if let Some(frame_observer) = &self.frame_observer {
+            frame_observer(FrameDirection::Outbound, header);
+        }
+    }
+
+    pub fn frames_written(&self) -> &FrameCounts {
+        &self.frames
+    }
+
     pub fn extend_frame_header_buffer(&mut self, buffer: FrameHeaderBuffer) {
         self.deque
             .push_back(Item::FrameHeaderBuffer(Cursor::new(buffer)));
@@ -122,7 +161,7 @@ impl WriteBuffer {
         self.deque.push_back(Item::Zeroes(Zeroes(zeroes)));
     }
 
-    pub fn tail_vec(&mut self) -> WriteBufferTailVec {
+    pub fn tail_vec(&mut self) -> WriteBufferTailVec<'_> {
         match self.deque.pop_back() {
             Some(Item::Vec(cursor)) => WriteBufferTailVec {
                 write_buffer: self,
@@ -133,9 +172,10 @@ impl WriteBuffer {
                 if let Some(v) = o {
                     self.deque.push_back(v);
                 }
+                let data = self.pool.pop().unwrap_or_default();
                 WriteBufferTailVec {
                     write_buffer: self,
-                    data: Vec::new(),
+                    data,
                     position: 0,
                 }
             }
@@ -143,22 +183,22 @@ impl WriteBuffer {
     }
 }
 
-impl Into<Vec<u8>> for WriteBuffer {
-    fn into(mut self) -> Vec<u8> {
-        let mut v = Vec::with_capacity(self.remaining());
-        while self.has_remaining() {
-            let bytes = self.bytes();
+impl From<WriteBuffer> for Vec<u8> {
+    fn from(mut buf: WriteBuffer) -> Self {
+        let mut v = Vec::with_capacity(buf.remaining());
+        while buf.has_remaining() {
+            let bytes = buf.bytes();
             v.extend_from_slice(bytes);
             let len = bytes.len();
-            self.advance(len);
+            buf.advance(len);
         }
         v
     }
 }
 
-impl Into<Bytes> for WriteBuffer {
-    fn into(self) -> Bytes {
-        Bytes::from(Into::<Vec<u8>>::into(self))
+impl From<WriteBuffer> for Bytes {
+    fn from(buf: WriteBuffer) -> Self {
+        Bytes::from(Into::<Vec<u8>>::into(buf))
     }
 }
 
@@ -186,7 +226,11 @@ impl<'a> WriteBufferTailVec<'a> {
     /// Pos is relative to "data"
     pub fn patch_buf(&mut self, pos: usize, data: &[u8]) {
         let patch_pos = self.position + pos;
-        (&mut self.data[patch_pos..patch_pos + data.len()]).copy_from_slice(data);
+        self.data[patch_pos..patch_pos + data.len()].copy_from_slice(data);
+    }
+
+    pub fn frame_written(&mut self, header: &FrameHeader) {
+        self.write_buffer.frame_written(header);
     }
 
     pub fn extend_from_slice(&mut self, data: &[u8]) {
@@ -230,4 +274,18 @@ mod test {
         assert_eq!(b'f', buf.get_u8());
         assert_eq!(0, buf.remaining());
     }
+
+    #[test]
+    fn vec_reused_after_write() {
+        let mut buf = WriteBuffer::new();
+        buf.extend_from_slice(b"abcd");
+        let ptr = buf.bytes().as_ptr();
+        buf.extend_from_bytes(Bytes::from_static(b"ef"));
+        buf.advance(6);
+        assert_eq!(0, buf.remaining());
+
+        buf.extend_from_slice(b"gh");
+        assert_eq!(ptr, buf.bytes().as_ptr());
+        assert_eq!(b"gh", buf.bytes());
+    }
 }
@@ -23,7 +23,7 @@ impl Buf for Zeroes {
     }
 
     fn bytes_vectored<'a>(&'a self, dst: &mut [IoSlice<'a>]) -> usize {
-        let mut c = self.clone();
+        let mut c = *self;
         let mut n = 0;
         while c.0 != 0 && n != dst.len() {
             let len = c.bytes().len();
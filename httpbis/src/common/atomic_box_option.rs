@@ -14,7 +14,7 @@ impl<T> Drop for AtomicBoxOption<T> {
         let ptr = self.load_raw(Ordering::Relaxed);
         if !ptr.is_null() {
             unsafe {
-                Box::from_raw(ptr);
+                drop(Box::from_raw(ptr));
             }
         }
     }
@@ -109,7 +109,7 @@ mod test {
     impl Canary {
         fn new(count: Arc<AtomicUsize>) -> Canary {
             count.fetch_add(1, Ordering::Relaxed);
-            Canary { count: count }
+            Canary { count }
         }
     }
 
@@ -17,7 +17,7 @@ impl ClientOrServer {
     }
 
     pub fn who_initiated_stream(stream_id: StreamId) -> ClientOrServer {
-        match stream_id % 2 == 0 {
+        match stream_id.is_multiple_of(2) {
             true => ClientOrServer::Server,
             false => ClientOrServer::Client,
         }
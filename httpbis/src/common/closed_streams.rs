@@ -19,7 +19,7 @@ impl ClosedStreams {
     }
 
     pub fn contains(&self, stream_id: StreamId) -> bool {
-        self.set.get(&stream_id).is_some()
+        self.set.contains(&stream_id)
     }
 
     pub fn add(&mut self, stream_id: StreamId) {
@@ -37,7 +37,7 @@ impl ClosedStreams {
     pub fn self_check(&self) {
         assert_eq!(self.set.len(), self.lru.len());
         for stream_id in &self.lru {
-            assert!(self.set.get(stream_id).is_some());
+            assert!(self.set.contains(stream_id));
         }
     }
 }
@@ -1,8 +1,210 @@
-#[derive(Default, Debug, Clone)]
-pub struct CommonConf {}
+use std::cmp;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::common::flush::FlushStrategy;
+use crate::common::scheduling::SchedulingPolicy;
+use crate::common::stats::ConnectionStats;
+use crate::error;
+use crate::result;
+use crate::solicit::frame::FrameHeader;
+use crate::solicit::frame::HttpSetting;
+use crate::solicit::frame::Settings;
+use crate::solicit::frame::SettingsFrame;
+use crate::solicit::stream_id::StreamId;
+use crate::solicit::window_size::AutoTuningWindow;
+use crate::solicit::window_size::PendingWindowUpdate;
+
+/// Direction of frame passed to [`FrameObserver`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameDirection {
+    /// Frame received from peer, observed before it is processed.
+    Inbound,
+    /// Frame queued for sending.
+    Outbound,
+}
+
+/// Callback invoked with header of each frame, see [`CommonConf::frame_observer`].
+pub type FrameObserver = Arc<dyn Fn(FrameDirection, &FrameHeader) + Send + Sync>;
+
+/// Callback invoked with type, stream id and payload of each frame of unknown type,
+/// see [`CommonConf::on_unknown_frame`].
+pub type UnknownFrameCallback = Arc<dyn Fn(u8, StreamId, &[u8]) + Send + Sync>;
+
+/// Callback invoked with connection statistics, see [`CommonConf::on_stats`].
+pub type StatsCallback = Arc<dyn Fn(&ConnectionStats) + Send + Sync>;
+
+/// Configuration shared by client and server.
+#[derive(Default, Clone)]
+pub struct CommonConf {
+    /// Maximum size of HPACK dynamic table used to decode incoming headers.
+    ///
+    /// Advertised to the peer with `SETTINGS_HEADER_TABLE_SIZE`.
+    pub hpack_decoder_max_table_size: Option<u32>,
+    /// Maximum size of HPACK dynamic table used to encode outgoing headers.
+    ///
+    /// Effective size is the minimum of this value and peer
+    /// `SETTINGS_HEADER_TABLE_SIZE`.
+    pub hpack_encoder_max_table_size: Option<u32>,
+    /// How outgoing DATA of concurrent streams is scheduled, default is `Fifo`.
+    pub scheduling_policy: Option<SchedulingPolicy>,
+    /// Close connection when no frames are received from peer for this time.
+    ///
+    /// Default is no timeout.
+    pub keep_alive_timeout: Option<Duration>,
+    /// When set, `PING` is sent after `keep_alive_timeout`, and connection is
+    /// closed only if nothing is received within this time after `PING`.
+    pub keep_alive_ping_timeout: Option<Duration>,
+    /// Invoked synchronously in connection loop for every inbound and outbound frame.
+    /// `CONTINUATION` frames are observed separately.
+    pub frame_observer: Option<FrameObserver>,
+    /// All settings sent in the first `SETTINGS` frame, overrides
+    /// `hpack_decoder_max_table_size`. Push is advertised only if enabled
+    /// both here and by client push handler, server never enables push.
+    ///
+    /// Validated when client or server is built.
+    pub initial_settings: Option<Settings>,
+    /// Invoked synchronously in connection loop for frames of extension types
+    /// (RFC 7540 section 5.5), which are otherwise ignored.
+    ///
+    /// `ORIGIN` frames (RFC 8336) are passed to the callback too,
+    /// before they are processed.
+    pub on_unknown_frame: Option<UnknownFrameCallback>,
+    /// Send `WINDOW_UPDATE` once received data consumed by reader exceeds
+    /// this fraction of the initial window, must be greater than `0.0` and less than `1.0`.
+    ///
+    /// Default is `0.5`.
+    pub window_update_fraction: Option<f32>,
+    /// Grow connection receive window up to this size when peer manages to send
+    /// the whole window within `CONN_WINDOW_AUTO_TUNING_PERIOD`.
+    ///
+    /// By default connection window is not auto-tuned.
+    pub conn_window_auto_tuning_max: Option<u32>,
+    /// When queued frames are written to the socket, default is `OnIdle`.
+    ///
+    /// Regardless of strategy, queue is written once it grows large.
+    pub flush_strategy: Option<FlushStrategy>,
+    /// Invoked synchronously in connection loop with a snapshot of statistics
+    /// every `stats_interval` and once more when connection is closed.
+    pub on_stats: Option<StatsCallback>,
+    /// How often `on_stats` is invoked.
+    ///
+    /// Default is one second.
+    pub stats_interval: Option<Duration>,
+    /// Max size of header block fragments accumulated from `HEADERS`, `PUSH_PROMISE`
+    /// and `CONTINUATION` frames, connection is closed with `COMPRESSION_ERROR` when exceeded.
+    ///
+    /// Default is `DEFAULT_MAX_HEADER_BLOCK_SIZE`.
+    pub max_header_block_size: Option<usize>,
+    /// Max number of `CONTINUATION` frames per header block,
+    /// connection is closed with `ENHANCE_YOUR_CALM` when exceeded.
+    ///
+    /// Default is `DEFAULT_MAX_CONTINUATION_FRAMES`.
+    pub max_continuation_frames: Option<usize>,
+}
+
+/// Default `CommonConf::window_update_fraction`.
+pub const DEFAULT_WINDOW_UPDATE_FRACTION: f32 = 0.5;
+
+/// Sampling period of `CommonConf::conn_window_auto_tuning_max`.
+pub const CONN_WINDOW_AUTO_TUNING_PERIOD: Duration = Duration::from_secs(1);
+
+/// Default `CommonConf::stats_interval`.
+pub const DEFAULT_STATS_INTERVAL: Duration = Duration::from_secs(1);
+
+impl fmt::Debug for CommonConf {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CommonConf")
+            .field(
+                "hpack_decoder_max_table_size",
+                &self.hpack_decoder_max_table_size,
+            )
+            .field(
+                "hpack_encoder_max_table_size",
+                &self.hpack_encoder_max_table_size,
+            )
+            .field("scheduling_policy", &self.scheduling_policy)
+            .field("keep_alive_timeout", &self.keep_alive_timeout)
+            .field("keep_alive_ping_timeout", &self.keep_alive_ping_timeout)
+            .field(
+                "frame_observer",
+                &self.frame_observer.as_ref().map(|_| ".."),
+            )
+            .field("initial_settings", &self.initial_settings)
+            .field(
+                "on_unknown_frame",
+                &self.on_unknown_frame.as_ref().map(|_| ".."),
+            )
+            .field("window_update_fraction", &self.window_update_fraction)
+            .field(
+                "conn_window_auto_tuning_max",
+                &self.conn_window_auto_tuning_max,
+            )
+            .field("flush_strategy", &self.flush_strategy)
+            .field("on_stats", &self.on_stats.as_ref().map(|_| ".."))
+            .field("stats_interval", &self.stats_interval)
+            .field("max_header_block_size", &self.max_header_block_size)
+            .field("max_continuation_frames", &self.max_continuation_frames)
+            .finish()
+    }
+}
 
 impl CommonConf {
     pub fn new() -> CommonConf {
         Default::default()
     }
+
+    /// Check configured values before connection is started.
+    pub(crate) fn validate(&self) -> result::Result<()> {
+        if let Some(settings) = &self.initial_settings {
+            settings.validate().map_err(error::Error::InvalidSettings)?;
+        }
+        if let Some(fraction) = self.window_update_fraction {
+            // Window would never be increased with `1.0`
+            if !(fraction > 0.0 && fraction < 1.0) {
+                return Err(error::Error::InvalidWindowUpdateFraction(fraction));
+            }
+        }
+        Ok(())
+    }
+
+    /// Consumed octets of `initial_window_size` after which `WINDOW_UPDATE` is sent.
+    pub(crate) fn window_update_threshold(&self, initial_window_size: u32) -> u32 {
+        let fraction = self
+            .window_update_fraction
+            .unwrap_or(DEFAULT_WINDOW_UPDATE_FRACTION);
+        (initial_window_size as f64 * fraction as f64) as u32
+    }
+
+    /// Accumulator of consumed octets flushed at `window_update_fraction`.
+    pub(crate) fn pending_window_update(&self) -> PendingWindowUpdate {
+        PendingWindowUpdate::new(
+            self.window_update_fraction
+                .unwrap_or(DEFAULT_WINDOW_UPDATE_FRACTION),
+        )
+    }
+
+    /// Connection receive window, which starts at `initial_window_size`.
+    pub(crate) fn conn_in_window(&self, initial_window_size: u32) -> AutoTuningWindow {
+        let max = cmp::max(
+            self.conn_window_auto_tuning_max.unwrap_or(0),
+            initial_window_size,
+        );
+        AutoTuningWindow::new(initial_window_size, max, CONN_WINDOW_AUTO_TUNING_PERIOD)
+    }
+
+    /// The first `SETTINGS` frame.
+    pub(crate) fn initial_settings_frame(&self, enable_push: bool) -> SettingsFrame {
+        if let Some(mut settings) = self.initial_settings {
+            settings.enable_push &= enable_push;
+            return SettingsFrame::from_settings(settings.to_settings());
+        }
+
+        let mut settings = vec![HttpSetting::EnablePush(enable_push)];
+        if let Some(size) = self.hpack_decoder_max_table_size {
+            settings.push(HttpSetting::HeaderTableSize(size));
+        }
+        SettingsFrame::from_settings(settings)
+    }
 }
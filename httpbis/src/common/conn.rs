@@ -1,3 +1,4 @@
+use std::cmp;
 use std::collections::HashMap;
 use std::pin::Pin;
 
@@ -7,8 +8,8 @@ use crate::AnySocketAddr;
 
 use crate::solicit::frame::GoawayFrame;
 use crate::solicit::frame::HttpFrameType;
-use crate::solicit::frame::HttpSettings;
 use crate::solicit::frame::RstStreamFrame;
+use crate::solicit::frame::Settings;
 use crate::solicit::frame::WindowUpdateFrame;
 use crate::solicit::session::StreamState;
 use crate::solicit::session::StreamStateIdleOrClosed;
@@ -21,20 +22,31 @@ use super::stream_map::*;
 use super::types::*;
 use super::window_size;
 
-pub use crate::resp::Response;
-
 use crate::client_died_error_holder::ConnDiedType;
 use crate::client_died_error_holder::SomethingDiedErrorHolder;
 use crate::codec::http_decode_read::HttpDecodeRead;
+use crate::codec::http_framed_read::DEFAULT_MAX_CONTINUATION_FRAMES;
+use crate::codec::http_framed_read::DEFAULT_MAX_HEADER_BLOCK_SIZE;
 use crate::codec::queued_write::QueuedWrite;
+use crate::common::conf::DEFAULT_STATS_INTERVAL;
+use crate::common::conn_close::ConnectionClose;
 use crate::common::conn_command_channel::ConnCommandReceiver;
 use crate::common::conn_command_channel::ConnCommandSender;
 use crate::common::conn_read::ConnReadSideCustom;
 use crate::common::conn_write::ConnWriteSideCustom;
+use crate::common::data_coalesce::DataCoalesce;
+use crate::common::flush::FlushStrategy;
 use crate::common::init_where::InitWhere;
+use crate::common::keep_alive::KeepAlive;
+use crate::common::keep_alive::KeepAliveEvent;
+use crate::common::stats::ConnectionStats;
+use crate::common::stats::FlowControlBlockedTime;
+use crate::common::stats::StatsReporter;
 use crate::hpack;
 use crate::solicit::stream_id::StreamId;
+use crate::solicit::window_size::AutoTuningWindow;
 use crate::solicit::window_size::NonNegativeWindowSize;
+use crate::solicit::window_size::PendingWindowUpdate;
 use crate::solicit::window_size::WindowSize;
 use crate::ErrorCode;
 use futures::channel::oneshot;
@@ -47,13 +59,19 @@ use futures::stream::Stream;
 use futures::task::Context;
 
 use crate::net::socket::SocketStream;
+use crate::net::socket::VectoredWriteSocket;
 use std::mem;
+use std::sync::atomic::AtomicU32;
 use std::sync::Arc;
 use std::task::Poll;
+use std::time::Duration;
+use std::time::Instant;
 use tokio::io::split;
 use tokio::io::ReadHalf;
 use tokio::io::WriteHalf;
 use tokio::runtime::Handle;
+use tokio::time;
+use tokio::time::Delay;
 
 /// Client or server fields of connection
 pub trait SideSpecific: Send + 'static {}
@@ -74,34 +92,65 @@ pub(crate) struct Conn<T: Types, I: SocketStream> {
     pub streams: StreamMap<T>,
     /// Last streams known to be closed by peer
     pub peer_closed_streams: ClosedStreams,
+    /// Last streams reset by us, frames peer sent before seeing `RST_STREAM` are ignored
+    pub reset_streams: ClosedStreams,
 
     pub last_local_stream_id: StreamId,
     pub last_peer_stream_id: StreamId,
     pub goaway_sent: Option<GoawayFrame>,
     pub goaway_received: Option<GoawayFrame>,
-    pub ping_sent: Option<u64>,
+    /// Graceful shutdown started: new peer streams are refused,
+    /// final GOAWAY is sent once all streams are finished.
+    pub draining: bool,
+    /// Outstanding PINGs by opaque data, with send time
+    pub pings_sent: HashMap<u64, (Instant, oneshot::Sender<result::Result<Duration>>)>,
+    /// Opaque data of the next PING
+    pub next_ping_opaque_data: u64,
+    /// Present if `keep_alive_timeout` is configured
+    pub keep_alive: Option<KeepAlive>,
+    /// Present if DATA coalescing is configured
+    pub data_coalesce: Option<DataCoalesce>,
+    /// Present if `on_stats` is configured
+    pub stats_reporter: Option<StatsReporter>,
+    /// Started when frames are queued with `FlushStrategy::Interval`
+    pub flush_timer: Option<Delay>,
+    /// Events processed since queue was last written
+    pub events_since_flush: u32,
+    /// Streams opened since connection start
+    pub streams_opened: u64,
+    pub flow_control_blocked_time: FlowControlBlockedTime,
 
     /// Tracks the size of the outbound flow control window
     pub out_window_size: WindowSize,
     /// Tracks the size of the inbound flow control window
     pub in_window_size: NonNegativeWindowSize,
+    /// Target size of the inbound window
+    pub in_window_target: AutoTuningWindow,
+    /// Octets consumed from inbound window, but not yet returned to peer
+    pub in_window_update: PendingWindowUpdate,
 
     /// Window size from pumper point of view
     pub pump_out_window_size: window_size::ConnOutWindowSender,
 
-    pub framed_read: HttpDecodeRead<ReadHalf<I>>,
+    pub framed_read: HttpDecodeRead<ReadHalf<VectoredWriteSocket<I>>>,
 
-    pub queued_write: QueuedWrite<WriteHalf<I>>,
+    pub queued_write: QueuedWrite<WriteHalf<VectoredWriteSocket<I>>>,
     /// The HPACK encoder used to encode headers before sending them on this connection.
     pub encoder: hpack::Encoder,
+    /// Connection configuration.
+    pub conf: CommonConf,
     pub write_rx: ConnCommandReceiver<T>,
 
     /// Last known peer settings
-    pub peer_settings: HttpSettings,
+    pub peer_settings: Settings,
+    /// Peer advertised `SETTINGS_ENABLE_CONNECT_PROTOCOL` (RFC 8441)
+    pub peer_enable_connect_protocol: bool,
+    /// `peer_settings.max_header_list_size` shared with stream senders
+    pub peer_max_header_list_size: Arc<AtomicU32>,
     /// Last our settings acknowledged
-    pub our_settings_ack: HttpSettings,
+    pub our_settings_ack: Settings,
     /// Last our settings sent
-    pub our_settings_sent: Option<HttpSettings>,
+    pub our_settings_sent: Option<Settings>,
 }
 
 impl<T, I> Drop for Conn<T, I>
@@ -124,6 +173,9 @@ pub struct ConnStateSnapshot {
     pub streams: HashMap<StreamId, HttpStreamStateSnapshot>,
 }
 
+/// Queue is written regardless of `FlushStrategy` once it reaches this size.
+const FLUSH_QUEUED_BYTES: usize = 0x8000;
+
 impl ConnStateSnapshot {
     pub fn single_stream(&self) -> (u32, &HttpStreamStateSnapshot) {
         let mut iter = self.streams.iter();
@@ -144,8 +196,8 @@ where
     pub fn new(
         loop_handle: Handle,
         specific: T::SideSpecific,
-        _conf: CommonConf,
-        sent_settings: HttpSettings,
+        conf: CommonConf,
+        sent_settings: Settings,
         to_write_tx: ConnCommandSender<T>,
         write_rx: ConnCommandReceiver<T>,
         socket: I,
@@ -156,12 +208,39 @@ where
             NonNegativeWindowSize::new(DEFAULT_SETTINGS.initial_window_size as i32);
         let out_window_size = WindowSize::new(DEFAULT_SETTINGS.initial_window_size as i32);
 
+        // Connection window is kept at least as large as configured stream window
+        let in_window_target = conf.conn_in_window(cmp::max(
+            DEFAULT_SETTINGS.initial_window_size,
+            sent_settings.initial_window_size,
+        ));
+        // Connection window starts at default size regardless of settings,
+        // so growth up to configured stream window is sent with the first update
+        let mut in_window_update = conf.pending_window_update();
+        in_window_update
+            .add(in_window_target.window() - DEFAULT_SETTINGS.initial_window_size)
+            .expect("initial window size is validated");
+
         let pump_window_size = window_size::ConnOutWindowSender::new(out_window_size.size() as u32);
 
-        let (read, write) = split(socket);
+        let (read, write) = split(VectoredWriteSocket(socket));
+
+        let mut framed_read = HttpDecodeRead::new(read);
+        framed_read.set_frame_observer(conf.frame_observer.clone());
+        framed_read.set_header_block_limits(
+            conf.max_header_block_size
+                .unwrap_or(DEFAULT_MAX_HEADER_BLOCK_SIZE),
+            conf.max_continuation_frames
+                .unwrap_or(DEFAULT_MAX_CONTINUATION_FRAMES),
+        );
+        let mut queued_write = QueuedWrite::new(write);
+        queued_write.set_frame_observer(conf.frame_observer.clone());
 
-        let framed_read = HttpDecodeRead::new(read);
-        let queued_write = QueuedWrite::new(write);
+        let mut encoder = hpack::Encoder::new();
+        if let Some(size) = conf.hpack_encoder_max_table_size {
+            if size < DEFAULT_SETTINGS.header_table_size {
+                encoder.set_max_table_size(size as usize);
+            }
+        }
 
         Conn {
             peer_addr,
@@ -174,16 +253,40 @@ where
             loop_handle,
             goaway_sent: None,
             goaway_received: None,
-            ping_sent: None,
+            draining: false,
+            pings_sent: HashMap::new(),
+            next_ping_opaque_data: 1,
+            keep_alive: conf
+                .keep_alive_timeout
+                .map(|timeout| KeepAlive::new(timeout, conf.keep_alive_ping_timeout)),
+            data_coalesce: None,
+            stats_reporter: conf.on_stats.clone().map(|callback| {
+                StatsReporter::new(
+                    callback,
+                    conf.stats_interval.unwrap_or(DEFAULT_STATS_INTERVAL),
+                )
+            }),
+            flush_timer: None,
+            events_since_flush: 0,
+            streams_opened: 0,
+            flow_control_blocked_time: FlowControlBlockedTime::new(),
             pump_out_window_size: pump_window_size,
             peer_closed_streams: ClosedStreams::new(),
+            reset_streams: ClosedStreams::new(),
             framed_read,
             queued_write,
             write_rx,
-            encoder: hpack::Encoder::new(),
+            encoder,
+            conf,
             in_window_size,
+            in_window_target,
+            in_window_update,
             out_window_size,
             peer_settings: DEFAULT_SETTINGS,
+            peer_enable_connect_protocol: false,
+            peer_max_header_list_size: Arc::new(AtomicU32::new(
+                DEFAULT_SETTINGS.max_header_list_size,
+            )),
             our_settings_ack: DEFAULT_SETTINGS,
             our_settings_sent: Some(sent_settings),
         }
@@ -205,12 +308,12 @@ where
         in_rem_content_length: Option<u64>,
         in_message_stage: InMessageStage,
         specific: T::HttpStreamSpecific,
-    ) -> (HttpStreamRef<T>, window_size::StreamOutWindowReceiver) {
+    ) -> (HttpStreamRef<'_, T>, window_size::StreamOutWindowReceiver) {
         let (out_window_sender, out_window_receiver) = self
             .pump_out_window_size
-            .new_stream(self.peer_settings.initial_window_size as u32);
+            .new_stream(self.peer_settings.initial_window_size);
 
-        let stream = HttpStreamCommon::new(
+        let mut stream = HttpStreamCommon::new(
             self.our_settings_sent().initial_window_size,
             self.peer_settings.initial_window_size,
             out_window_sender,
@@ -218,7 +321,9 @@ where
             in_message_stage,
             specific,
         );
+        stream.data_coalesce_max_bytes = self.data_coalesce.as_ref().map(|c| c.max_bytes);
 
+        self.streams_opened += 1;
         let stream = self.streams.insert(stream_id, stream);
 
         (stream, out_window_receiver)
@@ -235,14 +340,47 @@ where
         }
     }
 
-    pub fn our_settings_sent(&self) -> &HttpSettings {
+    pub fn stats(&self) -> ConnectionStats {
+        ConnectionStats {
+            active_streams: self.streams.len(),
+            streams_opened: self.streams_opened,
+            bytes_sent: self.queued_write.bytes_sent(),
+            bytes_received: self.framed_read.bytes_received(),
+            frames_sent: self.queued_write.frames_sent().clone(),
+            frames_received: self.framed_read.frames_received().clone(),
+            out_window_size: self.out_window_size.size(),
+            in_window_size: self.in_window_size.size(),
+            streams: self.streams.stats(),
+            hpack_encoder_table_size: self.encoder.table_size(),
+            hpack_decoder_table_size: self.framed_read.hpack_table_size(),
+            flow_control_blocked_stream_time: self.flow_control_blocked_time.total(),
+            socket_writes: self.queued_write.socket_writes(),
+        }
+    }
+
+    fn report_stats(&self) {
+        if let Some(stats_reporter) = &self.stats_reporter {
+            stats_reporter.report(&self.stats());
+        }
+    }
+
+    pub fn our_settings_sent(&self) -> &Settings {
         if let Some(ref sent) = self.our_settings_sent {
-            &sent
+            sent
         } else {
             &self.our_settings_ack
         }
     }
 
+    /// Apply peer `SETTINGS_HEADER_TABLE_SIZE` limited by our configuration to the encoder.
+    pub fn set_encoder_max_table_size(&mut self, peer_size: u32) {
+        let size = match self.conf.hpack_encoder_max_table_size {
+            Some(max) => cmp::min(max, peer_size),
+            None => peer_size,
+        };
+        self.encoder.set_max_table_size(size as usize);
+    }
+
     /// Internal helper method that decreases the outbound flow control window size.
     fn _decrease_out_window(&mut self, size: u32) -> result::Result<()> {
         // The size by which we decrease the window must be at most 2^31 - 1. We should be able to
@@ -263,8 +401,11 @@ where
         debug_assert!(size < 0x80000000);
         let old_in_window_size = self.in_window_size.size();
         self.in_window_size
-            .try_decrease_to_non_negative(size as i32)
-            .map_err(|_| error::Error::WindowSizeOverflow)?;
+            .try_decrease_checked(size as i32)
+            .map_err(|overshoot| {
+                warn!("peer exceeded conn window by {} octets", overshoot);
+                error::Error::WindowSizeOverflow
+            })?;
         let new_in_window_size = self.in_window_size.size();
         debug!(
             "decrease conn window: {} -> {}",
@@ -282,6 +423,15 @@ where
         Ok(())
     }
 
+    pub fn process_stats(
+        &mut self,
+        sender: oneshot::Sender<ConnectionStats>,
+    ) -> result::Result<()> {
+        // ignore send error, requester might be already gone
+        drop(sender.send(self.stats()));
+        Ok(())
+    }
+
     pub fn send_rst_stream(
         &mut self,
         stream_id: StreamId,
@@ -289,6 +439,7 @@ where
     ) -> result::Result<()> {
         // TODO: probably notify handlers
         self.streams.remove_stream(stream_id);
+        self.reset_streams.add(stream_id);
 
         let rst_stream = RstStreamFrame::new(stream_id, error_code);
         self.send_frame_and_notify(rst_stream);
@@ -319,41 +470,74 @@ where
         }
     }
 
+    /// Get stream for received frame, or send stream or connection error
+    /// if frame is not allowed in stream state (RFC 7540 section 5.1).
     pub fn get_stream_maybe_send_error(
         &mut self,
         stream_id: StreamId,
         frame_type: HttpFrameType,
-    ) -> result::Result<Option<HttpStreamRef<T>>> {
+    ) -> result::Result<Option<HttpStreamRef<'_, T>>> {
         let stream_state = self.stream_state(stream_id);
 
         match stream_state {
             StreamState::Idle => {
-                let send_connection_error = match frame_type {
-                    HttpFrameType::Headers
-                    | HttpFrameType::Priority
-                    | HttpFrameType::PushPromise => false,
-                    _ => true,
-                };
+                let send_connection_error = !matches!(
+                    frame_type,
+                    HttpFrameType::Headers | HttpFrameType::Priority | HttpFrameType::PushPromise
+                );
 
                 if send_connection_error {
-                    debug!("stream is idle: {}, sending GOAWAY", stream_id);
-                    self.send_goaway(ErrorCode::StreamClosed)?;
+                    debug!(
+                        "{} on idle stream {}, sending GOAWAY",
+                        frame_type, stream_id
+                    );
+                    self.send_goaway(ErrorCode::ProtocolError)?;
                 }
             }
             StreamState::Open | StreamState::HalfClosedLocal => {}
-            // TODO
-            StreamState::ReservedLocal | StreamState::ReservedRemote => {}
+            StreamState::ReservedLocal => {
+                let send_connection_error = !matches!(
+                    frame_type,
+                    HttpFrameType::WindowUpdate
+                        | HttpFrameType::Priority
+                        | HttpFrameType::RstStream
+                );
+
+                if send_connection_error {
+                    debug!(
+                        "{} on reserved local stream {}, sending GOAWAY",
+                        frame_type, stream_id
+                    );
+                    self.send_goaway(ErrorCode::ProtocolError)?;
+                    return Ok(None);
+                }
+            }
+            StreamState::ReservedRemote => {
+                let send_connection_error = !matches!(
+                    frame_type,
+                    HttpFrameType::Headers | HttpFrameType::Priority | HttpFrameType::RstStream
+                );
+
+                if send_connection_error {
+                    debug!(
+                        "{} on reserved remote stream {}, sending GOAWAY",
+                        frame_type, stream_id
+                    );
+                    self.send_goaway(ErrorCode::ProtocolError)?;
+                    return Ok(None);
+                }
+            }
             StreamState::HalfClosedRemote => {
                 // If an endpoint receives additional frames, other than
                 // WINDOW_UPDATE, PRIORITY, or RST_STREAM, for a stream that is in
                 // this state, it MUST respond with a stream error (Section 5.4.2) of
                 // type STREAM_CLOSED.
-                let send_rst = match frame_type {
+                let send_rst = !matches!(
+                    frame_type,
                     HttpFrameType::WindowUpdate
-                    | HttpFrameType::Priority
-                    | HttpFrameType::RstStream => false,
-                    _ => true,
-                };
+                        | HttpFrameType::Priority
+                        | HttpFrameType::RstStream
+                );
 
                 if send_rst {
                     debug!(
@@ -383,12 +567,12 @@ where
                 // time after sending END_STREAM as a connection error
                 // (Section 5.4.1) of type PROTOCOL_ERROR.
 
-                let send_stream_closed = match frame_type {
+                let send_stream_closed = !matches!(
+                    frame_type,
                     HttpFrameType::RstStream
-                    | HttpFrameType::Priority
-                    | HttpFrameType::WindowUpdate => false,
-                    _ => true,
-                };
+                        | HttpFrameType::Priority
+                        | HttpFrameType::WindowUpdate
+                );
 
                 // TODO: http2 spec requires sending stream or connection error
                 // depending on how stream was closed
@@ -396,6 +580,12 @@ where
                     if self.peer_closed_streams.contains(stream_id) {
                         debug!("stream is closed by peer: {}, sending GOAWAY", stream_id);
                         self.send_goaway(ErrorCode::StreamClosed)?;
+                    } else if self.reset_streams.contains(stream_id) {
+                        // 5.1: frames received after sending RST_STREAM must be ignored
+                        debug!(
+                            "stream is reset by us: {}, ignoring {}",
+                            stream_id, frame_type
+                        );
                     } else {
                         debug!("stream is closed by us: {}, sending RST_STREAM", stream_id);
                         self.send_rst_stream(stream_id, ErrorCode::StreamClosed)?;
@@ -410,13 +600,18 @@ where
     pub fn get_stream_for_headers_maybe_send_error(
         &mut self,
         stream_id: StreamId,
-    ) -> result::Result<Option<HttpStreamRef<T>>> {
+    ) -> result::Result<Option<HttpStreamRef<'_, T>>> {
         self.get_stream_maybe_send_error(stream_id, HttpFrameType::Headers)
     }
 
     pub fn increase_in_window(&mut self, stream_id: StreamId, increase: u32) -> result::Result<()> {
         if let Some(mut stream) = self.streams.get_mut(stream_id) {
-            if let Err(_) = stream.stream().in_window_size.try_increase(increase) {
+            if stream
+                .stream()
+                .in_window_size
+                .try_increase(increase)
+                .is_err()
+            {
                 return Err(error::Error::StreamInWindowOverflow(
                     stream_id,
                     stream.stream().in_window_size.size(),
@@ -433,9 +628,73 @@ where
         Ok(())
     }
 
+    /// Ready when queued frames should be written according to `FlushStrategy::Interval`.
+    fn poll_flush_interval(&mut self, cx: &mut Context<'_>, interval: Duration) -> bool {
+        if self.queued_write.queued_bytes_len() == 0 {
+            return false;
+        }
+        let timer = self
+            .flush_timer
+            .get_or_insert_with(|| time::delay_for(interval));
+        if Pin::new(timer).poll(cx).is_pending() {
+            return false;
+        }
+        self.flush_timer = None;
+        true
+    }
+
     fn poll_next_event(&mut self, cx: &mut Context<'_>) -> Poll<result::Result<LoopEvent<T>>> {
-        // Always flush outgoing queue
-        self.poll_flush(cx)?;
+        let flush_strategy = self.conf.flush_strategy.unwrap_or_default();
+        // Connection is closing after GOAWAY, peer may still be sending frames
+        let mut flush = flush_strategy.flush_before_next_event(self.events_since_flush)
+            || self.queued_write.goaway_queued()
+            || self.queued_write.queued_bytes_len() >= FLUSH_QUEUED_BYTES;
+
+        loop {
+            if flush {
+                self.events_since_flush = 0;
+            }
+            if let Poll::Ready(event) = self.poll_next_event_flush(cx, flush)? {
+                self.events_since_flush += 1;
+                return Poll::Ready(Ok(event));
+            }
+
+            // Nothing is ready
+            if flush {
+                return Poll::Pending;
+            }
+            flush = match flush_strategy {
+                FlushStrategy::Immediate | FlushStrategy::OnIdle => true,
+                FlushStrategy::Interval(interval) => self.poll_flush_interval(cx, interval),
+            };
+            if !flush {
+                return Poll::Pending;
+            }
+        }
+    }
+
+    fn poll_next_event_flush(
+        &mut self,
+        cx: &mut Context<'_>,
+        flush: bool,
+    ) -> Poll<result::Result<LoopEvent<T>>> {
+        if self.draining && self.streams.is_empty() && !self.queued_write.goaway_queued() {
+            // Final GOAWAY with the last stream we actually processed
+            self.send_goaway(ErrorCode::NoError)?;
+        }
+
+        // Frames are queued in the same order regardless of flush strategy
+        loop {
+            if flush {
+                self.poll_flush(cx)?;
+            } else {
+                self.buffer_outg_conn()?;
+            }
+            // streams may have been closed by flush
+            if !self.start_queued_streams()? {
+                break;
+            }
+        }
 
         if self.queued_write.goaway_queued_and_flushed() {
             info!("GOAWAY written and flushed, closing connection");
@@ -457,10 +716,51 @@ where
         };
 
         match self.poll_recv_http_frame(cx)? {
-            Poll::Ready(m) => return Poll::Ready(Ok(LoopEvent::Frame(m))),
+            Poll::Ready(m) => {
+                if let Some(keep_alive) = &mut self.keep_alive {
+                    keep_alive.frame_received();
+                }
+                return Poll::Ready(Ok(LoopEvent::Frame(m)));
+            }
             Poll::Pending => {}
         }
 
+        if let Some(keep_alive) = &mut self.keep_alive {
+            match keep_alive.poll(cx) {
+                Poll::Pending => {}
+                Poll::Ready(KeepAliveEvent::SendPing) => {
+                    debug!("nothing received within keep alive timeout, sending PING");
+                    // ACK is not awaited explicitly: any received frame resets the timer
+                    let (tx, _rx) = oneshot::channel();
+                    self.process_ping_message(tx)?;
+                    // Flush PING and register timer waker
+                    return self.poll_next_event(cx);
+                }
+                Poll::Ready(KeepAliveEvent::Close) => {
+                    info!("nothing received within keep alive timeout, closing connection");
+                    return Poll::Ready(Err(error::Error::KeepAliveTimeout));
+                }
+            }
+        }
+
+        if let Some(data_coalesce) = &mut self.data_coalesce {
+            if data_coalesce.poll(cx).is_ready() {
+                self.streams.flush_coalesced_data();
+                // Flush DATA and register timer waker
+                return self.poll_next_event(cx);
+            }
+        }
+
+        let stats_due = match &mut self.stats_reporter {
+            Some(stats_reporter) => stats_reporter.poll(cx).is_ready(),
+            None => false,
+        };
+        if stats_due {
+            self.report_stats();
+            // Register timer waker
+            return self.poll_next_event(cx);
+        }
+
         Poll::Pending
     }
 
@@ -469,7 +769,7 @@ where
         future::poll_fn(|cx| self.poll_next_event(cx)).await
     }
 
-    async fn run_loop(mut self) -> result::Result<()> {
+    async fn run_loop(&mut self) -> result::Result<()> {
         loop {
             let event = self.next_event().await?;
             match event {
@@ -480,8 +780,31 @@ where
         }
     }
 
-    pub fn run(self) -> impl Future<Output = result::Result<()>> + Send {
+    fn connection_close(&self, r: result::Result<()>) -> ConnectionClose {
+        match r {
+            Err(e) => ConnectionClose::from(e),
+            Ok(()) => match (&self.goaway_sent, &self.goaway_received) {
+                (Some(frame), _) if self.queued_write.goaway_queued_and_flushed() => {
+                    ConnectionClose::goaway_sent(frame)
+                }
+                (_, Some(frame)) => ConnectionClose::goaway_received(frame),
+                (Some(frame), None) => ConnectionClose::goaway_sent(frame),
+                (None, None) => ConnectionClose::Dropped,
+            },
+        }
+    }
+
+    async fn run_impl(mut self) -> ConnectionClose {
+        let r = self.run_loop().await;
+        let close = self.connection_close(r);
+        info!("connection closed: {}", close);
+        self.report_stats();
+        mem::take(&mut self.streams).conn_died(|| error::Error::ConnectionClosed(close.clone()));
+        close
+    }
+
+    pub fn run(self) -> impl Future<Output = ConnectionClose> + Send {
         let ndc = Arc::new(format!("{} {}", T::CONN_NDC, self.peer_addr));
-        log_ndc_future(ndc, self.run_loop())
+        log_ndc_future(ndc, self.run_impl())
     }
 }
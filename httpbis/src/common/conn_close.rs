@@ -0,0 +1,95 @@
+//! Reason connection was closed.
+
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+
+use bytes::Bytes;
+
+use crate::error;
+use crate::solicit::frame::GoawayFrame;
+use crate::ErrorCode;
+
+/// Why connection was closed.
+///
+/// Returned from server connection future, and passed to
+/// each stream outstanding at close as `Error::ConnectionClosed`.
+#[derive(Debug, Clone)]
+pub enum ConnectionClose {
+    /// Final `GOAWAY` sent by us, either on shutdown or on error detected by us.
+    GoawaySent {
+        error_code: ErrorCode,
+        debug_data: Bytes,
+    },
+    /// `GOAWAY` received from peer, and all streams below last stream id are completed.
+    GoawayReceived {
+        error_code: ErrorCode,
+        debug_data: Bytes,
+    },
+    /// Peer closed the socket without `GOAWAY`.
+    PeerClosed,
+    /// Socket IO error.
+    IoError(Arc<io::Error>),
+    /// Peer violated the protocol, connection closed without `GOAWAY`.
+    ProtocolError(Arc<error::Error>),
+    /// Nothing received within keep alive timeout.
+    IdleTimeout,
+    /// All connection handles were dropped.
+    Dropped,
+}
+
+impl ConnectionClose {
+    pub(crate) fn goaway_sent(frame: &GoawayFrame) -> ConnectionClose {
+        ConnectionClose::GoawaySent {
+            error_code: frame.error_code(),
+            debug_data: frame.debug_data.clone(),
+        }
+    }
+
+    pub(crate) fn goaway_received(frame: &GoawayFrame) -> ConnectionClose {
+        ConnectionClose::GoawayReceived {
+            error_code: frame.error_code(),
+            debug_data: frame.debug_data.clone(),
+        }
+    }
+
+    /// Error code of `GOAWAY` frame, `None` if connection was closed without `GOAWAY`.
+    pub fn error_code(&self) -> Option<ErrorCode> {
+        match self {
+            ConnectionClose::GoawaySent { error_code, .. }
+            | ConnectionClose::GoawayReceived { error_code, .. } => Some(*error_code),
+            _ => None,
+        }
+    }
+}
+
+impl From<error::Error> for ConnectionClose {
+    fn from(e: error::Error) -> ConnectionClose {
+        match e {
+            error::Error::EofFromStream => ConnectionClose::PeerClosed,
+            error::Error::IoError(e) => ConnectionClose::IoError(Arc::new(e)),
+            error::Error::KeepAliveTimeout => ConnectionClose::IdleTimeout,
+            // Connection command channel closed
+            error::Error::ClientDied(None) => ConnectionClose::Dropped,
+            e => ConnectionClose::ProtocolError(Arc::new(e)),
+        }
+    }
+}
+
+impl fmt::Display for ConnectionClose {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConnectionClose::GoawaySent { error_code, .. } => {
+                write!(f, "GOAWAY sent with code {:?}", error_code)
+            }
+            ConnectionClose::GoawayReceived { error_code, .. } => {
+                write!(f, "GOAWAY received with code {:?}", error_code)
+            }
+            ConnectionClose::PeerClosed => write!(f, "peer closed connection"),
+            ConnectionClose::IoError(e) => write!(f, "IO error: {}", e),
+            ConnectionClose::ProtocolError(e) => write!(f, "protocol error: {}", e),
+            ConnectionClose::IdleTimeout => write!(f, "keep alive timeout"),
+            ConnectionClose::Dropped => write!(f, "connection dropped"),
+        }
+    }
+}
@@ -7,6 +7,7 @@ use crate::common::stream::HttpStreamCommon;
 use crate::common::stream::HttpStreamData;
 use crate::common::stream::InMessageStage;
 use crate::common::stream_map::HttpStreamRef;
+use crate::common::trace;
 use crate::common::types::Types;
 use crate::error;
 use crate::result;
@@ -18,14 +19,16 @@ use crate::solicit::frame::HeadersDecodedFrame;
 use crate::solicit::frame::HttpFrameDecoded;
 use crate::solicit::frame::HttpFrameType;
 use crate::solicit::frame::HttpSetting;
+use crate::solicit::frame::OriginFrame;
 use crate::solicit::frame::PingFrame;
 use crate::solicit::frame::PriorityFrame;
+use crate::solicit::frame::PushPromiseDecodedFrame;
 use crate::solicit::frame::RstStreamFrame;
 use crate::solicit::frame::SettingsFrame;
 use crate::solicit::frame::WindowUpdateFrame;
+use crate::solicit::frame::ORIGIN_FRAME_TYPE;
 use crate::solicit::stream_id::StreamId;
 use crate::solicit::window_size::MAX_WINDOW_SIZE;
-use crate::solicit::DEFAULT_SETTINGS;
 use crate::solicit_misc::HttpFrameClassified;
 use crate::solicit_misc::HttpFrameConn;
 use crate::solicit_misc::HttpFrameStream;
@@ -34,7 +37,9 @@ use crate::Headers;
 
 use crate::net::socket::SocketStream;
 use futures::task::Context;
+use std::sync::atomic::Ordering;
 use std::task::Poll;
+use std::time::Instant;
 
 pub(crate) trait ConnReadSideCustom {
     type Types: Types;
@@ -44,7 +49,27 @@ pub(crate) trait ConnReadSideCustom {
         stream_id: StreamId,
         end_stream: EndStream,
         headers: Headers,
-    ) -> result::Result<Option<HttpStreamRef<Self::Types>>>;
+    ) -> result::Result<Option<HttpStreamRef<'_, Self::Types>>>;
+
+    /// Peer sent `PUSH_PROMISE` frame.
+    fn process_push_promise(&mut self, frame: PushPromiseDecodedFrame) -> result::Result<()>;
+
+    /// Peer reset a stream which was not completed.
+    fn process_rst_stream_of_open_stream(&mut self, _stream_id: StreamId) -> result::Result<()> {
+        Ok(())
+    }
+
+    /// Peer settings frame applied.
+    fn process_peer_settings_applied(&mut self) {}
+
+    /// Peer acknowledged our settings.
+    fn process_our_settings_acked(&mut self) {}
+
+    /// First `GOAWAY` frame received from peer.
+    fn process_goaway_received(&mut self, _frame: &GoawayFrame) {}
+
+    /// Peer sent valid `ORIGIN` frame.
+    fn process_origin(&mut self, _frame: OriginFrame) {}
 }
 
 impl<T, I> Conn<T, I>
@@ -61,33 +86,56 @@ where
         cx: &mut Context<'_>,
     ) -> Poll<result::Result<HttpFrameDecodedOrGoaway>> {
         let max_frame_size = self.our_settings_ack.max_frame_size;
+        let max_header_list_size = self.our_settings_ack.max_header_list_size;
 
-        self.framed_read.poll_http_frame(cx, max_frame_size)
+        self.framed_read
+            .poll_http_frame(cx, max_frame_size, max_header_list_size)
     }
 
-    fn process_data_frame(&mut self, frame: DataFrame) -> result::Result<Option<HttpStreamRef<T>>> {
+    fn process_data_frame(
+        &mut self,
+        frame: DataFrame,
+    ) -> result::Result<Option<HttpStreamRef<'_, T>>> {
         let stream_id = frame.get_stream_id();
 
-        self.decrease_in_window(frame.payload_len())?;
+        // A receiver MAY respond with a connection error (Section 5.4.1) of type
+        // FLOW_CONTROL_ERROR if it is unable to accept a frame.
+        if self.decrease_in_window(frame.payload_len()).is_err() {
+            self.send_flow_control_error()?;
+            return Ok(None);
+        }
 
-        let increment_conn =
-        // TODO: need something better
-            if self.in_window_size.size() < (DEFAULT_SETTINGS.initial_window_size / 2) as i32 {
-                let increment = DEFAULT_SETTINGS.initial_window_size;
+        // Connection window is returned to peer as soon as frame is received,
+        // stream windows limit buffered data
+        self.in_window_target
+            .record_consumed(frame.payload_len(), Instant::now());
+        if let Some(update) = self.in_window_target.recommended_update() {
+            self.in_window_update.add(update).map_err(|()| {
+                error::Error::ConnInWindowOverflow(self.in_window_size.size(), update)
+            })?;
+        }
+        let window = self.in_window_target.window();
+        let increment_conn = match self.in_window_update.take_if_threshold(window) {
+            Some(increment) => {
                 let old_in_window_size = self.in_window_size.size();
-                self.in_window_size.try_increase(increment)
-                    .map_err(|()| error::Error::ConnInWindowOverflow(self.in_window_size.size(), increment))?;
+                self.in_window_size.try_increase(increment).map_err(|()| {
+                    error::Error::ConnInWindowOverflow(self.in_window_size.size(), increment)
+                })?;
                 let new_in_window_size = self.in_window_size.size();
-                debug!("requesting increase in window: {} -> {}", old_in_window_size, new_in_window_size);
+                debug!(
+                    "requesting increase in window: {} -> {}",
+                    old_in_window_size, new_in_window_size
+                );
 
                 Some(increment)
-            } else {
-                None
-            };
+            }
+            None => None,
+        };
 
         let mut error = None;
+        let mut increment_padding = None;
 
-        loop {
+        'stream: {
             // If a DATA frame is received whose stream is not in "open" or
             // "half-closed (local)" state, the recipient MUST respond with
             // a stream error (Section 5.4.2) of type STREAM_CLOSED.
@@ -96,6 +144,11 @@ where
             {
                 Some(stream) => stream,
                 None => {
+                    // Connection window is already increased, peer must be told
+                    if let Some(increment_conn) = increment_conn {
+                        let window_update = WindowUpdateFrame::for_connection(increment_conn);
+                        self.send_frame_and_notify(window_update);
+                    }
                     return Ok(None);
                 }
             };
@@ -104,24 +157,39 @@ where
                 if in_rem_content_length < frame.data.len() as u64 {
                     warn!("stream data underflow content-length");
                     error = Some(ErrorCode::ProtocolError);
-                    break;
+                    break 'stream;
                 }
 
                 let in_rem_content_length = in_rem_content_length - frame.data.len() as u64;
                 stream.stream().in_rem_content_length = Some(in_rem_content_length);
             }
 
-            assert_eq!(
-                InMessageStage::AfterInitialHeaders,
-                stream.stream().in_message_stage
-            );
+            if frame.is_end_of_stream() && stream.stream().in_content_length_incomplete() {
+                warn!("stream {} data shorter than content-length", stream_id);
+                error = Some(ErrorCode::ProtocolError);
+                break 'stream;
+            }
+
+            if stream.stream().in_message_stage != InMessageStage::AfterInitialHeaders {
+                warn!("DATA before HEADERS in stream {}", stream_id);
+                error = Some(ErrorCode::ProtocolError);
+                break 'stream;
+            }
 
             let old_in_window_size = stream.stream().in_window_size.size();
-            stream
+            if let Err(overshoot) = stream
                 .stream()
                 .in_window_size
-                .try_decrease_to_non_negative(frame.payload_len() as i32)
-                .map_err(|()| error::Error::CodeError(ErrorCode::FlowControlError))?;
+                .try_decrease_checked(frame.payload_len() as i32)
+            {
+                warn!(
+                    "peer exceeded stream {} window by {} octets",
+                    frame.get_stream_id(),
+                    overshoot
+                );
+                self.send_flow_control_error()?;
+                return Ok(None);
+            }
             let new_in_window_size = stream.stream().in_window_size.size();
 
             debug!(
@@ -131,9 +199,20 @@ where
                 new_in_window_size
             );
 
+            // Padding is never delivered to the application,
+            // so return its flow control credit immediately.
+            let padding = frame.payload_len() - frame.data.len() as u32;
+            if padding != 0 && !frame.is_end_of_stream() {
+                stream
+                    .stream()
+                    .in_window_size
+                    .try_increase(padding)
+                    .expect("padding was just subtracted from the window");
+                increment_padding = Some(padding);
+            }
+
             let end_of_stream = frame.is_end_of_stream();
             stream.stream().data_recvd(frame.data, end_of_stream);
-            break;
         }
 
         if let Some(increment_conn) = increment_conn {
@@ -141,6 +220,11 @@ where
             self.send_frame_and_notify(window_update);
         }
 
+        if let Some(increment_padding) = increment_padding {
+            let window_update = WindowUpdateFrame::for_stream(stream_id, increment_padding);
+            self.send_frame_and_notify(window_update);
+        }
+
         if let Some(error) = error {
             self.send_rst_stream(stream_id, error)?;
             return Ok(None);
@@ -155,19 +239,17 @@ where
 
     fn process_ping(&mut self, frame: PingFrame) -> result::Result<()> {
         if frame.is_ack() {
-            if let Some(opaque_data) = self.ping_sent.take() {
-                if opaque_data == frame.opaque_data {
-                    Ok(())
-                } else {
-                    Err(error::Error::PingAckOpaqueDataMismatch(
-                        opaque_data,
-                        frame.opaque_data,
-                    ))
-                }
+            if let Some((sent, sender)) = self.pings_sent.remove(&frame.opaque_data) {
+                // ignore error: caller may be no longer interested in the result
+                drop(sender.send(Ok(sent.elapsed())));
             } else {
-                warn!("PING ACK without PING");
-                Ok(())
+                // Outstanding PINGs, if any, are still waiting for their ACKs
+                warn!(
+                    "PING ACK {} does not match any outstanding PING",
+                    frame.opaque_data
+                );
             }
+            Ok(())
         } else {
             let ping = PingFrame::new_ack(frame.opaque_data());
             self.send_frame_and_notify(ping);
@@ -176,41 +258,76 @@ where
     }
 
     fn process_goaway(&mut self, frame: GoawayFrame) -> result::Result<()> {
-        if let Some(..) = self.goaway_received {
-            return Err(error::Error::GoawayAfterGoaway);
+        // Several GOAWAY frames are allowed (RFC 7540 section 6.8),
+        // but last stream id must not increase
+        if let Some(prev) = &self.goaway_received {
+            if frame.last_stream_id > prev.last_stream_id {
+                return Err(error::Error::GoawayAfterGoaway);
+            }
         }
 
         let last_stream_id = frame.last_stream_id;
-        let raw_error_code = frame.error_code.0;
-
-        self.goaway_received = Some(frame);
 
         for (stream_id, mut stream) in self.streams.remove_local_streams_with_id_gt(last_stream_id)
         {
             debug!("removed stream {} because of GOAWAY", stream_id);
-            stream.goaway_recvd(raw_error_code);
+            stream.goaway_recvd(&frame);
         }
 
+        self.process_goaway_received(&frame);
+
+        self.goaway_received = Some(frame);
+
         Ok(())
     }
 
     fn process_headers_frame(
         &mut self,
         frame: HeadersDecodedFrame,
-    ) -> result::Result<Option<HttpStreamRef<T>>> {
+    ) -> result::Result<Option<HttpStreamRef<'_, T>>> {
         let end_stream = if frame.is_end_of_stream() {
             EndStream::Yes
         } else {
             EndStream::No
         };
 
-        self.process_headers(frame.stream_id, end_stream, frame.headers)
+        let stream_id = frame.stream_id;
+
+        // A stream cannot depend on itself (RFC 7540 section 5.3.1).
+        if let Some(stream_dep) = &frame.stream_dep {
+            if stream_dep.stream_id == stream_id {
+                warn!("stream {} depends on itself", stream_id);
+                self.send_rst_stream(stream_id, ErrorCode::ProtocolError)?;
+                return Ok(None);
+            }
+        }
+
+        if self
+            .process_headers(stream_id, end_stream, frame.headers)?
+            .is_none()
+        {
+            return Ok(None);
+        }
+        if let Some(stream_dep) = frame.stream_dep {
+            self.streams.set_priority(stream_id, stream_dep);
+        }
+        Ok(self.streams.get_mut(stream_id))
     }
 
     fn process_priority_frame(
         &mut self,
         frame: PriorityFrame,
-    ) -> result::Result<Option<HttpStreamRef<T>>> {
+    ) -> result::Result<Option<HttpStreamRef<'_, T>>> {
+        // A stream cannot depend on itself. An endpoint MUST treat this as a
+        // stream error (Section 5.4.2) of type PROTOCOL_ERROR.
+        if frame.stream_dep == frame.stream_id {
+            warn!("stream {} depends on itself", frame.stream_id);
+            self.send_rst_stream(frame.stream_id, ErrorCode::ProtocolError)?;
+            return Ok(None);
+        }
+
+        self.streams
+            .set_priority(frame.get_stream_id(), frame.stream_dependency());
         Ok(self.streams.get_mut(frame.get_stream_id()))
     }
 
@@ -218,7 +335,14 @@ where
         assert!(frame.is_ack());
 
         if let Some(settings) = self.our_settings_sent.take() {
+            if settings.header_table_size != self.our_settings_ack.header_table_size {
+                self.framed_read
+                    .set_max_table_size(settings.header_table_size);
+            }
+            debug!("our settings acknowledged: {:?}", settings);
+            trace::our_settings_acked(&settings);
             self.our_settings_ack = settings;
+            self.process_our_settings_acked();
             Ok(())
         } else {
             Err(error::Error::SettingsAckWithoutSettingsSent)
@@ -228,6 +352,8 @@ where
     fn process_settings_req(&mut self, frame: SettingsFrame) -> result::Result<()> {
         assert!(!frame.is_ack());
 
+        debug!("peer settings changed: {:?}", frame.settings);
+
         for setting in frame.settings {
             match setting {
                 HttpSetting::InitialWindowSize(new_size) => {
@@ -241,19 +367,44 @@ where
                     }
 
                     let old_size = self.peer_settings.initial_window_size;
-                    let delta = (new_size as i32) - (old_size as i32);
 
-                    if delta != 0 {
-                        self.streams.add_out_window(delta);
+                    if new_size != old_size {
+                        // An endpoint MUST treat a change to SETTINGS_INITIAL_WINDOW_SIZE
+                        // that causes any flow-control window to exceed the maximum size
+                        // as a connection error of type FLOW_CONTROL_ERROR.
+                        if let Err(()) = self.streams.add_out_window(old_size, new_size) {
+                            self.send_flow_control_error()?;
+                            return Ok(());
+                        }
                     }
                 }
-                HttpSetting::HeaderTableSize(_new_size) => {}
+                HttpSetting::HeaderTableSize(new_size) => {
+                    // The encoder emits dynamic table size update
+                    // at the beginning of the next header block.
+                    self.set_encoder_max_table_size(new_size);
+                }
+                HttpSetting::EnableConnectProtocol(false) if self.peer_enable_connect_protocol => {
+                    // A sender MUST NOT send a SETTINGS_ENABLE_CONNECT_PROTOCOL parameter
+                    // with the value of 0 after previously sending a value of 1
+                    // (RFC 8441 section 3).
+                    warn!("peer disabled extended CONNECT");
+                    self.send_goaway(ErrorCode::ProtocolError)?;
+                    return Ok(());
+                }
+                HttpSetting::EnableConnectProtocol(enabled) => {
+                    self.peer_enable_connect_protocol = enabled;
+                }
                 _ => {}
             }
 
             self.peer_settings.apply(setting);
         }
 
+        self.peer_max_header_list_size
+            .store(self.peer_settings.max_header_list_size, Ordering::SeqCst);
+        trace::peer_settings_changed(&self.peer_settings);
+        self.process_peer_settings_applied();
+
         self.send_ack_settings()?;
 
         Ok(())
@@ -270,7 +421,7 @@ where
     fn process_stream_window_update_frame(
         &mut self,
         frame: WindowUpdateFrame,
-    ) -> result::Result<Option<HttpStreamRef<T>>> {
+    ) -> result::Result<Option<HttpStreamRef<'_, T>>> {
         let mut stream =
             match self.get_stream_maybe_send_error(frame.stream_id, HttpFrameType::WindowUpdate)? {
                 Some(s) => s,
@@ -285,6 +436,17 @@ where
                 }
             };
 
+        // 6.9
+        // A receiver MUST treat the receipt of a WINDOW_UPDATE frame with a
+        // flow-control window increment of 0 as a stream error of type
+        // PROTOCOL_ERROR; errors on the connection flow-control window MUST be
+        // treated as a connection error.
+        if frame.increment == 0 {
+            info!("zero window increment on stream: {}", frame.stream_id);
+            self.send_rst_stream(frame.stream_id, ErrorCode::ProtocolError)?;
+            return Ok(None);
+        }
+
         // 6.9.1
         // A sender MUST NOT allow a flow-control window to exceed 2^31-1
         // octets.  If a sender receives a WINDOW_UPDATE that causes a flow-
@@ -293,7 +455,7 @@ where
         // sends a RST_STREAM with an error code of FLOW_CONTROL_ERROR; for the
         // connection, a GOAWAY frame with an error code of FLOW_CONTROL_ERROR
         // is sent.
-        if let Err(..) = stream.try_increase_window_size(frame.increment) {
+        if stream.try_increase_window_size(frame.increment).is_err() {
             info!("failed to increment stream window: {}", frame.stream_id);
             self.send_rst_stream(frame.stream_id, ErrorCode::FlowControlError)?;
             return Ok(None);
@@ -315,6 +477,12 @@ where
 
         let old_window_size = self.out_window_size.size();
 
+        // 6.9
+        if frame.increment == 0 {
+            info!("zero connection window increment");
+            return self.send_goaway(ErrorCode::ProtocolError);
+        }
+
         // 6.9.1
         // A sender MUST NOT allow a flow-control window to exceed 2^31-1
         // octets.  If a sender receives a WINDOW_UPDATE that causes a flow-
@@ -323,7 +491,7 @@ where
         // sends a RST_STREAM with an error code of FLOW_CONTROL_ERROR; for the
         // connection, a GOAWAY frame with an error code of FLOW_CONTROL_ERROR
         // is sent.
-        if let Err(_) = self.out_window_size.try_increase(frame.increment) {
+        if self.out_window_size.try_increase(frame.increment).is_err() {
             info!("attempted to increase window size too far");
             self.send_flow_control_error()?;
             return Ok(());
@@ -341,12 +509,14 @@ where
     fn process_rst_stream_frame(
         &mut self,
         frame: RstStreamFrame,
-    ) -> result::Result<Option<HttpStreamRef<T>>> {
+    ) -> result::Result<Option<HttpStreamRef<'_, T>>> {
         let stream_id = frame.get_stream_id();
         let dropped_data = if let Some(stream) =
             self.get_stream_maybe_send_error(stream_id, HttpFrameType::RstStream)?
         {
-            stream.rst_received_remove(frame.error_code())
+            let dropped_data = stream.rst_received_remove(frame.error_code());
+            self.process_rst_stream_of_open_stream(stream_id)?;
+            dropped_data
         } else {
             DroppedData { size: 0 }
         };
@@ -377,11 +547,9 @@ where
         // 6.8
         // Once sent, the sender will ignore frames sent on streams initiated by the receiver
         // if the stream has an identifier higher than the included last stream identifier.
-        if let Some(ref f) = self.goaway_sent.as_ref() {
-            if T::init_where(stream_id) != InitWhere::Locally {
-                if stream_id > f.last_stream_id {
-                    return Ok(());
-                }
+        if let Some(f) = self.goaway_sent.as_ref() {
+            if T::init_where(stream_id) != InitWhere::Locally && stream_id > f.last_stream_id {
+                return Ok(());
             }
         }
 
@@ -391,8 +559,9 @@ where
                 HttpFrameStream::Headers(headers) => self.process_headers_frame(headers)?,
                 HttpFrameStream::Priority(priority) => self.process_priority_frame(priority)?,
                 HttpFrameStream::RstStream(rst) => self.process_rst_stream_frame(rst)?,
-                HttpFrameStream::PushPromise(_f) => {
-                    return Err(error::Error::NotImplemented("PUSH_PROMISE"))
+                HttpFrameStream::PushPromise(push_promise) => {
+                    self.process_push_promise(push_promise)?;
+                    None
                 }
                 HttpFrameStream::WindowUpdate(window_update) => {
                     self.process_stream_window_update_frame(window_update)?
@@ -418,7 +587,18 @@ where
         match HttpFrameClassified::from(frame) {
             HttpFrameClassified::Conn(f) => self.process_conn_frame(f),
             HttpFrameClassified::Stream(f) => self.process_stream_frame(f),
-            HttpFrameClassified::Unknown(_f) => {
+            HttpFrameClassified::Unknown(f) => {
+                // Extension frames are reported to callback even if understood
+                if let Some(on_unknown_frame) = &self.conf.on_unknown_frame {
+                    on_unknown_frame(f.frame_type(), f.get_stream_id(), &f.payload());
+                }
+                if f.frame_type() == ORIGIN_FRAME_TYPE {
+                    // RFC 8336 2.1: ignored on non-zero stream
+                    match OriginFrame::from_raw(&f) {
+                        Ok(frame) => self.process_origin(frame),
+                        Err(e) => warn!("ignoring ORIGIN frame: {:?}", e),
+                    }
+                }
                 // 4.1
                 // Implementations MUST ignore and discard any frame that has a type that is unknown.
                 Ok(())
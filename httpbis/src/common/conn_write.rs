@@ -8,22 +8,33 @@ use crate::data_or_headers_with_flag::DataOrHeadersWithFlag;
 use crate::common::conn::ConnStateSnapshot;
 use crate::common::conn_read::ConnReadSideCustom;
 use crate::common::pump_stream_to_write_loop::PumpStreamToWrite;
+use crate::common::scheduling::fair_quotas;
+use crate::common::scheduling::weighted_quotas;
+use crate::common::scheduling::SchedulingPolicy;
+use crate::common::stats::ConnectionStats;
 use crate::common::stream::HttpStreamCommand;
 use crate::common::window_size::StreamOutWindowReceiver;
 use crate::data_or_headers::DataOrHeaders;
 
+use crate::error::Error;
 use crate::result;
 use crate::solicit::end_stream::EndStream;
 use crate::solicit::frame::DataFlag;
 use crate::solicit::frame::DataFrame;
 use crate::solicit::frame::Flags;
+use crate::solicit::frame::FrameHeader;
 use crate::solicit::frame::GoawayFrame;
 use crate::solicit::frame::HeadersFlag;
 use crate::solicit::frame::HeadersMultiFrame;
 use crate::solicit::frame::HttpFrame;
+use crate::solicit::frame::PingFrame;
+use crate::solicit::frame::RawFrame;
+use crate::solicit::frame::RawHttpFrameType;
 use crate::solicit::frame::RstStreamFrame;
 use crate::solicit::frame::SettingsFrame;
 use crate::solicit::stream_id::StreamId;
+use crate::solicit::stream_id::MAX_STREAM_ID;
+use crate::solicit::window_size::WindowSize;
 use crate::ErrorCode;
 use crate::Headers;
 use crate::HttpStreamAfterHeaders;
@@ -34,6 +45,8 @@ use std::cmp;
 
 use crate::net::socket::SocketStream;
 use std::task::Poll;
+use std::time::Duration;
+use std::time::Instant;
 
 pub(crate) trait ConnWriteSideCustom {
     type Types: Types;
@@ -42,6 +55,13 @@ pub(crate) trait ConnWriteSideCustom {
         &mut self,
         message: <Self::Types as Types>::ToWriteMessage,
     ) -> result::Result<()>;
+
+    /// Start streams queued locally because of peer `SETTINGS_MAX_CONCURRENT_STREAMS`.
+    ///
+    /// Return `true` if any stream was started.
+    fn start_queued_streams(&mut self) -> result::Result<bool> {
+        Ok(false)
+    }
 }
 
 impl<T, I> Conn<T, I>
@@ -57,7 +77,7 @@ where
 
         // if client requested end of stream,
         // we must send at least one frame with end stream flag
-        if end_stream == EndStream::Yes && data.len() == 0 {
+        if end_stream == EndStream::Yes && data.is_empty() {
             let mut frame = DataFrame::with_data(stream_id, Bytes::new());
             frame.set_flag(DataFlag::EndStream);
 
@@ -98,7 +118,6 @@ where
             flags,
             stream_id,
             headers,
-            stream_dep: None,
             padding_len: 0,
             encoder: &mut self.encoder,
             max_frame_size: self.peer_settings.max_frame_size,
@@ -142,13 +161,20 @@ where
     }
 
     pub fn buffer_outg_conn(&mut self) -> result::Result<bool> {
-        let mut updated = false;
-
         // shortcut
         if !self.has_write_buffer_capacity() {
-            return Ok(updated);
+            return Ok(false);
         }
 
+        match self.conf.scheduling_policy.unwrap_or_default() {
+            SchedulingPolicy::Fifo => self.buffer_outg_conn_fifo(),
+            policy => self.buffer_outg_conn_quotas(policy),
+        }
+    }
+
+    fn buffer_outg_conn_fifo(&mut self) -> result::Result<bool> {
+        let mut updated = false;
+
         let writable_stream_ids = self.streams.writable_stream_ids();
 
         for &stream_id in &writable_stream_ids {
@@ -174,6 +200,82 @@ where
         Ok(updated)
     }
 
+    /// Serve writable streams with per round quotas of connection window.
+    fn buffer_outg_conn_quotas(&mut self, policy: SchedulingPolicy) -> result::Result<bool> {
+        let mut updated = false;
+
+        // Repeat while progress is made: window unused by blocked streams
+        // and streams unblocked by parents are served in the next round.
+        loop {
+            let writable_stream_ids = self.streams.writable_stream_ids().to_vec();
+            let conn_window = self.out_window_size.available();
+
+            let quotas = if policy == SchedulingPolicy::Fair {
+                fair_quotas(conn_window, &writable_stream_ids)
+            } else {
+                let streams: Vec<_> = writable_stream_ids
+                    .into_iter()
+                    .map(|id| {
+                        let priority = self
+                            .streams
+                            .get_mut(id)
+                            .unwrap()
+                            .stream_ref()
+                            .priority
+                            .clone();
+                        (id, priority)
+                    })
+                    .collect();
+                weighted_quotas(conn_window, &streams)
+            };
+
+            let mut progress = false;
+
+            for (stream_id, quota) in quotas {
+                let mut quota_window = WindowSize::new(quota as i32);
+                loop {
+                    if !self.has_write_buffer_capacity() {
+                        return Ok(updated);
+                    }
+
+                    let stream = match self.streams.get_mut(stream_id) {
+                        Some(stream) => stream,
+                        None => break,
+                    };
+
+                    let mut window = quota_window.min(self.out_window_size);
+                    let before = window.size();
+                    let (command, stream) = stream.pop_outg_maybe_remove(&mut window);
+                    let cont = stream.is_some();
+                    let command = match command {
+                        Some(command) => command,
+                        None => break,
+                    };
+                    let used = before - window.size();
+                    if used != 0 {
+                        quota_window.try_decrease_to_non_negative(used).unwrap();
+                        self.out_window_size
+                            .try_decrease_to_non_negative(used)
+                            .unwrap();
+                    }
+
+                    self.write_part(stream_id, command);
+                    updated = true;
+                    progress = true;
+
+                    // Stream is removed from map
+                    if !cont {
+                        break;
+                    }
+                }
+            }
+
+            if !progress {
+                return Ok(updated);
+            }
+        }
+    }
+
     pub fn send_frame_and_notify<F: Into<HttpFrame>>(&mut self, frame: F) {
         // TODO: some of frames should not be in front of GOAWAY
         self.queued_write.queue_not_goaway(frame.into());
@@ -198,6 +300,13 @@ where
         Ok(())
     }
 
+    fn process_stream_cancel(&mut self, stream_id: StreamId) -> result::Result<()> {
+        if self.streams.get_mut(stream_id).is_some() {
+            self.send_rst_stream(stream_id, ErrorCode::Cancel)?;
+        }
+        Ok(())
+    }
+
     fn process_stream_enqueue(
         &mut self,
         stream_id: StreamId,
@@ -205,7 +314,16 @@ where
     ) -> result::Result<()> {
         let stream = self.streams.get_mut(stream_id);
         if let Some(mut stream) = stream {
+            let data = match part.content {
+                DataOrHeaders::Data(..) => true,
+                DataOrHeaders::Headers(..) => false,
+            };
             stream.push_back_part(part);
+            if data {
+                if let Some(data_coalesce) = &mut self.data_coalesce {
+                    data_coalesce.data_queued();
+                }
+            }
         } else {
             if let DataOrHeaders::Data(data) = part.content {
                 self.pump_out_window_size.increase(data.len());
@@ -214,6 +332,13 @@ where
         Ok(())
     }
 
+    fn process_stream_flush(&mut self, stream_id: StreamId) -> result::Result<()> {
+        if let Some(mut stream) = self.streams.get_mut(stream_id) {
+            stream.flush_coalesced_data();
+        }
+        Ok(())
+    }
+
     fn process_stream_pull(
         &mut self,
         stream_id: StreamId,
@@ -233,6 +358,19 @@ where
         Ok(())
     }
 
+    pub fn process_ping_message(
+        &mut self,
+        sender: oneshot::Sender<result::Result<Duration>>,
+    ) -> result::Result<()> {
+        let opaque_data = self.next_ping_opaque_data;
+        self.next_ping_opaque_data = self.next_ping_opaque_data.wrapping_add(1);
+
+        self.pings_sent
+            .insert(opaque_data, (Instant::now(), sender));
+        self.send_frame_and_notify(PingFrame::with_data(opaque_data));
+        Ok(())
+    }
+
     pub fn process_common_message(&mut self, common: CommonToWriteMessage) -> result::Result<()> {
         match common {
             CommonToWriteMessage::StreamEnd(stream_id, error_code) => {
@@ -241,6 +379,12 @@ where
             CommonToWriteMessage::StreamEnqueue(stream_id, part) => {
                 self.process_stream_enqueue(stream_id, part)?;
             }
+            CommonToWriteMessage::StreamCancel(stream_id) => {
+                self.process_stream_cancel(stream_id)?;
+            }
+            CommonToWriteMessage::StreamFlush(stream_id) => {
+                self.process_stream_flush(stream_id)?;
+            }
             CommonToWriteMessage::Pull(stream_id, stream, out_window_receiver) => {
                 self.process_stream_pull(stream_id, stream, out_window_receiver)?;
             }
@@ -250,6 +394,15 @@ where
             CommonToWriteMessage::DumpState(sender) => {
                 self.process_dump_state(sender)?;
             }
+            CommonToWriteMessage::Stats(sender) => {
+                self.process_stats(sender)?;
+            }
+            CommonToWriteMessage::Ping(sender) => {
+                self.process_ping_message(sender)?;
+            }
+            CommonToWriteMessage::RawFrame(frame) => {
+                self.send_frame_and_notify(HttpFrame::Unknown(frame));
+            }
         }
         Ok(())
     }
@@ -257,11 +410,38 @@ where
     pub fn send_goaway(&mut self, error_code: ErrorCode) -> result::Result<()> {
         debug!("requesting to send GOAWAY with code {:?}", error_code);
         let frame = GoawayFrame::new(self.last_peer_stream_id, error_code);
+        if self.goaway_sent.is_none() {
+            self.goaway_sent = Some(frame.clone());
+        }
         self.queued_write.queue_goaway(frame);
         Ok(())
     }
 
+    /// Start graceful shutdown (RFC 7540 section 6.8).
+    ///
+    /// First GOAWAY with maximum stream id is sent to notify peer
+    /// to stop opening new streams, while streams in flight are processed.
+    pub fn start_graceful_shutdown(&mut self) -> result::Result<()> {
+        if self.draining {
+            return Ok(());
+        }
+        debug!("starting graceful shutdown");
+        self.draining = true;
+        let frame = GoawayFrame::new(MAX_STREAM_ID, ErrorCode::NoError);
+        self.queued_write.queue_not_goaway(frame);
+        Ok(())
+    }
+
     pub fn poll_flush(&mut self, cx: &mut Context<'_>) -> result::Result<()> {
+        let r = self.poll_flush_impl(cx);
+        let blocked = self
+            .streams
+            .update_flow_control_blocked(self.out_window_size);
+        self.flow_control_blocked_time.update(blocked);
+        r
+    }
+
+    fn poll_flush_impl(&mut self, cx: &mut Context<'_>) -> result::Result<()> {
         self.buffer_outg_conn()?;
         loop {
             match self.queued_write.poll(cx) {
@@ -283,6 +463,37 @@ pub enum CommonToWriteMessage {
     IncreaseInWindow(StreamId, u32),
     StreamEnqueue(StreamId, DataOrHeadersWithFlag),
     StreamEnd(StreamId, ErrorCode), // send when user provided handler completed the stream
+    /// Response was dropped before completion, send `RST_STREAM(CANCEL)`.
+    StreamCancel(StreamId),
+    /// Send coalesced DATA of the stream now.
+    StreamFlush(StreamId),
     Pull(StreamId, HttpStreamAfterHeaders, StreamOutWindowReceiver),
     DumpState(oneshot::Sender<ConnStateSnapshot>),
+    Stats(oneshot::Sender<ConnectionStats>),
+    /// Send PING, reply with round-trip time when ACK is received.
+    Ping(oneshot::Sender<result::Result<Duration>>),
+    /// Send frame of extension type.
+    RawFrame(RawFrame),
+}
+
+/// Frame for `CommonToWriteMessage::RawFrame`, rejecting frame types
+/// defined by RFC 7540 and payloads exceeding `max_frame_size`.
+pub(crate) fn extension_frame(
+    frame_type: u8,
+    flags: u8,
+    stream_id: StreamId,
+    payload: &[u8],
+    max_frame_size: u32,
+) -> result::Result<RawFrame> {
+    if RawHttpFrameType(frame_type).known().is_ok() {
+        return Err(Error::InvalidFrame(format!(
+            "not an extension frame type: {}",
+            frame_type
+        )));
+    }
+    if payload.len() > max_frame_size as usize {
+        return Err(Error::PayloadTooLarge(payload.len() as u32, max_frame_size));
+    }
+    let header = FrameHeader::new(payload.len() as u32, frame_type, flags, stream_id);
+    Ok(RawFrame::from_parts(header, payload))
 }
@@ -0,0 +1,52 @@
+//! Hold small outgoing DATA to send it in fewer frames.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+use std::time::Duration;
+
+use tokio::time;
+use tokio::time::Delay;
+
+/// Default time DATA is held if less than `max_bytes` is queued.
+pub(crate) const DEFAULT_DATA_COALESCE_DELAY: Duration = Duration::from_millis(1);
+
+/// Connection-wide flush timer of coalesced DATA.
+pub(crate) struct DataCoalesce {
+    /// Stream DATA is held until this many bytes are queued.
+    pub max_bytes: usize,
+    delay: Duration,
+    timer: Option<Delay>,
+}
+
+impl DataCoalesce {
+    pub fn new(max_bytes: usize, delay: Duration) -> DataCoalesce {
+        DataCoalesce {
+            max_bytes,
+            delay,
+            timer: None,
+        }
+    }
+
+    /// Start the timer unless already started.
+    pub fn data_queued(&mut self) {
+        if self.timer.is_none() {
+            self.timer = Some(time::delay_for(self.delay));
+        }
+    }
+
+    /// Ready when held DATA must be flushed.
+    pub fn poll(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        match &mut self.timer {
+            Some(timer) => {
+                if Pin::new(timer).poll(cx).is_pending() {
+                    return Poll::Pending;
+                }
+                self.timer = None;
+                Poll::Ready(())
+            }
+            None => Poll::Pending,
+        }
+    }
+}
@@ -0,0 +1,47 @@
+//! When queued frames are written to the socket.
+
+use std::time::Duration;
+
+/// When connection writes queued frames to the socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FlushStrategy {
+    /// Write after processing each event, e. g. each received frame.
+    Immediate,
+    /// Write when no more incoming frames or commands are immediately ready,
+    /// or after 64 events were processed without writing.
+    #[default]
+    OnIdle,
+    /// Write at most once per interval after frames are queued.
+    Interval(Duration),
+}
+
+/// Queue is written with `FlushStrategy::OnIdle` after this many events
+/// even if more events are ready.
+const ON_IDLE_MAX_EVENTS: u32 = 64;
+
+impl FlushStrategy {
+    /// Queue must be written before next event is processed,
+    /// even if more events are ready.
+    pub(crate) fn flush_before_next_event(&self, events_since_flush: u32) -> bool {
+        match self {
+            FlushStrategy::Immediate => true,
+            FlushStrategy::OnIdle => events_since_flush >= ON_IDLE_MAX_EVENTS,
+            FlushStrategy::Interval(_) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn flush_before_next_event() {
+        assert!(FlushStrategy::Immediate.flush_before_next_event(0));
+        assert!(!FlushStrategy::OnIdle.flush_before_next_event(0));
+        assert!(!FlushStrategy::OnIdle.flush_before_next_event(63));
+        assert!(FlushStrategy::OnIdle.flush_before_next_event(64));
+        let interval = FlushStrategy::Interval(Duration::from_millis(10));
+        assert!(!interval.flush_before_next_event(1000));
+    }
+}
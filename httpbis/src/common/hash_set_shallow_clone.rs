@@ -52,7 +52,7 @@ impl<T: Hash + Eq + Clone> HashSetShallowClone<T> {
 
     pub fn items(&mut self) -> HashSetShallowCloneItems<T> {
         // TODO: store delta and update shallow map
-        if let None = self.items {
+        if self.items.is_none() {
             if self.set.is_empty() {
                 // Avoid allocation
                 return HashSetShallowCloneItems { items: None };
@@ -85,7 +85,7 @@ impl<T: Hash + Eq + Clone> HashSetShallowClone<T> {
         inserted
     }
 
-    pub fn _iter(&self) -> hash_set::Iter<T> {
+    pub fn _iter(&self) -> hash_set::Iter<'_, T> {
         self.set.iter()
     }
 }
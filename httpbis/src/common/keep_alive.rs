@@ -0,0 +1,57 @@
+//! Close connection when nothing is received from peer for some time.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+use std::time::Duration;
+
+use tokio::time;
+use tokio::time::Delay;
+use tokio::time::Instant;
+
+pub(crate) enum KeepAliveEvent {
+    /// Probe peer with `PING`
+    SendPing,
+    /// Peer is considered dead
+    Close,
+}
+
+/// Timer reset by each frame received from peer.
+pub(crate) struct KeepAlive {
+    timeout: Duration,
+    ping_timeout: Option<Duration>,
+    delay: Delay,
+    ping_sent: bool,
+}
+
+impl KeepAlive {
+    pub fn new(timeout: Duration, ping_timeout: Option<Duration>) -> KeepAlive {
+        KeepAlive {
+            timeout,
+            ping_timeout,
+            delay: time::delay_for(timeout),
+            ping_sent: false,
+        }
+    }
+
+    pub fn frame_received(&mut self) {
+        self.ping_sent = false;
+        self.delay.reset(Instant::now() + self.timeout);
+    }
+
+    pub fn poll(&mut self, cx: &mut Context<'_>) -> Poll<KeepAliveEvent> {
+        if Pin::new(&mut self.delay).poll(cx).is_pending() {
+            return Poll::Pending;
+        }
+
+        match self.ping_timeout {
+            Some(ping_timeout) if !self.ping_sent => {
+                self.ping_sent = true;
+                self.delay.reset(Instant::now() + ping_timeout);
+                Poll::Ready(KeepAliveEvent::SendPing)
+            }
+            _ => Poll::Ready(KeepAliveEvent::Close),
+        }
+    }
+}
@@ -71,8 +71,19 @@ impl<T: Types> PumpStreamToWrite<T> {
 
             match part_opt {
                 Some(part) => {
-                    if let DataOrTrailers::Data(ref d, _) = part {
-                        self.out_window.decrease(d.len());
+                    match part {
+                        DataOrTrailers::Data(ref d, _) => self.out_window.decrease(d.len()),
+                        DataOrTrailers::Trailers(ref trailers) => {
+                            if let Err(e) = trailers.validate_trailers() {
+                                warn!("invalid trailers: {:?}", e);
+                                let rst = CommonToWriteMessage::StreamEnd(
+                                    self.stream_id,
+                                    ErrorCode::InternalError,
+                                );
+                                drop(self.to_write_tx.unbounded_send(rst.into()));
+                                break;
+                            }
+                        }
                     }
 
                     let msg = CommonToWriteMessage::StreamEnqueue(self.stream_id, part.into());
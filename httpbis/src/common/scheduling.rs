@@ -0,0 +1,181 @@
+//! Distribution of connection window between streams.
+
+use std::cmp;
+use std::collections::HashMap;
+
+use crate::solicit::frame::StreamDependency;
+use crate::solicit::stream_id::StreamId;
+
+/// How outgoing DATA of multiple streams shares the connection window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SchedulingPolicy {
+    /// Each writable stream is drained as far as windows allow
+    /// before proceeding to the next stream.
+    #[default]
+    Fifo,
+    /// Connection window is distributed proportionally to stream weights;
+    /// dependent streams are served only when their parent is blocked.
+    WeightedFair,
+    /// Connection window is split equally between writable streams,
+    /// regardless of priority.
+    Fair,
+}
+
+/// Split connection window `conn_window` equally between writable `streams`.
+///
+/// Remainder of division is given to the first streams, one octet each,
+/// so quotas never exceed the window.
+pub(crate) fn fair_quotas(conn_window: u32, streams: &[StreamId]) -> Vec<(StreamId, u32)> {
+    if streams.is_empty() {
+        return Vec::new();
+    }
+
+    let n = streams.len() as u32;
+    let quota = conn_window / n;
+    let remainder = (conn_window % n) as usize;
+    streams
+        .iter()
+        .enumerate()
+        .map(|(i, &id)| (id, quota + (i < remainder) as u32))
+        .collect()
+}
+
+/// Split connection window `conn_window` between writable `streams`.
+///
+/// Streams whose parent is also writable get nothing. Every other stream
+/// gets at least one octet if window is not empty.
+///
+/// Dependencies are acyclic when maintained with `StreamMap::set_priority`,
+/// but streams forming a cycle are served as if they depended on stream 0,
+/// so that they are not starved.
+pub(crate) fn weighted_quotas(
+    conn_window: u32,
+    streams: &[(StreamId, StreamDependency)],
+) -> Vec<(StreamId, u32)> {
+    let parents: HashMap<StreamId, StreamId> = streams
+        .iter()
+        .map(|(id, dep)| (*id, dep.stream_id))
+        .collect();
+
+    // Walk up writable parents, each stream is visited once: walk stops at
+    // streams already resolved, cycle is found if walk returns to its own path
+    let mut in_cycle: HashMap<StreamId, bool> = HashMap::new();
+    for &(id, _) in streams {
+        let mut path: Vec<StreamId> = Vec::new();
+        let mut on_path: HashMap<StreamId, usize> = HashMap::new();
+        let mut next = id;
+        while !in_cycle.contains_key(&next) {
+            if let Some(&pos) = on_path.get(&next) {
+                for &c in &path[pos..] {
+                    in_cycle.insert(c, true);
+                }
+                break;
+            }
+            match parents.get(&next) {
+                Some(&parent) => {
+                    on_path.insert(next, path.len());
+                    path.push(next);
+                    next = parent;
+                }
+                None => break,
+            }
+        }
+        for c in path {
+            in_cycle.entry(c).or_insert(false);
+        }
+    }
+
+    let active: Vec<(StreamId, u64)> = streams
+        .iter()
+        .filter(|(id, dep)| {
+            dep.stream_id == 0 || !parents.contains_key(&dep.stream_id) || in_cycle[id]
+        })
+        .map(|(id, dep)| (*id, dep.weight as u64 + 1))
+        .collect();
+
+    let total_weight: u64 = active.iter().map(|(_, w)| w).sum();
+
+    active
+        .into_iter()
+        .map(|(id, weight)| {
+            let quota = conn_window as u64 * weight / total_weight;
+            (id, cmp::max(quota as u32, cmp::min(1, conn_window)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn weighted_quotas_proportional() {
+        let streams = vec![
+            (1, StreamDependency::new(0, 255, false)),
+            (3, StreamDependency::new(0, 0, false)),
+        ];
+        assert_eq!(vec![(1, 65280), (3, 255)], weighted_quotas(65535, &streams));
+    }
+
+    #[test]
+    fn weighted_quotas_dependency() {
+        let streams = vec![
+            (1, StreamDependency::new(0, 15, false)),
+            (3, StreamDependency::new(1, 15, false)),
+            (5, StreamDependency::new(7, 15, false)),
+        ];
+        // Stream 3 waits for stream 1, parent of stream 5 is not writable
+        assert_eq!(vec![(1, 500), (5, 500)], weighted_quotas(1000, &streams));
+    }
+
+    #[test]
+    fn weighted_quotas_cycle() {
+        let streams = vec![
+            (1, StreamDependency::new(3, 255, false)),
+            (3, StreamDependency::new(1, 0, false)),
+            (5, StreamDependency::new(5, 15, false)),
+            (7, StreamDependency::new(1, 15, false)),
+        ];
+        // Streams 1 and 3 depend on each other, stream 5 depends on itself,
+        // stream 7 waits for stream 1
+        assert_eq!(
+            vec![(1, 25600), (3, 100), (5, 1600)],
+            weighted_quotas(27300, &streams)
+        );
+    }
+
+    #[test]
+    fn weighted_quotas_long_chain() {
+        let mut streams: Vec<_> = (1..10000)
+            .map(|i| (i * 2 + 1, StreamDependency::new(i * 2 - 1, 15, false)))
+            .collect();
+        streams.push((1, StreamDependency::new(0, 15, false)));
+        assert_eq!(vec![(1, 1000)], weighted_quotas(1000, &streams));
+
+        // Stream 1 closes the chain into a cycle
+        streams.last_mut().unwrap().1 = StreamDependency::new(19999, 15, false);
+        assert_eq!(10000, weighted_quotas(10000, &streams).len());
+    }
+
+    #[test]
+    fn fair_quotas_equal() {
+        assert_eq!(
+            vec![(1, 250), (3, 250), (5, 250), (7, 250)],
+            fair_quotas(1000, &[1, 3, 5, 7])
+        );
+        assert_eq!(vec![(1, 4), (3, 3), (5, 3)], fair_quotas(10, &[1, 3, 5]));
+        assert_eq!(vec![(1, 1), (3, 0)], fair_quotas(1, &[1, 3]));
+        assert_eq!(vec![(1, 0)], fair_quotas(0, &[1]));
+        assert_eq!(Vec::<(StreamId, u32)>::new(), fair_quotas(1000, &[]));
+    }
+
+    #[test]
+    fn weighted_quotas_small_window() {
+        let streams = vec![
+            (1, StreamDependency::new(0, 255, false)),
+            (3, StreamDependency::new(0, 0, false)),
+        ];
+        assert_eq!(vec![(1, 9), (3, 1)], weighted_quotas(10, &streams));
+        assert_eq!(vec![(1, 0), (3, 0)], weighted_quotas(0, &streams));
+    }
+}
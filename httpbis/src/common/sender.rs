@@ -6,15 +6,20 @@ use crate::data_or_headers::DataOrHeaders;
 use crate::data_or_headers_with_flag::DataOrHeadersWithFlag;
 use crate::error;
 use crate::result;
+use crate::solicit::header::HeaderError;
 use crate::solicit::stream_id::StreamId;
 use crate::ErrorCode;
 use crate::Headers;
 use crate::HttpStreamAfterHeaders;
 use crate::StreamDead;
 use bytes::Bytes;
+use futures::future;
 use futures::stream::Stream;
+use futures::Future;
 
 use futures::task::Context;
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::task::Poll;
 
@@ -29,12 +34,17 @@ pub enum SenderState {
 pub enum SendError {
     ConnectionDied(Arc<error::Error>),
     IncorrectState(SenderState),
+    /// Trailers contain pseudo-headers or uppercase names.
+    InvalidTrailers(HeaderError),
+    /// Header list size exceeds `SETTINGS_MAX_HEADER_LIST_SIZE` advertised by peer.
+    HeaderListTooLarge(usize, u32),
 }
 
 struct CanSendData<T: Types> {
     write_tx: ConnCommandSender<T>,
     out_window: StreamOutWindowReceiver,
     seen_headers: bool,
+    peer_max_header_list_size: Arc<AtomicU32>,
 }
 
 /// Shared implementation of sender for client and server
@@ -49,12 +59,14 @@ impl<T: Types> CommonSender<T> {
         write_tx: ConnCommandSender<T>,
         out_window: StreamOutWindowReceiver,
         seen_headers: bool,
+        peer_max_header_list_size: Arc<AtomicU32>,
     ) -> Self {
         CommonSender {
             state: Some(CanSendData {
                 write_tx,
                 out_window,
                 seen_headers,
+                peer_max_header_list_size,
             }),
             stream_id,
         }
@@ -68,6 +80,10 @@ impl<T: Types> CommonSender<T> {
         }
     }
 
+    pub fn stream_id(&self) -> StreamId {
+        self.stream_id
+    }
+
     pub fn poll(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), StreamDead>> {
         match self.state {
             Some(ref mut state) => state.out_window.poll(cx),
@@ -76,6 +92,10 @@ impl<T: Types> CommonSender<T> {
         }
     }
 
+    pub fn window_available(&mut self) -> impl Future<Output = Result<(), StreamDead>> + '_ {
+        future::poll_fn(move |cx| self.poll(cx))
+    }
+
     fn get_can_send(&mut self) -> Result<&mut CanSendData<T>, SendError> {
         match self.state {
             Some(ref mut state) => Ok(state),
@@ -139,10 +159,23 @@ impl<T: Types> CommonSender<T> {
         self.send_headers_impl(headers, true)
     }
 
+    /// Check headers against peer limit before they are queued for encoding.
+    fn check_header_list_size(&mut self, headers: &Headers) -> Result<(), SendError> {
+        let max = self
+            .get_can_send()?
+            .peer_max_header_list_size
+            .load(Ordering::SeqCst);
+        if !headers.fits_within(max) {
+            return Err(SendError::HeaderListTooLarge(headers.list_size(), max));
+        }
+        Ok(())
+    }
+
     pub fn send_headers_impl(&mut self, headers: Headers, last: bool) -> Result<(), SendError> {
         if self.state() != SenderState::ExpectingHeaders {
             return Err(SendError::IncorrectState(self.state()));
         }
+        self.check_header_list_size(&headers)?;
         let stream_id = self.stream_id;
         self.send_common(CommonToWriteMessage::StreamEnqueue(
             stream_id,
@@ -163,6 +196,10 @@ impl<T: Types> CommonSender<T> {
         if self.state() != SenderState::ExpectingBodyOrTrailers {
             return Err(SendError::IncorrectState(self.state()));
         }
+        trailers
+            .validate_trailers()
+            .map_err(SendError::InvalidTrailers)?;
+        self.check_header_list_size(&trailers)?;
         let stream_id = self.stream_id;
         self.send_common(CommonToWriteMessage::StreamEnqueue(
             stream_id,
@@ -205,6 +242,11 @@ impl<T: Types> CommonSender<T> {
         self.pull_from_stream(HttpStreamAfterHeaders::bytes(stream))
     }
 
+    pub fn flush(&mut self) -> Result<(), SendError> {
+        let stream_id = self.stream_id;
+        self.send_common(CommonToWriteMessage::StreamFlush(stream_id))
+    }
+
     pub fn reset(&mut self, error_code: ErrorCode) -> Result<(), SendError> {
         // TODO: do nothing if stream is explicitly closed
         let stream_id = self.stream_id;
@@ -0,0 +1,186 @@
+//! Connection statistics.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+use std::time::Duration;
+use std::time::Instant;
+
+use tokio::time;
+use tokio::time::Delay;
+
+use crate::common::conf::StatsCallback;
+use crate::solicit::frame::RawHttpFrameType;
+use crate::solicit::stream_id::StreamId;
+
+/// Number of frames by type.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FrameCounts {
+    pub data: u64,
+    pub headers: u64,
+    pub priority: u64,
+    pub rst_stream: u64,
+    pub settings: u64,
+    pub push_promise: u64,
+    pub ping: u64,
+    pub goaway: u64,
+    pub window_update: u64,
+    pub continuation: u64,
+    /// Frames of unknown type
+    pub unknown: u64,
+}
+
+impl FrameCounts {
+    pub(crate) fn inc(&mut self, frame_type: u8) {
+        let count = match RawHttpFrameType(frame_type) {
+            RawHttpFrameType::DATA => &mut self.data,
+            RawHttpFrameType::HEADERS => &mut self.headers,
+            RawHttpFrameType::PRIORITY => &mut self.priority,
+            RawHttpFrameType::RST_STREAM => &mut self.rst_stream,
+            RawHttpFrameType::SETTINGS => &mut self.settings,
+            RawHttpFrameType::PUSH_PROMISE => &mut self.push_promise,
+            RawHttpFrameType::PING => &mut self.ping,
+            RawHttpFrameType::GOAWAY => &mut self.goaway,
+            RawHttpFrameType::WINDOW_UPDATE => &mut self.window_update,
+            RawHttpFrameType::CONTINUATION => &mut self.continuation,
+            _ => &mut self.unknown,
+        };
+        *count += 1;
+    }
+}
+
+/// Flow control state of a stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamStats {
+    pub out_window_size: i32,
+    pub in_window_size: i32,
+    /// Outgoing DATA queued in the connection
+    pub queued_out_data_size: usize,
+}
+
+/// Snapshot of connection statistics.
+#[derive(Debug, Clone)]
+pub struct ConnectionStats {
+    pub active_streams: usize,
+    /// Streams opened by either side, including pushed streams
+    pub streams_opened: u64,
+    /// Bytes of frames queued for sending
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    /// Frames queued for sending, GOAWAY and RST_STREAM counts included
+    pub frames_sent: FrameCounts,
+    pub frames_received: FrameCounts,
+    pub out_window_size: i32,
+    pub in_window_size: i32,
+    /// Active streams
+    pub streams: HashMap<StreamId, StreamStats>,
+    /// Current size of HPACK dynamic table used to encode outgoing headers
+    pub hpack_encoder_table_size: usize,
+    /// Current size of HPACK dynamic table used to decode incoming headers
+    pub hpack_decoder_table_size: usize,
+    /// Sum over streams of time stream had DATA to send,
+    /// but stream or connection window was exhausted.
+    pub flow_control_blocked_stream_time: Duration,
+    /// Number of socket write calls
+    pub socket_writes: u64,
+}
+
+/// Snapshot of server statistics.
+#[derive(Debug, Clone)]
+pub struct ServerStats {
+    /// Number of open connections
+    pub connection_count: usize,
+    /// Statistics of open connections by connection id
+    pub connections: HashMap<u64, ConnectionStats>,
+}
+
+/// Integral of number of flow control blocked streams over time.
+pub(crate) struct FlowControlBlockedTime {
+    blocked_streams: u32,
+    updated: Instant,
+    total: Duration,
+}
+
+impl FlowControlBlockedTime {
+    pub fn new() -> FlowControlBlockedTime {
+        FlowControlBlockedTime {
+            blocked_streams: 0,
+            updated: Instant::now(),
+            total: Duration::from_secs(0),
+        }
+    }
+
+    fn total_at(&self, now: Instant) -> Duration {
+        self.total + (now - self.updated) * self.blocked_streams
+    }
+
+    pub fn total(&self) -> Duration {
+        self.total_at(Instant::now())
+    }
+
+    /// Set number of blocked streams from now on.
+    pub fn update(&mut self, blocked_streams: u32) {
+        if blocked_streams == 0 && self.blocked_streams == 0 {
+            return;
+        }
+        let now = Instant::now();
+        self.total = self.total_at(now);
+        self.updated = now;
+        self.blocked_streams = blocked_streams;
+    }
+}
+
+/// Invokes `CommonConf::on_stats` periodically.
+pub(crate) struct StatsReporter {
+    callback: StatsCallback,
+    interval: Duration,
+    delay: Delay,
+}
+
+impl StatsReporter {
+    pub fn new(callback: StatsCallback, interval: Duration) -> StatsReporter {
+        StatsReporter {
+            callback,
+            interval,
+            delay: time::delay_for(interval),
+        }
+    }
+
+    /// Ready when the next snapshot is due.
+    pub fn poll(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        if Pin::new(&mut self.delay).poll(cx).is_pending() {
+            return Poll::Pending;
+        }
+        self.delay.reset(time::Instant::now() + self.interval);
+        Poll::Ready(())
+    }
+
+    pub fn report(&self, stats: &ConnectionStats) {
+        (self.callback)(stats);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn frame_counts() {
+        let mut counts = FrameCounts::default();
+        counts.inc(RawHttpFrameType::DATA.0);
+        counts.inc(RawHttpFrameType::DATA.0);
+        counts.inc(RawHttpFrameType::GOAWAY.0);
+        counts.inc(0xbb);
+        assert_eq!(
+            FrameCounts {
+                data: 2,
+                goaway: 1,
+                unknown: 1,
+                ..Default::default()
+            },
+            counts
+        );
+    }
+}
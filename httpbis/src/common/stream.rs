@@ -1,10 +1,12 @@
-use std::cmp;
+use std::time::Instant;
 
 use bytes::Bytes;
 
 use crate::error;
 
 use crate::solicit::end_stream::EndStream;
+use crate::solicit::frame::GoawayFrame;
+use crate::solicit::frame::StreamDependency;
 use crate::solicit::header::Headers;
 use crate::solicit::session::StreamState;
 use crate::solicit::window_size::NonNegativeWindowSize;
@@ -14,7 +16,9 @@ use super::types::Types;
 
 use super::stream_queue::StreamQueue;
 use super::window_size;
+use crate::common::stats::StreamStats;
 use crate::common::stream_handler::StreamHandlerInternal;
+use crate::common::trace::StreamSpan;
 use crate::data_or_headers::DataOrHeaders;
 use crate::data_or_headers_with_flag::DataOrHeadersWithFlag;
 use crate::ErrorCode;
@@ -75,6 +79,22 @@ pub(crate) struct HttpStreamCommon<T: Types> {
     // Incoming remaining content-length
     pub in_rem_content_length: Option<u64>,
     pub in_message_stage: InMessageStage,
+    /// Priority as last specified by peer with HEADERS or PRIORITY frame.
+    pub priority: StreamDependency,
+    /// Hold outgoing DATA until this many bytes are queued, `None` to send immediately.
+    pub data_coalesce_max_bytes: Option<usize>,
+    /// Send held DATA regardless of size.
+    pub data_coalesce_flush: bool,
+    pub opened: Instant,
+    /// DATA payload bytes sent and received
+    pub out_data_bytes: u64,
+    pub in_data_bytes: u64,
+    /// Error code of `RST_STREAM` sent or received
+    pub reset: Option<ErrorCode>,
+    /// Set while stream has DATA to send but no window
+    pub flow_control_blocked_since: Option<Instant>,
+    /// `tracing` span of this stream
+    pub span: StreamSpan,
 }
 
 impl<T: Types> HttpStreamCommon<T> {
@@ -96,6 +116,16 @@ impl<T: Types> HttpStreamCommon<T> {
             pump_out_window,
             in_rem_content_length,
             in_message_stage,
+            // Default priority: weight 16, dependency on stream 0 (RFC 7540 section 5.3.5)
+            priority: StreamDependency::new(0, 15, false),
+            data_coalesce_max_bytes: None,
+            data_coalesce_flush: false,
+            opened: Instant::now(),
+            out_data_bytes: 0,
+            in_data_bytes: 0,
+            reset: None,
+            flow_control_blocked_since: None,
+            span: StreamSpan::none(),
         }
     }
 
@@ -132,12 +162,45 @@ impl<T: Types> HttpStreamCommon<T> {
         }
     }
 
+    pub fn stats(&self) -> StreamStats {
+        StreamStats {
+            out_window_size: self.out_window_size.size(),
+            in_window_size: self.in_window_size.size(),
+            queued_out_data_size: self.outgoing.data_size(),
+        }
+    }
+
+    /// DATA is queued but cannot be sent because of stream or connection window.
+    pub fn is_flow_control_blocked(&self, conn_out_window_size: WindowSize) -> bool {
+        match self.outgoing.front() {
+            Some(DataOrHeaders::Data(data)) if !data.is_empty() => {
+                self.out_window_size.is_exhausted() || conn_out_window_size.is_exhausted()
+            }
+            _ => false,
+        }
+    }
+
+    /// Queued DATA is too small to be sent yet.
+    pub fn data_coalesce_hold(&self) -> bool {
+        match self.data_coalesce_max_bytes {
+            Some(max_bytes) => {
+                !self.data_coalesce_flush
+                    && !self.outgoing.is_closed()
+                    && self.outgoing.data_size() < max_bytes
+            }
+            None => false,
+        }
+    }
+
     /// Must be kept in sync with `pop_outg`.
     pub fn is_writable(&self) -> bool {
         match self.outgoing.front() {
             Some(front) => match front {
                 DataOrHeaders::Headers(..) => true,
-                DataOrHeaders::Data(data) => data.len() == 0 || self.out_window_size.size() > 0,
+                DataOrHeaders::Data(data) => {
+                    (data.is_empty() || self.out_window_size.size() > 0)
+                        && !self.data_coalesce_hold()
+                }
             },
             None => {
                 if let Some(_error_code) = self.outgoing.end() {
@@ -188,7 +251,10 @@ impl<T: Types> HttpStreamCommon<T> {
                     self.close_local();
                     Some(match error_code {
                         ErrorCode::NoError => HttpStreamCommand::Data(Bytes::new(), EndStream::Yes),
-                        error_code => HttpStreamCommand::Rst(error_code),
+                        error_code => {
+                            self.reset = Some(error_code);
+                            HttpStreamCommand::Rst(error_code)
+                        }
                     })
                 }
             } else {
@@ -196,11 +262,7 @@ impl<T: Types> HttpStreamCommon<T> {
             };
         }
 
-        let pop_headers = if let &DataOrHeaders::Headers(..) = self.outgoing.front().unwrap() {
-            true
-        } else {
-            false
-        };
+        let pop_headers = matches!(self.outgoing.front().unwrap(), DataOrHeaders::Headers(..));
         if pop_headers {
             let r = self.outgoing.pop_front().unwrap();
             let last = self.outgoing.end() == Some(ErrorCode::NoError);
@@ -209,28 +271,36 @@ impl<T: Types> HttpStreamCommon<T> {
             }
             return Some(HttpStreamCommand::from(DataOrHeadersWithFlag {
                 content: r,
-                last: last,
+                last,
             }));
         }
 
-        if self.out_window_size.size() <= 0 || conn_out_window_size.size() <= 0 {
+        if self.data_coalesce_hold() {
             return None;
         }
 
-        let mut data = if let Some(DataOrHeaders::Data(data)) = self.outgoing.pop_front() {
-            data
-        } else {
-            unreachable!()
+        if self.out_window_size.is_exhausted() || conn_out_window_size.is_exhausted() {
+            return None;
+        }
+
+        let mut data = match self.data_coalesce_max_bytes {
+            Some(max_bytes) => self.outgoing.pop_front_data_coalesced(max_bytes).unwrap(),
+            None => match self.outgoing.pop_front() {
+                Some(DataOrHeaders::Data(data)) => data,
+                _ => unreachable!(),
+            },
         };
 
         // Min of connection and stream window size
-        let max_window = cmp::min(self.out_window_size.size(), conn_out_window_size.size());
+        let max_window = self.out_window_size.min(*conn_out_window_size).available();
 
         if data.len() as usize > max_window as usize {
             trace!("truncating data of len {} to {}", data.len(), max_window);
             let size = max_window as usize;
             let rem = data.split_off(size);
             self.outgoing.push_front(DataOrHeaders::Data(rem));
+            // Remaining data is sent as window allows
+            self.data_coalesce_flush = self.data_coalesce_max_bytes.is_some();
         };
 
         self.out_window_size
@@ -240,6 +310,12 @@ impl<T: Types> HttpStreamCommon<T> {
             .try_decrease_to_non_negative(data.len() as i32)
             .unwrap();
 
+        if self.outgoing.is_empty() {
+            self.data_coalesce_flush = false;
+        }
+
+        self.out_data_bytes += data.len() as u64;
+
         let last = self.outgoing.end() == Some(ErrorCode::NoError);
         if last {
             self.close_local();
@@ -247,18 +323,25 @@ impl<T: Types> HttpStreamCommon<T> {
 
         Some(HttpStreamCommand::from(DataOrHeadersWithFlag {
             content: DataOrHeaders::Data(data),
-            last: last,
+            last,
         }))
     }
 
     pub fn data_recvd(&mut self, data: Bytes, last: bool) {
+        self.in_data_bytes += data.len() as u64;
         if let Some(ref mut response_handler) = self.peer_tx {
             // TODO: reset stream if rx is dead
             drop(response_handler.data_frame(data, last));
         }
     }
 
+    /// Message ended before `content-length` octets of DATA were received.
+    pub fn in_content_length_incomplete(&self) -> bool {
+        matches!(self.in_rem_content_length, Some(rem) if rem != 0)
+    }
+
     pub fn rst_recvd(&mut self, error_code: ErrorCode) -> DroppedData {
+        self.reset = Some(error_code);
         if let Some(response_handler) = self.peer_tx.take() {
             drop(response_handler.rst(error_code));
         }
@@ -267,10 +350,10 @@ impl<T: Types> HttpStreamCommon<T> {
         }
     }
 
-    pub fn goaway_recvd(&mut self, _raw_error_code: u32) {
+    pub fn goaway_recvd(&mut self, frame: &GoawayFrame) {
         if let Some(response_handler) = self.peer_tx.take() {
             // it is OK to ignore error: handler may be already dead
-            drop(response_handler.error(error::Error::GoawayReceived));
+            drop(response_handler.error(error::Error::GoawayReceived(frame.clone())));
         }
     }
 }
@@ -3,12 +3,12 @@
 use futures::stream::Stream;
 use std::task::Poll;
 
-use crate::solicit::DEFAULT_SETTINGS;
-
 use crate::result;
 
 use super::stream_queue_sync::StreamQueueSyncReceiver;
 use super::types::Types;
+use crate::common::client_or_server::ClientOrServer;
+use crate::common::conn_write::CommonToWriteMessage;
 use crate::common::increase_in_window::IncreaseInWindow;
 use crate::data_or_headers::DataOrHeaders;
 use crate::data_or_headers_with_flag::DataOrHeadersWithFlag;
@@ -20,6 +20,12 @@ use std::pin::Pin;
 pub(crate) struct StreamFromNetwork<T: Types> {
     pub rx: StreamQueueSyncReceiver<T>,
     pub increase_in_window: IncreaseInWindow<T>,
+    /// Do not increase window while more than this many bytes are buffered.
+    pub recv_buffer_limit: Option<usize>,
+    /// Window size at the moment of stream start, also used as increment.
+    pub initial_window_size: u32,
+    /// Increase window when more than this many bytes of it are consumed.
+    pub window_update_threshold: u32,
 }
 
 impl<T: Types> Stream for StreamFromNetwork<T> {
@@ -43,11 +49,16 @@ impl<T: Types> Stream for StreamFromNetwork<T> {
         {
             self.increase_in_window.data_frame_processed(b.len() as u32);
 
-            // TODO: use different
             // TODO: increment after process of the frame (i. e. on next poll)
-            let edge = DEFAULT_SETTINGS.initial_window_size / 2;
-            if self.increase_in_window.in_window_size() < edge {
-                let inc = DEFAULT_SETTINGS.initial_window_size;
+            let consumed = self
+                .initial_window_size
+                .saturating_sub(self.increase_in_window.in_window_size());
+            let over_limit = match self.recv_buffer_limit {
+                Some(limit) => self.rx.buffered() > limit,
+                None => false,
+            };
+            if consumed > self.window_update_threshold && !over_limit {
+                let inc = self.initial_window_size;
                 self.increase_in_window.increase_window(inc)?;
             }
         }
@@ -58,6 +69,121 @@ impl<T: Types> Stream for StreamFromNetwork<T> {
 
 impl<T: Types> Drop for StreamFromNetwork<T> {
     fn drop(&mut self) {
-        // TODO: reset stream
+        // Server handler may drop request body and still send response
+        if T::CLIENT_OR_SERVER == ClientOrServer::Client && !self.rx.eof_received() {
+            let m = CommonToWriteMessage::StreamCancel(self.increase_in_window.stream_id);
+            // ignore error, stream is gone if connection is dead
+            drop(self.increase_in_window.to_write_tx.unbounded_send(m.into()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use bytes::Bytes;
+    use futures::stream::StreamExt;
+    use futures::task::noop_waker_ref;
+
+    use crate::client_died_error_holder::SomethingDiedErrorHolder;
+    use crate::common::conf::CommonConf;
+    use crate::common::conn_command_channel::conn_command_channel;
+    use crate::common::conn_command_channel::ConnCommandReceiver;
+    use crate::common::stream_queue_sync::stream_queue_sync;
+    use crate::server::conn::ServerToWriteMessage;
+    use crate::server::stream_handler::ServerRequestStreamHandler;
+    use crate::server::types::ServerTypes;
+    use crate::solicit::DEFAULT_SETTINGS;
+
+    fn poll_increase(rx: &mut ConnCommandReceiver<ServerTypes>) -> Option<u32> {
+        let mut cx = Context::from_waker(noop_waker_ref());
+        match rx.poll_next_unpin(&mut cx) {
+            Poll::Ready(Some(ServerToWriteMessage::Common(
+                CommonToWriteMessage::IncreaseInWindow(_, inc),
+            ))) => Some(inc),
+            Poll::Pending => None,
+            _ => panic!("unexpected message"),
+        }
+    }
+
+    #[test]
+    fn recv_buffer_limit() {
+        let (write_tx, mut write_rx) = conn_command_channel(SomethingDiedErrorHolder::new());
+        let (mut tx, rx) = stream_queue_sync::<ServerTypes>();
+        let mut stream = StreamFromNetwork {
+            rx,
+            increase_in_window: IncreaseInWindow {
+                stream_id: 1,
+                in_window_size: DEFAULT_SETTINGS.initial_window_size,
+                to_write_tx: write_tx,
+            },
+            recv_buffer_limit: Some(10000),
+            initial_window_size: DEFAULT_SETTINGS.initial_window_size,
+            window_update_threshold: DEFAULT_SETTINGS.initial_window_size / 2,
+        };
+
+        for _ in 0..3 {
+            tx.data_frame(Bytes::from(vec![0; 20000]), false).unwrap();
+        }
+
+        let mut cx = Context::from_waker(noop_waker_ref());
+        assert!(stream.poll_next_unpin(&mut cx).is_ready());
+        assert_eq!(None, poll_increase(&mut write_rx));
+
+        // window is low, but 20000 bytes are still buffered
+        assert!(stream.poll_next_unpin(&mut cx).is_ready());
+        assert_eq!(None, poll_increase(&mut write_rx));
+
+        assert!(stream.poll_next_unpin(&mut cx).is_ready());
+        assert_eq!(
+            Some(DEFAULT_SETTINGS.initial_window_size),
+            poll_increase(&mut write_rx)
+        );
+    }
+
+    /// Bytes consumed before the first window increase.
+    fn first_increase_after(window_update_threshold: u32) -> u32 {
+        let (write_tx, mut write_rx) = conn_command_channel(SomethingDiedErrorHolder::new());
+        let (mut tx, rx) = stream_queue_sync::<ServerTypes>();
+        let mut stream = StreamFromNetwork {
+            rx,
+            increase_in_window: IncreaseInWindow {
+                stream_id: 1,
+                in_window_size: DEFAULT_SETTINGS.initial_window_size,
+                to_write_tx: write_tx,
+            },
+            recv_buffer_limit: None,
+            initial_window_size: DEFAULT_SETTINGS.initial_window_size,
+            window_update_threshold,
+        };
+
+        let mut cx = Context::from_waker(noop_waker_ref());
+        let mut consumed = 0;
+        loop {
+            tx.data_frame(Bytes::from(vec![0; 1000]), false).unwrap();
+            assert!(stream.poll_next_unpin(&mut cx).is_ready());
+            consumed += 1000;
+            if let Some(inc) = poll_increase(&mut write_rx) {
+                assert_eq!(DEFAULT_SETTINGS.initial_window_size, inc);
+                return consumed;
+            }
+        }
+    }
+
+    #[test]
+    fn window_update_threshold() {
+        let conf = CommonConf::default();
+        let threshold = conf.window_update_threshold(DEFAULT_SETTINGS.initial_window_size);
+        assert_eq!(32767, threshold);
+        assert_eq!(33000, first_increase_after(threshold));
+
+        let conf = CommonConf {
+            window_update_fraction: Some(0.1),
+            ..CommonConf::default()
+        };
+        let threshold = conf.window_update_threshold(DEFAULT_SETTINGS.initial_window_size);
+        assert_eq!(6553, threshold);
+        assert_eq!(7000, first_increase_after(threshold));
     }
 }
@@ -1,6 +1,7 @@
 use std::collections::hash_map::Entry;
 use std::collections::hash_map::OccupiedEntry;
 use std::collections::HashMap;
+use std::time::Instant;
 
 use super::stream::HttpStreamCommand;
 use super::stream::HttpStreamCommon;
@@ -9,12 +10,15 @@ use super::types::Types;
 use crate::common::hash_set_shallow_clone::HashSetShallowClone;
 use crate::common::hash_set_shallow_clone::HashSetShallowCloneItems;
 use crate::common::init_where::InitWhere;
+use crate::common::stats::StreamStats;
 use crate::common::stream::DroppedData;
 use crate::data_or_headers::DataOrHeaders;
 use crate::data_or_headers_with_flag::DataOrHeadersWithFlag;
+use crate::solicit::frame::StreamDependency;
 use crate::solicit::session::StreamState;
 use crate::solicit::stream_id::StreamId;
 use crate::solicit::window_size::WindowSize;
+use crate::solicit::DEFAULT_SETTINGS;
 use crate::{error, ErrorCode};
 
 #[derive(Default)]
@@ -22,12 +26,69 @@ pub(crate) struct StreamMap<T: Types> {
     map: HashMap<StreamId, HttpStreamCommon<T>>,
     // This field must be kept in sync with stream state.
     writable_streams: HashSetShallowClone<StreamId>,
+    // This field must be kept in sync with stream state too.
+    flow_control_blocked: FlowControlBlocked,
+}
+
+/// Streams stalled by flow control, i. e. streams with `flow_control_blocked_since`.
+struct FlowControlBlocked {
+    count: u32,
+    conn_out_window_size: WindowSize,
+}
+
+impl Default for FlowControlBlocked {
+    fn default() -> FlowControlBlocked {
+        FlowControlBlocked {
+            count: 0,
+            conn_out_window_size: WindowSize::new(DEFAULT_SETTINGS.initial_window_size as i32),
+        }
+    }
+}
+
+impl FlowControlBlocked {
+    fn sync<T: Types>(&mut self, stream_id: StreamId, s: &mut HttpStreamCommon<T>) {
+        let blocked = s.is_flow_control_blocked(self.conn_out_window_size);
+        match (blocked, s.flow_control_blocked_since) {
+            (true, None) => {
+                debug!(
+                    "stream {} blocked by flow control, stream window {}, conn window {}",
+                    stream_id,
+                    s.out_window_size.size(),
+                    self.conn_out_window_size.size()
+                );
+                s.span.flow_control_blocked(
+                    s.out_window_size.size(),
+                    self.conn_out_window_size.size(),
+                );
+                s.flow_control_blocked_since = Some(Instant::now());
+                self.count += 1;
+            }
+            (false, Some(since)) => {
+                debug!(
+                    "stream {} unblocked by flow control after {:?}",
+                    stream_id,
+                    since.elapsed()
+                );
+                s.span.flow_control_unblocked(since.elapsed());
+                s.flow_control_blocked_since = None;
+                self.count -= 1;
+            }
+            _ => {}
+        }
+    }
+
+    fn remove<T: Types>(&mut self, s: &HttpStreamCommon<T>) {
+        if s.flow_control_blocked_since.is_some() {
+            self.count -= 1;
+        }
+    }
 }
 
 /// Reference to a stream within `StreamMap`
 pub(crate) struct HttpStreamRef<'m, T: Types + 'm> {
     entry: OccupiedEntry<'m, StreamId, HttpStreamCommon<T>>,
     writable_streams: &'m mut HashSetShallowClone<StreamId>,
+    flow_control_blocked: &'m mut FlowControlBlocked,
 }
 
 impl<T: Types> StreamMap<T> {
@@ -35,11 +96,12 @@ impl<T: Types> StreamMap<T> {
         StreamMap {
             map: HashMap::new(),
             writable_streams: HashSetShallowClone::new(),
+            flow_control_blocked: FlowControlBlocked::default(),
         }
     }
 
     /// Insert a stream into a map and return a reference to it
-    pub fn insert(&mut self, id: StreamId, stream: HttpStreamCommon<T>) -> HttpStreamRef<T> {
+    pub fn insert(&mut self, id: StreamId, stream: HttpStreamCommon<T>) -> HttpStreamRef<'_, T> {
         match self.map.entry(id) {
             Entry::Occupied(_) => panic!("stream to insert that already exists: {}", id),
             Entry::Vacant(v) => v.insert(stream),
@@ -51,16 +113,58 @@ impl<T: Types> StreamMap<T> {
         stream
     }
 
-    pub fn get_mut(&mut self, id: StreamId) -> Option<HttpStreamRef<T>> {
+    pub fn get_mut(&mut self, id: StreamId) -> Option<HttpStreamRef<'_, T>> {
         match self.map.entry(id) {
             Entry::Occupied(e) => Some(HttpStreamRef {
                 entry: e,
                 writable_streams: &mut self.writable_streams,
+                flow_control_blocked: &mut self.flow_control_blocked,
             }),
             Entry::Vacant(_) => None,
         }
     }
 
+    /// Set priority of stream `id` specified by peer.
+    ///
+    /// When stream is made dependent on one of its own dependencies, that
+    /// dependency is first moved to the previous parent of the stream
+    /// (RFC 7540 section 5.3.3), so dependencies never form a cycle.
+    /// Exclusive dependency makes other dependencies of the new parent
+    /// dependent on the stream.
+    pub fn set_priority(&mut self, id: StreamId, dep: StreamDependency) {
+        debug_assert_ne!(id, dep.stream_id, "stream depends on itself");
+        let prev_parent = match self.map.get(&id) {
+            Some(s) => s.priority.stream_id,
+            None => return,
+        };
+        if self.depends_on(dep.stream_id, id) {
+            let new_parent = self.map.get_mut(&dep.stream_id).unwrap();
+            new_parent.priority.stream_id = prev_parent;
+            new_parent.priority.is_exclusive = false;
+        }
+        if dep.is_exclusive {
+            for (&sibling_id, sibling) in self.map.iter_mut() {
+                if sibling_id != id && sibling.priority.stream_id == dep.stream_id {
+                    sibling.priority.stream_id = id;
+                }
+            }
+        }
+        self.map.get_mut(&id).unwrap().priority = dep;
+    }
+
+    /// Stream `id` is a descendant of stream `ancestor`.
+    fn depends_on(&self, mut id: StreamId, ancestor: StreamId) -> bool {
+        // Closed streams are removed from map, so the walk stops there
+        for _ in 0..self.map.len() {
+            match self.map.get(&id) {
+                Some(s) if s.priority.stream_id == ancestor => return true,
+                Some(s) => id = s.priority.stream_id,
+                None => return false,
+            }
+        }
+        false
+    }
+
     pub fn remove_stream(&mut self, id: StreamId) {
         if let Some(r) = self.get_mut(id) {
             r.remove();
@@ -82,12 +186,33 @@ impl<T: Types> StreamMap<T> {
                     None
                 }
             })
-            .collect()
+            .collect();
+        self.sync_flow_control_blocked();
+    }
+
+    fn sync_flow_control_blocked(&mut self) {
+        for (&stream_id, s) in &mut self.map {
+            self.flow_control_blocked.sync(stream_id, s);
+        }
+    }
+
+    /// Send held DATA of all streams.
+    pub fn flush_coalesced_data(&mut self) {
+        for s in self.map.values_mut() {
+            if s.data_coalesce_hold() {
+                s.data_coalesce_flush = true;
+            }
+        }
+        self.sync_is_writable();
     }
 
     /// Increment or decrement each stream out window
-    pub fn add_out_window(&mut self, delta: i32) {
-        for (_, s) in &mut self.map {
+    /// after peer changed initial window size from `old` to `new`.
+    ///
+    /// Fail if any window would overflow.
+    pub fn add_out_window(&mut self, old: u32, new: u32) -> Result<(), ()> {
+        let delta = new as isize - old as isize;
+        for s in self.map.values_mut() {
             // In addition to changing the flow-control window for streams
             // that are not yet active, a SETTINGS frame can alter the initial
             // flow-control window size for streams with active flow-control windows
@@ -96,12 +221,12 @@ impl<T: Types> StreamMap<T> {
             // a receiver MUST adjust the size of all stream flow-control windows
             // that it maintains by the difference between the new value
             // and the old value.
-            // TODO: handle overflow
-            s.out_window_size.try_add(delta).unwrap();
-            s.pump_out_window.increase(delta as isize);
+            s.out_window_size.apply_initial_window_delta(old, new)?;
+            s.pump_out_window.increase(delta);
         }
 
         self.sync_is_writable();
+        Ok(())
     }
 
     /// Remove locally initiated streams with id > given.
@@ -118,7 +243,10 @@ impl<T: Types> StreamMap<T> {
 
         let mut r = Vec::new();
         for r_id in stream_ids {
-            r.push((r_id, self.map.remove(&r_id).unwrap()))
+            let s = self.map.remove(&r_id).unwrap();
+            self.writable_streams.remove(&r_id);
+            self.flow_control_blocked.remove(&s);
+            r.push((r_id, s))
         }
         r
     }
@@ -127,6 +255,19 @@ impl<T: Types> StreamMap<T> {
         self.map.is_empty()
     }
 
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Number of streams initiated by this side, i. e. streams counted
+    /// against peer `SETTINGS_MAX_CONCURRENT_STREAMS` (RFC 7540 section 5.1.2).
+    pub fn locally_initiated_len(&self) -> usize {
+        self.map
+            .keys()
+            .filter(|&&s| T::init_where(s) == InitWhere::Locally)
+            .count()
+    }
+
     pub fn _stream_ids(&self) -> Vec<StreamId> {
         self.map.keys().cloned().collect()
     }
@@ -139,6 +280,38 @@ impl<T: Types> StreamMap<T> {
         self.map.iter().map(|(&k, s)| (k, s.snapshot())).collect()
     }
 
+    pub fn stats(&self) -> HashMap<StreamId, StreamStats> {
+        self.map.iter().map(|(&k, s)| (k, s.stats())).collect()
+    }
+
+    /// Track streams stalled by flow control, return the number of stalled streams.
+    ///
+    /// Streams are updated when their state changes, so all streams
+    /// are visited only when connection window becomes exhausted or available.
+    pub fn update_flow_control_blocked(&mut self, conn_out_window_size: WindowSize) -> u32 {
+        let was_exhausted = self
+            .flow_control_blocked
+            .conn_out_window_size
+            .is_exhausted();
+        self.flow_control_blocked.conn_out_window_size = conn_out_window_size;
+        if was_exhausted != conn_out_window_size.is_exhausted() {
+            self.sync_flow_control_blocked();
+        }
+        debug_assert!(self
+            .map
+            .values()
+            .all(|s| s.is_flow_control_blocked(conn_out_window_size)
+                == s.flow_control_blocked_since.is_some()));
+        debug_assert_eq!(
+            self.map
+                .values()
+                .filter(|s| s.flow_control_blocked_since.is_some())
+                .count() as u32,
+            self.flow_control_blocked.count
+        );
+        self.flow_control_blocked.count
+    }
+
     pub fn conn_died<F>(mut self, error: F)
     where
         F: Fn() -> error::Error,
@@ -168,8 +341,21 @@ impl<'m, T: Types + 'm> HttpStreamRef<'m, T> {
 
     fn remove(self) {
         let stream_id = self.id();
-        debug!("removing stream {}", stream_id);
+        let s = self.entry.get();
+        let elapsed = s.opened.elapsed();
+        match s.reset {
+            Some(error_code) => debug!(
+                "stream {} closed: reset {:?}, sent {} bytes, received {} bytes in {:?}",
+                stream_id, error_code, s.out_data_bytes, s.in_data_bytes, elapsed
+            ),
+            None => debug!(
+                "stream {} closed: complete, sent {} bytes, received {} bytes in {:?}",
+                stream_id, s.out_data_bytes, s.in_data_bytes, elapsed
+            ),
+        }
+        s.span.closed(s.reset, s.out_data_bytes, s.in_data_bytes);
         self.writable_streams.remove(&stream_id);
+        self.flow_control_blocked.remove(s);
         self.entry.remove();
     }
 
@@ -198,6 +384,9 @@ impl<'m, T: Types + 'm> HttpStreamRef<'m, T> {
     fn sync_writable(&mut self) {
         let writable = self.stream().is_writable();
         self.mark_writable(writable);
+        let stream_id = self.id();
+        self.flow_control_blocked
+            .sync(stream_id, self.entry.get_mut());
     }
 
     pub fn remove_if_closed(mut self) -> Option<Self> {
@@ -259,6 +448,11 @@ impl<'m, T: Types + 'm> HttpStreamRef<'m, T> {
         self.sync_writable();
     }
 
+    pub fn flush_coalesced_data(&mut self) {
+        self.stream().data_coalesce_flush = true;
+        self.sync_writable();
+    }
+
     pub fn close_outgoing(&mut self, error_core: ErrorCode) {
         self.stream().outgoing.close(error_core);
         self.sync_writable();
@@ -1,5 +1,9 @@
 use std::collections::VecDeque;
 
+use bytes::BufMut;
+use bytes::Bytes;
+use bytes::BytesMut;
+
 use crate::data_or_headers::DataOrHeaders;
 
 use crate::data_or_headers_with_flag::DataOrHeadersWithFlag;
@@ -41,7 +45,7 @@ impl StreamQueue {
     }
 
     pub fn push_back(&mut self, part: DataOrHeaders) {
-        if let Some(_) = self.end {
+        if self.end.is_some() {
             return;
         }
         self.data_size += data_size(&part);
@@ -69,16 +73,56 @@ impl StreamQueue {
         }
     }
 
+    /// Pop front DATA joined with following DATA up to `max_bytes` total.
+    pub fn pop_front_data_coalesced(&mut self, max_bytes: usize) -> Option<Bytes> {
+        let first = match self.queue.front() {
+            Some(DataOrHeaders::Data(data)) => data.len(),
+            _ => return None,
+        };
+        let mut count = 1;
+        let mut len = first;
+        for part in self.queue.iter().skip(1) {
+            match part {
+                DataOrHeaders::Data(data) if len + data.len() <= max_bytes => {
+                    count += 1;
+                    len += data.len();
+                }
+                _ => break,
+            }
+        }
+
+        if count == 1 {
+            return match self.pop_front() {
+                Some(DataOrHeaders::Data(data)) => Some(data),
+                _ => unreachable!(),
+            };
+        }
+
+        let mut r = BytesMut::with_capacity(len);
+        for _ in 0..count {
+            match self.pop_front() {
+                Some(DataOrHeaders::Data(data)) => r.put(data),
+                _ => unreachable!(),
+            }
+        }
+        Some(r.freeze())
+    }
+
     pub fn front(&self) -> Option<&DataOrHeaders> {
         self.queue.front()
     }
 
     pub fn close(&mut self, error_code: ErrorCode) {
-        if None == self.end {
+        if self.end.is_none() {
             self.end = Some(error_code);
         }
     }
 
+    /// Nothing more will be added.
+    pub fn is_closed(&self) -> bool {
+        self.end.is_some()
+    }
+
     pub fn end(&self) -> Option<ErrorCode> {
         if !self.is_empty() {
             None
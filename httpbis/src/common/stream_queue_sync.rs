@@ -22,19 +22,35 @@ use bytes::Bytes;
 use futures::task::Context;
 use std::marker;
 use std::pin::Pin;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
 
 pub(crate) struct StreamQueueSyncSender<T: Types> {
     sender: UnboundedSender<Result<DataOrHeadersWithFlag, error::Error>>,
+    /// Bytes of `DATA` queued but not yet taken by receiver.
+    buffered: Arc<AtomicUsize>,
     _marker: marker::PhantomData<T>,
 }
 
 pub(crate) struct StreamQueueSyncReceiver<T: Types> {
     receiver: UnboundedReceiver<Result<DataOrHeadersWithFlag, error::Error>>,
     eof_received: bool,
+    buffered: Arc<AtomicUsize>,
     _marker: marker::PhantomData<T>,
 }
 
 impl<T: Types> StreamQueueSyncSender<T> {
+    fn send_data(&self, data: Bytes, end_stream: bool) -> result::Result<()> {
+        let len = data.len();
+        self.send(Ok(DataOrHeadersWithFlag {
+            content: DataOrHeaders::Data(data),
+            last: end_stream,
+        }))?;
+        self.buffered.fetch_add(len, Ordering::SeqCst);
+        Ok(())
+    }
+
     fn send(&self, item: Result<DataOrHeadersWithFlag, error::Error>) -> result::Result<()> {
         if let Err(_send_error) = self.sender.unbounded_send(item) {
             // TODO: better error
@@ -47,10 +63,7 @@ impl<T: Types> StreamQueueSyncSender<T> {
 
 impl ServerRequestStreamHandler for StreamQueueSyncSender<ServerTypes> {
     fn data_frame(&mut self, data: Bytes, end_stream: bool) -> result::Result<()> {
-        self.send(Ok(DataOrHeadersWithFlag {
-            content: DataOrHeaders::Data(data),
-            last: end_stream,
-        }))
+        self.send_data(data, end_stream)
     }
 
     fn trailers(&mut self, trailers: Headers) -> result::Result<()> {
@@ -78,10 +91,7 @@ impl ClientResponseStreamHandler for StreamQueueSyncSender<ClientTypes> {
     }
 
     fn data_frame(&mut self, data: Bytes, end_stream: bool) -> result::Result<()> {
-        self.send(Ok(DataOrHeadersWithFlag {
-            content: DataOrHeaders::Data(data),
-            last: end_stream,
-        }))
+        self.send_data(data, end_stream)
     }
 
     fn trailers(&mut self, trailers: Headers) -> result::Result<()> {
@@ -100,6 +110,18 @@ impl ClientResponseStreamHandler for StreamQueueSyncSender<ClientTypes> {
     }
 }
 
+impl<T: Types> StreamQueueSyncReceiver<T> {
+    /// Last part or error was read.
+    pub fn eof_received(&self) -> bool {
+        self.eof_received
+    }
+
+    /// Bytes of `DATA` received from network but not yet read.
+    pub fn buffered(&self) -> usize {
+        self.buffered.load(Ordering::SeqCst)
+    }
+}
+
 impl<T: Types> Stream for StreamQueueSyncReceiver<T> {
     type Item = result::Result<DataOrHeadersWithFlag>;
 
@@ -129,6 +151,9 @@ impl<T: Types> Stream for StreamQueueSyncReceiver<T> {
                 if part.last {
                     self.eof_received = true;
                 }
+                if let DataOrHeaders::Data(ref data) = part.content {
+                    self.buffered.fetch_sub(data.len(), Ordering::SeqCst);
+                }
                 part
             }
         };
@@ -140,14 +165,17 @@ impl<T: Types> Stream for StreamQueueSyncReceiver<T> {
 pub(crate) fn stream_queue_sync<T: Types>() -> (StreamQueueSyncSender<T>, StreamQueueSyncReceiver<T>)
 {
     let (utx, urx) = unbounded();
+    let buffered = Arc::new(AtomicUsize::new(0));
 
     let tx = StreamQueueSyncSender {
         sender: utx,
+        buffered: buffered.clone(),
         _marker: marker::PhantomData,
     };
     let rx = StreamQueueSyncReceiver {
         receiver: urx,
         eof_received: false,
+        buffered,
         _marker: marker::PhantomData,
     };
 
@@ -0,0 +1,91 @@
+//! Optional `tracing` integration.
+//!
+//! With `tracing` feature enabled each stream gets a span with stream id,
+//! method, path, outcome and byte counts, and settings changes and flow
+//! control stalls are reported as events. Without the feature everything
+//! here compiles to nothing.
+
+#![cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+
+use std::time::Duration;
+
+use crate::solicit::frame::Settings;
+use crate::solicit::stream_id::StreamId;
+use crate::ErrorCode;
+
+/// Span covering stream lifetime.
+pub(crate) struct StreamSpan {
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+impl StreamSpan {
+    /// Span of stream for which `opened` has not been called yet.
+    pub fn none() -> StreamSpan {
+        StreamSpan {
+            #[cfg(feature = "tracing")]
+            span: tracing::Span::none(),
+        }
+    }
+
+    pub fn opened(stream_id: StreamId, method: &str, path: &str) -> StreamSpan {
+        StreamSpan {
+            #[cfg(feature = "tracing")]
+            span: tracing::debug_span!(
+                "http2_stream",
+                stream_id,
+                method,
+                path,
+                outcome = tracing::field::Empty,
+                sent_bytes = tracing::field::Empty,
+                received_bytes = tracing::field::Empty,
+            ),
+        }
+    }
+
+    pub fn flow_control_blocked(&self, stream_window: i32, conn_window: i32) {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            parent: &self.span,
+            stream_window,
+            conn_window,
+            "blocked by flow control"
+        );
+    }
+
+    pub fn flow_control_unblocked(&self, blocked_for: Duration) {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            parent: &self.span,
+            blocked_for = ?blocked_for,
+            "unblocked by flow control"
+        );
+    }
+
+    /// Record outcome and byte counts, `reset` is error code of `RST_STREAM`
+    /// sent or received.
+    pub fn closed(&self, reset: Option<ErrorCode>, sent_bytes: u64, received_bytes: u64) {
+        #[cfg(feature = "tracing")]
+        {
+            match reset {
+                Some(error_code) => self
+                    .span
+                    .record("outcome", tracing::field::debug(error_code)),
+                None => self.span.record("outcome", "complete"),
+            };
+            self.span.record("sent_bytes", sent_bytes);
+            self.span.record("received_bytes", received_bytes);
+            tracing::debug!(parent: &self.span, "stream closed");
+        }
+    }
+}
+
+pub(crate) fn peer_settings_changed(settings: &Settings) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(settings = ?settings, "peer settings changed");
+}
+
+pub(crate) fn our_settings_acked(settings: &Settings) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(settings = ?settings, "our settings acknowledged");
+}
@@ -5,7 +5,6 @@ use crate::common::init_where::InitWhere;
 use crate::common::stream::HttpStreamData;
 use crate::common::stream::HttpStreamDataSpecific;
 use crate::common::stream_handler::StreamHandlerInternal;
-use crate::req_resp::RequestOrResponse;
 use crate::solicit::stream_id::StreamId;
 
 /// Client or server type names for connection and stream
@@ -21,9 +20,6 @@ pub(crate) trait Types: Default + Clone + Unpin + 'static {
     /// Runtime check if this type is constructed for client or server
     const CLIENT_OR_SERVER: ClientOrServer;
 
-    /// Outgoing messages are requests or responses
-    const OUT_REQUEST_OR_RESPONSE: RequestOrResponse;
-
     const CONN_NDC: &'static str;
 
     /// Is stream initiated locally or by peer?
@@ -1,6 +1,5 @@
 #![allow(dead_code)]
 
-use std::mem;
 use std::sync::Arc;
 use std::sync::Mutex;
 
@@ -14,6 +13,7 @@ struct WakerShared {
     waiters: Mutex<Vec<Arc<WaiterShared>>>,
 }
 
+#[derive(Clone)]
 pub struct Waker {
     shared: Arc<WakerShared>,
 }
@@ -29,7 +29,7 @@ impl Waker {
 
     pub fn wake_all(&self) {
         let mut lock = self.shared.waiters.lock().expect("lock");
-        let waiters = mem::replace(&mut *lock, Vec::new());
+        let waiters = std::mem::take(&mut *lock);
         for waiter in waiters {
             debug_assert!(waiter.waker_knows.load(Ordering::Relaxed));
             waiter.waker_knows.store(false, Ordering::Relaxed);
@@ -37,6 +37,13 @@ impl Drop for ConnOutWindowSender {
     }
 }
 
+/// Does not close the connection window when dropped.
+#[derive(Clone)]
+pub struct ConnOutWindowHandle {
+    waker: Waker,
+    shared: Arc<ConnOutWindowShared>,
+}
+
 struct ConnOutWindowReceiver {
     shared: Arc<ConnOutWindowShared>,
 }
@@ -71,29 +78,23 @@ impl ConnOutWindowSender {
     }
 
     pub fn new_stream(&self, initial: u32) -> (StreamOutWindowSender, StreamOutWindowReceiver) {
-        let shared = Arc::new(StreamWindowShared {
-            conn: self.shared.clone(),
-            window_size: AtomicIsize::new(initial as isize),
-            task: AtomicBoxOption::new(),
-            closed: AtomicBool::new(false),
-        });
-
-        let sender = StreamOutWindowSender {
-            shared: shared.clone(),
-        };
-        let receiver = StreamOutWindowReceiver {
-            conn_waiter: self.waker.new_waiter(),
-            shared: shared,
-        };
-        (sender, receiver)
+        new_stream(&self.waker, &self.shared, initial)
+    }
+
+    /// Handle to create stream windows outside of connection task.
+    pub fn handle(&self) -> ConnOutWindowHandle {
+        ConnOutWindowHandle {
+            waker: self.waker.clone(),
+            shared: self.shared.clone(),
+        }
     }
 
     pub fn get(&self) -> isize {
-        self.shared.window_size.load(Ordering::SeqCst) as isize
+        self.shared.window_size.load(Ordering::SeqCst)
     }
 
     pub fn increase(&self, size: usize) {
-        assert!(size <= isize::max_value() as usize);
+        assert!(size <= isize::MAX as usize);
         let old_size = self
             .shared
             .window_size
@@ -106,15 +107,40 @@ impl ConnOutWindowSender {
     }
 }
 
+impl ConnOutWindowHandle {
+    pub fn new_stream(&self, initial: u32) -> (StreamOutWindowSender, StreamOutWindowReceiver) {
+        new_stream(&self.waker, &self.shared, initial)
+    }
+}
+
+fn new_stream(
+    waker: &Waker,
+    conn: &Arc<ConnOutWindowShared>,
+    initial: u32,
+) -> (StreamOutWindowSender, StreamOutWindowReceiver) {
+    let shared = Arc::new(StreamWindowShared {
+        conn: conn.clone(),
+        window_size: AtomicIsize::new(initial as isize),
+        task: AtomicBoxOption::new(),
+        closed: AtomicBool::new(false),
+    });
+
+    let sender = StreamOutWindowSender {
+        shared: shared.clone(),
+    };
+    let receiver = StreamOutWindowReceiver {
+        conn_waiter: waker.new_waiter(),
+        shared,
+    };
+    (sender, receiver)
+}
+
 impl StreamOutWindowSender {
     /// `size` can be negative when INITIAL_WINDOW_SIZE
     /// setting changes to lower value.
     pub fn increase(&self, size: isize) {
-        let old_size = self
-            .shared
-            .window_size
-            .fetch_add(size as isize, Ordering::SeqCst);
-        let new_size = old_size + size as isize;
+        let old_size = self.shared.window_size.fetch_add(size, Ordering::SeqCst);
+        let new_size = old_size + size;
         if new_size > 0 {
             if let Some(task) = self.shared.task.swap_null(Ordering::SeqCst) {
                 task.wake();
@@ -123,7 +149,7 @@ impl StreamOutWindowSender {
     }
 
     pub fn get(&self) -> isize {
-        self.shared.window_size.load(Ordering::SeqCst) as isize
+        self.shared.window_size.load(Ordering::SeqCst)
     }
 }
 
@@ -15,11 +15,14 @@ use crate::solicit::header::Headers;
 use crate::data_or_headers::DataOrHeaders;
 use crate::data_or_headers_with_flag::DataOrHeadersWithFlag;
 use crate::data_or_headers_with_flag::DataOrHeadersWithFlagStream;
+use crate::http_stream_async_read::async_read_bytes;
+use crate::http_stream_async_read::HttpStreamAsyncRead;
 use crate::misc::any_to_string;
 use crate::solicit::end_stream::EndStream;
 use futures::stream::StreamExt;
 use futures::task::Context;
 use std::pin::Pin;
+use tokio::io::AsyncRead;
 
 /// Stream frame content after initial headers
 pub enum DataOrTrailers {
@@ -90,6 +93,14 @@ impl HttpStreamAfterHeaders {
         HttpStreamAfterHeaders::new(bytes.map_ok(DataOrTrailers::intermediate_data))
     }
 
+    /// Create a response from `AsyncRead`, reader is polled when stream is polled.
+    pub fn from_async_read<R>(reader: R) -> HttpStreamAfterHeaders
+    where
+        R: AsyncRead + Send + 'static,
+    {
+        HttpStreamAfterHeaders::bytes(async_read_bytes(reader))
+    }
+
     pub fn once(part: DataOrHeaders) -> HttpStreamAfterHeaders {
         let part = match part {
             DataOrHeaders::Data(data) => DataOrTrailers::Data(data, EndStream::Yes),
@@ -122,10 +133,15 @@ impl HttpStreamAfterHeaders {
         })
     }
 
+    /// Read `DATA` as `AsyncRead`, trailers are available after EOF.
+    pub fn into_async_read(self) -> HttpStreamAsyncRead {
+        HttpStreamAsyncRead::new(self)
+    }
+
     pub(crate) fn into_flag_stream(
         self,
     ) -> impl Stream<Item = result::Result<DataOrHeadersWithFlag>> + Send {
-        TryStreamExt::map_ok(self.0, |r| DataOrTrailers::into_part(r))
+        TryStreamExt::map_ok(self.0, DataOrTrailers::into_part)
     }
 
     // TODO: drop
@@ -5,7 +5,7 @@ pub(crate) struct DisplayCommaSeparated<'a, A: fmt::Display>(pub &'a [A]);
 
 impl<'a, A: fmt::Display> fmt::Display for DisplayCommaSeparated<'a, A> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        for (i, item) in self.0.into_iter().enumerate() {
+        for (i, item) in self.0.iter().enumerate() {
             if i != 0 {
                 write!(f, ", ")?;
             }
@@ -1,3 +1,4 @@
+use std::cmp;
 use std::error::Error as std_Error;
 use std::fmt;
 use std::io;
@@ -8,14 +9,16 @@ use crate::assert_types::*;
 
 use crate::hpack::decoder::DecoderError;
 
-use tls_api;
-
+use crate::common::conn_close::ConnectionClose;
 use crate::common::sender::SendError;
 use crate::display_comma_separated::DisplayCommaSeparated;
+use crate::misc::BsDebug;
 use crate::solicit::error_code::ErrorCode;
+use crate::solicit::frame::GoawayFrame;
 use crate::solicit::frame::HttpFrameType;
 use crate::solicit::frame::ParseFrameError;
 use crate::solicit::frame::RawHttpFrameType;
+use crate::solicit_async::PREFACE;
 use crate::StreamDead;
 use crate::StreamId;
 use std::net::SocketAddr;
@@ -55,6 +58,18 @@ pub enum Error {
     MalformedResponse,
     /// Connection timed out.
     ConnectionTimeout,
+    /// Request did not complete within `request_timeout`.
+    RequestTimeout,
+    /// Nothing received from peer within keep alive timeout.
+    KeepAliveTimeout,
+    /// Peer disabled server push with `SETTINGS_ENABLE_PUSH`,
+    /// or push is initiated from pushed response.
+    PushDisabled,
+    /// Promised request is not a valid `GET` or `HEAD` request.
+    InvalidPushRequest,
+    /// Request has `:protocol` pseudo-header, but server did not enable
+    /// `SETTINGS_ENABLE_CONNECT_PROTOCOL`.
+    ConnectProtocolDisabled,
     /// Shutdown of local client or server
     Shutdown,
     /// Request handler panicked.
@@ -119,15 +134,16 @@ pub enum Error {
     ConnInWindowOverflow(i32, u32),
     /// Ping response wrong payload.
     PingAckOpaqueDataMismatch(u64, u64),
-    /// Goaway after goaway.
+    /// `GOAWAY` with last stream id greater than in previous `GOAWAY`.
     GoawayAfterGoaway,
     /// Got `SETTINGS` ack without `SETTINGS` sent.
     SettingsAckWithoutSettingsSent,
     /// `GOAWAY`
     // TODO: explain
     Goaway,
-    /// Received `GOAWAY`
-    GoawayReceived,
+    /// Received `GOAWAY`, stream id is greater than last stream id in the frame,
+    /// so the stream was not processed by peer and can be safely retried.
+    GoawayReceived(GoawayFrame),
     /// Stream died.
     // TODO: explain
     PullStreamDied,
@@ -135,8 +151,214 @@ pub enum Error {
     PayloadTooLarge(u32, u32),
     /// Request is made using HTTP/1
     RequestIsMadeUsingHttp1,
+    /// Client sent HTTP/2 preface over plain connection, but prior knowledge is not allowed.
+    PriorKnowledgeNotAllowed,
+    /// Protocol negotiated with ALPN is not allowed (or not negotiated when required).
+    AlpnProtocolNotAllowed(Option<Vec<u8>>),
     /// Listen address is not specified.
     ListenAddrNotSpecified,
+    /// Client did not start connection with HTTP/2 preface.
+    PrefaceError(PrefaceError),
+    /// Connection closed while stream was in progress.
+    ConnectionClosed(ConnectionClose),
+    /// Configured initial settings are not allowed by RFC 7540 section 6.5.2.
+    InvalidSettings(ParseFrameError),
+    /// Configured `CommonConf::window_update_fraction` is not in `0.0..1.0` exclusive.
+    InvalidWindowUpdateFraction(f32),
+    /// Header list size exceeds `SETTINGS_MAX_HEADER_LIST_SIZE` advertised by peer.
+    HeaderListTooLarge(usize, u32),
+}
+
+/// Client connection preface (RFC 7540 section 3.5) not received.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrefaceError {
+    /// Bytes received instead of preface, truncated to preface length.
+    pub received: Vec<u8>,
+}
+
+impl PrefaceError {
+    pub(crate) fn new(received: &[u8]) -> PrefaceError {
+        let len = cmp::min(received.len(), PREFACE.len());
+        PrefaceError {
+            received: received[..len].to_vec(),
+        }
+    }
+}
+
+impl fmt::Display for PrefaceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "wrong connection preface: {:?}", BsDebug(&self.received))?;
+        if self.received.first() == Some(&0x16) {
+            write!(f, ", likely TLS")?;
+        }
+        Ok(())
+    }
+}
+
+/// Error of single request, returned from request futures (RFC 7540 section 5.4).
+#[derive(Debug)]
+pub enum StreamError {
+    /// Only this stream failed, connection is still usable (RFC 7540 section 5.4.2).
+    Stream {
+        /// Code of `RST_STREAM` sent or received, `None` if stream was not reset.
+        error_code: Option<ErrorCode>,
+        /// Underlying error.
+        error: Error,
+    },
+    /// Stream was terminated together with the connection.
+    Connection(ConnectionError),
+}
+
+impl StreamError {
+    /// Code of `RST_STREAM` or of `GOAWAY` which terminated the stream.
+    pub fn error_code(&self) -> Option<ErrorCode> {
+        match self {
+            StreamError::Stream { error_code, .. } => *error_code,
+            StreamError::Connection(e) => e.error_code,
+        }
+    }
+
+    /// Underlying error.
+    pub fn error(&self) -> &Error {
+        match self {
+            StreamError::Stream { error, .. } => error,
+            StreamError::Connection(e) => &e.error,
+        }
+    }
+}
+
+impl From<Error> for StreamError {
+    fn from(error: Error) -> StreamError {
+        match error {
+            Error::RstStreamReceived(error_code) => StreamError::Stream {
+                error_code: Some(error_code),
+                error,
+            },
+            // Stream is cancelled by us
+            Error::RequestTimeout => StreamError::Stream {
+                error_code: Some(ErrorCode::Cancel),
+                error,
+            },
+            error if error.is_connection_scoped() => {
+                StreamError::Connection(ConnectionError::from(error))
+            }
+            error => StreamError::Stream {
+                error_code: None,
+                error,
+            },
+        }
+    }
+}
+
+impl From<StreamError> for Error {
+    fn from(e: StreamError) -> Error {
+        match e {
+            StreamError::Stream { error, .. } => error,
+            StreamError::Connection(e) => e.error,
+        }
+    }
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StreamError::Stream { error, .. } => write!(f, "stream error: {}", error),
+            StreamError::Connection(e) => fmt::Display::fmt(e, f),
+        }
+    }
+}
+
+impl std_Error for StreamError {
+    fn source(&self) -> Option<&(dyn std_Error + 'static)> {
+        Some(self.error())
+    }
+}
+
+/// Error which terminated the connection with all its streams (RFC 7540 section 5.4.1),
+/// returned from connection futures.
+#[derive(Debug)]
+pub struct ConnectionError {
+    /// Code of `GOAWAY` sent or received, `None` if connection was closed without `GOAWAY`.
+    pub error_code: Option<ErrorCode>,
+    /// Underlying error.
+    pub error: Error,
+}
+
+impl ConnectionError {
+    /// Why connection was closed, `None` if connection was not established.
+    pub fn close(&self) -> Option<&ConnectionClose> {
+        self.error.connection_close()
+    }
+}
+
+impl From<Error> for ConnectionError {
+    fn from(error: Error) -> ConnectionError {
+        ConnectionError {
+            error_code: error.goaway_error_code(),
+            error,
+        }
+    }
+}
+
+impl From<ConnectionClose> for ConnectionError {
+    fn from(close: ConnectionClose) -> ConnectionError {
+        ConnectionError::from(Error::ConnectionClosed(close))
+    }
+}
+
+impl From<ConnectionError> for Error {
+    fn from(e: ConnectionError) -> Error {
+        e.error
+    }
+}
+
+impl fmt::Display for ConnectionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "connection error: {}", self.error)
+    }
+}
+
+impl std_Error for ConnectionError {
+    fn source(&self) -> Option<&(dyn std_Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl Error {
+    /// Error terminated the connection with all its streams.
+    fn is_connection_scoped(&self) -> bool {
+        matches!(
+            self,
+            Error::ConnectionClosed(..)
+                | Error::GoawayReceived(..)
+                | Error::KeepAliveTimeout
+                | Error::ConnDied
+                | Error::ClientDied(..)
+                | Error::ClientDiedAndReconnectFailed
+        )
+    }
+
+    fn connection_close(&self) -> Option<&ConnectionClose> {
+        match self {
+            Error::ConnectionClosed(close) => Some(close),
+            Error::ClientDied(Some(e)) => e.connection_close(),
+            _ => None,
+        }
+    }
+
+    /// Code of `GOAWAY` sent or received, or code of connection error detected by us.
+    fn goaway_error_code(&self) -> Option<ErrorCode> {
+        match self {
+            Error::ConnectionClosed(ConnectionClose::ProtocolError(e)) => match **e {
+                Error::CodeError(error_code) => Some(error_code),
+                _ => Some(ErrorCode::ProtocolError),
+            },
+            Error::ConnectionClosed(close) => close.error_code(),
+            Error::GoawayReceived(frame) => Some(frame.error_code()),
+            Error::ClientDied(Some(e)) => e.goaway_error_code(),
+            _ => None,
+        }
+    }
 }
 
 fn _assert_error_sync_send() {
@@ -194,7 +416,9 @@ impl fmt::Display for Error {
             Error::IoError(_) => write!(f, "Encountered an IO error"),
             Error::TlsError(_) => write!(f, "Encountered TLS error"),
             Error::CodeError(_) => write!(f, "Encountered HTTP named error"),
-            Error::RstStreamReceived(_) => write!(f, "Received RST_STREAM from peer"),
+            Error::RstStreamReceived(error_code) => {
+                write!(f, "Received RST_STREAM from peer: {:?}", error_code)
+            }
             Error::InvalidFrame(..) => {
                 write!(f, "Encountered an invalid or unexpected HTTP/2 frame")
             }
@@ -208,6 +432,11 @@ impl fmt::Display for Error {
             }
             Error::MalformedResponse => write!(f, "The received response was malformed"),
             Error::ConnectionTimeout => write!(f, "Connection time out"),
+            Error::RequestTimeout => write!(f, "Request time out"),
+            Error::KeepAliveTimeout => write!(f, "Keep alive time out"),
+            Error::PushDisabled => write!(f, "Server push is disabled"),
+            Error::InvalidPushRequest => write!(f, "Invalid push request"),
+            Error::ConnectProtocolDisabled => write!(f, "Extended CONNECT is disabled by peer"),
             Error::Shutdown => write!(f, "Local shutdown"),
             Error::HandlerPanicked(_) => write!(f, "Handler panicked"),
             Error::ParseFrameError(_) => write!(f, "Failed to parse frame"),
@@ -263,7 +492,7 @@ impl fmt::Display for Error {
             }
             Error::GoawayAfterGoaway => write!(
                 f,
-                "{} after {}",
+                "{} after {} increased last stream id",
                 HttpFrameType::Goaway,
                 HttpFrameType::Goaway
             ),
@@ -274,11 +503,37 @@ impl fmt::Display for Error {
                 HttpFrameType::Settings
             ),
             Error::Goaway => write!(f, "{}", HttpFrameType::Goaway),
-            Error::GoawayReceived => write!(f, "{} received", HttpFrameType::Goaway),
+            Error::GoawayReceived(frame) => write!(
+                f,
+                "{} received, last stream id: {}, error code: {:?}",
+                HttpFrameType::Goaway,
+                frame.last_stream_id(),
+                frame.error_code()
+            ),
             Error::PullStreamDied => write!(f, "Pull stream died"),
             Error::PayloadTooLarge(_, _) => write!(f, "Payload too large"),
+            Error::HeaderListTooLarge(size, max) => write!(
+                f,
+                "Header list size {} exceeds peer max header list size {}",
+                size, max
+            ),
             Error::RequestIsMadeUsingHttp1 => write!(f, "Request is made using HTTP/1"),
+            Error::PriorKnowledgeNotAllowed => {
+                write!(f, "HTTP/2 with prior knowledge is not allowed")
+            }
+            Error::AlpnProtocolNotAllowed(Some(ref p)) => write!(
+                f,
+                "ALPN protocol is not allowed: {:?}",
+                String::from_utf8_lossy(p)
+            ),
+            Error::AlpnProtocolNotAllowed(None) => write!(f, "ALPN protocol is not negotiated"),
             Error::ListenAddrNotSpecified => write!(f, "Listen addr not specified"),
+            Error::ConnectionClosed(close) => write!(f, "Connection closed: {}", close),
+            Error::PrefaceError(e) => write!(f, "{}", e),
+            Error::InvalidSettings(e) => write!(f, "Invalid settings: {:?}", e),
+            Error::InvalidWindowUpdateFraction(fraction) => {
+                write!(f, "Invalid window update fraction: {}", fraction)
+            }
         }
     }
 }
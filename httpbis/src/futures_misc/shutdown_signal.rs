@@ -11,7 +11,7 @@ use void::Void;
 
 pub fn shutdown_signal() -> (ShutdownSignal, ShutdownFuture) {
     let (tx, rx) = unbounded();
-    (ShutdownSignal { tx: tx }, ShutdownFuture { rx: rx })
+    (ShutdownSignal { tx }, ShutdownFuture { rx })
 }
 
 pub struct ShutdownSignal {
@@ -4,10 +4,12 @@
 //! The decoder only follows HPACK rules, without performing any additional
 //! (semantic) checks on the header name/value pairs, i.e. it considers the
 //! headers as opaque octets.
+use std::mem;
 use std::num::Wrapping;
 
 use bytes::Buf;
 use bytes::Bytes;
+use bytes::BytesMut;
 
 use super::huffman::HuffmanDecoder;
 use super::huffman::HuffmanDecoderError;
@@ -23,12 +25,12 @@ use super::HeaderTable;
 /// Returns a tuple representing the decoded integer and the number
 /// of bytes from the buffer that were used.
 fn decode_integer(buf: &[u8], prefix_size: u8) -> Result<(usize, usize), DecoderError> {
-    if prefix_size < 1 || prefix_size > 8 {
+    if !(1..=8).contains(&prefix_size) {
         return Err(DecoderError::IntegerDecodingError(
             IntegerDecodingError::InvalidPrefix,
         ));
     }
-    if buf.len() < 1 {
+    if buf.is_empty() {
         return Err(DecoderError::IntegerDecodingError(
             IntegerDecodingError::NotEnoughOctets,
         ));
@@ -187,7 +189,12 @@ pub enum StringDecodingError {
 /// of an HPACK header set.
 #[derive(PartialEq, Copy, Clone, Debug)]
 pub enum DecoderError {
-    HeaderIndexOutOfBounds,
+    /// Indexed representation references a table entry which doesn't exist.
+    /// Indices past the static table (61 entries) refer to the dynamic table.
+    ///
+    /// Called `HeaderIndexOutOfBounds` without the index before 0.10,
+    /// enum variants cannot have deprecated aliases.
+    InvalidTableIndex(usize),
     IntegerDecodingError(IntegerDecodingError),
     StringDecodingError(StringDecodingError),
     /// The size of the dynamic table can never be allowed to exceed the max
@@ -195,6 +202,18 @@ pub enum DecoderError {
     /// made by SizeUpdate blocks).
     InvalidMaxDynamicSize(u32, u32),
     SizeUpdateMustBeFirstField,
+    /// Header list size (RFC 7540 section 6.5.2) exceeds given limit.
+    HeaderListTooLarge(usize),
+}
+
+impl DecoderError {
+    /// For `InvalidTableIndex` error, whether the index is in the dynamic table range.
+    pub fn is_dynamic_table_index(&self) -> bool {
+        match *self {
+            DecoderError::InvalidTableIndex(index) => index > StaticTable::new().len(),
+            _ => false,
+        }
+    }
 }
 
 /// The result returned by the `decode` method of the `Decoder`.
@@ -202,14 +221,24 @@ pub type DecoderResult = Result<Vec<(Bytes, Bytes)>, DecoderError>;
 
 /// Decodes headers encoded using HPACK.
 ///
-/// For now, incremental decoding is not supported, i.e. it is necessary
-/// to pass in the entire encoded representation of all headers to the
-/// decoder, rather than processing it piece-by-piece.
+/// Either the entire encoded representation of all headers is passed
+/// to `decode`, or block fragments are passed to `decode_fragment`,
+/// which buffers them until the end of the block.
 pub struct Decoder {
     // The dynamic table will own its own copy of headers
     header_table: HeaderTable,
     // Max configured size
     max_size: u32,
+    // Header block fragments received so far by `decode_fragment`
+    fragments: BytesMut,
+    // Limit of `decode_fragment` buffer and decoded header list
+    max_header_list_size: Option<usize>,
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 /// Represents a decoder of HPACK encoded headers. Maintains the state
@@ -233,6 +262,8 @@ impl Decoder {
         Decoder {
             header_table: HeaderTable::with_static_table(static_table),
             max_size: 4096,
+            fragments: BytesMut::new(),
+            max_header_list_size: None,
         }
     }
 
@@ -244,6 +275,19 @@ impl Decoder {
             .set_max_table_size(new_max_size);
     }
 
+    /// Limit header blocks passed to `decode_fragment`, both buffered
+    /// fragments and decoded header list (RFC 7540 section 6.5.2).
+    ///
+    /// Unlimited by default.
+    pub fn set_max_header_list_size(&mut self, max_header_list_size: usize) {
+        self.max_header_list_size = Some(max_header_list_size);
+    }
+
+    /// Current size of the dynamic table (RFC 7541 section 4.1).
+    pub fn table_size(&self) -> usize {
+        self.header_table.dynamic_table.get_size()
+    }
+
     /// Decodes the headers found in the given buffer `buf`. Invokes the callback `cb` for each
     /// decoded header in turn, by providing it the header name and value as `Cow` byte array
     /// slices.
@@ -259,9 +303,35 @@ impl Decoder {
     ///
     /// If an error is encountered during the decoding of any header, decoding halts and the
     /// appropriate error is returned as the `Err` variant of the `Result`.
-    pub fn decode_with_cb<F>(&mut self, mut buf: Bytes, mut cb: F) -> Result<(), DecoderError>
+    pub fn decode_with_cb<F>(&mut self, buf: Bytes, mut cb: F) -> Result<(), DecoderError>
     where
         F: FnMut(Bytes, Bytes),
+    {
+        self.decode_with_sensitivity_cb(buf, |n, v, _| cb(n, v))
+    }
+
+    /// Same as `decode_with_cb`, but the callback also receives a flag
+    /// which is `true` if the header was encoded as never indexed literal.
+    ///
+    /// Intermediaries must forward such headers with the same representation.
+    pub fn decode_with_sensitivity_cb<F>(
+        &mut self,
+        buf: Bytes,
+        mut cb: F,
+    ) -> Result<(), DecoderError>
+    where
+        F: FnMut(Bytes, Bytes, bool),
+    {
+        self.decode_with_try_cb(buf, |n, v, s| {
+            cb(n, v, s);
+            Ok(())
+        })
+    }
+
+    /// Decoding stops at the first error returned by the callback.
+    fn decode_with_try_cb<F>(&mut self, mut buf: Bytes, mut cb: F) -> Result<(), DecoderError>
+    where
+        F: FnMut(Bytes, Bytes, bool) -> Result<(), DecoderError>,
     {
         let mut current_size_update = true;
 
@@ -274,14 +344,14 @@ impl Decoder {
             let consumed = match FieldRepresentation::new(initial_octet) {
                 FieldRepresentation::Indexed => {
                     let ((name, value), consumed) = self.decode_indexed(&buf[..])?;
-                    cb(name, value);
+                    cb(name, value, false)?;
 
                     consumed
                 }
                 FieldRepresentation::LiteralWithIncrementalIndexing => {
                     let ((name, value), consumed) = {
                         let ((name, value), consumed) = self.decode_literal(&buf, true)?;
-                        cb(name.clone(), value.clone());
+                        cb(name.clone(), value.clone(), false)?;
 
                         ((name, value), consumed)
                     };
@@ -296,17 +366,15 @@ impl Decoder {
                 }
                 FieldRepresentation::LiteralWithoutIndexing => {
                     let ((name, value), consumed) = self.decode_literal(&buf, false)?;
-                    cb(name, value);
+                    cb(name, value, false)?;
 
                     consumed
                 }
                 FieldRepresentation::LiteralNeverIndexed => {
-                    // Same as the previous one, except if we were also a proxy
-                    // we would need to make sure not to change the
-                    // representation received here. We don't care about this
-                    // for now.
+                    // Same as the previous one, except the representation
+                    // must be preserved by intermediaries, so report it.
                     let ((name, value), consumed) = self.decode_literal(&buf, false)?;
-                    cb(name, value);
+                    cb(name, value, true)?;
 
                     consumed
                 }
@@ -347,6 +415,79 @@ impl Decoder {
         Ok(header_list)
     }
 
+    /// Decode the header block like `decode`, additionally returning
+    /// for each header whether it was encoded as never indexed literal.
+    pub fn decode_with_sensitivity(
+        &mut self,
+        buf: Bytes,
+    ) -> Result<Vec<(Bytes, Bytes, bool)>, DecoderError> {
+        let mut header_list = Vec::new();
+
+        self.decode_with_sensitivity_cb(buf, |n, v, s| header_list.push((n, v, s)))?;
+
+        Ok(header_list)
+    }
+
+    /// Same as `decode_with_sensitivity`, but fails with `HeaderListTooLarge`
+    /// as soon as decoded header list size exceeds `max_list_size`,
+    /// without decoding the rest of the block.
+    ///
+    /// Decoder state is not usable after this error.
+    pub fn decode_with_sensitivity_limit(
+        &mut self,
+        buf: Bytes,
+        max_list_size: usize,
+    ) -> Result<Vec<(Bytes, Bytes, bool)>, DecoderError> {
+        let mut header_list = Vec::new();
+        let mut list_size = 0;
+
+        self.decode_with_try_cb(buf, |n, v, s| {
+            // Same as `Header::list_size`
+            list_size += n.len() + v.len() + 32;
+            if list_size > max_list_size {
+                return Err(DecoderError::HeaderListTooLarge(max_list_size));
+            }
+            header_list.push((n, v, s));
+            Ok(())
+        })?;
+
+        Ok(header_list)
+    }
+
+    /// Decode the header block split into fragments (e. g. HEADERS followed
+    /// by CONTINUATION frames).
+    ///
+    /// Fragments are buffered until `end` is `true`, then the whole block
+    /// is decoded. A block ending in the middle of a representation
+    /// is an error.
+    ///
+    /// Buffered fragments exceeding `set_max_header_list_size` are discarded
+    /// with `HeaderListTooLarge` error.
+    pub fn decode_fragment(
+        &mut self,
+        fragment: &[u8],
+        end: bool,
+    ) -> Result<Option<Vec<(Bytes, Bytes)>>, DecoderError> {
+        if let Some(max) = self.max_header_list_size {
+            if self.fragments.len() + fragment.len() > max {
+                self.fragments.clear();
+                return Err(DecoderError::HeaderListTooLarge(max));
+            }
+        }
+        self.fragments.extend_from_slice(fragment);
+        if !end {
+            return Ok(None);
+        }
+        let block = mem::take(&mut self.fragments).freeze();
+        match self.max_header_list_size {
+            Some(max) => {
+                let headers = self.decode_with_sensitivity_limit(block, max)?;
+                Ok(Some(headers.into_iter().map(|(n, v, _)| (n, v)).collect()))
+            }
+            None => self.decode(block).map(Some),
+        }
+    }
+
     #[cfg(test)]
     pub fn decode_for_test<B>(&mut self, buf: B) -> DecoderResult
     where
@@ -376,7 +517,7 @@ impl Decoder {
     fn get_from_table(&self, index: usize) -> Result<(Bytes, Bytes), DecoderError> {
         self.header_table
             .get_from_table(index)
-            .ok_or(DecoderError::HeaderIndexOutOfBounds)
+            .ok_or(DecoderError::InvalidTableIndex(index))
     }
 
     /// Decodes a literal header representation from the given buffer.
@@ -535,86 +676,86 @@ mod tests {
 
     #[test]
     fn test_detect_literal_without_indexing() {
-        assert!(match FieldRepresentation::new(0) {
-            FieldRepresentation::LiteralWithoutIndexing => true,
-            _ => false,
-        });
-        assert!(match FieldRepresentation::new((1 << 4) - 1) {
-            FieldRepresentation::LiteralWithoutIndexing => true,
-            _ => false,
-        });
-        assert!(match FieldRepresentation::new(2) {
-            FieldRepresentation::LiteralWithoutIndexing => true,
-            _ => false,
-        });
+        assert!(matches!(
+            FieldRepresentation::new(0),
+            FieldRepresentation::LiteralWithoutIndexing
+        ));
+        assert!(matches!(
+            FieldRepresentation::new((1 << 4) - 1),
+            FieldRepresentation::LiteralWithoutIndexing
+        ));
+        assert!(matches!(
+            FieldRepresentation::new(2),
+            FieldRepresentation::LiteralWithoutIndexing
+        ));
     }
 
     #[test]
     fn test_detect_literal_never_indexed() {
-        assert!(match FieldRepresentation::new(1 << 4) {
-            FieldRepresentation::LiteralNeverIndexed => true,
-            _ => false,
-        });
-        assert!(match FieldRepresentation::new((1 << 4) + 15) {
-            FieldRepresentation::LiteralNeverIndexed => true,
-            _ => false,
-        });
+        assert!(matches!(
+            FieldRepresentation::new(1 << 4),
+            FieldRepresentation::LiteralNeverIndexed
+        ));
+        assert!(matches!(
+            FieldRepresentation::new((1 << 4) + 15),
+            FieldRepresentation::LiteralNeverIndexed
+        ));
     }
 
     #[test]
     fn test_detect_literal_incremental_indexing() {
-        assert!(match FieldRepresentation::new(1 << 6) {
-            FieldRepresentation::LiteralWithIncrementalIndexing => true,
-            _ => false,
-        });
-        assert!(match FieldRepresentation::new((1 << 6) + (1 << 4)) {
-            FieldRepresentation::LiteralWithIncrementalIndexing => true,
-            _ => false,
-        });
-        assert!(match FieldRepresentation::new((1 << 7) - 1) {
-            FieldRepresentation::LiteralWithIncrementalIndexing => true,
-            _ => false,
-        });
+        assert!(matches!(
+            FieldRepresentation::new(1 << 6),
+            FieldRepresentation::LiteralWithIncrementalIndexing
+        ));
+        assert!(matches!(
+            FieldRepresentation::new((1 << 6) + (1 << 4)),
+            FieldRepresentation::LiteralWithIncrementalIndexing
+        ));
+        assert!(matches!(
+            FieldRepresentation::new((1 << 7) - 1),
+            FieldRepresentation::LiteralWithIncrementalIndexing
+        ));
     }
 
     #[test]
     fn test_detect_indexed() {
-        assert!(match FieldRepresentation::new(1 << 7) {
-            FieldRepresentation::Indexed => true,
-            _ => false,
-        });
-        assert!(match FieldRepresentation::new((1 << 7) + (1 << 4)) {
-            FieldRepresentation::Indexed => true,
-            _ => false,
-        });
-        assert!(match FieldRepresentation::new((1 << 7) + (1 << 5)) {
-            FieldRepresentation::Indexed => true,
-            _ => false,
-        });
-        assert!(match FieldRepresentation::new((1 << 7) + (1 << 6)) {
-            FieldRepresentation::Indexed => true,
-            _ => false,
-        });
-        assert!(match FieldRepresentation::new(255) {
-            FieldRepresentation::Indexed => true,
-            _ => false,
-        });
+        assert!(matches!(
+            FieldRepresentation::new(1 << 7),
+            FieldRepresentation::Indexed
+        ));
+        assert!(matches!(
+            FieldRepresentation::new((1 << 7) + (1 << 4)),
+            FieldRepresentation::Indexed
+        ));
+        assert!(matches!(
+            FieldRepresentation::new((1 << 7) + (1 << 5)),
+            FieldRepresentation::Indexed
+        ));
+        assert!(matches!(
+            FieldRepresentation::new((1 << 7) + (1 << 6)),
+            FieldRepresentation::Indexed
+        ));
+        assert!(matches!(
+            FieldRepresentation::new(255),
+            FieldRepresentation::Indexed
+        ));
     }
 
     #[test]
     fn test_detect_dynamic_table_size_update() {
-        assert!(match FieldRepresentation::new(1 << 5) {
-            FieldRepresentation::SizeUpdate => true,
-            _ => false,
-        });
-        assert!(match FieldRepresentation::new((1 << 5) + (1 << 4)) {
-            FieldRepresentation::SizeUpdate => true,
-            _ => false,
-        });
-        assert!(match FieldRepresentation::new((1 << 6) - 1) {
-            FieldRepresentation::SizeUpdate => true,
-            _ => false,
-        });
+        assert!(matches!(
+            FieldRepresentation::new(1 << 5),
+            FieldRepresentation::SizeUpdate
+        ));
+        assert!(matches!(
+            FieldRepresentation::new((1 << 5) + (1 << 4)),
+            FieldRepresentation::SizeUpdate
+        ));
+        assert!(matches!(
+            FieldRepresentation::new((1 << 6) - 1),
+            FieldRepresentation::SizeUpdate
+        ));
     }
 
     #[test]
@@ -662,7 +803,7 @@ mod tests {
         {
             let full_string: Vec<u8> = (0u8..200).collect();
             let mut encoded = encode_integer(full_string.len(), 7);
-            encoded.extend(full_string.clone().into_iter());
+            encoded.extend(full_string.clone());
 
             assert_eq!(
                 (Bytes::from(full_string), encoded.len()),
@@ -672,7 +813,7 @@ mod tests {
         {
             let full_string: Vec<u8> = (0u8..127).collect();
             let mut encoded = encode_integer(full_string.len(), 7);
-            encoded.extend(full_string.clone().into_iter());
+            encoded.extend(full_string.clone());
 
             assert_eq!(
                 (Bytes::from(full_string), encoded.len()),
@@ -688,7 +829,7 @@ mod tests {
     fn test_decode_fully_in_static_table() {
         let mut decoder = Decoder::new();
 
-        let header_list = decoder.decode_for_test(&[0x82]).unwrap();
+        let header_list = decoder.decode_for_test([0x82]).unwrap();
 
         assert_eq!(
             vec![(Bytes::from(&b":method"[..]), Bytes::from(&b"GET"[..]))],
@@ -700,7 +841,7 @@ mod tests {
     fn test_decode_multiple_fully_in_static_table() {
         let mut decoder = Decoder::new();
 
-        let header_list = decoder.decode_for_test(&[0x82, 0x86, 0x84]).unwrap();
+        let header_list = decoder.decode_for_test([0x82, 0x86, 0x84]).unwrap();
 
         assert_eq!(
             header_list,
@@ -722,7 +863,7 @@ mod tests {
             0x04, 0x0c, 0x2f, 0x73, 0x61, 0x6d, 0x70, 0x6c, 0x65, 0x2f, 0x70, 0x61, 0x74, 0x68,
         ];
 
-        let header_list = decoder.decode_for_test(&hex_dump).unwrap();
+        let header_list = decoder.decode_for_test(hex_dump).unwrap();
 
         assert_eq!(
             header_list,
@@ -746,7 +887,7 @@ mod tests {
             0x75, 0x73, 0x74, 0x6f, 0x6d, 0x2d, 0x68, 0x65, 0x61, 0x64, 0x65, 0x72,
         ];
 
-        let header_list = decoder.decode_for_test(&hex_dump).unwrap();
+        let header_list = decoder.decode_for_test(hex_dump).unwrap();
 
         assert_eq!(
             header_list,
@@ -774,7 +915,7 @@ mod tests {
                 0x75, 0x73, 0x74, 0x6f, 0x6d, 0x2d, 0x68, 0x65, 0x61, 0x64, 0x65, 0x72,
             ];
 
-            let header_list = decoder.decode_for_test(&hex_dump).unwrap();
+            let header_list = decoder.decode_for_test(hex_dump).unwrap();
 
             assert_eq!(
                 header_list,
@@ -809,7 +950,7 @@ mod tests {
                 0x2d,
             ];
 
-            let header_list = decoder.decode_for_test(&hex_dump).unwrap();
+            let header_list = decoder.decode_for_test(hex_dump).unwrap();
 
             assert_eq!(
                 header_list,
@@ -840,7 +981,7 @@ mod tests {
             0x72, 0x65, 0x74,
         ];
 
-        let header_list = decoder.decode_for_test(&hex_dump).unwrap();
+        let header_list = decoder.decode_for_test(hex_dump).unwrap();
 
         assert_eq!(
             header_list,
@@ -863,7 +1004,7 @@ mod tests {
                 0x6c, 0x65, 0x2e, 0x63, 0x6f, 0x6d,
             ];
 
-            let header_list = decoder.decode_for_test(&hex_dump).unwrap();
+            let header_list = decoder.decode_for_test(hex_dump).unwrap();
 
             assert_eq!(
                 header_list,
@@ -889,7 +1030,7 @@ mod tests {
                 0x82, 0x86, 0x84, 0xbe, 0x58, 0x08, 0x6e, 0x6f, 0x2d, 0x63, 0x61, 0x63, 0x68, 0x65,
             ];
 
-            let header_list = decoder.decode_for_test(&hex_dump).unwrap();
+            let header_list = decoder.decode_for_test(hex_dump).unwrap();
 
             assert_eq!(
                 header_list,
@@ -923,7 +1064,7 @@ mod tests {
                 0x65,
             ];
 
-            let header_list = decoder.decode_for_test(&hex_dump).unwrap();
+            let header_list = decoder.decode_for_test(hex_dump).unwrap();
 
             assert_eq!(
                 header_list,
@@ -1005,7 +1146,7 @@ mod tests {
             // Second Response (C.5.2.)
             let hex_dump = [0x48, 0x03, 0x33, 0x30, 0x37, 0xc1, 0xc0, 0xbf];
 
-            let header_list = decoder.decode_for_test(&hex_dump).unwrap();
+            let header_list = decoder.decode_for_test(hex_dump).unwrap();
 
             assert_eq!(
                 header_list,
@@ -1143,7 +1284,7 @@ mod tests {
                 0x20,
             ];
 
-            let header_list = decoder.decode_for_test(&hex_dump).unwrap();
+            let header_list = decoder.decode_for_test(hex_dump).unwrap();
 
             // Headers have been correctly decoded...
             assert_eq!(
@@ -1185,7 +1326,7 @@ mod tests {
                 0x90, 0xf4, 0xff,
             ];
 
-            let header_list = decoder.decode_for_test(&hex_dump).unwrap();
+            let header_list = decoder.decode_for_test(hex_dump).unwrap();
 
             assert_eq!(
                 header_list,
@@ -1211,7 +1352,7 @@ mod tests {
                 0x82, 0x86, 0x84, 0xbe, 0x58, 0x86, 0xa8, 0xeb, 0x10, 0x64, 0x9c, 0xbf,
             ];
 
-            let header_list = decoder.decode_for_test(&hex_dump).unwrap();
+            let header_list = decoder.decode_for_test(hex_dump).unwrap();
 
             assert_eq!(
                 header_list,
@@ -1244,7 +1385,7 @@ mod tests {
                 0x89, 0x25, 0xa8, 0x49, 0xe9, 0x5b, 0xb8, 0xe8, 0xb4, 0xbf,
             ];
 
-            let header_list = decoder.decode_for_test(&hex_dump).unwrap();
+            let header_list = decoder.decode_for_test(hex_dump).unwrap();
 
             assert_eq!(
                 header_list,
@@ -1325,7 +1466,7 @@ mod tests {
             // Second Response (C.6.2.)
             let hex_dump = [0x48, 0x83, 0x64, 0x0e, 0xff, 0xc1, 0xc0, 0xbf];
 
-            let header_list = decoder.decode_for_test(&hex_dump).unwrap();
+            let header_list = decoder.decode_for_test(hex_dump).unwrap();
 
             assert_eq!(
                 header_list,
@@ -1428,29 +1569,149 @@ mod tests {
         let raw_messages = [
             // This indicates that the index of the header is 0, which is
             // invalid...
-            vec![0x80],
+            (vec![0x80], 0, false),
             // This indicates that the index of the header is 62, which is out
             // of the bounds of the header table, given that there are no
             // entries in the dynamic table and the static table contains 61
             // elements.
-            vec![0xbe],
+            (vec![0xbe], 62, true),
             // Literal encoded with an indexed name where the index is out of
             // bounds.
-            vec![126, 1, 65],
+            (vec![126, 1, 65], 62, true),
         ];
 
         // Check them all...
-        for raw_message in raw_messages.iter() {
+        for &(ref raw_message, index, dynamic) in raw_messages.iter() {
+            let result = decoder.decode(Bytes::copy_from_slice(raw_message));
             assert!(
-                is_decoder_error(
-                    &DecoderError::HeaderIndexOutOfBounds,
-                    &decoder.decode(Bytes::copy_from_slice(&raw_message))
-                ),
+                is_decoder_error(&DecoderError::InvalidTableIndex(index), &result),
                 "Expected index out of bounds"
             );
+            assert_eq!(dynamic, result.unwrap_err().is_dynamic_table_index());
         }
     }
 
+    /// Tests that decoding stops as soon as header list size exceeds the limit.
+    #[test]
+    fn test_decode_list_size_limit() {
+        // `:method: GET` twice, then an index out of bounds of the table
+        let block = Bytes::from_static(&[0x82, 0x82, 0xbf]);
+
+        let mut decoder = Decoder::new();
+        assert_eq!(
+            Err(DecoderError::InvalidTableIndex(63)),
+            decoder
+                .decode_with_sensitivity_limit(block.clone(), 1000)
+                .map(|h| h.len())
+        );
+
+        // Size of `:method: GET` entry is 7 + 3 + 32
+        let mut decoder = Decoder::new();
+        assert_eq!(
+            Err(DecoderError::HeaderListTooLarge(50)),
+            decoder
+                .decode_with_sensitivity_limit(block.clone(), 50)
+                .map(|h| h.len())
+        );
+
+        let mut decoder = Decoder::new();
+        assert_eq!(
+            Ok(2),
+            decoder
+                .decode_with_sensitivity_limit(block.slice(..2), 84)
+                .map(|h| h.len())
+        );
+    }
+
+    #[test]
+    fn test_decode_fragment() {
+        let mut decoder = Decoder::new();
+        // RFC 7541 C.3.1 split in the middle of the literal value
+        let block = [
+            0x82, 0x86, 0x84, 0x41, 0x0f, 0x77, 0x77, 0x77, 0x2e, 0x65, 0x78, 0x61, 0x6d, 0x70,
+            0x6c, 0x65, 0x2e, 0x63, 0x6f, 0x6d,
+        ];
+        assert_eq!(None, decoder.decode_fragment(&block[..3], false).unwrap());
+        assert_eq!(None, decoder.decode_fragment(&block[3..8], false).unwrap());
+        let headers = decoder.decode_fragment(&block[8..], true).unwrap().unwrap();
+        assert_eq!(
+            vec![
+                (Bytes::from_static(b":method"), Bytes::from_static(b"GET")),
+                (Bytes::from_static(b":scheme"), Bytes::from_static(b"http")),
+                (Bytes::from_static(b":path"), Bytes::from_static(b"/")),
+                (
+                    Bytes::from_static(b":authority"),
+                    Bytes::from_static(b"www.example.com")
+                ),
+            ],
+            headers
+        );
+    }
+
+    #[test]
+    fn test_decode_fragment_premature_end() {
+        let mut decoder = Decoder::new();
+        assert_eq!(None, decoder.decode_fragment(&[0x82, 0x41], false).unwrap());
+        assert_eq!(
+            Err(DecoderError::StringDecodingError(
+                StringDecodingError::NotEnoughOctets
+            )),
+            decoder.decode_fragment(&[0x0f, b'w'], true)
+        );
+        // Buffered fragments are discarded with the block
+        assert_eq!(
+            Some(vec![(
+                Bytes::from_static(b":method"),
+                Bytes::from_static(b"GET")
+            )]),
+            decoder.decode_fragment(&[0x82], true).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_decode_fragment_max_header_list_size() {
+        let mut decoder = Decoder::new();
+        decoder.set_max_header_list_size(40);
+        assert_eq!(None, decoder.decode_fragment(&[0x82; 30], false).unwrap());
+        assert_eq!(
+            Err(DecoderError::HeaderListTooLarge(40)),
+            decoder.decode_fragment(&[0x82; 11], false)
+        );
+        // Buffer is discarded, one `:method: GET` is 42 octets decoded
+        assert_eq!(
+            Err(DecoderError::HeaderListTooLarge(40)),
+            decoder.decode_fragment(&[0x82], true)
+        );
+        decoder.set_max_header_list_size(42);
+        assert_eq!(
+            Some(vec![(
+                Bytes::from_static(b":method"),
+                Bytes::from_static(b"GET")
+            )]),
+            decoder.decode_fragment(&[0x82], true).unwrap()
+        );
+    }
+
+    /// Tests that an index past the combined static and dynamic table
+    /// reports the offending index.
+    #[test]
+    fn test_index_past_dynamic_table() {
+        let mut decoder = Decoder::new();
+        // Literal with incremental indexing: adds one entry to the dynamic table
+        decoder
+            .decode_for_test([0x40, 0x01, b'a', 0x01, b'b'])
+            .unwrap();
+
+        assert_eq!(
+            vec![(Bytes::from_static(b"a"), Bytes::from_static(b"b"))],
+            decoder.decode_for_test([0xbe]).unwrap()
+        );
+        assert_eq!(
+            Err(DecoderError::InvalidTableIndex(63)),
+            decoder.decode_for_test([0xbf])
+        );
+    }
+
     /// Tests that if a header encoded using a literal string representation
     /// (using Huffman encoding) contains an invalid string encoding, an error
     /// is returned.
@@ -1463,12 +1724,12 @@ mod tests {
             0x90, 0xf4, 0xfe,
         ];
 
-        assert!(match decoder.decode_for_test(&hex_dump) {
-            Err(DecoderError::StringDecodingError(StringDecodingError::HuffmanDecoderError(
-                HuffmanDecoderError::InvalidPadding,
-            ))) => true,
-            _ => false,
-        });
+        assert!(matches!(
+            decoder.decode_for_test(hex_dump),
+            Err(DecoderError::StringDecodingError(
+                StringDecodingError::HuffmanDecoderError(HuffmanDecoderError::InvalidPadding,)
+            ))
+        ));
     }
 
     /// Tests that if the message cuts short before the header key is decoded,
@@ -1482,12 +1743,14 @@ mod tests {
             0x40, 0x0a, b'c', b'u', b's', b't', b'o', b'm', b'-', b'k', b'e',
         ];
 
-        let result = decoder.decode_for_test(&hex_dump);
+        let result = decoder.decode_for_test(hex_dump);
 
-        assert!(match result {
-            Err(DecoderError::StringDecodingError(StringDecodingError::NotEnoughOctets)) => true,
-            _ => false,
-        });
+        assert!(matches!(
+            result,
+            Err(DecoderError::StringDecodingError(
+                StringDecodingError::NotEnoughOctets
+            ))
+        ));
     }
 
     /// Tests that when a header is encoded as a literal with both a name and
@@ -1501,224 +1764,13 @@ mod tests {
             0x40, 0x0a, b'c', b'u', b's', b't', b'o', b'm', b'-', b'k', b'e', b'y',
         ];
 
-        let result = decoder.decode_for_test(&hex_dump);
-
-        assert!(match result {
-            Err(DecoderError::IntegerDecodingError(IntegerDecodingError::NotEnoughOctets)) => true,
-            _ => false,
-        });
-    }
-}
-
-/// The module defines interop tests between this HPACK decoder
-/// and some other encoder implementations, based on their results
-/// published at
-/// [http2jp/hpack-test-case](https://github.com/http2jp/hpack-test-case)
-#[cfg(feature = "interop_tests")]
-#[cfg(test)]
-mod interop_tests {
-    use std::collections::HashMap;
-    use std::fs::{self, File};
-    use std::io::Read;
-    use std::path::{Path, PathBuf};
-
-    use rustc_serialize::hex::FromHex;
-    use rustc_serialize::Decoder as JsonDecoder;
-    use rustc_serialize::{json, Decodable};
+        let result = decoder.decode_for_test(hex_dump);
 
-    use super::Decoder;
-
-    /// Defines the structure of a single part of a story file. We only care
-    /// about the bytes and corresponding headers and ignore the rest.
-    struct TestFixture {
-        wire_bytes: Vec<u8>,
-        headers: Vec<(Vec<u8>, Vec<u8>)>,
-    }
-
-    /// Defines the structure corresponding to a full story file. We only
-    /// care about the cases for now.
-    #[derive(RustcDecodable)]
-    struct TestStory {
-        cases: Vec<TestFixture>,
-    }
-
-    /// A custom implementation of the `rustc_serialize::Decodable` trait for
-    /// `TestFixture`s. This is necessary for two reasons:
-    ///
-    ///  - The original story files store the raw bytes as a hex-encoded
-    ///    *string*, so we convert it to a `Vec<u8>` at parse time
-    ///  - The original story files store the list of headers as an array of
-    ///    objects, where each object has a single key. We convert this to a
-    ///    more natural representation of a `Vec` of two-tuples.
-    ///
-    /// For an example of the test story JSON structure check the
-    /// `test_story_parser_sanity_check` test function or one of the fixtures
-    /// in the directory `fixtures/hpack/interop`.
-    impl Decodable for TestFixture {
-        fn decode<D: JsonDecoder>(d: &mut D) -> Result<Self, D::Error> {
-            d.read_struct("root", 0, |d| {
-                Ok(TestFixture {
-                    wire_bytes: d.read_struct_field("wire", 0, |d| {
-                        // Read the `wire` field...
-                        Decodable::decode(d).and_then(|res: String| {
-                            // If valid, parse out the octets from the String by
-                            // considering it a hex encoded byte sequence.
-                            Ok(res.from_hex().unwrap())
-                        })
-                    })?,
-                    headers: d.read_struct_field("headers", 0, |d| {
-                        // Read the `headers` field...
-                        d.read_seq(|d, len| {
-                            // ...since it's an array, we step into the sequence
-                            // and read each element.
-                            let mut ret: Vec<(Vec<u8>, Vec<u8>)> = Vec::new();
-                            for i in (0..len) {
-                                // Individual elements are encoded as a simple
-                                // JSON object with one key: value pair.
-                                let header: HashMap<String, String> =
-                                    d.read_seq_elt(i, |d| Decodable::decode(d))?;
-                                // We convert it to a tuple, which is a more
-                                // natural representation of headers.
-                                for (name, value) in header.into_iter() {
-                                    ret.push((name.as_bytes().to_vec(), value.as_bytes().to_vec()));
-                                }
-                            }
-                            Ok(ret)
-                        })
-                    })?,
-                })
-            })
-        }
-    }
-
-    /// Tests that the `TestStory` can be properly read out of a JSON encoded
-    /// string. Sanity check for the `Decodable` implementation.
-    #[test]
-    fn test_story_parser_sanity_check() {
-        let raw_json = stringify!(
-            {
-              "cases": [
-                {
-                  "seqno": 0,
-                  "wire": "82864188f439ce75c875fa5784",
-                  "headers": [
-                    {
-                      ":method": "GET"
-                    },
-                    {
-                      ":scheme": "http"
-                    },
-                    {
-                      ":authority": "yahoo.co.jp"
-                    },
-                    {
-                      ":path": "/"
-                    }
-                  ]
-                },
-                {
-                  "seqno": 1,
-                  "wire": "8286418cf1e3c2fe8739ceb90ebf4aff84",
-                  "headers": [
-                    {
-                      ":method": "GET"
-                    },
-                    {
-                      ":scheme": "http"
-                    },
-                    {
-                      ":authority": "www.yahoo.co.jp"
-                    },
-                    {
-                      ":path": "/"
-                    }
-                  ]
-                }
-              ],
-              "draft": 9
-            }
-        );
-
-        let decoded: TestStory = json::decode(raw_json).unwrap();
-
-        assert_eq!(decoded.cases.len(), 2);
-        assert_eq!(
-            decoded.cases[0].wire_bytes,
-            vec![0x82, 0x86, 0x41, 0x88, 0xf4, 0x39, 0xce, 0x75, 0xc8, 0x75, 0xfa, 0x57, 0x84]
-        );
-        assert_eq!(
-            decoded.cases[0].headers,
-            vec![
-                (b":method".to_vec(), b"GET".to_vec()),
-                (b":scheme".to_vec(), b"http".to_vec()),
-                (b":authority".to_vec(), b"yahoo.co.jp".to_vec()),
-                (b":path".to_vec(), b"/".to_vec()),
-            ]
-        );
-    }
-
-    /// A helper function that performs an interop test for a given story file.
-    ///
-    /// It does so by first decoding the JSON representation of the story into
-    /// a `TestStory` struct. After this, each subsequent block of headers is
-    /// passed to the same decoder instance (since each story represents one
-    /// coder context). The result returned by the decoder is compared to the
-    /// headers stored for that particular block within the story file.
-    fn test_story(story_file_name: PathBuf) {
-        // Set up the story by parsing the given file
-        let story: TestStory = {
-            let mut file = File::open(&story_file_name).unwrap();
-            let mut raw_story = String::new();
-            file.read_to_string(&mut raw_story).unwrap();
-            json::decode(&raw_story).unwrap()
-        };
-        // Set up the decoder
-        let mut decoder = Decoder::new();
-
-        // Now check whether we correctly decode each case
-        for case in story.cases.iter() {
-            let decoded = decoder.decode(&case.wire_bytes).unwrap();
-            assert_eq!(decoded, case.headers);
-        }
-    }
-
-    /// Tests a full fixture set, provided a path to a directory containing a
-    /// number of story files (and no other file types).
-    ///
-    /// It calls the `test_story` function for each file found in the given
-    /// directory.
-    fn test_fixture_set(fixture_dir: &str) {
-        let files = fs::read_dir(&Path::new(fixture_dir)).unwrap();
-
-        for fixture in files {
-            let file_name = fixture.unwrap().path();
-            debug!("Testing fixture: {:?}", file_name);
-            test_story(file_name);
-        }
-    }
-
-    #[test]
-    fn test_nghttp2_interop() {
-        test_fixture_set("fixtures/hpack/interop/nghttp2");
-    }
-
-    #[test]
-    fn test_nghttp2_change_table_size_interop() {
-        test_fixture_set("fixtures/hpack/interop/nghttp2-change-table-size");
-    }
-
-    #[test]
-    fn test_go_hpack_interop() {
-        test_fixture_set("fixtures/hpack/interop/go-hpack");
-    }
-
-    #[test]
-    fn test_node_http2_hpack_interop() {
-        test_fixture_set("fixtures/hpack/interop/node-http2-hpack");
-    }
-
-    #[test]
-    fn test_haskell_http2_linear_huffman() {
-        test_fixture_set("fixtures/hpack/interop/haskell-http2-linear-huffman");
+        assert!(matches!(
+            result,
+            Err(DecoderError::IntegerDecodingError(
+                IntegerDecodingError::NotEnoughOctets
+            ))
+        ));
     }
 }
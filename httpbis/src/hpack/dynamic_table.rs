@@ -46,7 +46,7 @@ impl DynamicTable {
         DynamicTable {
             table: VecDeque::new(),
             size: 0,
-            max_size: max_size,
+            max_size,
         }
     }
 
@@ -79,7 +79,6 @@ impl DynamicTable {
     }
 
     /// Returns the maximum size of the table in octets.
-    #[cfg(test)]
     pub fn get_max_table_size(&self) -> usize {
         self.max_size
     }
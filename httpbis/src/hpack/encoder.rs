@@ -3,21 +3,22 @@
 //!
 //! Clients should use the `Encoder` struct as the API for performing HPACK
 //! encoding.
+use std::cmp;
 use std::num::Wrapping;
 
 use bytes::Bytes;
 
 use super::HeaderTable;
+use crate::hpack::huffman;
 use crate::hpack::static_table::StaticTable;
 use crate::hpack::HeaderValueFound;
+use crate::solicit::header::Headers;
 use bytes::BytesMut;
 
 pub trait EncodeBuf {
     fn write_all(&mut self, bytes: &[u8]);
 
-    fn reserve(&mut self, additional: usize) {
-        drop(additional);
-    }
+    fn reserve(&mut self, _additional: usize) {}
 
     fn write_u8(&mut self, b: u8) {
         self.write_all(&[b]);
@@ -78,7 +79,7 @@ pub fn encode_integer_into<W: EncodeBuf>(
     value -= mask;
     while value >= 128 {
         writer.write_u8(((value % 128) + 128) as u8);
-        value = value / 128;
+        value /= 128;
     }
     writer.write_u8(value as u8);
 }
@@ -95,6 +96,21 @@ pub fn encode_integer(value: usize, prefix_size: u8) -> Vec<u8> {
     res
 }
 
+/// HPACK encoding strategy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HpackMode {
+    /// Index headers in the dynamic table, Huffman-encode strings when it is shorter.
+    Huffman,
+    /// Index headers in the dynamic table, never use Huffman encoding.
+    ///
+    /// Mode of newly created encoder.
+    NoHuffman,
+    /// Use neither the dynamic table nor Huffman encoding.
+    ///
+    /// Output depends only on the header list, so it can be compared to fixed bytes.
+    NoIndexing,
+}
+
 /// Represents an HPACK encoder. Allows clients to encode arbitrary header sets
 /// and tracks the encoding context. That is, encoding subsequent header sets
 /// will use the context built by previous encode calls.
@@ -103,6 +119,15 @@ pub fn encode_integer(value: usize, prefix_size: u8) -> Vec<u8> {
 pub struct Encoder {
     /// The header table represents the encoder's context
     header_table: HeaderTable,
+    /// Minimum table size set since the last header block, if table size changed.
+    pending_size_update: Option<usize>,
+    mode: HpackMode,
+}
+
+impl Default for Encoder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Encoder {
@@ -111,9 +136,36 @@ impl Encoder {
     pub fn new() -> Encoder {
         Encoder {
             header_table: HeaderTable::with_static_table(StaticTable::new()),
+            pending_size_update: None,
+            mode: HpackMode::NoHuffman,
         }
     }
 
+    /// Set encoding strategy for subsequent header blocks.
+    pub fn set_mode(&mut self, mode: HpackMode) {
+        self.mode = mode;
+    }
+
+    /// Sets a new maximum dynamic table size for the encoder.
+    ///
+    /// The change is signaled to the decoder by dynamic table size update
+    /// at the beginning of the next encoded header block.
+    /// Size zero disables indexing.
+    pub fn set_max_table_size(&mut self, new_max_size: usize) {
+        self.pending_size_update = Some(match self.pending_size_update {
+            Some(size) => cmp::min(size, new_max_size),
+            None => new_max_size,
+        });
+        self.header_table
+            .dynamic_table
+            .set_max_table_size(new_max_size);
+    }
+
+    /// Current size of the dynamic table (RFC 7541 section 4.1).
+    pub fn table_size(&self) -> usize {
+        self.header_table.dynamic_table.get_size()
+    }
+
     /// Encodes the given headers using the HPACK rules and returns a newly
     /// allocated `Vec` containing the bytes representing the encoded header
     /// set.
@@ -121,10 +173,9 @@ impl Encoder {
     /// The encoder so far supports only a single, extremely simple encoding
     /// strategy, whereby each header is represented as an indexed header if
     /// already found in the header table and a literal otherwise. When a
-    /// header isn't found in the table, it is added if the header name wasn't
-    /// found either (i.e. there are never two header names with different
-    /// values in the produced header table). Strings are always encoded as
-    /// literals (Huffman encoding is not used).
+    /// header isn't found in the table, it is added unless it is a
+    /// pseudo-header whose name is already found. Strings are encoded as
+    /// literals unless Huffman encoding is enabled with `HpackMode::Huffman`.
     pub fn encode_for_test<'b, I>(&mut self, headers: I) -> Vec<u8>
     where
         I: IntoIterator<Item = (&'b [u8], &'b [u8])>,
@@ -152,8 +203,47 @@ impl Encoder {
         I: IntoIterator<Item = (&'b [u8], &'b [u8])>,
         W: EncodeBuf,
     {
-        for header in headers {
-            self.encode_header_into(header, writer);
+        self.encode_into_with_sensitivity(headers.into_iter().map(|(n, v)| (n, v, false)), writer)
+    }
+
+    /// Encode a header list with its sensitivity flags.
+    ///
+    /// The encoder is meant to be kept for the whole connection,
+    /// so headers repeated in subsequent header blocks are encoded as indices.
+    pub fn encode_headers_into<W: EncodeBuf>(&mut self, headers: &Headers, out: &mut W) {
+        self.encode_into_with_sensitivity(
+            headers
+                .iter()
+                .map(|h| (h.name().as_bytes(), h.value(), h.is_sensitive())),
+            out,
+        )
+    }
+
+    /// Same as `encode_into`, but each header carries a sensitivity flag.
+    ///
+    /// Sensitive headers are encoded with never indexed literal representation
+    /// (RFC 7541 section 6.2.3) and never added to the dynamic table.
+    pub fn encode_into_with_sensitivity<'b, I, W>(&mut self, headers: I, writer: &mut W)
+    where
+        I: IntoIterator<Item = (&'b [u8], &'b [u8], bool)>,
+        W: EncodeBuf,
+    {
+        if let Some(min_size) = self.pending_size_update.take() {
+            // RFC 7541 section 4.2: the smallest size must be signaled
+            // if the size was reduced and then increased.
+            let size = self.header_table.dynamic_table.get_max_table_size();
+            if min_size < size {
+                encode_integer_into(min_size, 5, 0x20, writer);
+            }
+            encode_integer_into(size, 5, 0x20, writer);
+        }
+
+        for (name, value, sensitive) in headers {
+            if sensitive {
+                self.encode_never_indexed((name, value), writer);
+            } else {
+                self.encode_header_into((name, value), writer);
+            }
         }
     }
 
@@ -162,7 +252,13 @@ impl Encoder {
     /// Any errors are propagated, similarly to the `encode_into` method, and it is the callers
     /// responsiblity to make sure that the paired encoder sees them too.
     fn encode_header_into<W: EncodeBuf>(&mut self, header: (&[u8], &[u8]), writer: &mut W) {
+        let may_index = self.mode != HpackMode::NoIndexing && self.fits_into_table(header);
         match self.header_table.find_header(header) {
+            None if !may_index => {
+                // The header would not fit into the dynamic table anyway
+                // (e. g. table size is zero), or indexing is disabled.
+                self.encode_literal(&header, false, writer);
+            }
             None => {
                 // The name of the header is in no tables: need to encode
                 // it with both a literal name and value.
@@ -172,6 +268,17 @@ impl Encoder {
                     Bytes::copy_from_slice(header.1),
                 );
             }
+            Some((index, HeaderValueFound::NameOnlyFound))
+                if !header.0.starts_with(b":") && may_index =>
+            {
+                // Regular header values like `content-type` are likely
+                // repeated in subsequent header blocks, so index them.
+                self.encode_indexed_name((index, header.1), true, writer);
+                self.header_table.add_header(
+                    Bytes::copy_from_slice(header.0),
+                    Bytes::copy_from_slice(header.1),
+                );
+            }
             Some((index, HeaderValueFound::NameOnlyFound)) => {
                 // The name of the header is at the given index, but the
                 // value does not match the current one: need to encode
@@ -186,6 +293,26 @@ impl Encoder {
         };
     }
 
+    /// Header entry is not larger than the dynamic table.
+    fn fits_into_table(&self, header: (&[u8], &[u8])) -> bool {
+        header.0.len() + header.1.len() + 32 <= self.header_table.dynamic_table.get_max_table_size()
+    }
+
+    /// Encodes a header as a never indexed literal. Header name is taken
+    /// from the table if possible, but the table is never modified.
+    fn encode_never_indexed<W: EncodeBuf>(&mut self, header: (&[u8], &[u8]), buf: &mut W) {
+        match self.header_table.find_header(header) {
+            Some((index, _)) => {
+                encode_integer_into(index, 4, 0x10, buf);
+            }
+            None => {
+                buf.write_u8(0x10);
+                self.encode_string_literal(header.0, buf);
+            }
+        }
+        self.encode_string_literal(header.1, buf);
+    }
+
     /// Encodes a header as a literal (i.e. both the name and the value are
     /// encoded as a string literal) and places the result in the given buffer
     /// `buf`.
@@ -194,7 +321,7 @@ impl Encoder {
     ///
     /// - `header` - the header to be encoded
     /// - `should_index` - indicates whether the given header should be indexed, i.e.
-    ///                    inserted into the dynamic table
+    ///   inserted into the dynamic table
     /// - `buf` - The buffer into which the result is placed
     ///
     fn encode_literal<W: EncodeBuf>(
@@ -206,17 +333,26 @@ impl Encoder {
         let mask = if should_index { 0x40 } else { 0x0 };
 
         buf.write_u8(mask);
-        self.encode_string_literal(&header.0, buf);
-        self.encode_string_literal(&header.1, buf);
+        self.encode_string_literal(header.0, buf);
+        self.encode_string_literal(header.1, buf);
     }
 
     /// Encodes a string literal and places the result in the given buffer
     /// `buf`.
     ///
-    /// The function does not consider Huffman encoding for now, but always
-    /// produces a string literal representations, according to the HPACK spec
-    /// section 5.2.
+    /// In `HpackMode::Huffman` the string is Huffman-encoded if that is shorter,
+    /// according to the HPACK spec section 5.2.
     fn encode_string_literal<W: EncodeBuf>(&mut self, octet_str: &[u8], buf: &mut W) {
+        if self.mode == HpackMode::Huffman {
+            let encoded = huffman::encode(octet_str);
+            if encoded.len() < octet_str.len() {
+                buf.reserve(encoded.len() + 1);
+                encode_integer_into(encoded.len(), 7, 0x80, buf);
+                buf.write_all(&encoded);
+                return;
+            }
+        }
+
         buf.reserve(octet_str.len() + 1);
         encode_integer_into(octet_str.len(), 7, 0, buf);
         buf.write_all(octet_str);
@@ -234,7 +370,7 @@ impl Encoder {
 
         encode_integer_into(header.0, prefix, mask, buf);
         // So far, we rely on just one strategy for encoding string literals.
-        self.encode_string_literal(&header.1, buf);
+        self.encode_string_literal(header.1, buf);
     }
 
     /// Encodes an indexed header (a header that is fully in the header table)
@@ -251,9 +387,13 @@ impl Encoder {
 #[cfg(test)]
 mod tests {
     use bytes::Bytes;
+    use bytes::BytesMut;
+
+    use crate::solicit::header::Headers;
 
     use super::encode_integer;
     use super::Encoder;
+    use super::HpackMode;
 
     use super::super::Decoder;
 
@@ -277,9 +417,9 @@ mod tests {
     /// # Returns
     ///
     /// A `bool` indicating whether such a decoding can be performed.
-    fn is_decodable(buf: &Vec<u8>, headers: &[(Vec<u8>, Vec<u8>)]) -> bool {
+    fn is_decodable(buf: &[u8], headers: &[(Vec<u8>, Vec<u8>)]) -> bool {
         let mut decoder = Decoder::new();
-        match decoder.decode_for_test(&buf[..]).ok() {
+        match decoder.decode_for_test(buf).ok() {
             Some(h) => {
                 h == headers
                     .iter()
@@ -361,7 +501,7 @@ mod tests {
         {
             let mut encoder: Encoder = Encoder::new();
             // `:method` is in the static table, but only for GET and POST
-            let headers = vec![(b":method", b"PUT")];
+            let headers = [(b":method", b"PUT")];
 
             let result = encoder.encode_for_test(headers.iter().map(|h| (&h.0[..], &h.1[..])));
 
@@ -374,7 +514,7 @@ mod tests {
         {
             let mut encoder: Encoder = Encoder::new();
             // `:method` is in the static table, but only for GET and POST
-            let headers = vec![(b":authority".to_vec(), b"example.com".to_vec())];
+            let headers = [(b":authority".to_vec(), b"example.com".to_vec())];
 
             let result = encoder.encode_for_test(headers.iter().map(|h| (&h.0[..], &h.1[..])));
 
@@ -402,4 +542,134 @@ mod tests {
 
         assert!(is_decodable(&result, &headers));
     }
+
+    #[test]
+    fn test_sensitive_header_not_indexed() {
+        let mut encoder: Encoder = Encoder::new();
+        let headers: Vec<(&[u8], &[u8], bool)> = vec![
+            (b"authorization", b"secret", true),
+            (b"custom-key", b"custom-value", true),
+        ];
+
+        let mut result = Vec::new();
+        encoder.encode_into_with_sensitivity(headers.iter().cloned(), &mut result);
+
+        assert_eq!(0, encoder.header_table.dynamic_table.len());
+        assert_eq!(0x10, result[0] & 0xf0);
+
+        let mut decoder = Decoder::new();
+        let decoded = decoder
+            .decode_with_sensitivity(Bytes::from(result))
+            .unwrap();
+        assert_eq!(
+            vec![
+                (
+                    Bytes::from_static(b"authorization"),
+                    Bytes::from_static(b"secret"),
+                    true
+                ),
+                (
+                    Bytes::from_static(b"custom-key"),
+                    Bytes::from_static(b"custom-value"),
+                    true
+                ),
+            ],
+            decoded
+        );
+    }
+
+    #[test]
+    fn test_zero_table_size_disables_indexing() {
+        let mut encoder: Encoder = Encoder::new();
+        encoder.set_max_table_size(0);
+        let headers = [(b"custom-key".to_vec(), b"custom-value".to_vec())];
+
+        let first = encoder.encode_for_test(headers.iter().map(|h| (&h.0[..], &h.1[..])));
+        // Dynamic table size update to zero
+        assert_eq!(0x20, first[0]);
+        let second = encoder.encode_for_test(headers.iter().map(|h| (&h.0[..], &h.1[..])));
+        assert_eq!(first.len() - 1, second.len());
+        assert_eq!(0, encoder.header_table.dynamic_table.len());
+
+        let mut decoder = Decoder::new();
+        assert_eq!(
+            vec![(
+                Bytes::from_static(b"custom-key"),
+                Bytes::from_static(b"custom-value")
+            )],
+            decoder.decode_for_test(&first).unwrap()
+        );
+        decoder.decode_for_test(&second).unwrap();
+    }
+
+    #[test]
+    fn test_size_update_reduced_then_increased() {
+        let mut encoder: Encoder = Encoder::new();
+        encoder.set_max_table_size(0);
+        encoder.set_max_table_size(100);
+        let result = encoder.encode_for_test(vec![]);
+        assert_eq!(vec![0x20, 0x3f, 100 - 31], result);
+    }
+
+    #[test]
+    fn test_encode_headers_reuses_dynamic_table() {
+        let mut headers = Headers::new_status(200);
+        headers.add("content-type", "application/json");
+        headers.add("x-request-source", "backend-service");
+
+        let mut encoder: Encoder = Encoder::new();
+        let mut first = BytesMut::new();
+        encoder.encode_headers_into(&headers, &mut first);
+        let mut second = BytesMut::new();
+        encoder.encode_headers_into(&headers, &mut second);
+
+        // Each header is a single octet index in the second block
+        assert_eq!(3, second.len());
+        assert!(second.len() * 4 < first.len());
+
+        let mut decoder = Decoder::new();
+        decoder.decode(first.freeze()).unwrap();
+        assert_eq!(3, decoder.decode(second.freeze()).unwrap().len());
+    }
+
+    #[test]
+    fn test_no_indexing_mode_fixed_bytes() {
+        let headers = vec![
+            (&b":method"[..], &b"GET"[..]),
+            (&b":path"[..], &b"/x"[..]),
+            (&b"custom-key"[..], &b"custom-value"[..]),
+        ];
+        let mut expected = vec![0x82, 0x05, 2, b'/', b'x', 0x00, 10];
+        expected.extend_from_slice(b"custom-key");
+        expected.push(12);
+        expected.extend_from_slice(b"custom-value");
+
+        let mut encoder: Encoder = Encoder::new();
+        encoder.set_mode(HpackMode::NoIndexing);
+        // Nothing is added to the dynamic table, so output is the same each time
+        assert_eq!(expected, encoder.encode_for_test(headers.clone()));
+        assert_eq!(expected, encoder.encode_for_test(headers));
+        assert_eq!(0, encoder.table_size());
+    }
+
+    #[test]
+    fn test_huffman_mode() {
+        let headers = vec![(&b"custom-key"[..], &b"custom-value"[..])];
+
+        let mut encoder: Encoder = Encoder::new();
+        encoder.set_mode(HpackMode::Huffman);
+        let huffman = encoder.encode_for_test(headers.clone());
+        let mut encoder: Encoder = Encoder::new();
+        let plain = encoder.encode_for_test(headers.clone());
+        assert!(huffman.len() < plain.len());
+
+        let mut decoder = Decoder::new();
+        assert_eq!(
+            vec![(
+                Bytes::from_static(b"custom-key"),
+                Bytes::from_static(b"custom-value")
+            )],
+            decoder.decode_for_test(&huffman).unwrap()
+        );
+    }
 }
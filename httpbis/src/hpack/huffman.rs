@@ -4,6 +4,8 @@
 
 use std::collections::HashMap;
 
+use bytes::Bytes;
+
 /// Represents a symbol that can be inserted into a Huffman-encoded octet
 /// string.
 enum HuffmanCodeSymbol {
@@ -49,6 +51,12 @@ pub struct HuffmanDecoder {
     eos_codepoint: (u32, u8),
 }
 
+impl Default for HuffmanDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl HuffmanDecoder {
     /// Constructs a new `HuffmanDecoder` using the given table of
     /// (code point, code length) tuples to represent the Huffman code.
@@ -61,18 +69,13 @@ impl HuffmanDecoder {
         let mut eos_codepoint: Option<(u32, u8)> = None;
 
         for (symbol, &(code, code_len)) in table.iter().enumerate() {
-            if !decoder_table.contains_key(&code_len) {
-                decoder_table.insert(code_len, HashMap::new());
-            }
+            decoder_table.entry(code_len).or_default();
             let subtable = decoder_table.get_mut(&code_len).unwrap();
             let huff_symbol = HuffmanCodeSymbol::new(symbol);
-            match huff_symbol {
-                HuffmanCodeSymbol::EndOfString => {
-                    // We also remember the code point of the EOS for easier
-                    // reference later on.
-                    eos_codepoint = Some((code, code_len));
-                }
-                _ => {}
+            if let HuffmanCodeSymbol::EndOfString = huff_symbol {
+                // We also remember the code point of the EOS for easier
+                // reference later on.
+                eos_codepoint = Some((code, code_len));
             };
             subtable.insert(code, huff_symbol);
         }
@@ -109,9 +112,9 @@ impl HuffmanDecoder {
             if self.table.contains_key(&current_len) {
                 let length_table = self.table.get(&current_len).unwrap();
                 if length_table.contains_key(&current) {
-                    let decoded_symbol = match length_table.get(&current).unwrap() {
-                        &HuffmanCodeSymbol::Symbol(symbol) => symbol,
-                        &HuffmanCodeSymbol::EndOfString => {
+                    let decoded_symbol = match *length_table.get(&current).unwrap() {
+                        HuffmanCodeSymbol::Symbol(symbol) => symbol,
+                        HuffmanCodeSymbol::EndOfString => {
                             // If the EOS symbol is detected within the stream,
                             // we need to consider it an error.
                             return Err(HuffmanDecoderError::EOSInString);
@@ -163,6 +166,40 @@ impl HuffmanDecoder {
     }
 }
 
+/// Decode a Huffman-encoded octet string (RFC 7541 section 5.2).
+///
+/// Padding longer than 7 bits or padding which is not a prefix
+/// of the EOS code is rejected.
+pub fn decode(input: &[u8]) -> Result<Bytes, HuffmanDecoderError> {
+    HuffmanDecoder::new().decode(input).map(Bytes::from)
+}
+
+/// Huffman-encode an octet string, padding the last octet with
+/// the most significant bits of EOS.
+pub fn encode(input: &[u8]) -> Bytes {
+    let mut result = Vec::with_capacity(input.len());
+    // Pending bits are stored in the least significant bits
+    let mut bits: u64 = 0;
+    let mut bits_len: u32 = 0;
+
+    for &b in input {
+        let (code, code_len) = HUFFMAN_CODE_TABLE[b as usize];
+        bits = (bits << code_len) | code as u64;
+        bits_len += code_len as u32;
+        while bits_len >= 8 {
+            bits_len -= 8;
+            result.push((bits >> bits_len) as u8);
+        }
+    }
+
+    if bits_len > 0 {
+        let pad_len = 8 - bits_len;
+        result.push(((bits << pad_len) | ((1 << pad_len) - 1)) as u8);
+    }
+
+    Bytes::from(result)
+}
+
 /// A helper struct that represents an iterator over individual bits of all
 /// bytes found in a wrapped Iterator over bytes.
 /// Bits are represented as `bool`s, where `true` corresponds to a set bit and
@@ -203,9 +240,7 @@ where
         }
 
         // If we still have `None`, it means the buffer has been exhausted
-        if self.current_byte.is_none() {
-            return None;
-        }
+        self.current_byte?;
 
         let b = *self.current_byte.unwrap();
 
@@ -223,7 +258,7 @@ where
     }
 }
 
-static HUFFMAN_CODE_TABLE: &'static [(u32, u8)] = &[
+static HUFFMAN_CODE_TABLE: &[(u32, u8)] = &[
     (0x1ff8, 13),
     (0x7fffd8, 23),
     (0xfffffe2, 28),
@@ -500,7 +535,7 @@ mod tests {
         let expected_result = to_expected_bit_result(&[0, 0, 0, 0, 1, 0, 1, 0]);
 
         let mut res: Vec<bool> = Vec::new();
-        for b in BitIterator::new(vec![10u8].iter()) {
+        for b in BitIterator::new([10u8].iter()) {
             res.push(b);
         }
 
@@ -515,7 +550,7 @@ mod tests {
         ]);
 
         let mut res: Vec<bool> = Vec::new();
-        for b in BitIterator::new(vec![10u8, 255, 128, 1, 0, 170].iter()) {
+        for b in BitIterator::new([10u8, 255, 128, 1, 0, 170].iter()) {
             res.push(b);
         }
 
@@ -540,7 +575,7 @@ mod tests {
             assert_eq!(result, expected_result);
         }
         {
-            let hex_buffer = [0x0 + 7];
+            let hex_buffer = [7];
             let expected_result = vec![b'0'];
 
             let result = decoder.decode(&hex_buffer).ok().unwrap();
@@ -708,4 +743,70 @@ mod tests {
             );
         }
     }
+
+    /// Examples from RFC 7541 Appendix C.4 and C.6.
+    const RFC_EXAMPLES: &[(&[u8], &[u8])] = &[
+        (
+            b"www.example.com",
+            &[
+                0xf1, 0xe3, 0xc2, 0xe5, 0xf2, 0x3a, 0x6b, 0xa0, 0xab, 0x90, 0xf4, 0xff,
+            ],
+        ),
+        (b"no-cache", &[0xa8, 0xeb, 0x10, 0x64, 0x9c, 0xbf]),
+        (
+            b"custom-key",
+            &[0x25, 0xa8, 0x49, 0xe9, 0x5b, 0xa9, 0x7d, 0x7f],
+        ),
+        (
+            b"custom-value",
+            &[0x25, 0xa8, 0x49, 0xe9, 0x5b, 0xb8, 0xe8, 0xb4, 0xbf],
+        ),
+        (b"302", &[0x64, 0x02]),
+        (b"private", &[0xae, 0xc3, 0x77, 0x1a, 0x4b]),
+        (
+            b"https://www.example.com",
+            &[
+                0x9d, 0x29, 0xad, 0x17, 0x18, 0x63, 0xc7, 0x8f, 0x0b, 0x97, 0xc8, 0xe9, 0xae, 0x82,
+                0xae, 0x43, 0xd3,
+            ],
+        ),
+    ];
+
+    #[test]
+    fn test_encode_rfc_examples() {
+        for &(plain, encoded) in RFC_EXAMPLES {
+            assert_eq!(encoded, &super::encode(plain)[..]);
+        }
+    }
+
+    #[test]
+    fn test_decode_rfc_examples() {
+        for &(plain, encoded) in RFC_EXAMPLES {
+            assert_eq!(plain, &super::decode(encoded).unwrap()[..]);
+        }
+    }
+
+    #[test]
+    fn test_encode_decode_all_octets() {
+        let input: Vec<u8> = (0..=255).collect();
+        assert_eq!(
+            &input[..],
+            &super::decode(&super::encode(&input)).unwrap()[..]
+        );
+        assert_eq!(0, super::encode(b"").len());
+    }
+
+    #[test]
+    fn test_decode_bad_padding() {
+        // 'o' followed by 8 bits of padding
+        assert_eq!(
+            Err(HuffmanDecoderError::PaddingTooLarge),
+            super::decode(&[(0x7 << 3) + 7, 0xff])
+        );
+        // Padding is not all ones
+        assert_eq!(
+            Err(HuffmanDecoderError::InvalidPadding),
+            super::decode(&[(0x7 << 3) + 6])
+        );
+    }
 }
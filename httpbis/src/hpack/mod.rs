@@ -5,6 +5,7 @@
 // Re-export the main HPACK API entry points.
 pub use self::decoder::Decoder;
 pub use self::encoder::Encoder;
+pub use self::encoder::HpackMode;
 use crate::hpack::dynamic_table::DynamicTable;
 use crate::hpack::static_table::StaticTable;
 use bytes::Bytes;
@@ -13,7 +14,7 @@ pub mod decoder;
 mod dynamic_table;
 pub mod encoder;
 pub mod huffman;
-mod static_table;
+pub(crate) mod static_table;
 
 /// The struct represents the header table obtained by merging the static and
 /// dynamic tables into a single index address space, as described in section
@@ -34,7 +35,7 @@ impl HeaderTable {
     /// the given static table.
     pub fn with_static_table(static_table: StaticTable) -> HeaderTable {
         HeaderTable {
-            static_table: static_table,
+            static_table,
             dynamic_table: DynamicTable::new(),
         }
     }
@@ -47,6 +48,8 @@ impl HeaderTable {
     ///
     /// The type yielded by the iterator is `(&[u8], &[u8])`, where the tuple
     /// corresponds to the header name, value pairs in the described order.
+    // `map` shortens `'static` lifetime of static table items to the lifetime of `self`
+    #[allow(clippy::map_identity)]
     pub fn iter(&self) -> impl Iterator<Item = (&[u8], &[u8])> {
         self.static_table
             .iter()
@@ -87,10 +90,10 @@ impl HeaderTable {
 
         match self.static_table.get_by_index(real_index as u32) {
             Ok((k, v)) => Some((Bytes::from_static(k), Bytes::from_static(v))),
-            Err(dynamic_index) => match self.dynamic_table.get(dynamic_index as usize) {
-                Some(&(ref name, ref value)) => Some((name.clone(), value.clone())),
-                None => None,
-            },
+            Err(dynamic_index) => self
+                .dynamic_table
+                .get(dynamic_index as usize)
+                .map(|(name, value)| (name.clone(), value.clone())),
         }
     }
 
@@ -136,10 +139,7 @@ impl HeaderTable {
 
         // Finally, if there's no header with a matching name and value,
         // return one that matched only the name, if that *was* found.
-        match matching_name {
-            Some(i) => Some((i, HeaderValueFound::NameOnlyFound)),
-            None => None,
-        }
+        matching_name.map(|i| (i, HeaderValueFound::NameOnlyFound))
     }
 }
 
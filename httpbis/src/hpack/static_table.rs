@@ -7,7 +7,11 @@ impl StaticTable {
     }
 
     pub fn iter(&self) -> impl Iterator<Item = (&'static [u8], &'static [u8])> {
-        self.0.iter().map(|h| *h)
+        self.0.iter().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
     }
 
     pub fn get_by_index(&self, index: u32) -> Result<(&'static [u8], &'static [u8]), u32> {
@@ -19,9 +23,17 @@ impl StaticTable {
     }
 }
 
+/// 1-based HPACK index of the first static table entry with given name.
+pub(crate) fn static_name_index(name: &[u8]) -> Option<u8> {
+    STATIC_TABLE
+        .iter()
+        .position(|&(n, _)| n == name)
+        .map(|i| (i + 1) as u8)
+}
+
 /// The table represents the static header table defined by the HPACK spec.
 /// (HPACK, Appendix A)
-pub(crate) static STATIC_TABLE: &'static [(&'static [u8], &'static [u8])] = &[
+pub(crate) static STATIC_TABLE: &[(&[u8], &[u8])] = &[
     (b":authority", b""),
     (b":method", b"GET"),
     (b":method", b"POST"),
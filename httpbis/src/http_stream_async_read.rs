@@ -0,0 +1,157 @@
+//! `AsyncRead` adapters for stream body.
+
+use std::cmp;
+use std::io;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+
+use bytes::Buf;
+use bytes::Bytes;
+use futures::stream;
+use futures::stream::Stream;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncReadExt;
+
+use crate::data_or_trailers::DataOrTrailers;
+use crate::data_or_trailers::HttpStreamAfterHeaders;
+use crate::error;
+use crate::result;
+use crate::solicit::header::Headers;
+
+/// Size of buffer passed to reader, default max frame size.
+const READ_CHUNK_SIZE: usize = 16384;
+
+/// Stream of chunks read from `reader` until EOF or first error.
+pub(crate) fn async_read_bytes<R>(reader: R) -> impl Stream<Item = result::Result<Bytes>> + Send
+where
+    R: AsyncRead + Send + 'static,
+{
+    stream::unfold(Some(Box::pin(reader)), |reader| async move {
+        let mut reader = reader?;
+        let mut buf = vec![0; READ_CHUNK_SIZE];
+        match reader.read(&mut buf).await {
+            Ok(0) => None,
+            Ok(len) => {
+                buf.truncate(len);
+                Some((Ok(Bytes::from(buf)), Some(reader)))
+            }
+            Err(e) => Some((Err(error::Error::IoError(e)), None)),
+        }
+    })
+}
+
+/// Body of a stream as `AsyncRead`.
+///
+/// Flow control window is returned to the peer as frames are pulled from the stream,
+/// so at most one `DATA` frame is buffered here.
+pub struct HttpStreamAsyncRead {
+    stream: HttpStreamAfterHeaders,
+    /// Remaining bytes of the last `DATA` frame.
+    buf: Bytes,
+    trailers: Option<Headers>,
+    eof: bool,
+}
+
+impl HttpStreamAsyncRead {
+    pub(crate) fn new(stream: HttpStreamAfterHeaders) -> HttpStreamAsyncRead {
+        HttpStreamAsyncRead {
+            stream,
+            buf: Bytes::new(),
+            trailers: None,
+            eof: false,
+        }
+    }
+
+    /// Trailers, available after read returned EOF.
+    pub fn trailers(&self) -> Option<&Headers> {
+        self.trailers.as_ref()
+    }
+
+    /// Take trailers, available after read returned EOF.
+    pub fn into_trailers(self) -> Option<Headers> {
+        self.trailers
+    }
+}
+
+impl AsyncRead for HttpStreamAsyncRead {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let me = &mut *self;
+        loop {
+            if !me.buf.is_empty() || buf.is_empty() {
+                let len = cmp::min(buf.len(), me.buf.len());
+                buf[..len].copy_from_slice(&me.buf[..len]);
+                me.buf.advance(len);
+                return Poll::Ready(Ok(len));
+            }
+
+            if me.eof {
+                return Poll::Ready(Ok(0));
+            }
+
+            match Pin::new(&mut me.stream).poll_next(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Some(Ok(DataOrTrailers::Data(data, _)))) => me.buf = data,
+                Poll::Ready(Some(Ok(DataOrTrailers::Trailers(trailers)))) => {
+                    me.trailers = Some(trailers);
+                    me.eof = true;
+                }
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Err(io::Error::other(e))),
+                Poll::Ready(None) => me.eof = true,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use futures::executor;
+    use futures::stream;
+    use tokio::io::AsyncReadExt;
+
+    use crate::error;
+    use crate::solicit::end_stream::EndStream;
+
+    #[test]
+    fn read_data_then_trailers() {
+        let mut trailers = Headers::new();
+        trailers.add("grpc-status", "0");
+        let parts = vec![
+            Ok(DataOrTrailers::Data(
+                Bytes::from_static(b"abc"),
+                EndStream::No,
+            )),
+            Ok(DataOrTrailers::Data(
+                Bytes::from_static(b"defg"),
+                EndStream::No,
+            )),
+            Ok(DataOrTrailers::Trailers(trailers)),
+        ];
+        let mut read = HttpStreamAfterHeaders::new(stream::iter(parts)).into_async_read();
+
+        let mut buf = [0; 2];
+        assert_eq!(2, executor::block_on(read.read(&mut buf)).unwrap());
+        assert_eq!(b"ab", &buf);
+        assert_eq!(None, read.trailers());
+
+        let mut rem = Vec::new();
+        executor::block_on(read.read_to_end(&mut rem)).unwrap();
+        assert_eq!(b"cdefg", &rem[..]);
+        assert_eq!(Some("0"), read.trailers().unwrap().get_opt("grpc-status"));
+    }
+
+    #[test]
+    fn read_error() {
+        let parts = vec![Err(error::Error::RequestTimeout)];
+        let mut read = HttpStreamAfterHeaders::new(stream::iter(parts)).into_async_read();
+
+        let mut buf = Vec::new();
+        assert!(executor::block_on(read.read_to_end(&mut buf)).is_err());
+    }
+}
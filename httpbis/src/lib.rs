@@ -1,4 +1,8 @@
-#![deny(intra_doc_link_resolution_failure)]
+#![deny(rustdoc::broken_intra_doc_links)]
+// Fixing `result_unit_err` would change public API
+#![allow(clippy::result_unit_err)]
+#![allow(clippy::too_many_arguments)]
+#![allow(clippy::type_complexity)]
 // TODO: add docs
 //#![deny(missing_docs)]
 
@@ -8,6 +12,31 @@
 //!
 //! This crate is used to implement [`grpc` crate](https://github.com/stepancheg/grpc-rust),
 //! and probably not usable for anything else.
+//!
+//! # TLS
+//!
+//! TLS implementation is pluggable: [`ClientBuilder`] and [`ServerBuilder`]
+//! are parameterized with [`tls_api::TlsConnector`] and [`tls_api::TlsAcceptor`],
+//! so any implementation of these traits (`tls-api-openssl`, `tls-api-native-tls`,
+//! or a custom one) can be used. The crate itself depends only on the traits.
+//! Negotiated ALPN protocol is taken from the TLS stream returned by the implementation.
+//!
+//! Default implementation based on `rustls` is available with `rustls` cargo feature:
+//! `ClientBuilder::new_rustls` and `ServerBuilder::new_rustls` create builders using it,
+//! and `tls_api_rustls` crate is re-exported as `httpbis::tls_api_rustls`.
+//!
+//! # Logging
+//!
+//! Connection and stream events are logged with [`log`](https://docs.rs/log) crate
+//! at `debug` level: stream open (with method and path) and close (with outcome
+//! and byte counts), flow control stalls and settings changes.
+//! Log records of a connection are emitted within `log-ndc` context of that connection.
+//!
+//! With `tracing` cargo feature enabled, streams are also reported to
+//! [`tracing`](https://docs.rs/tracing): each stream gets a `http2_stream` span
+//! with `stream_id`, `method` and `path` fields, `outcome`, `sent_bytes` and
+//! `received_bytes` are recorded when stream is closed, flow control stalls
+//! are events within that span, and settings changes are connection-level events.
 
 #[macro_use]
 extern crate log;
@@ -29,6 +58,7 @@ mod common;
 mod data_or_headers;
 mod data_or_headers_with_flag;
 mod data_or_trailers;
+mod http_stream_async_read;
 mod message;
 
 mod futures_misc;
@@ -38,7 +68,7 @@ mod req_resp;
 
 mod assert_types;
 
-mod hpack;
+pub mod hpack;
 mod solicit_async;
 mod solicit_misc;
 
@@ -47,8 +77,6 @@ mod misc;
 
 mod resp;
 
-mod exec;
-
 mod log_ndc_future;
 
 pub(crate) mod net;
@@ -58,26 +86,88 @@ pub(crate) mod bytes_ext;
 pub use crate::net::addr::AnySocketAddr;
 
 pub use crate::solicit::error_code::ErrorCode;
+pub use crate::solicit::frame::FrameHeader;
+pub use crate::solicit::frame::GoawayFrame;
+pub use crate::solicit::header::builder::HeadersBuilder;
 pub use crate::solicit::header::name::HeaderName;
 pub use crate::solicit::header::name::PseudoHeaderName;
 pub use crate::solicit::header::value::HeaderValue;
+pub use crate::solicit::header::value::HeaderValueRef;
+pub use crate::solicit::header::value::HeaderValueValidation;
 pub use crate::solicit::header::Header;
 pub use crate::solicit::header::Headers;
 pub use crate::solicit::stream_id::StreamId;
 pub use crate::solicit::HttpScheme;
 
 pub use crate::client::conf::ClientConf;
+pub use crate::client::pool::ClientPool;
+pub use crate::client::pool::ClientPoolConf;
+pub use crate::client::push::ClientPushHandler;
 pub use crate::client::req::ClientRequest;
 pub use crate::client::tls::ClientTlsOption;
 pub use crate::client::Client;
 pub use crate::client::ClientBuilder;
 pub use crate::client::ClientInterface;
+pub use crate::codec::http_framed_read::DEFAULT_MAX_CONTINUATION_FRAMES;
+pub use crate::codec::http_framed_read::DEFAULT_MAX_HEADER_BLOCK_SIZE;
+pub use crate::common::conf::CommonConf;
+pub use crate::common::conf::FrameDirection;
+pub use crate::common::conf::FrameObserver;
+pub use crate::common::conf::StatsCallback;
+pub use crate::common::conf::UnknownFrameCallback;
+pub use crate::common::conf::DEFAULT_STATS_INTERVAL;
+pub use crate::common::conf::DEFAULT_WINDOW_UPDATE_FRACTION;
+pub use crate::common::conn_close::ConnectionClose;
+pub use crate::common::flush::FlushStrategy;
+pub use crate::common::scheduling::SchedulingPolicy;
 pub use crate::common::sender::SendError;
 pub use crate::common::sender::SenderState;
+pub use crate::common::stats::ConnectionStats;
+pub use crate::common::stats::FrameCounts;
+pub use crate::common::stats::ServerStats;
+pub use crate::common::stats::StreamStats;
 pub use crate::common::window_size::StreamDead;
 
+#[allow(deprecated)]
+pub use crate::solicit::frame::HttpSettings;
+pub use crate::solicit::frame::Settings;
+
+/// HTTP/2 frames, for parsing and serializing without a connection.
+pub mod frame {
+    pub use crate::solicit::frame::parse_frame;
+    pub use crate::solicit::frame::ContinuationFlag;
+    pub use crate::solicit::frame::ContinuationFrame;
+    pub use crate::solicit::frame::DataFlag;
+    pub use crate::solicit::frame::DataFrame;
+    pub use crate::solicit::frame::Flags;
+    pub use crate::solicit::frame::Frame;
+    pub use crate::solicit::frame::FrameHeader;
+    pub use crate::solicit::frame::FrameIR;
+    pub use crate::solicit::frame::GoawayFrame;
+    pub use crate::solicit::frame::HeadersFlag;
+    pub use crate::solicit::frame::HeadersFrame;
+    pub use crate::solicit::frame::HttpFrame;
+    pub use crate::solicit::frame::HttpSetting;
+    pub use crate::solicit::frame::OriginFrame;
+    pub use crate::solicit::frame::ParseFrameError;
+    pub use crate::solicit::frame::ParseFrameResult;
+    pub use crate::solicit::frame::PingFrame;
+    pub use crate::solicit::frame::PriorityFrame;
+    pub use crate::solicit::frame::PushPromiseFlag;
+    pub use crate::solicit::frame::PushPromiseFrame;
+    pub use crate::solicit::frame::RawFrame;
+    pub use crate::solicit::frame::RawHttpFrameType;
+    pub use crate::solicit::frame::RstStreamFrame;
+    pub use crate::solicit::frame::Settings;
+    pub use crate::solicit::frame::SettingsFlag;
+    pub use crate::solicit::frame::SettingsFrame;
+    pub use crate::solicit::frame::StreamDependency;
+    pub use crate::solicit::frame::WindowUpdateFrame;
+}
+
 pub use crate::server::conf::ServerAlpn;
 pub use crate::server::conf::ServerConf;
+pub use crate::server::conf::DEFAULT_ALPN_PROTOCOLS;
 pub use crate::server::handler::ServerHandler;
 pub use crate::server::handler::ServerHandlerContext;
 pub use crate::server::handler_paths::ServerHandlerPaths;
@@ -86,35 +176,53 @@ pub use crate::server::req::ServerRequest;
 pub use crate::server::resp::ServerResponse;
 pub use crate::server::stream_handler::ServerRequestStreamHandler;
 pub use crate::server::tls::ServerTlsOption;
+pub use crate::server::tls::TlsInfo;
 pub use crate::server::Server;
 pub use crate::server::ServerBuilder;
 
 pub use crate::data_or_trailers::DataOrTrailers;
 pub use crate::data_or_trailers::HttpStreamAfterHeaders;
+pub use crate::http_stream_async_read::HttpStreamAsyncRead;
 pub use crate::resp::Response;
 
 pub use crate::message::SimpleHttpMessage;
 
+pub use crate::error::ConnectionError;
 pub use crate::error::Error;
+pub use crate::error::PrefaceError;
+pub use crate::error::StreamError;
 pub use crate::result::Result;
 
 pub use bytes_ext::buf_get_bytes::BufGetBytes;
 pub use bytes_ext::bytes_deque::BytesDeque;
 
+/// Re-export of TLS traits, custom TLS implementations must use this version.
+pub use tls_api;
+
+/// Re-export of default TLS implementation.
+#[cfg(feature = "rustls")]
+pub use tls_api_rustls;
+
 /// Functions used in tests
 #[doc(hidden)]
 pub mod for_test {
+    pub use crate::codec::write_buffer::WriteBuffer;
     pub use crate::common::conn::ConnStateSnapshot;
     pub use crate::common::stream::HttpStreamStateSnapshot;
     pub use crate::server::conn::ServerConn;
+    pub use crate::server::conn::ServerConnFuture;
     pub use crate::solicit_async::recv_raw_frame_sync;
 
-    pub use crate::solicit::frame::HttpSettings;
+    pub use crate::solicit::frame::Settings;
+    pub use crate::solicit::window_size::AutoTuningWindow;
+    pub use crate::solicit::window_size::PendingWindowUpdate;
     pub use crate::solicit::window_size::WindowSize;
     pub use crate::solicit::DEFAULT_SETTINGS;
 
+    /// Frames are exported by public `frame` module.
     pub mod solicit {
-        pub use crate::solicit::*;
+        pub use crate::solicit::header;
+        pub use crate::solicit::DEFAULT_SETTINGS;
     }
     pub mod hpack {
         pub use crate::hpack::*;
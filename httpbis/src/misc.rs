@@ -6,7 +6,7 @@ pub struct BsDebug<'a>(pub &'a [u8]);
 
 fn fmt_b(b: u8, f: &mut fmt::Formatter) -> fmt::Result {
     // ASCII printable
-    if b >= 0x20 && b < 0x7f {
+    if (0x20..0x7f).contains(&b) {
         write!(f, "{}", b as char)
     } else {
         write!(f, "\\x{:02x}", b)
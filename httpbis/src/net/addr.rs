@@ -42,11 +42,10 @@ impl AnySocketAddr {
     /// Get port number of TCP socket, or error from Unix socket.
     pub fn port(&self) -> io::Result<u16> {
         match self {
-            &AnySocketAddr::Inet(ref inet_addr) => Ok(inet_addr.port()),
-            &AnySocketAddr::Unix(_) => Err(io::Error::new(
-                io::ErrorKind::Other,
-                "Cannot get port from unix domain socket",
-            )),
+            AnySocketAddr::Inet(inet_addr) => Ok(inet_addr.port()),
+            &AnySocketAddr::Unix(_) => {
+                Err(io::Error::other("Cannot get port from unix domain socket"))
+            }
         }
     }
 }
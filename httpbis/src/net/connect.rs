@@ -21,8 +21,8 @@ impl ToClientStream for AnySocketAddr {
         handle: &Handle,
     ) -> Pin<Box<dyn Future<Output = io::Result<Pin<Box<dyn SocketStream>>>> + Send>> {
         match self {
-            &AnySocketAddr::Inet(ref inet_addr) => inet_addr.connect(handle),
-            &AnySocketAddr::Unix(ref unix_addr) => unix_addr.connect(handle),
+            AnySocketAddr::Inet(inet_addr) => inet_addr.connect(handle),
+            AnySocketAddr::Unix(unix_addr) => unix_addr.connect(handle),
         }
     }
 
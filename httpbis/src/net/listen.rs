@@ -34,15 +34,15 @@ pub trait SocketListener: Send {
 impl ToSocketListener for AnySocketAddr {
     fn listen(&self, conf: &ServerConf) -> io::Result<Box<dyn ToTokioListener + Send>> {
         match self {
-            &AnySocketAddr::Inet(ref inet_addr) => inet_addr.listen(conf),
-            &AnySocketAddr::Unix(ref unix_addr) => unix_addr.listen(conf),
+            AnySocketAddr::Inet(inet_addr) => inet_addr.listen(conf),
+            AnySocketAddr::Unix(unix_addr) => unix_addr.listen(conf),
         }
     }
 
     fn cleanup(&self) {
         match self {
-            &AnySocketAddr::Inet(ref inet_addr) => inet_addr.cleanup(),
-            &AnySocketAddr::Unix(ref unix_addr) => unix_addr.cleanup(),
+            AnySocketAddr::Inet(inet_addr) => inet_addr.cleanup(),
+            AnySocketAddr::Unix(unix_addr) => unix_addr.cleanup(),
         }
     }
 }
@@ -1,7 +1,12 @@
 use std::fmt;
 use std::io;
+use std::mem::MaybeUninit;
+use std::task::Context;
+use std::task::Poll;
 
 use crate::AnySocketAddr;
+use bytes::Buf;
+use bytes::BufMut;
 use tokio::io::AsyncRead;
 use tokio::io::AsyncWrite;
 use tokio::macros::support::Pin;
@@ -17,6 +22,25 @@ pub trait SocketStream:
     fn set_tcp_nodelay(&self, no_delay: bool) -> io::Result<()>;
 
     fn peer_addr(&self) -> io::Result<AnySocketAddr>;
+
+    /// Write as much of `buf` as possible with a single vectored write.
+    ///
+    /// Default implementation writes only the first chunk of `buf`.
+    fn poll_write_buf_dyn(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut dyn Buf,
+    ) -> Poll<io::Result<usize>> {
+        if !buf.has_remaining() {
+            return Poll::Ready(Ok(0));
+        }
+        let n = match self.poll_write(cx, buf.bytes()) {
+            Poll::Ready(Ok(n)) => n,
+            r => return r,
+        };
+        buf.advance(n);
+        Poll::Ready(Ok(n))
+    }
 }
 
 impl<S: SocketStream + ?Sized> SocketStream for Pin<Box<S>> {
@@ -31,4 +55,66 @@ impl<S: SocketStream + ?Sized> SocketStream for Pin<Box<S>> {
     fn peer_addr(&self) -> io::Result<AnySocketAddr> {
         (**self).peer_addr()
     }
+
+    fn poll_write_buf_dyn(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut dyn Buf,
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().as_mut().poll_write_buf_dyn(cx, buf)
+    }
+}
+
+/// Socket wrapper which makes `poll_write_buf` use
+/// [`SocketStream::poll_write_buf_dyn`], so vectored writes
+/// are not lost behind `Pin<Box<dyn SocketStream>>`.
+#[derive(Debug)]
+pub(crate) struct VectoredWriteSocket<I: SocketStream>(pub I);
+
+impl<I: SocketStream> AsyncRead for VectoredWriteSocket<I> {
+    unsafe fn prepare_uninitialized_buffer(&self, buf: &mut [MaybeUninit<u8>]) -> bool {
+        self.0.prepare_uninitialized_buffer(buf)
+    }
+
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().0).poll_read(cx, buf)
+    }
+
+    fn poll_read_buf<B: BufMut>(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut B,
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().0).poll_read_buf(cx, buf)
+    }
+}
+
+impl<I: SocketStream> AsyncWrite for VectoredWriteSocket<I> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().0).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().0).poll_shutdown(cx)
+    }
+
+    fn poll_write_buf<B: Buf>(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut B,
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().0).poll_write_buf_dyn(cx, buf)
+    }
 }
@@ -7,8 +7,6 @@ use tokio::net::TcpStream;
 use futures::future::Future;
 use futures::future::TryFutureExt;
 
-use net2;
-
 use crate::net::addr::AnySocketAddr;
 use crate::net::connect::ToClientStream;
 use crate::net::listen::SocketListener;
@@ -16,7 +14,11 @@ use crate::net::listen::ToSocketListener;
 use crate::net::listen::ToTokioListener;
 use crate::net::socket::SocketStream;
 use crate::ServerConf;
+use bytes::Buf;
 use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+use tokio::io::AsyncWrite;
 use tokio::runtime::Handle;
 
 impl ToSocketListener for SocketAddr {
@@ -97,7 +99,7 @@ impl ToClientStream for SocketAddr {
         &self,
         _handle: &Handle,
     ) -> Pin<Box<dyn Future<Output = io::Result<Pin<Box<dyn SocketStream>>>> + Send>> {
-        let future = TcpStream::connect(self.clone())
+        let future = TcpStream::connect(*self)
             .map_ok(|stream| Box::pin(stream) as Pin<Box<dyn SocketStream>>);
         Box::pin(future)
     }
@@ -119,4 +121,13 @@ impl SocketStream for TcpStream {
     fn peer_addr(&self) -> io::Result<AnySocketAddr> {
         Ok(AnySocketAddr::Inet(TcpStream::peer_addr(self)?))
     }
+
+    fn poll_write_buf_dyn(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        mut buf: &mut dyn Buf,
+    ) -> Poll<io::Result<usize>> {
+        // `writev`
+        AsyncWrite::poll_write_buf(self, cx, &mut buf)
+    }
 }
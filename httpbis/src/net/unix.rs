@@ -16,11 +16,19 @@ use crate::net::listen::ToSocketListener;
 use crate::net::listen::ToTokioListener;
 use crate::net::socket::SocketStream;
 use crate::ServerConf;
+#[cfg(unix)]
+use bytes::Buf;
 use std::fmt;
 #[cfg(unix)]
 use std::os::unix::net::SocketAddr;
 use std::path::PathBuf;
 use std::pin::Pin;
+#[cfg(unix)]
+use std::task::Context;
+#[cfg(unix)]
+use std::task::Poll;
+#[cfg(unix)]
+use tokio::io::AsyncWrite;
 use tokio::runtime::Handle;
 
 /// Unix socket address, which is filesystem path.
@@ -143,7 +151,7 @@ impl ToClientStream for SocketAddrUnix {
         };
         match handle.enter(|| UnixStream::from_std(stream)) {
             Ok(stream) => Box::pin(async { Ok(Box::pin(stream) as Pin<Box<dyn SocketStream>>) }),
-            Err(e) => return Box::pin(async { Err(e) }),
+            Err(e) => Box::pin(async { Err(e) }),
         }
     }
 
@@ -171,15 +179,21 @@ impl SocketStream for UnixStream {
     }
 
     fn set_tcp_nodelay(&self, _no_delay: bool) -> io::Result<()> {
-        Err(io::Error::new(
-            io::ErrorKind::Other,
-            "Cannot set nodelay on unix domain socket",
-        ))
+        Err(io::Error::other("Cannot set nodelay on unix domain socket"))
     }
 
     fn peer_addr(&self) -> io::Result<AnySocketAddr> {
         Ok(AnySocketAddr::from(UnixStream::peer_addr(self)?))
     }
+
+    fn poll_write_buf_dyn(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        mut buf: &mut dyn Buf,
+    ) -> Poll<io::Result<usize>> {
+        // `writev`
+        AsyncWrite::poll_write_buf(self, cx, &mut buf)
+    }
 }
 
 #[cfg(test)]
@@ -16,6 +16,7 @@ use crate::data_or_headers_with_flag::DataOrHeadersWithFlag;
 use crate::data_or_headers_with_flag::DataOrHeadersWithFlagStream;
 use crate::data_or_trailers::*;
 use crate::error;
+use crate::error::StreamError;
 use crate::result;
 use futures::task::Context;
 use std::pin::Pin;
@@ -136,6 +137,20 @@ impl Response {
                 }),
         )
     }
+
+    /// Response headers and body stream, failing with error scoped to this request.
+    pub fn into_scoped(
+        self,
+    ) -> impl Future<Output = Result<(Headers, HttpStreamAfterHeaders), StreamError>> + Send {
+        self.0.map_err(StreamError::from)
+    }
+
+    /// Collect response, failing with error scoped to this request.
+    pub fn collect_scoped(
+        self,
+    ) -> impl Future<Output = Result<SimpleHttpMessage, StreamError>> + Send {
+        self.collect().map_err(StreamError::from)
+    }
 }
 
 impl Future for Response {
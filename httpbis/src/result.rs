@@ -1,5 +1,3 @@
-use std;
-
 use crate::error;
 
 /// A convenience `Result` type that has the `HttpError` type as the error
@@ -1,4 +1,5 @@
 use crate::common::conf::CommonConf;
+use std::time::Duration;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ServerAlpn {
@@ -8,6 +9,9 @@ pub enum ServerAlpn {
     Require,
 }
 
+/// ALPN protocols offered by server by default.
+pub const DEFAULT_ALPN_PROTOCOLS: &[&str] = &["h2"];
+
 #[derive(Default, Debug, Clone)]
 pub struct ServerConf {
     /// TCP_NODELAY
@@ -15,6 +19,12 @@ pub struct ServerConf {
     pub thread_name: Option<String>,
 
     pub alpn: Option<ServerAlpn>,
+    /// ALPN protocols in order of preference, set on acceptor by
+    /// `ServerBuilder::set_tls_acceptor_builder`. Connection is closed
+    /// if client negotiated protocol not in this list.
+    ///
+    /// Default is `DEFAULT_ALPN_PROTOCOLS`.
+    pub alpn_protocols: Option<Vec<String>>,
 
     // Bind on both IPv4 and IPv6 addresses when addr is IPv6
     pub only_v6: Option<bool>,
@@ -23,6 +33,62 @@ pub struct ServerConf {
     pub reuse_port: Option<bool>,
     pub backlog: Option<i32>,
 
+    /// Max number of streams client may reset within `rapid_reset_window`,
+    /// connection is closed with `ENHANCE_YOUR_CALM` when exceeded.
+    pub rapid_reset_threshold: Option<u32>,
+    /// Time window for `rapid_reset_threshold`.
+    pub rapid_reset_window: Option<Duration>,
+
+    /// Accept HTTP/1.1 requests with `Upgrade: h2c` (RFC 7540 section 3.2),
+    /// and serve the request as stream 1. Requests with body are not upgraded.
+    ///
+    /// Default is `false`.
+    pub allow_h2c_upgrade: Option<bool>,
+    /// Accept plain connections which start with HTTP/2 preface (RFC 7540 section 3.4).
+    /// When disabled, plain connections must start with h2c upgrade.
+    ///
+    /// Default is `true`.
+    pub allow_h2c_prior_knowledge: Option<bool>,
+
+    /// Join small chunks of response body into DATA frames of up to this size.
+    /// Body is held until this many bytes are queued, end of stream,
+    /// `ServerResponse::flush` or `data_coalesce_delay`.
+    ///
+    /// Default is no coalescing.
+    pub data_coalesce_max_bytes: Option<u32>,
+    /// Max time body is held by `data_coalesce_max_bytes`.
+    ///
+    /// Default is 1 millisecond.
+    pub data_coalesce_delay: Option<Duration>,
+
+    /// Max number of open connections. When reached, new plain connections
+    /// are closed with `GOAWAY(ENHANCE_YOUR_CALM)`, and TLS connections are closed
+    /// before handshake.
+    ///
+    /// Default is no limit.
+    pub max_connections: Option<usize>,
+
+    /// Stop sending `WINDOW_UPDATE` for a stream while more than this many bytes
+    /// of request body are received but not yet read by handler.
+    ///
+    /// Default is no limit, window is increased as handler reads.
+    pub stream_recv_buffer_limit: Option<usize>,
+
+    /// Reset stream with `RST_STREAM(CANCEL)` when deadline from
+    /// `grpc-timeout` request header passes.
+    ///
+    /// Default is `false`, deadline is only reported by `ServerRequest::deadline`.
+    pub grpc_timeout_cancel: Option<bool>,
+
+    /// Advertise `SETTINGS_ENABLE_CONNECT_PROTOCOL` and accept extended CONNECT
+    /// requests with `:protocol` pseudo-header (RFC 8441), e.g. WebSockets.
+    ///
+    /// Default is `false`. Plain CONNECT requests are always accepted.
+    ///
+    /// Not a field of `Settings`, so it is sent even if
+    /// `CommonConf::initial_settings` are set.
+    pub enable_connect_protocol: Option<bool>,
+
     pub common: CommonConf,
 }
 
@@ -30,4 +96,14 @@ impl ServerConf {
     pub fn new() -> ServerConf {
         Default::default()
     }
+
+    pub(crate) fn alpn_protocols(&self) -> Vec<String> {
+        match &self.alpn_protocols {
+            Some(protocols) => protocols.clone(),
+            None => DEFAULT_ALPN_PROTOCOLS
+                .iter()
+                .map(|p| p.to_string())
+                .collect(),
+        }
+    }
 }
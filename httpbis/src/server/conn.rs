@@ -1,18 +1,24 @@
 use std::panic;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
 use crate::error;
+use crate::error::ConnectionError;
 use crate::result;
 use crate::AnySocketAddr;
 
 use crate::solicit::end_stream::EndStream;
 use crate::solicit::frame::HttpSetting;
-use crate::solicit::frame::SettingsFrame;
+use crate::solicit::frame::OriginFrame;
+use crate::solicit::frame::PushPromiseDecodedFrame;
+use crate::solicit::frame::PushPromiseMultiFrame;
 use crate::solicit::header::*;
 use crate::solicit::DEFAULT_SETTINGS;
 
 use futures::channel::oneshot;
 use futures::future;
+use futures::future::AbortHandle;
+use futures::Future;
 use futures::FutureExt;
 use futures::TryFutureExt;
 
@@ -31,37 +37,71 @@ use crate::common::init_where::InitWhere;
 use crate::assert_types::assert_send_future;
 use crate::client_died_error_holder::SomethingDiedErrorHolder;
 use crate::common::conn::Conn;
-use crate::common::conn::SideSpecific;
 use crate::common::conn::ConnStateSnapshot;
+use crate::common::conn::SideSpecific;
+use crate::common::conn_close::ConnectionClose;
 use crate::common::conn_command_channel::conn_command_channel;
 use crate::common::conn_command_channel::ConnCommandSender;
 use crate::common::conn_read::ConnReadSideCustom;
 use crate::common::conn_write::CommonToWriteMessage;
 use crate::common::conn_write::ConnWriteSideCustom;
+use crate::common::data_coalesce::DataCoalesce;
+use crate::common::data_coalesce::DEFAULT_DATA_COALESCE_DELAY;
 use crate::common::sender::CommonSender;
+use crate::common::stats::ConnectionStats;
 use crate::common::stream::HttpStreamCommon;
 use crate::common::stream::HttpStreamData;
 use crate::common::stream::HttpStreamDataSpecific;
 use crate::common::stream::InMessageStage;
 use crate::common::stream_handler::StreamHandlerInternal;
 use crate::common::stream_map::HttpStreamRef;
+use crate::common::trace::StreamSpan;
 use crate::headers_place::HeadersPlace;
 use crate::misc::any_to_string;
 use crate::req_resp::RequestOrResponse;
+use crate::server::h2c::H2cUpgrade;
 use crate::server::handler::ServerHandler;
 use crate::server::handler::ServerHandlerContext;
+use crate::server::push::PushPromiseMessage;
+use crate::server::push::PushSender;
+use crate::server::push::PushShared;
+use crate::server::rapid_reset::RapidResetDetector;
+use crate::server::rapid_reset::DEFAULT_RAPID_RESET_THRESHOLD;
+use crate::server::rapid_reset::DEFAULT_RAPID_RESET_WINDOW;
 use crate::server::req::ServerRequest;
+use crate::server::tls::check_alpn_protocol;
+use crate::server::tls::TlsInfo;
+use crate::server::tls_handshake::HandshakeRecorder;
 use crate::server::types::ServerTypes;
 use crate::solicit::stream_id::StreamId;
 use crate::ErrorCode;
+use crate::ServerAlpn;
 use crate::ServerConf;
 use crate::ServerResponse;
 use crate::ServerTlsOption;
 use std::net::SocketAddr;
 use std::pin::Pin;
+use std::time::Duration;
+use std::time::Instant;
 use tokio::runtime::Handle;
+use tokio::time;
 
-pub struct ServerStreamData {}
+/// Longer `grpc-timeout` exceeds tokio timer limit and is not enforced.
+const MAX_DEADLINE_TIMER: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+#[derive(Default)]
+pub struct ServerStreamData {
+    /// `grpc-timeout` timer, aborted when stream is removed.
+    deadline_timer: Option<AbortHandle>,
+}
+
+impl Drop for ServerStreamData {
+    fn drop(&mut self) {
+        if let Some(deadline_timer) = self.deadline_timer.take() {
+            deadline_timer.abort();
+        }
+    }
+}
 
 impl HttpStreamDataSpecific for ServerStreamData {}
 
@@ -82,6 +122,16 @@ impl HttpStreamData for ServerStream {
 
 pub(crate) struct ServerConnData {
     factory: Arc<dyn ServerHandler>,
+    /// Dropped together with connection to notify graceful shutdown waiters.
+    shutdown_waiters: Vec<oneshot::Sender<()>>,
+    rapid_reset: RapidResetDetector,
+    push: Arc<PushShared>,
+    /// `None` for plain connections.
+    tls_info: Option<TlsInfo>,
+    stream_recv_buffer_limit: Option<usize>,
+    grpc_timeout_cancel: bool,
+    /// `SETTINGS_ENABLE_CONNECT_PROTOCOL` advertised in initial settings.
+    enable_connect_protocol: bool,
 }
 
 impl SideSpecific for ServerConnData {}
@@ -98,7 +148,7 @@ where
         stream_id: StreamId,
         headers: Headers,
         end_stream: EndStream,
-    ) -> result::Result<HttpStreamRef<ServerTypes>> {
+    ) -> result::Result<HttpStreamRef<'_, ServerTypes>> {
         if ServerTypes::init_where(stream_id) == InitWhere::Locally {
             return Err(error::Error::InitiatedStreamWithServerIdFromClient(
                 stream_id,
@@ -114,13 +164,23 @@ where
 
         self.last_peer_stream_id = stream_id;
 
-        debug!("new stream: {}", stream_id);
+        debug!(
+            "stream {} opened: {} {}",
+            stream_id,
+            headers.get_opt(":method").unwrap_or(""),
+            headers.get_opt(":path").unwrap_or("")
+        );
 
-        let (_, out_window) = self.new_stream_data(
+        let (mut stream, out_window) = self.new_stream_data(
             stream_id,
             headers.content_length(),
             InMessageStage::AfterInitialHeaders,
-            ServerStreamData {},
+            ServerStreamData::default(),
+        );
+        stream.stream().span = StreamSpan::opened(
+            stream_id,
+            headers.get_opt(":method").unwrap_or(""),
+            headers.get_opt(":path").unwrap_or(""),
         );
 
         let in_window_size = self
@@ -131,11 +191,27 @@ where
             .in_window_size
             .size() as u32;
 
+        let grpc_timeout = headers.grpc_timeout();
+        let deadline = grpc_timeout.map(|t| Instant::now() + t);
+
         let factory = self.specific.factory.clone();
 
         let sender = ServerResponse {
-            common: CommonSender::new(stream_id, self.to_write_tx.clone(), out_window, false),
+            common: CommonSender::new(
+                stream_id,
+                self.to_write_tx.clone(),
+                out_window,
+                false,
+                self.peer_max_header_list_size.clone(),
+            ),
             drop_callback: None,
+            push: Some(PushSender {
+                shared: self.specific.push.clone(),
+                out_window: self.pump_out_window_size.handle(),
+                write_tx: self.to_write_tx.clone(),
+                peer_max_header_list_size: self.peer_max_header_list_size.clone(),
+            }),
+            conn_tx: self.to_write_tx.clone(),
         };
 
         let context = ServerHandlerContext {
@@ -149,8 +225,12 @@ where
                 end_stream: end_stream == EndStream::Yes,
                 stream_id,
                 in_window_size,
+                window_update_threshold: self.conf.window_update_threshold(in_window_size),
+                recv_buffer_limit: self.specific.stream_recv_buffer_limit,
+                deadline,
                 stream_handler: &mut stream_handler,
                 to_write_tx: &self.to_write_tx,
+                tls_info: self.specific.tls_info.as_ref(),
             };
 
             panic::catch_unwind(panic::AssertUnwindSafe(|| {
@@ -175,12 +255,144 @@ where
 
         stream.stream().peer_tx = stream_handler;
 
+        if let (Some(timeout), true) = (grpc_timeout, self.specific.grpc_timeout_cancel) {
+            if timeout <= MAX_DEADLINE_TIMER {
+                let to_write_tx = self.to_write_tx.clone();
+                let (timer, deadline_timer) = future::abortable(async move {
+                    time::delay_for(timeout).await;
+                    // ignore error: connection may be already closed
+                    drop(
+                        to_write_tx
+                            .unbounded_send(ServerToWriteMessage::DeadlineExceeded(stream_id)),
+                    );
+                });
+                self.loop_handle.spawn(timer);
+                stream.stream().specific.deadline_timer = Some(deadline_timer);
+            } else {
+                debug!(
+                    "not enforcing grpc-timeout of stream {}: {:?}",
+                    stream_id, timeout
+                );
+            }
+        }
+
         Ok(stream)
     }
+
+    fn process_deadline_exceeded(&mut self, stream_id: StreamId) -> result::Result<()> {
+        let mut stream = match self.streams.get_mut(stream_id) {
+            Some(stream) => stream,
+            None => return Ok(()),
+        };
+
+        debug!("request deadline exceeded: {}", stream_id);
+
+        if let Some(handler) = stream.stream().peer_tx.take() {
+            // it is OK to ignore error: handler may be already dead
+            drop(handler.error(error::Error::RequestTimeout));
+        }
+
+        // Return unsent data to connection pump window
+        let dropped = stream.stream().outgoing.data_size();
+        self.pump_out_window_size.increase(dropped);
+
+        self.send_rst_stream(stream_id, ErrorCode::Cancel)
+    }
+
+    /// Apply settings from `HTTP2-Settings` and start stream 1 from upgraded request.
+    fn process_h2c_upgrade(&mut self, upgrade: H2cUpgrade) -> result::Result<()> {
+        let H2cUpgrade { headers, settings } = upgrade;
+
+        if settings.header_table_size != self.peer_settings.header_table_size {
+            self.set_encoder_max_table_size(settings.header_table_size);
+        }
+        // There are no streams yet, so initial window size can be replaced as is
+        self.peer_settings = settings;
+        self.peer_max_header_list_size
+            .store(settings.max_header_list_size, Ordering::SeqCst);
+        self.process_peer_settings_applied();
+
+        // 3.2: request is assigned stream 1, which is half-closed (remote)
+        let stream = self.new_stream_from_client(1, headers, EndStream::Yes)?;
+        stream.close_remote();
+        self.peer_closed_streams.add(1);
+        Ok(())
+    }
+
+    fn process_push_promise_message(&mut self, message: PushPromiseMessage) -> result::Result<()> {
+        let PushPromiseMessage {
+            associated_stream_id,
+            promised_stream_id,
+            headers,
+            out_window,
+        } = message;
+
+        self.last_local_stream_id = promised_stream_id;
+
+        // 8.2.1: PUSH_PROMISE frames MUST only be sent on a peer-initiated stream
+        // that is in either the "open" or "half-closed (remote)" state.
+        let associated_stream_open = match self.streams.get_stream_state(associated_stream_id) {
+            Some(state) => !state.is_closed_local(),
+            None => false,
+        };
+        // 5.1.2: pushed streams are counted against peer `SETTINGS_MAX_CONCURRENT_STREAMS`
+        let max_concurrent_streams_reached = self.streams.locally_initiated_len()
+            >= self.peer_settings.max_concurrent_streams as usize;
+        if !associated_stream_open
+            || !self.peer_settings.enable_push
+            || max_concurrent_streams_reached
+        {
+            debug!(
+                "not sending push promise {} on stream {}",
+                promised_stream_id, associated_stream_id
+            );
+            // Pushed response sender sees stream dead when `out_window` is dropped
+            return Ok(());
+        }
+
+        debug!(
+            "push promise {} on stream {}",
+            promised_stream_id, associated_stream_id
+        );
+
+        out_window.increase(self.peer_settings.initial_window_size as isize);
+
+        let mut stream = HttpStreamCommon::new(
+            self.our_settings_sent().initial_window_size,
+            self.peer_settings.initial_window_size,
+            out_window,
+            None,
+            InMessageStage::AfterTrailingHeaders,
+            ServerStreamData::default(),
+        );
+        stream.data_coalesce_max_bytes = self.data_coalesce.as_ref().map(|c| c.max_bytes);
+        // Client never sends on pushed stream
+        stream.close_remote();
+        self.streams_opened += 1;
+        self.streams.insert(promised_stream_id, stream);
+
+        self.queued_write.queue_not_goaway(PushPromiseMultiFrame {
+            stream_id: associated_stream_id,
+            promised_stream_id,
+            headers,
+            encoder: &mut self.encoder,
+            max_frame_size: self.peer_settings.max_frame_size,
+        });
+        Ok(())
+    }
 }
 
 pub enum ServerToWriteMessage {
     Common(CommonToWriteMessage),
+    /// Start graceful shutdown, sender is dropped when connection is closed.
+    GracefulShutdown(oneshot::Sender<()>),
+    /// Graceful shutdown timed out, send final GOAWAY now.
+    Shutdown,
+    PushPromise(PushPromiseMessage),
+    /// Send `ORIGIN` frame.
+    Origin(OriginFrame),
+    /// `grpc-timeout` of the stream passed.
+    DeadlineExceeded(StreamId),
 }
 
 impl From<CommonToWriteMessage> for ServerToWriteMessage {
@@ -198,6 +410,21 @@ where
     fn process_message(&mut self, message: ServerToWriteMessage) -> result::Result<()> {
         match message {
             ServerToWriteMessage::Common(common) => self.process_common_message(common),
+            ServerToWriteMessage::GracefulShutdown(waiter) => {
+                self.specific.shutdown_waiters.push(waiter);
+                self.start_graceful_shutdown()
+            }
+            ServerToWriteMessage::Shutdown => self.send_goaway(ErrorCode::NoError),
+            ServerToWriteMessage::PushPromise(message) => {
+                self.process_push_promise_message(message)
+            }
+            ServerToWriteMessage::Origin(frame) => {
+                self.queued_write.queue_not_goaway(frame);
+                Ok(())
+            }
+            ServerToWriteMessage::DeadlineExceeded(stream_id) => {
+                self.process_deadline_exceeded(stream_id)
+            }
         }
     }
 }
@@ -213,11 +440,37 @@ where
         stream_id: StreamId,
         end_stream: EndStream,
         headers: Headers,
-    ) -> result::Result<Option<HttpStreamRef<ServerTypes>>> {
+    ) -> result::Result<Option<HttpStreamRef<'_, ServerTypes>>> {
+        if self.streams.get_stream_state(stream_id).is_none() {
+            // 5.1.1: new stream id must be odd and greater than all streams opened by client
+            if ServerTypes::init_where(stream_id) == InitWhere::Locally {
+                warn!("client opened stream with even id {}", stream_id);
+                self.send_goaway(ErrorCode::ProtocolError)?;
+                return Ok(None);
+            }
+            // Streams closed by client or reset by us are handled below
+            if stream_id <= self.last_peer_stream_id
+                && !self.peer_closed_streams.contains(stream_id)
+                && !self.reset_streams.contains(stream_id)
+            {
+                warn!(
+                    "client opened stream {} after stream {}",
+                    stream_id, self.last_peer_stream_id
+                );
+                self.send_goaway(ErrorCode::ProtocolError)?;
+                return Ok(None);
+            }
+        }
+
         let existing_stream = self
             .get_stream_for_headers_maybe_send_error(stream_id)?
             .is_some();
 
+        if !existing_stream && stream_id <= self.last_peer_stream_id {
+            // Closed stream, error is already sent
+            return Ok(None);
+        }
+
         let headers_place = match existing_stream {
             true => HeadersPlace::Trailing,
             false => HeadersPlace::Initial,
@@ -229,7 +482,27 @@ where
             return Ok(None);
         }
 
+        // A server that did not advertise `SETTINGS_ENABLE_CONNECT_PROTOCOL`
+        // treats `:protocol` as malformed (RFC 8441 section 3).
+        if !self.specific.enable_connect_protocol && headers.get_opt(":protocol").is_some() {
+            warn!("extended CONNECT is not enabled: {:?}", headers);
+            self.send_rst_stream(stream_id, ErrorCode::ProtocolError)?;
+            return Ok(None);
+        }
+
         if !existing_stream {
+            if self.draining {
+                debug!("refusing stream {} during graceful shutdown", stream_id);
+                self.send_rst_stream(stream_id, ErrorCode::RefusedStream)?;
+                return Ok(None);
+            }
+
+            if end_stream == EndStream::Yes && headers.content_length().unwrap_or(0) != 0 {
+                warn!("stream {} ended without content-length data", stream_id);
+                self.send_rst_stream(stream_id, ErrorCode::ProtocolError)?;
+                return Ok(None);
+            }
+
             return self
                 .new_stream_from_client(stream_id, headers, end_stream)
                 .map(Some);
@@ -241,24 +514,68 @@ where
             return Ok(None);
         }
 
+        if self
+            .streams
+            .get_mut(stream_id)
+            .unwrap()
+            .stream()
+            .in_content_length_incomplete()
+        {
+            warn!("stream {} trailers before content-length data", stream_id);
+            self.send_rst_stream(stream_id, ErrorCode::ProtocolError)?;
+            return Ok(None);
+        }
+
         let mut stream = self.streams.get_mut(stream_id).unwrap();
         stream.stream().trailers_recvd(headers);
         Ok(Some(stream))
     }
+
+    fn process_push_promise(&mut self, frame: PushPromiseDecodedFrame) -> result::Result<()> {
+        // 8.2: A client cannot push.
+        warn!(
+            "PUSH_PROMISE received from client on stream {}",
+            frame.stream_id
+        );
+        self.send_goaway(ErrorCode::ProtocolError)
+    }
+
+    fn process_peer_settings_applied(&mut self) {
+        self.specific
+            .push
+            .enable_push
+            .store(self.peer_settings.enable_push, Ordering::SeqCst);
+    }
+
+    fn process_rst_stream_of_open_stream(&mut self, stream_id: StreamId) -> result::Result<()> {
+        if self.specific.rapid_reset.reset_received(Instant::now()) {
+            warn!(
+                "too many streams reset by client, last: {}, sending GOAWAY",
+                stream_id
+            );
+            self.send_goaway(ErrorCode::EnhanceYourCalm)?;
+        }
+        Ok(())
+    }
 }
 
 pub struct ServerConn {
     write_tx: ConnCommandSender<ServerTypes>,
 }
 
+/// Future of server connection, resolves when connection is closed.
+pub type ServerConnFuture =
+    Pin<Box<dyn Future<Output = Result<ConnectionClose, ConnectionError>> + Send>>;
+
 impl ServerConn {
     fn connected<F, I>(
         lh: &Handle,
-        socket: HttpFutureSend<I>,
+        socket: HttpFutureSend<(I, Option<TlsInfo>)>,
         peer_addr: AnySocketAddr,
+        plain: bool,
         conf: ServerConf,
         service: Arc<F>,
-    ) -> (ServerConn, HttpFutureSend<()>)
+    ) -> (ServerConn, ServerConnFuture)
     where
         F: ServerHandler,
         I: SocketStream,
@@ -269,18 +586,60 @@ impl ServerConn {
 
         let (write_tx, write_rx) = conn_command_channel(conn_died_error_holder.clone());
 
-        let settings_frame = SettingsFrame::from_settings(vec![HttpSetting::EnablePush(false)]);
+        let enable_connect_protocol = conf.enable_connect_protocol.unwrap_or(false);
+        let mut settings_frame = conf.common.initial_settings_frame(false);
+        if enable_connect_protocol {
+            settings_frame
+                .settings
+                .push(HttpSetting::EnableConnectProtocol(true));
+        }
         let mut settings = DEFAULT_SETTINGS;
         settings.apply_from_frame(&settings_frame);
 
         let write_tx_copy = write_tx.clone();
 
-        let run = socket.and_then(move |mut conn| async move {
-            server_handshake(&mut conn, settings_frame).await?;
+        let data_coalesce = conf.data_coalesce_max_bytes.map(|max_bytes| {
+            DataCoalesce::new(
+                max_bytes as usize,
+                conf.data_coalesce_delay
+                    .unwrap_or(DEFAULT_DATA_COALESCE_DELAY),
+            )
+        });
+
+        let rapid_reset = RapidResetDetector::new(
+            conf.rapid_reset_threshold
+                .unwrap_or(DEFAULT_RAPID_RESET_THRESHOLD),
+            conf.rapid_reset_window
+                .unwrap_or(DEFAULT_RAPID_RESET_WINDOW),
+        );
 
-            let conn_data = Conn::<ServerTypes, I>::new(
+        // h2c is HTTP/2 over cleartext, TLS connections always start with preface
+        let allow_prior_knowledge = !plain || conf.allow_h2c_prior_knowledge.unwrap_or(true);
+        let allow_h2c_upgrade = plain && conf.allow_h2c_upgrade.unwrap_or(false);
+        let stream_recv_buffer_limit = conf.stream_recv_buffer_limit;
+        let grpc_timeout_cancel = conf.grpc_timeout_cancel.unwrap_or(false);
+
+        let run = socket.and_then(move |(mut conn, tls_info)| async move {
+            let upgrade = server_handshake(
+                &mut conn,
+                settings_frame,
+                allow_prior_knowledge,
+                allow_h2c_upgrade,
+            )
+            .await?;
+
+            let mut conn_data = Conn::<ServerTypes, I>::new(
                 lh,
-                ServerConnData { factory: service },
+                ServerConnData {
+                    factory: service,
+                    shutdown_waiters: Vec::new(),
+                    rapid_reset,
+                    push: Arc::new(PushShared::new()),
+                    tls_info,
+                    stream_recv_buffer_limit,
+                    grpc_timeout_cancel,
+                    enable_connect_protocol,
+                },
                 conf.common,
                 settings,
                 write_tx_copy,
@@ -290,14 +649,20 @@ impl ServerConn {
                 conn_died_error_holder,
             );
 
-            conn_data.run().await
+            conn_data.data_coalesce = data_coalesce;
+
+            if let Some(upgrade) = upgrade {
+                conn_data.process_h2c_upgrade(upgrade)?;
+            }
+
+            Ok(conn_data.run().await)
         });
 
         let run = assert_send_future(run);
 
         let future = Box::pin(run.then(|x| {
             info!("connection end: {:?}", x);
-            future::ready(x)
+            future::ready(x.map_err(ConnectionError::from))
         }));
 
         (ServerConn { write_tx }, future)
@@ -310,19 +675,27 @@ impl ServerConn {
         tls: ServerTlsOption<A>,
         conf: ServerConf,
         service: Arc<S>,
-    ) -> (ServerConn, HttpFutureSend<()>)
+    ) -> (ServerConn, ServerConnFuture)
     where
         S: ServerHandler,
         A: TlsAcceptor,
     {
         match tls {
             ServerTlsOption::Plain => {
-                let socket = Box::pin(future::ok(socket));
-                ServerConn::connected(lh, socket, peer_addr, conf, service)
+                let socket = Box::pin(future::ok((socket, None)));
+                ServerConn::connected(lh, socket, peer_addr, true, conf, service)
             }
             ServerTlsOption::Tls(acceptor) => {
-                let socket = Box::pin(async move { Ok(acceptor.accept(socket).await?) });
-                ServerConn::connected(lh, socket, peer_addr, conf, service)
+                let alpn = conf.alpn.clone().unwrap_or(ServerAlpn::Ignore);
+                let alpn_protocols = conf.alpn_protocols();
+                let socket = Box::pin(async move {
+                    let mut socket = acceptor.accept(HandshakeRecorder::new(socket)).await?;
+                    let alpn_protocol = socket.get_alpn_protocol();
+                    check_alpn_protocol(&alpn, &alpn_protocols, alpn_protocol.clone())?;
+                    let tls_info = socket.get_mut().finish(alpn_protocol);
+                    Ok((socket, Some(tls_info)))
+                });
+                ServerConn::connected(lh, socket, peer_addr, false, conf, service)
             }
         }
     }
@@ -333,7 +706,7 @@ impl ServerConn {
         peer_addr: SocketAddr,
         conf: ServerConf,
         service: Arc<S>,
-    ) -> (ServerConn, HttpFutureSend<()>)
+    ) -> (ServerConn, ServerConnFuture)
     where
         S: ServerHandler,
     {
@@ -354,7 +727,7 @@ impl ServerConn {
         peer_addr: SocketAddr,
         conf: ServerConf,
         f: F,
-    ) -> (ServerConn, HttpFutureSend<()>)
+    ) -> (ServerConn, ServerConnFuture)
     where
         F: Fn(ServerHandlerContext, ServerRequest, ServerResponse) -> result::Result<()>
             + Send
@@ -383,13 +756,71 @@ impl ServerConn {
         ServerConn::new_plain_single_thread(lh, socket, peer_addr, conf, Arc::new(HttpServiceFn(f)))
     }
 
+    /// Start graceful shutdown of this connection.
+    ///
+    /// Returned future resolves when connection is closed.
+    pub(crate) fn shutdown_graceful(&self) -> impl Future<Output = ()> + Send {
+        let (tx, rx) = oneshot::channel();
+        // if connection is already dead, sender is dropped immediately
+        drop(
+            self.write_tx
+                .unbounded_send(ServerToWriteMessage::GracefulShutdown(tx)),
+        );
+        rx.map(|_| ())
+    }
+
+    /// Send `ORIGIN` frame.
+    pub(crate) fn send_origin(&self, frame: OriginFrame) {
+        // ignore error, sender is dropped if connection is dead
+        drop(
+            self.write_tx
+                .unbounded_send(ServerToWriteMessage::Origin(frame)),
+        );
+    }
+
+    /// Send final GOAWAY without waiting for streams to complete.
+    pub(crate) fn shutdown_now(&self) {
+        drop(self.write_tx.unbounded_send(ServerToWriteMessage::Shutdown));
+    }
+
+    /// Send `PING` and wait for `ACK`, return round-trip time.
+    pub fn ping(&self) -> HttpFutureSend<Duration> {
+        let (tx, rx) = oneshot::channel();
+
+        // ignore error, sender is dropped if connection is dead
+        drop(
+            self.write_tx
+                .unbounded_send(ServerToWriteMessage::Common(CommonToWriteMessage::Ping(tx))),
+        );
+
+        Box::pin(
+            rx.map_err(|_| error::Error::ConnDied)
+                .and_then(future::ready),
+        )
+    }
+
+    pub fn stats(&self) -> HttpFutureSend<ConnectionStats> {
+        let (tx, rx) = oneshot::channel();
+
+        // ignore error, sender is dropped if connection is dead
+        drop(self.write_tx.unbounded_send(ServerToWriteMessage::Common(
+            CommonToWriteMessage::Stats(tx),
+        )));
+
+        Box::pin(rx.map_err(|_| error::Error::ConnDied))
+    }
+
     /// For tests
     pub fn dump_state(&self) -> HttpFutureSend<ConnStateSnapshot> {
         let (tx, rx) = oneshot::channel();
 
-        if let Err(_) = self.write_tx.unbounded_send(ServerToWriteMessage::Common(
-            CommonToWriteMessage::DumpState(tx),
-        )) {
+        if self
+            .write_tx
+            .unbounded_send(ServerToWriteMessage::Common(
+                CommonToWriteMessage::DumpState(tx),
+            ))
+            .is_err()
+        {
             return Box::pin(future::err(error::Error::FailedToSendReqToDumpState));
         }
 
@@ -0,0 +1,224 @@
+//! HTTP/1.1 `Upgrade: h2c` (RFC 7540 section 3.2).
+
+use bytes::Bytes;
+
+use crate::headers_place::HeadersPlace;
+use crate::req_resp::RequestOrResponse;
+use crate::solicit::frame::Settings;
+use crate::solicit::header::name::HeaderName;
+use crate::solicit::header::Header;
+use crate::solicit::header::HeaderError;
+use crate::solicit::header::Headers;
+use crate::solicit::DEFAULT_SETTINGS;
+
+/// Response sent when server accepts the upgrade.
+pub(crate) const SWITCHING_PROTOCOLS_RESPONSE: &[u8] = b"\
+HTTP/1.1 101 Switching Protocols\r\n\
+Connection: Upgrade\r\n\
+Upgrade: h2c\r\n\
+\r\n\
+";
+
+/// Max size of HTTP/1.1 request head read before giving up.
+pub(crate) const MAX_REQUEST_HEAD_SIZE: usize = 16 * 1024;
+
+/// Upgrade request accepted by the server.
+pub(crate) struct H2cUpgrade {
+    /// Request converted to HTTP/2 headers, it becomes stream 1.
+    pub headers: Headers,
+    /// Client settings from `HTTP2-Settings` header.
+    pub settings: Settings,
+}
+
+fn has_token(value: &str, token: &str) -> bool {
+    value
+        .split(',')
+        .any(|t| t.trim().eq_ignore_ascii_case(token))
+}
+
+/// Values of all header fields with given lower case name.
+fn get_all<'a>(fields: &'a [(String, &'a str)], name: &'a str) -> impl Iterator<Item = &'a str> {
+    fields
+        .iter()
+        .filter(move |(n, _)| n == name)
+        .map(|(_, v)| *v)
+}
+
+/// Parse HTTP/1.1 request head (including final empty line).
+///
+/// Return `None` if request is not a valid h2c upgrade request.
+/// Requests with body are not upgraded.
+pub(crate) fn parse_upgrade_request(head: &[u8]) -> Option<H2cUpgrade> {
+    let head = std::str::from_utf8(head).ok()?;
+    let mut lines = head.split("\r\n");
+
+    let mut request_line = lines.next()?.split(' ');
+    let method = request_line.next()?;
+    let path = request_line.next()?;
+    if request_line.next()? != "HTTP/1.1" || request_line.next().is_some() {
+        return None;
+    }
+    if method.is_empty() || path.is_empty() {
+        return None;
+    }
+
+    let mut fields = Vec::new();
+    for line in lines.take_while(|l| !l.is_empty()) {
+        let colon = line.find(':')?;
+        let name = line[..colon].to_ascii_lowercase();
+        let value = line[colon + 1..].trim();
+        fields.push((name, value));
+    }
+
+    let connection: Vec<&str> = get_all(&fields, "connection").collect();
+    if !connection.iter().any(|v| has_token(v, "upgrade"))
+        || !connection.iter().any(|v| has_token(v, "http2-settings"))
+    {
+        return None;
+    }
+    if !get_all(&fields, "upgrade").any(|v| has_token(v, "h2c")) {
+        return None;
+    }
+    let mut http2_settings = get_all(&fields, "http2-settings");
+    let http2_settings = http2_settings.next()?;
+    if get_all(&fields, "http2-settings").count() != 1 {
+        return None;
+    }
+    if get_all(&fields, "transfer-encoding").next().is_some() {
+        return None;
+    }
+    if get_all(&fields, "content-length").any(|v| v != "0") {
+        return None;
+    }
+
+    let settings = decode_base64url(http2_settings)?;
+    let settings = DEFAULT_SETTINGS.parse_payload(&settings).ok()?;
+
+    let mut headers = vec![
+        Header::new(":method", method.to_owned()),
+        Header::new(":scheme", "http"),
+        Header::new(":path", path.to_owned()),
+    ];
+    if let Some(host) = get_all(&fields, "host").next() {
+        headers.push(Header::new(":authority", host.to_owned()));
+    }
+
+    for (name, value) in &fields {
+        if name == "host" || name == "http2-settings" || name == "te" {
+            continue;
+        }
+        // Headers listed in `Connection` are connection-specific
+        if connection.iter().any(|v| has_token(v, name)) {
+            continue;
+        }
+        let name = match HeaderName::new_validate(Bytes::copy_from_slice(name.as_bytes())) {
+            Ok(name) => name,
+            Err((HeaderError::ConnectionSpecificHeader(..), _)) => continue,
+            Err(_) => return None,
+        };
+        headers.push(Header::new(name, value.to_string()));
+    }
+
+    let headers = Headers::from_vec(headers);
+    headers
+        .validate(RequestOrResponse::Request, HeadersPlace::Initial)
+        .ok()?;
+
+    Some(H2cUpgrade { headers, settings })
+}
+
+/// Decode URL-safe base64 without padding (RFC 4648 section 5).
+fn decode_base64url(s: &str) -> Option<Vec<u8>> {
+    fn value(c: u8) -> Option<u32> {
+        Some(match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'-' => 62,
+            b'_' => 63,
+            _ => return None,
+        } as u32)
+    }
+
+    let s = s.trim_end_matches('=').as_bytes();
+    if s.len() % 4 == 1 {
+        return None;
+    }
+
+    let mut r = Vec::with_capacity(s.len() * 3 / 4);
+    for chunk in s.chunks(4) {
+        let mut acc = 0;
+        for &c in chunk {
+            acc = (acc << 6) | value(c)?;
+        }
+        acc <<= 6 * (4 - chunk.len()) as u32;
+        let bytes = [(acc >> 16) as u8, (acc >> 8) as u8, acc as u8];
+        r.extend_from_slice(&bytes[..chunk.len() - 1]);
+    }
+    Some(r)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn base64url() {
+        assert_eq!(Some(b"".to_vec()), decode_base64url(""));
+        assert_eq!(Some(b"f".to_vec()), decode_base64url("Zg"));
+        assert_eq!(Some(b"fo".to_vec()), decode_base64url("Zm8"));
+        assert_eq!(Some(b"foo".to_vec()), decode_base64url("Zm9v"));
+        assert_eq!(Some(b"foob".to_vec()), decode_base64url("Zm9vYg=="));
+        assert_eq!(Some(vec![0xfb, 0xff]), decode_base64url("-_8"));
+        assert_eq!(None, decode_base64url("Zm9vY"));
+        assert_eq!(None, decode_base64url("Zm+v"));
+    }
+
+    #[test]
+    fn upgrade_request() {
+        let upgrade = parse_upgrade_request(
+            b"GET /hello HTTP/1.1\r\n\
+            Host: localhost\r\n\
+            Connection: Upgrade, HTTP2-Settings\r\n\
+            Upgrade: h2c\r\n\
+            HTTP2-Settings: AAQAAAAD\r\n\
+            Accept: */*\r\n\
+            \r\n",
+        )
+        .expect("upgrade");
+        assert_eq!("GET", upgrade.headers.method());
+        assert_eq!("/hello", upgrade.headers.path());
+        assert_eq!(Some("localhost"), upgrade.headers.get_opt(":authority"));
+        assert_eq!(Some("*/*"), upgrade.headers.get_opt("accept"));
+        assert_eq!(None, upgrade.headers.get_opt("http2-settings"));
+        assert_eq!(3, upgrade.settings.initial_window_size);
+    }
+
+    #[test]
+    fn not_upgrade_request() {
+        // No `HTTP2-Settings` in `Connection`
+        assert!(parse_upgrade_request(
+            b"GET / HTTP/1.1\r\nConnection: Upgrade\r\nUpgrade: h2c\r\nHTTP2-Settings: \r\n\r\n"
+        )
+        .is_none());
+        // Request with body
+        assert!(parse_upgrade_request(
+            b"POST / HTTP/1.1\r\n\
+            Connection: Upgrade, HTTP2-Settings\r\n\
+            Upgrade: h2c\r\n\
+            HTTP2-Settings: \r\n\
+            Content-Length: 3\r\n\
+            \r\n"
+        )
+        .is_none());
+        // Upgrade to something else
+        assert!(parse_upgrade_request(
+            b"GET / HTTP/1.1\r\n\
+            Connection: Upgrade, HTTP2-Settings\r\n\
+            Upgrade: websocket\r\n\
+            HTTP2-Settings: \r\n\
+            \r\n"
+        )
+        .is_none());
+    }
+}
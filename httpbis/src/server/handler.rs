@@ -31,3 +31,20 @@ pub trait ServerHandler: Send + Sync + 'static {
         resp: ServerResponse,
     ) -> result::Result<()>;
 }
+
+impl<F> ServerHandler for F
+where
+    F: Fn(ServerHandlerContext, ServerRequest, ServerResponse) -> result::Result<()>
+        + Send
+        + Sync
+        + 'static,
+{
+    fn start_request(
+        &self,
+        context: ServerHandlerContext,
+        req: ServerRequest,
+        resp: ServerResponse,
+    ) -> result::Result<()> {
+        self(context, req, resp)
+    }
+}
@@ -1,5 +1,6 @@
 use std::collections::hash_map;
 use std::collections::HashMap;
+use std::mem;
 use std::sync::Arc;
 
 use crate::result;
@@ -7,6 +8,7 @@ use crate::server::handler::ServerHandler;
 use crate::server::handler::ServerHandlerContext;
 use crate::server::req::ServerRequest;
 use crate::solicit::header::Headers;
+use crate::HttpStreamAfterHeaders;
 use crate::ServerResponse;
 
 #[derive(Default)]
@@ -145,26 +147,33 @@ impl ServerHandlerPaths {
             + Sync
             + 'static,
     {
-        impl<
-                F: Fn(ServerHandlerContext, ServerRequest, ServerResponse) -> result::Result<()>
-                    + Send
-                    + Sync
-                    + 'static,
-            > ServerHandler for F
-        {
-            fn start_request(
-                &self,
-                context: ServerHandlerContext,
-                req: ServerRequest,
-                resp: ServerResponse,
-            ) -> result::Result<()> {
-                self(context, req, resp)
-            }
-        }
-
         self.set_service(path, Arc::new(service))
     }
 
+    /// Register handler of full-duplex streams, e. g. gRPC bidi streaming.
+    ///
+    /// `service` is given request headers, request body and response sender.
+    /// Body and sender are independent and can be used concurrently,
+    /// e. g. from a task spawned with `ServerHandlerContext::loop_remote`.
+    /// Each half is finished with its own `END_STREAM`.
+    pub fn set_service_bidi<F>(&mut self, path: &str, service: F)
+    where
+        F: Fn(
+                ServerHandlerContext,
+                Headers,
+                HttpStreamAfterHeaders,
+                ServerResponse,
+            ) -> result::Result<()>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.set_service_fn(path, move |context, mut req, resp| {
+            let headers = mem::take(&mut req.headers);
+            service(context, headers, req.make_stream(), resp)
+        })
+    }
+
     pub fn remove_service(&mut self, path: &str) -> Option<Arc<dyn ServerHandler>> {
         assert!(path.starts_with("/"));
         self.root.remove_service(path)
@@ -182,11 +191,13 @@ impl ServerHandler for ServerHandlerPaths {
         req: ServerRequest,
         mut resp: ServerResponse,
     ) -> result::Result<()> {
-        if let Some(service) = self.find_service(req.headers.path()) {
-            info!("invoking user callback for path {}", req.headers.path());
+        // CONNECT requests have no `:path` and are served by root service
+        let path = req.headers.get_opt(":path").unwrap_or("/");
+        if let Some(service) = self.find_service(path) {
+            info!("invoking user callback for path {}", path);
             service.start_request(context, req, resp)
         } else {
-            info!("serving 404 for path {}", req.headers.path());
+            info!("serving 404 for path {}", path);
             drop(resp.send_headers(Headers::not_found_404()));
             drop(resp.close());
             Ok(())
@@ -1,12 +1,16 @@
 pub mod conf;
 pub mod conn;
+pub(crate) mod h2c;
 pub mod handler;
 pub mod handler_paths;
 pub(crate) mod increase_in_window;
+pub(crate) mod push;
+pub(crate) mod rapid_reset;
 pub mod req;
 pub mod resp;
 pub(crate) mod stream_handler;
 pub mod tls;
+pub(crate) mod tls_handshake;
 pub(crate) mod types;
 
 use futures::future::try_join_all;
@@ -18,14 +22,15 @@ use std::sync::Arc;
 use std::sync::Mutex;
 use std::thread;
 
-use tls_api;
-
 use futures::channel::oneshot;
 use futures::future;
 use futures::future::try_join;
 use futures::future::FutureExt;
 use futures::future::TryFutureExt;
+use futures::Future;
 
+use crate::common::conn_close::ConnectionClose;
+use crate::error::ConnectionError;
 use crate::error::Error;
 use crate::result::Result;
 
@@ -34,7 +39,6 @@ use crate::solicit_async::*;
 use crate::futures_misc::*;
 
 use tls_api::TlsAcceptor;
-use tls_api_stub;
 
 use crate::net::addr::AnySocketAddr;
 use crate::net::listen::ToSocketListener;
@@ -43,16 +47,27 @@ use crate::net::listen::ToTokioListener;
 pub use self::tls::ServerTlsOption;
 use crate::assert_types::assert_send_future;
 use crate::common::conn::ConnStateSnapshot;
+use crate::common::stats::ServerStats;
+use crate::net::socket::SocketStream;
 use crate::net::unix::SocketAddrUnix;
 use crate::result;
 pub use crate::server::conf::ServerConf;
 pub use crate::server::conn::ServerConn;
 use crate::server::handler::ServerHandler;
 use crate::server::handler_paths::ServerHandlerPaths;
+use crate::solicit::frame::GoawayFrame;
+use crate::solicit::frame::OriginFrame;
+use crate::solicit::frame::SettingsFrame;
+use crate::solicit::DEFAULT_SETTINGS;
+use crate::ErrorCode;
 use rand::thread_rng;
 use rand::Rng;
 use std::fmt;
+use std::pin::Pin;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
 use tokio::runtime::{Handle, Runtime};
+use tokio::time;
 
 pub struct ServerBuilder<A: tls_api::TlsAcceptor = tls_api_stub::TlsAcceptor> {
     pub conf: ServerConf,
@@ -77,6 +92,15 @@ impl ServerBuilder<tls_api_stub::TlsAcceptor> {
     }
 }
 
+#[cfg(feature = "rustls")]
+impl ServerBuilder<tls_api_rustls::TlsAcceptor> {
+    /// New server builder with default rustls TLS implementation,
+    /// acceptor is specified with `set_tls` or `set_tls_acceptor_builder`.
+    pub fn new_rustls() -> ServerBuilder<tls_api_rustls::TlsAcceptor> {
+        ServerBuilder::new()
+    }
+}
+
 #[cfg(unix)]
 impl ServerBuilder<tls_api_stub::TlsAcceptor> {
     /// New unix domain socket server with defaults
@@ -116,6 +140,12 @@ impl<A: tls_api::TlsAcceptor> ServerBuilder<A> {
     }
 }
 
+impl<A: tls_api::TlsAcceptor> Default for ServerBuilder<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<A: tls_api::TlsAcceptor> ServerBuilder<A> {
     /// New server builder with defaults.
     ///
@@ -138,13 +168,27 @@ impl<A: tls_api::TlsAcceptor> ServerBuilder<A> {
         self.tls = ServerTlsOption::Tls(Arc::new(acceptor));
     }
 
+    /// Set ALPN protocols from `conf` on acceptor builder, build and set acceptor.
+    pub fn set_tls_acceptor_builder(&mut self, mut builder: A::Builder) -> Result<()> {
+        if <A::Builder as tls_api::TlsAcceptorBuilder>::supports_alpn() {
+            let protocols = self.conf.alpn_protocols();
+            let protocols: Vec<&[u8]> = protocols.iter().map(|p| p.as_bytes()).collect();
+            tls_api::TlsAcceptorBuilder::set_alpn_protocols(&mut builder, &protocols)?;
+        }
+        self.set_tls(tls_api::TlsAcceptorBuilder::build(builder)?);
+        Ok(())
+    }
+
     pub fn build(self) -> Result<Server> {
+        self.conf.common.validate()?;
+
         let (alive_tx, alive_rx) = mpsc::channel();
 
         let state: Arc<Mutex<ServerState>> = Default::default();
 
         let state_copy = state.clone();
 
+        let (stop_accept_signal, stop_accept_future) = shutdown_signal();
         let (shutdown_signal, shutdown_future) = shutdown_signal();
 
         // TODO: why done_tx is unused?
@@ -171,6 +215,7 @@ impl<A: tls_api::TlsAcceptor> ServerBuilder<A> {
                 tls,
                 listen,
                 shutdown_future,
+                stop_accept_future,
                 conf,
                 service,
                 alive_tx,
@@ -198,6 +243,7 @@ impl<A: tls_api::TlsAcceptor> ServerBuilder<A> {
                             tls,
                             listen,
                             shutdown_future,
+                            stop_accept_future,
                             conf,
                             service,
                             alive_tx,
@@ -209,11 +255,12 @@ impl<A: tls_api::TlsAcceptor> ServerBuilder<A> {
         };
 
         Ok(Server {
-            state: state,
+            state,
             shutdown: shutdown_signal,
-            local_addr: local_addr,
+            stop_accept: stop_accept_signal,
+            local_addr,
             join: Some(join),
-            alive_rx: alive_rx,
+            alive_rx,
         })
     }
 }
@@ -227,6 +274,8 @@ pub struct Server {
     state: Arc<Mutex<ServerState>>,
     local_addr: AnySocketAddr,
     shutdown: ShutdownSignal,
+    /// Stop accepting connections, used by graceful shutdown.
+    stop_accept: ShutdownSignal,
     alive_rx: mpsc::Receiver<()>,
     join: Option<Completion>,
 }
@@ -243,6 +292,10 @@ impl fmt::Debug for Server {
 struct ServerState {
     last_conn_id: u64,
     conns: HashMap<u64, ServerConn>,
+    /// Graceful shutdown started, new connections are closed.
+    shutting_down: bool,
+    /// Sent in `ORIGIN` frame to each new connection.
+    origin_set: Option<Vec<String>>,
 }
 
 impl ServerState {
@@ -264,6 +317,27 @@ impl ServerState {
             conns: states.into_iter().collect(),
         }))
     }
+
+    fn stats(&self) -> HttpFutureSend<ServerStats> {
+        let futures: Vec<_> = self
+            .conns
+            .iter()
+            .map(|(&id, conn)| {
+                assert_send_future::<result::Result<_>, _>(
+                    conn.stats().map_ok(move |stats| (id, stats)),
+                )
+            })
+            .collect();
+
+        let j = try_join_all(futures);
+        let j = assert_send_future::<result::Result<_>, _>(j);
+
+        let connection_count = self.conns.len();
+        Box::pin(j.map_ok(move |stats| ServerStats {
+            connection_count,
+            connections: stats.into_iter().collect(),
+        }))
+    }
 }
 
 #[derive(Debug)]
@@ -280,6 +354,20 @@ impl ServerStateSnapshot {
     }
 }
 
+/// Close plain connection over `max_connections` with `GOAWAY(ENHANCE_YOUR_CALM)`.
+async fn reject_connection(mut socket: Pin<Box<dyn SocketStream>>) {
+    // Server preface must precede `GOAWAY`
+    let r = async {
+        send_frame(&mut socket, SettingsFrame::new()).await?;
+        send_frame(&mut socket, GoawayFrame::new(0, ErrorCode::EnhanceYourCalm)).await?;
+        socket.shutdown().await?;
+        Ok::<_, Error>(())
+    };
+    if let Err(e) = r.await {
+        debug!("failed to reject connection: {}", e);
+    }
+}
+
 fn spawn_server_event_loop<S, A>(
     handle: Handle,
     mut conn_handles: Vec<Handle>,
@@ -287,6 +375,7 @@ fn spawn_server_event_loop<S, A>(
     tls: ServerTlsOption<A>,
     listen: Box<dyn ToTokioListener + Send>,
     shutdown_future: ShutdownFuture,
+    stop_accept_future: ShutdownFuture,
     conf: ServerConf,
     service: S,
     _alive_tx: mpsc::Sender<()>,
@@ -303,7 +392,7 @@ where
         conn_handles.push(handle.clone());
     }
 
-    let loop_run = async move {
+    let accept_loop = async move {
         if false {
             // type hint
             return Ok(());
@@ -323,40 +412,78 @@ where
 
             // TODO: implement smarter selection
             let handle = conn_handles[thread_rng().gen_range(0, conn_handles.len())].clone();
-            let handle_clone = handle.clone();
-            let state_clone = state.clone();
-            handle.spawn({
-                let (conn, future) = ServerConn::new(
-                    &handle_clone,
-                    socket,
-                    peer_addr,
-                    tls.clone(),
-                    conf.clone(),
-                    service.clone(),
-                );
-
-                let conn_id = {
-                    let mut g = state_clone.lock().expect("lock");
-                    g.last_conn_id += 1;
-                    let conn_id = g.last_conn_id;
-                    let prev = g.conns.insert(conn_id, conn);
-                    assert!(prev.is_none());
-                    conn_id
-                };
 
-                let future = assert_send_future::<result::Result<()>, _>(future);
+            if let Some(max_connections) = conf.max_connections {
+                if state.lock().expect("lock").conns.len() >= max_connections {
+                    warn!(
+                        "max_connections {} reached, rejecting connection from {}",
+                        max_connections, peer_addr
+                    );
+                    if let ServerTlsOption::Plain = tls {
+                        handle.spawn(reject_connection(socket));
+                    }
+                    continue;
+                }
+            }
+
+            let (conn, future) = ServerConn::new(
+                &handle,
+                socket,
+                peer_addr,
+                tls.clone(),
+                conf.clone(),
+                service.clone(),
+            );
+
+            let conn_id = {
+                let mut g = state.lock().expect("lock");
+                // Checked under the lock graceful shutdown takes connections with,
+                // so each connection is either drained or never started
+                if g.shutting_down {
+                    info!("shutting down, closing new connection");
+                    continue;
+                }
+                g.last_conn_id += 1;
+                let conn_id = g.last_conn_id;
+                if let Some(origin_set) = &g.origin_set {
+                    conn.send_origin(OriginFrame::new(origin_set.clone()));
+                }
+                let prev = g.conns.insert(conn_id, conn);
+                assert!(prev.is_none());
+                conn_id
+            };
+
+            let future = assert_send_future::<
+                std::result::Result<ConnectionClose, ConnectionError>,
+                _,
+            >(future);
 
+            let state_clone = state.clone();
+            handle.spawn(
                 FutureExt::then(future, move |r| {
                     let mut g = state_clone.lock().expect("lock");
                     let removed = g.conns.remove(&conn_id);
                     assert!(removed.is_some());
                     future::ready(r)
                 })
+                .map_ok(|_| ())
                 .map_err(|e| {
                     warn!("connection end: {:?}", e);
-                    ()
-                })
-            });
+                }),
+            );
+        }
+    };
+
+    // Listener is dropped when accept is stopped,
+    // connections are served until shutdown signal.
+    let loop_run = async move {
+        match future::select(Box::pin(accept_loop), stop_accept_future).await {
+            future::Either::Left((r, _)) => r,
+            future::Either::Right((_, accept_loop)) => {
+                drop(accept_loop);
+                info!("stopped accepting connections");
+                future::pending().await
+            }
         }
     };
 
@@ -375,7 +502,7 @@ where
     let done = assert_send_future::<result::Result<_>, _>(done);
 
     let done = done.then(|_| {
-        drop(done_tx.send(()));
+        let _ = done_tx.send(());
         future::ready(())
     });
 
@@ -393,11 +520,78 @@ impl Server {
         self.alive_rx.try_recv() != Err(mpsc::TryRecvError::Disconnected)
     }
 
+    /// Gracefully shut down the server.
+    ///
+    /// Server stops accepting new connections. Each open connection sends GOAWAY with maximum stream id, refuses new streams,
+    /// finishes streams in flight, and then sends final GOAWAY and closes.
+    /// Connections with streams still active after `timeout` are closed without waiting.
+    /// Server is stopped after all connections are closed.
+    pub fn shutdown_graceful(&self, timeout: Duration) -> impl Future<Output = ()> + '_ {
+        let conns: Vec<_> = {
+            let mut g = self.state.lock().expect("lock");
+            g.shutting_down = true;
+            self.stop_accept.shutdown();
+            g.conns.values().map(|c| c.shutdown_graceful()).collect()
+        };
+
+        async move {
+            let mut conns = future::join_all(conns);
+            if time::timeout(timeout, &mut conns).await.is_err() {
+                warn!("graceful shutdown timed out, closing connections");
+                {
+                    let g = self.state.lock().expect("lock");
+                    for conn in g.conns.values() {
+                        conn.shutdown_now();
+                    }
+                }
+                conns.await;
+            }
+
+            self.shutdown.shutdown();
+        }
+    }
+
     // for tests
     pub fn dump_state(&self) -> HttpFutureSend<ServerStateSnapshot> {
         let g = self.state.lock().expect("lock");
         g.snapshot()
     }
+
+    /// Statistics of the server and its open connections.
+    pub fn stats(&self) -> HttpFutureSend<ServerStats> {
+        let g = self.state.lock().expect("lock");
+        g.stats()
+    }
+
+    /// Send `ORIGIN` frame (RFC 8336) with given origins, e.g. `https://example.com`,
+    /// to open connections, and to each new connection after `SETTINGS`.
+    pub fn send_origin_set(&self, origins: &[&str]) -> Result<()> {
+        let frame = origin_frame(origins)?;
+
+        let mut g = self.state.lock().expect("lock");
+        for conn in g.conns.values() {
+            conn.send_origin(frame.clone());
+        }
+        g.origin_set = Some(frame.origins);
+        Ok(())
+    }
+}
+
+/// `ORIGIN` frame with given origins, rejecting non-ASCII origins
+/// and frames exceeding default max frame size.
+pub(crate) fn origin_frame(origins: &[&str]) -> Result<OriginFrame> {
+    let frame = OriginFrame::new(origins.iter().map(|&o| o.to_owned()).collect());
+    if let Some(o) = origins.iter().find(|o| !o.is_ascii()) {
+        return Err(Error::InvalidFrame(format!("origin is not ASCII: {:?}", o)));
+    }
+    // Any peer accepts frames of default max size
+    if frame.payload_len() > DEFAULT_SETTINGS.max_frame_size {
+        return Err(Error::PayloadTooLarge(
+            frame.payload_len(),
+            DEFAULT_SETTINGS.max_frame_size,
+        ));
+    }
+    Ok(frame)
 }
 
 // We shutdown the server in the destructor.
@@ -0,0 +1,112 @@
+//! Server push: streams promised with `PUSH_PROMISE`.
+
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use crate::common::client_or_server::ClientOrServer;
+use crate::common::conn_command_channel::ConnCommandSender;
+use crate::common::sender::CommonSender;
+use crate::common::window_size::ConnOutWindowHandle;
+use crate::common::window_size::StreamOutWindowSender;
+use crate::error;
+use crate::headers_place::HeadersPlace;
+use crate::req_resp::RequestOrResponse;
+use crate::result;
+use crate::server::conn::ServerToWriteMessage;
+use crate::server::types::ServerTypes;
+use crate::solicit::header::Headers;
+use crate::solicit::stream_id::StreamId;
+use crate::ServerResponse;
+
+pub(crate) struct PushPromiseMessage {
+    pub associated_stream_id: StreamId,
+    pub promised_stream_id: StreamId,
+    pub headers: Headers,
+    /// Created with zero window, connection adds peer initial window size.
+    pub out_window: StreamOutWindowSender,
+}
+
+/// Push state shared by connection and its responses.
+pub(crate) struct PushShared {
+    /// Peer `SETTINGS_ENABLE_PUSH`.
+    pub enable_push: AtomicBool,
+    /// Locked while `PUSH_PROMISE` is queued,
+    /// so connection receives promised stream ids in increasing order.
+    last_promised_stream_id: Mutex<StreamId>,
+}
+
+impl PushShared {
+    pub fn new() -> PushShared {
+        PushShared {
+            enable_push: AtomicBool::new(true),
+            last_promised_stream_id: Mutex::new(0),
+        }
+    }
+}
+
+/// Part of `ServerResponse` to initiate a push.
+pub(crate) struct PushSender {
+    pub shared: Arc<PushShared>,
+    pub out_window: ConnOutWindowHandle,
+    pub write_tx: ConnCommandSender<ServerTypes>,
+    pub peer_max_header_list_size: Arc<AtomicU32>,
+}
+
+impl PushSender {
+    pub fn push_promise(
+        &self,
+        associated_stream_id: StreamId,
+        headers: Headers,
+    ) -> result::Result<ServerResponse> {
+        if !self.shared.enable_push.load(Ordering::SeqCst) {
+            return Err(error::Error::PushDisabled);
+        }
+
+        if let Err(e) = headers.validate(RequestOrResponse::Request, HeadersPlace::Initial) {
+            warn!("invalid push request headers: {:?}: {:?}", e, headers);
+            return Err(error::Error::InvalidPushRequest);
+        }
+        // 8.2: Promised requests MUST be cacheable and MUST be safe
+        match headers.method() {
+            "GET" | "HEAD" => {}
+            method => {
+                warn!("cannot push request with method {}", method);
+                return Err(error::Error::InvalidPushRequest);
+            }
+        }
+
+        let mut last_promised_stream_id = self.shared.last_promised_stream_id.lock().unwrap();
+        let promised_stream_id = match *last_promised_stream_id {
+            0 => ClientOrServer::Server.first_stream_id(),
+            n => n + 2,
+        };
+
+        let (out_window_sender, out_window) = self.out_window.new_stream(0);
+
+        self.write_tx
+            .unbounded_send(ServerToWriteMessage::PushPromise(PushPromiseMessage {
+                associated_stream_id,
+                promised_stream_id,
+                headers,
+                out_window: out_window_sender,
+            }))?;
+
+        *last_promised_stream_id = promised_stream_id;
+
+        Ok(ServerResponse {
+            common: CommonSender::new(
+                promised_stream_id,
+                self.write_tx.clone(),
+                out_window,
+                false,
+                self.peer_max_header_list_size.clone(),
+            ),
+            drop_callback: None,
+            push: None,
+            conn_tx: self.write_tx.clone(),
+        })
+    }
+}
@@ -0,0 +1,65 @@
+//! Protection against rapid reset attack (CVE-2023-44487):
+//! client opens streams and immediately resets them.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Default max number of streams reset by client within the window.
+pub const DEFAULT_RAPID_RESET_THRESHOLD: u32 = 200;
+/// Default time window to count streams reset by client.
+pub const DEFAULT_RAPID_RESET_WINDOW: Duration = Duration::from_secs(1);
+
+/// Counts streams reset by client within sliding time window.
+pub(crate) struct RapidResetDetector {
+    threshold: u32,
+    window: Duration,
+    resets: VecDeque<Instant>,
+}
+
+impl RapidResetDetector {
+    pub fn new(threshold: u32, window: Duration) -> RapidResetDetector {
+        RapidResetDetector {
+            threshold,
+            window,
+            resets: VecDeque::new(),
+        }
+    }
+
+    /// Record stream reset by client, return `true` if threshold exceeded.
+    pub fn reset_received(&mut self, now: Instant) -> bool {
+        while let Some(&front) = self.resets.front() {
+            if now.duration_since(front) < self.window {
+                break;
+            }
+            self.resets.pop_front();
+        }
+
+        self.resets.push_back(now);
+        self.resets.len() > self.threshold as usize
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn exceeded() {
+        let mut d = RapidResetDetector::new(3, Duration::from_secs(1));
+        let now = Instant::now();
+        assert!(!d.reset_received(now));
+        assert!(!d.reset_received(now));
+        assert!(!d.reset_received(now));
+        assert!(d.reset_received(now));
+    }
+
+    #[test]
+    fn window_slides() {
+        let mut d = RapidResetDetector::new(2, Duration::from_secs(1));
+        let now = Instant::now();
+        for i in 0..10 {
+            assert!(!d.reset_received(now + Duration::from_millis(600) * i));
+        }
+    }
+}
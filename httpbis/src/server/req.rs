@@ -1,3 +1,5 @@
+use std::time::Instant;
+
 use crate::common::conn_command_channel::ConnCommandSender;
 use crate::common::increase_in_window::IncreaseInWindow;
 use crate::common::stream_from_network::StreamFromNetwork;
@@ -5,6 +7,7 @@ use crate::common::stream_queue_sync::stream_queue_sync;
 use crate::server::increase_in_window::ServerIncreaseInWindow;
 use crate::server::stream_handler::ServerRequestStreamHandler;
 use crate::server::stream_handler::ServerRequestStreamHandlerHolder;
+use crate::server::tls::TlsInfo;
 use crate::server::types::ServerTypes;
 use crate::Headers;
 use crate::HttpStreamAfterHeaders;
@@ -18,20 +21,51 @@ pub struct ServerRequest<'a> {
     pub(crate) stream_id: StreamId,
     /// Stream in window size at the moment of request start
     pub(crate) in_window_size: u32,
+    pub(crate) window_update_threshold: u32,
+    pub(crate) recv_buffer_limit: Option<usize>,
+    pub(crate) deadline: Option<Instant>,
     pub(crate) stream_handler: &'a mut Option<ServerRequestStreamHandlerHolder>,
     pub(crate) to_write_tx: &'a ConnCommandSender<ServerTypes>,
+    pub(crate) tls_info: Option<&'a TlsInfo>,
 }
 
 impl<'a> ServerRequest<'a> {
+    /// HTTP/2 stream id.
+    pub fn stream_id(&self) -> StreamId {
+        self.stream_id
+    }
+
+    /// Deadline computed from `grpc-timeout` header at the moment of request start.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// TLS connection info, `None` for plain connections.
+    pub fn peer_tls_info(&self) -> Option<&TlsInfo> {
+        self.tls_info
+    }
+
+    /// Protocol negotiated with ALPN, `None` for plain connections
+    /// or if client did not use ALPN.
+    pub fn alpn_protocol(&self) -> Option<&[u8]> {
+        self.tls_info.and_then(|t| t.alpn_protocol.as_deref())
+    }
+
     pub fn make_stream(self) -> HttpStreamAfterHeaders {
         if self.end_stream {
             HttpStreamAfterHeaders::empty()
         } else {
+            let recv_buffer_limit = self.recv_buffer_limit;
+            let initial_window_size = self.in_window_size;
+            let window_update_threshold = self.window_update_threshold;
             self.register_stream_handler(|increase_in_window| {
                 let (inc_tx, inc_rx) = stream_queue_sync();
                 let stream_from_network = StreamFromNetwork {
                     rx: inc_rx,
                     increase_in_window: increase_in_window.0,
+                    recv_buffer_limit,
+                    initial_window_size,
+                    window_update_threshold,
                 };
 
                 (
@@ -1,19 +1,28 @@
 use crate::assert_types::assert_send;
+use crate::common::conn_command_channel::ConnCommandSender;
+use crate::common::conn_write::extension_frame;
+use crate::common::conn_write::CommonToWriteMessage;
 use crate::common::sender::CommonSender;
 use crate::common::sender::SendError;
 
+use crate::error;
 use crate::result;
+use crate::server::conn::ServerToWriteMessage;
+use crate::server::origin_frame;
+use crate::server::push::PushSender;
 use crate::server::types::ServerTypes;
+use crate::solicit::DEFAULT_SETTINGS;
 use crate::ErrorCode;
 use crate::Headers;
 use crate::HttpStreamAfterHeaders;
 use crate::SenderState;
 use crate::SimpleHttpMessage;
 use crate::StreamDead;
+use crate::StreamId;
 use bytes::Bytes;
 use futures::stream::Stream;
 use futures::task::Context;
-use std::mem;
+use futures::Future;
 use std::task::Poll;
 
 // NOTE: Keep in sync with ClientRequest
@@ -22,6 +31,10 @@ pub struct ServerResponse {
     // need to replace with FnOnce when rust allows it
     pub(crate) drop_callback:
         Option<Box<dyn FnMut(&mut ServerResponse) -> result::Result<()> + Send>>,
+    /// `None` for pushed responses
+    pub(crate) push: Option<PushSender>,
+    /// Connection messages not bound to this stream, usable after response is done.
+    pub(crate) conn_tx: ConnCommandSender<ServerTypes>,
 }
 
 impl Drop for ServerResponse {
@@ -31,7 +44,7 @@ impl Drop for ServerResponse {
                 "sender was not properly finished, state: {:?}, invoking custom callback",
                 self.state()
             );
-            if let Some(mut drop_callback) = mem::replace(&mut self.drop_callback, None) {
+            if let Some(mut drop_callback) = self.drop_callback.take() {
                 if let Err(e) = drop_callback(self) {
                     warn!("custom callback resulted in error: {:?}", e);
                 }
@@ -45,6 +58,11 @@ fn _assert_types() {
 }
 
 impl ServerResponse {
+    /// HTTP/2 stream id.
+    pub fn stream_id(&self) -> StreamId {
+        self.common.stream_id()
+    }
+
     pub fn state(&self) -> SenderState {
         self.common.state()
     }
@@ -64,6 +82,12 @@ impl ServerResponse {
         self.common.poll(cx)
     }
 
+    /// Future which resolves when flow control windows allow sending data,
+    /// pending while peer keeps stream or connection window at zero.
+    pub fn window_available(&mut self) -> impl Future<Output = Result<(), StreamDead>> + '_ {
+        self.common.window_available()
+    }
+
     pub fn send_headers(&mut self, headers: Headers) -> Result<(), SendError> {
         self.common.send_headers(headers)
     }
@@ -117,6 +141,60 @@ impl ServerResponse {
         self.send_message(SimpleHttpMessage::internal_error_500(message))
     }
 
+    /// Send `PUSH_PROMISE` with given request headers on this stream,
+    /// and return the sender of pushed response.
+    ///
+    /// Fails if peer disabled push, or this response is already completed.
+    /// Promise is not sent and pushed response fails if peer
+    /// `SETTINGS_MAX_CONCURRENT_STREAMS` streams are already open.
+    pub fn push_promise(&mut self, request_headers: Headers) -> result::Result<ServerResponse> {
+        if self.state() == SenderState::Done {
+            return Err(SendError::IncorrectState(SenderState::Done).into());
+        }
+        match self.push {
+            Some(ref push) => push.push_promise(self.common.stream_id(), request_headers),
+            None => Err(error::Error::PushDisabled),
+        }
+    }
+
+    /// Send frame of extension type (RFC 7540 section 5.5) on this response's connection.
+    ///
+    /// Frames of types defined in RFC 7540 are rejected, because they would
+    /// break connection state.
+    pub fn send_raw_frame(
+        &self,
+        frame_type: u8,
+        flags: u8,
+        stream_id: StreamId,
+        payload: &[u8],
+    ) -> result::Result<()> {
+        // Any peer accepts frames of default max size
+        let frame = extension_frame(
+            frame_type,
+            flags,
+            stream_id,
+            payload,
+            DEFAULT_SETTINGS.max_frame_size,
+        )?;
+        self.conn_tx.unbounded_send(ServerToWriteMessage::Common(
+            CommonToWriteMessage::RawFrame(frame),
+        ))
+    }
+
+    /// Send `ORIGIN` frame (RFC 8336) with given origins on this response's connection.
+    ///
+    /// Unlike `Server::send_origin_set`, other connections are not affected.
+    pub fn send_origin_set(&self, origins: &[&str]) -> result::Result<()> {
+        let frame = origin_frame(origins)?;
+        self.conn_tx
+            .unbounded_send(ServerToWriteMessage::Origin(frame))
+    }
+
+    /// Send DATA held because of `ServerConf::data_coalesce_max_bytes` now.
+    pub fn flush(&mut self) -> Result<(), SendError> {
+        self.common.flush()
+    }
+
     pub fn reset(&mut self, error_code: ErrorCode) -> Result<(), SendError> {
         self.common.reset(error_code)
     }
@@ -2,6 +2,10 @@ use std::sync::Arc;
 
 use tls_api::TlsAcceptor;
 
+use crate::error;
+use crate::result;
+use crate::server::conf::ServerAlpn;
+
 pub enum ServerTlsOption<A: TlsAcceptor> {
     Plain,
     Tls(Arc<A>),
@@ -11,7 +15,57 @@ impl<A: TlsAcceptor> Clone for ServerTlsOption<A> {
     fn clone(&self) -> Self {
         match self {
             &ServerTlsOption::Plain => ServerTlsOption::Plain,
-            &ServerTlsOption::Tls(ref a) => ServerTlsOption::Tls(a.clone()),
+            ServerTlsOption::Tls(a) => ServerTlsOption::Tls(a.clone()),
+        }
+    }
+}
+
+/// Information about TLS connection available to request handler.
+///
+/// `tls-api` exposes only negotiated protocol, other fields are parsed
+/// from plaintext handshake records when connection is accepted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TlsInfo {
+    /// Protocol negotiated with ALPN.
+    pub alpn_protocol: Option<Vec<u8>>,
+    /// Certificate chain presented by client, DER encoded, leaf first.
+    ///
+    /// Empty for TLS 1.3, where client certificate is encrypted.
+    pub peer_certificates: Vec<Vec<u8>>,
+    /// Negotiated cipher suite, e.g. `TLS_AES_128_GCM_SHA256`.
+    pub cipher: Option<String>,
+    /// Negotiated protocol version, e.g. `TLSv1.3`.
+    pub protocol_version: Option<String>,
+    /// Server name requested by client with SNI.
+    pub server_name: Option<String>,
+}
+
+/// Check protocol negotiated by TLS handshake is allowed.
+pub(crate) fn check_alpn_protocol(
+    alpn: &ServerAlpn,
+    protocols: &[String],
+    negotiated: Option<Vec<u8>>,
+) -> result::Result<()> {
+    match negotiated {
+        Some(ref p) if protocols.iter().any(|a| a.as_bytes() == &p[..]) => Ok(()),
+        None if *alpn == ServerAlpn::Ignore => Ok(()),
+        negotiated => Err(error::Error::AlpnProtocolNotAllowed(negotiated)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn alpn_protocol() {
+        let protocols = vec!["h2".to_owned()];
+        for alpn in &[ServerAlpn::Ignore, ServerAlpn::Require] {
+            assert!(check_alpn_protocol(alpn, &protocols, Some(b"h2".to_vec())).is_ok());
+            assert!(check_alpn_protocol(alpn, &protocols, Some(b"http/1.1".to_vec())).is_err());
         }
+        assert!(check_alpn_protocol(&ServerAlpn::Ignore, &protocols, None).is_ok());
+        assert!(check_alpn_protocol(&ServerAlpn::Require, &protocols, None).is_err());
     }
 }
@@ -0,0 +1,437 @@
+//! TLS handshake observed on the wire, used to fill `TlsInfo`.
+//!
+//! `tls-api` exposes only the negotiated ALPN protocol, so SNI server name,
+//! cipher suite, protocol version and client certificates are parsed
+//! from plaintext handshake records passing through the socket during accept.
+
+use std::fmt;
+use std::io;
+use std::mem::MaybeUninit;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+
+use tokio::io::AsyncRead;
+use tokio::io::AsyncWrite;
+
+use crate::net::socket::SocketStream;
+use crate::server::tls::TlsInfo;
+use crate::AnySocketAddr;
+
+/// Handshake is a few kilobytes, bigger records are not parsed.
+const MAX_RECORDED: usize = 64 * 1024;
+
+const CONTENT_TYPE_HANDSHAKE: u8 = 22;
+
+const HANDSHAKE_CLIENT_HELLO: u8 = 1;
+const HANDSHAKE_SERVER_HELLO: u8 = 2;
+const HANDSHAKE_CERTIFICATE: u8 = 11;
+
+const EXTENSION_SERVER_NAME: u16 = 0;
+const EXTENSION_SUPPORTED_VERSIONS: u16 = 43;
+
+/// Socket which records bytes read and written until `finish` is called.
+pub(crate) struct HandshakeRecorder<S: SocketStream> {
+    socket: S,
+    /// `None` after handshake.
+    recorded: Option<Recorded>,
+}
+
+#[derive(Default)]
+struct Recorded {
+    /// Sent by client.
+    read: Vec<u8>,
+    /// Sent by server.
+    written: Vec<u8>,
+}
+
+fn record(buf: &mut Vec<u8>, data: &[u8]) {
+    let len = data.len().min(MAX_RECORDED - buf.len().min(MAX_RECORDED));
+    buf.extend_from_slice(&data[..len]);
+}
+
+impl<S: SocketStream> HandshakeRecorder<S> {
+    pub fn new(socket: S) -> HandshakeRecorder<S> {
+        HandshakeRecorder {
+            socket,
+            recorded: Some(Recorded::default()),
+        }
+    }
+
+    /// Stop recording and parse recorded handshake.
+    pub fn finish(&mut self, alpn_protocol: Option<Vec<u8>>) -> TlsInfo {
+        let mut tls_info = TlsInfo {
+            alpn_protocol,
+            ..TlsInfo::default()
+        };
+        if let Some(recorded) = self.recorded.take() {
+            parse_client_handshake(&recorded.read, &mut tls_info);
+            parse_server_handshake(&recorded.written, &mut tls_info);
+        }
+        tls_info
+    }
+}
+
+impl<S: SocketStream> fmt::Debug for HandshakeRecorder<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HandshakeRecorder")
+            .field("socket", &self.socket)
+            .field("recording", &self.recorded.is_some())
+            .finish()
+    }
+}
+
+impl<S: SocketStream> AsyncRead for HandshakeRecorder<S> {
+    unsafe fn prepare_uninitialized_buffer(&self, buf: &mut [MaybeUninit<u8>]) -> bool {
+        self.socket.prepare_uninitialized_buffer(buf)
+    }
+
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let me = self.get_mut();
+        let r = Pin::new(&mut me.socket).poll_read(cx, buf);
+        if let (Poll::Ready(Ok(n)), Some(recorded)) = (&r, &mut me.recorded) {
+            record(&mut recorded.read, &buf[..*n]);
+        }
+        r
+    }
+}
+
+impl<S: SocketStream> AsyncWrite for HandshakeRecorder<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let me = self.get_mut();
+        let r = Pin::new(&mut me.socket).poll_write(cx, buf);
+        if let (Poll::Ready(Ok(n)), Some(recorded)) = (&r, &mut me.recorded) {
+            record(&mut recorded.written, &buf[..*n]);
+        }
+        r
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().socket).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().socket).poll_shutdown(cx)
+    }
+}
+
+impl<S: SocketStream> SocketStream for HandshakeRecorder<S> {
+    fn is_tcp(&self) -> bool {
+        self.socket.is_tcp()
+    }
+
+    fn set_tcp_nodelay(&self, no_delay: bool) -> io::Result<()> {
+        self.socket.set_tcp_nodelay(no_delay)
+    }
+
+    fn peer_addr(&self) -> io::Result<AnySocketAddr> {
+        self.socket.peer_addr()
+    }
+}
+
+/// Cursor over big-endian TLS wire encoding.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.0.len() < len {
+            return None;
+        }
+        let (r, rem) = self.0.split_at(len);
+        self.0 = rem;
+        Some(r)
+    }
+
+    fn uint(&mut self, len: usize) -> Option<usize> {
+        Some(
+            self.bytes(len)?
+                .iter()
+                .fold(0, |acc, &b| (acc << 8) | b as usize),
+        )
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.uint(1).map(|v| v as u8)
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.uint(2).map(|v| v as u16)
+    }
+
+    /// Vector with length prefix of `len_len` bytes.
+    fn vec(&mut self, len_len: usize) -> Option<Reader<'a>> {
+        let len = self.uint(len_len)?;
+        self.bytes(len).map(Reader)
+    }
+}
+
+/// Handshake messages from plaintext handshake records at the start of the stream.
+///
+/// Records after the first non-handshake record (`ChangeCipherSpec`
+/// or encrypted data) are not parsed.
+fn handshake_messages(stream: &[u8]) -> Vec<(u8, Vec<u8>)> {
+    let mut handshake = Vec::new();
+    let mut records = Reader(stream);
+    while let (Some(content_type), Some(_version)) = (records.u8(), records.u16()) {
+        match records.vec(2) {
+            Some(fragment) if content_type == CONTENT_TYPE_HANDSHAKE => {
+                handshake.extend_from_slice(fragment.0)
+            }
+            _ => break,
+        }
+    }
+
+    let mut messages = Vec::new();
+    let mut r = Reader(&handshake);
+    while let (Some(msg_type), Some(body)) = (r.u8(), r.vec(3)) {
+        messages.push((msg_type, body.0.to_vec()));
+    }
+    messages
+}
+
+fn parse_client_handshake(stream: &[u8], tls_info: &mut TlsInfo) {
+    for (msg_type, body) in handshake_messages(stream) {
+        match msg_type {
+            HANDSHAKE_CLIENT_HELLO if tls_info.server_name.is_none() => {
+                tls_info.server_name = client_hello_server_name(&body);
+            }
+            // Client certificate is plaintext before TLS 1.3
+            HANDSHAKE_CERTIFICATE => {
+                tls_info.peer_certificates = certificates(&body).unwrap_or_default();
+            }
+            _ => {}
+        }
+    }
+}
+
+fn parse_server_handshake(stream: &[u8], tls_info: &mut TlsInfo) {
+    for (msg_type, body) in handshake_messages(stream) {
+        if msg_type == HANDSHAKE_SERVER_HELLO {
+            if let Some((version, cipher)) = server_hello(&body) {
+                tls_info.protocol_version = Some(protocol_version_name(version));
+                tls_info.cipher = Some(cipher_suite_name(cipher));
+            }
+            return;
+        }
+    }
+}
+
+/// Extensions of hello message after given fixed fields.
+fn extensions<'a>(r: &mut Reader<'a>) -> Vec<(u16, Reader<'a>)> {
+    let mut r = match r.vec(2) {
+        Some(r) => r,
+        None => return Vec::new(),
+    };
+    let mut extensions = Vec::new();
+    while let (Some(extension_type), Some(data)) = (r.u16(), r.vec(2)) {
+        extensions.push((extension_type, data));
+    }
+    extensions
+}
+
+fn client_hello_server_name(body: &[u8]) -> Option<String> {
+    let mut r = Reader(body);
+    r.bytes(2 + 32)?; // legacy_version, random
+    r.vec(1)?; // legacy_session_id
+    r.vec(2)?; // cipher_suites
+    r.vec(1)?; // legacy_compression_methods
+    for (extension_type, mut data) in extensions(&mut r) {
+        if extension_type == EXTENSION_SERVER_NAME {
+            let mut names = data.vec(2)?;
+            while let (Some(name_type), Some(name)) = (names.u8(), names.vec(2)) {
+                // host_name
+                if name_type == 0 {
+                    return String::from_utf8(name.0.to_vec()).ok();
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Selected protocol version and cipher suite.
+fn server_hello(body: &[u8]) -> Option<(u16, u16)> {
+    let mut r = Reader(body);
+    let mut version = r.u16()?;
+    r.bytes(32)?; // random
+    r.vec(1)?; // legacy_session_id_echo
+    let cipher = r.u16()?;
+    r.u8()?; // legacy_compression_method
+    for (extension_type, mut data) in extensions(&mut r) {
+        // TLS 1.3 keeps legacy_version at TLS 1.2
+        if extension_type == EXTENSION_SUPPORTED_VERSIONS {
+            version = data.u16()?;
+        }
+    }
+    Some((version, cipher))
+}
+
+/// DER encoded certificates of `Certificate` message before TLS 1.3.
+fn certificates(body: &[u8]) -> Option<Vec<Vec<u8>>> {
+    let mut list = Reader(body).vec(3)?;
+    let mut certificates = Vec::new();
+    while !list.is_empty() {
+        certificates.push(list.vec(3)?.0.to_vec());
+    }
+    Some(certificates)
+}
+
+fn protocol_version_name(version: u16) -> String {
+    match version {
+        0x0300 => "SSLv3".to_owned(),
+        0x0301 => "TLSv1".to_owned(),
+        0x0302 => "TLSv1.1".to_owned(),
+        0x0303 => "TLSv1.2".to_owned(),
+        0x0304 => "TLSv1.3".to_owned(),
+        v => format!("0x{:04X}", v),
+    }
+}
+
+/// IANA name of common cipher suites, hex code of others.
+fn cipher_suite_name(cipher: u16) -> String {
+    let name = match cipher {
+        0x1301 => "TLS_AES_128_GCM_SHA256",
+        0x1302 => "TLS_AES_256_GCM_SHA384",
+        0x1303 => "TLS_CHACHA20_POLY1305_SHA256",
+        0x009c => "TLS_RSA_WITH_AES_128_GCM_SHA256",
+        0x009d => "TLS_RSA_WITH_AES_256_GCM_SHA384",
+        0x009e => "TLS_DHE_RSA_WITH_AES_128_GCM_SHA256",
+        0x009f => "TLS_DHE_RSA_WITH_AES_256_GCM_SHA384",
+        0xc02b => "TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256",
+        0xc02c => "TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384",
+        0xc02f => "TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256",
+        0xc030 => "TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384",
+        0xcca8 => "TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256",
+        0xcca9 => "TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256",
+        c => return format!("0x{:04X}", c),
+    };
+    name.to_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn with_len(len_len: usize, data: &[u8]) -> Vec<u8> {
+        let mut r = data.len().to_be_bytes()[8 - len_len..].to_vec();
+        r.extend_from_slice(data);
+        r
+    }
+
+    fn record(content_type: u8, fragment: &[u8]) -> Vec<u8> {
+        let mut r = vec![content_type, 3, 3];
+        r.extend(with_len(2, fragment));
+        r
+    }
+
+    fn handshake(msg_type: u8, body: &[u8]) -> Vec<u8> {
+        let mut r = vec![msg_type];
+        r.extend(with_len(3, body));
+        r
+    }
+
+    fn extension(extension_type: u16, data: &[u8]) -> Vec<u8> {
+        let mut r = extension_type.to_be_bytes().to_vec();
+        r.extend(with_len(2, data));
+        r
+    }
+
+    fn client_hello(server_name: &str) -> Vec<u8> {
+        let mut body = vec![3, 3];
+        body.extend(&[0; 32]);
+        body.extend(with_len(1, &[]));
+        body.extend(with_len(2, &[0x13, 0x01]));
+        body.extend(with_len(1, &[0]));
+        let mut names = vec![0];
+        names.extend(with_len(2, server_name.as_bytes()));
+        body.extend(with_len(
+            2,
+            &extension(EXTENSION_SERVER_NAME, &with_len(2, &names)),
+        ));
+        handshake(HANDSHAKE_CLIENT_HELLO, &body)
+    }
+
+    fn server_hello(cipher: u16, supported_version: Option<u16>) -> Vec<u8> {
+        let mut body = vec![3, 3];
+        body.extend(&[0; 32]);
+        body.extend(with_len(1, &[1, 2]));
+        body.extend(&cipher.to_be_bytes());
+        body.push(0);
+        let extensions = match supported_version {
+            Some(v) => extension(EXTENSION_SUPPORTED_VERSIONS, &v.to_be_bytes()),
+            None => Vec::new(),
+        };
+        body.extend(with_len(2, &extensions));
+        handshake(HANDSHAKE_SERVER_HELLO, &body)
+    }
+
+    #[test]
+    fn tls13() {
+        let mut tls_info = TlsInfo::default();
+
+        // ClientHello split between two records, then ChangeCipherSpec and encrypted data
+        let client_hello = client_hello("example.com");
+        let mut client = record(22, &client_hello[..10]);
+        client.extend(record(22, &client_hello[10..]));
+        client.extend(record(20, &[1]));
+        client.extend(record(23, &handshake(HANDSHAKE_CERTIFICATE, &[0, 0, 0])));
+        parse_client_handshake(&client, &mut tls_info);
+
+        let server = record(22, &server_hello(0x1302, Some(0x0304)));
+        parse_server_handshake(&server, &mut tls_info);
+
+        assert_eq!(Some("example.com"), tls_info.server_name.as_deref());
+        assert_eq!(Some("TLSv1.3"), tls_info.protocol_version.as_deref());
+        assert_eq!(Some("TLS_AES_256_GCM_SHA384"), tls_info.cipher.as_deref());
+        assert!(tls_info.peer_certificates.is_empty());
+    }
+
+    #[test]
+    fn tls12_client_certificate() {
+        let mut tls_info = TlsInfo::default();
+
+        let mut certificates = with_len(3, b"leaf");
+        certificates.extend(with_len(3, b"ca"));
+        let mut client = record(22, &client_hello("localhost"));
+        client.extend(record(
+            22,
+            &handshake(HANDSHAKE_CERTIFICATE, &with_len(3, &certificates)),
+        ));
+        parse_client_handshake(&client, &mut tls_info);
+
+        let server = record(22, &server_hello(0xc02f, None));
+        parse_server_handshake(&server, &mut tls_info);
+
+        assert_eq!(Some("localhost"), tls_info.server_name.as_deref());
+        assert_eq!(Some("TLSv1.2"), tls_info.protocol_version.as_deref());
+        assert_eq!(
+            Some("TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256"),
+            tls_info.cipher.as_deref()
+        );
+        assert_eq!(
+            vec![b"leaf".to_vec(), b"ca".to_vec()],
+            tls_info.peer_certificates
+        );
+    }
+
+    #[test]
+    fn truncated() {
+        let mut tls_info = TlsInfo::default();
+        let client_hello = record(22, &client_hello("localhost"));
+        parse_client_handshake(&client_hello[..client_hello.len() - 1], &mut tls_info);
+        parse_server_handshake(&[22, 3, 3, 0], &mut tls_info);
+        assert_eq!(TlsInfo::default(), tls_info);
+    }
+}
@@ -1,6 +1,5 @@
 use crate::common::client_or_server::ClientOrServer;
 use crate::common::types::Types;
-use crate::req_resp::RequestOrResponse;
 use crate::server::conn::ServerConnData;
 use crate::server::conn::ServerStream;
 use crate::server::conn::ServerStreamData;
@@ -18,6 +17,5 @@ impl Types for ServerTypes {
     type ToWriteMessage = ServerToWriteMessage;
 
     const CLIENT_OR_SERVER: ClientOrServer = ClientOrServer::Server;
-    const OUT_REQUEST_OR_RESPONSE: RequestOrResponse = RequestOrResponse::Response;
     const CONN_NDC: &'static str = "server conn";
 }
@@ -64,6 +64,13 @@ impl ErrorCode {
             _ => return Err(error_code),
         })
     }
+
+    /// Request reset with this code was not processed by peer and can be safely retried.
+    ///
+    /// Only `REFUSED_STREAM` gives such guarantee (RFC 7540 section 8.1.4).
+    pub fn is_retryable(&self) -> bool {
+        *self == ErrorCode::RefusedStream
+    }
 }
 
 impl From<u32> for ErrorCode {
@@ -118,10 +125,10 @@ impl AsRef<str> for ErrorCode {
     }
 }
 
-impl Into<u32> for ErrorCode {
+impl From<ErrorCode> for u32 {
     #[inline]
-    fn into(self) -> u32 {
-        self as u32
+    fn from(code: ErrorCode) -> Self {
+        code as u32
     }
 }
 
@@ -50,6 +50,7 @@ impl FrameBuilder for WriteBuffer {
     }
 
     fn write_header(&mut self, header: FrameHeader) {
+        self.frame_written(&header);
         self.extend_frame_header_buffer(pack_header(&header));
     }
 
@@ -38,7 +38,7 @@ impl Flag for DataFlag {
     }
 
     fn flags() -> &'static [Self] {
-        static FLAGS: &'static [DataFlag] = &[DataFlag::EndStream, DataFlag::Padded];
+        static FLAGS: &[DataFlag] = &[DataFlag::EndStream, DataFlag::Padded];
         FLAGS
     }
 }
@@ -77,7 +77,7 @@ impl DataFrame {
     /// given ID.
     pub fn new(stream_id: StreamId) -> DataFrame {
         DataFrame {
-            stream_id: stream_id,
+            stream_id,
             // All flags unset by default
             flags: Flags::default(),
             // No data stored in the frame yet
@@ -93,9 +93,9 @@ impl DataFrame {
     /// can either pass ownership of the buffer to the DataFrame or provide a temporary borrow.
     pub fn with_data(stream_id: StreamId, data: Bytes) -> DataFrame {
         DataFrame {
-            stream_id: stream_id,
+            stream_id,
             flags: Flags::default(),
-            data: data,
+            data,
             padding_len: 0,
         }
     }
@@ -105,6 +105,15 @@ impl DataFrame {
         self.flags.is_set(DataFlag::Padded)
     }
 
+    /// Padding length if the frame is padded.
+    pub fn padding(&self) -> Option<u8> {
+        if self.is_padded() {
+            Some(self.padding_len)
+        } else {
+            None
+        }
+    }
+
     /// Returns whther this frame ends the stream it is associated with.
     pub fn is_end_of_stream(&self) -> bool {
         self.flags.is_set(DataFlag::EndStream)
@@ -215,15 +224,21 @@ impl Frame for DataFrame {
 }
 
 impl FrameIR for DataFrame {
+    fn serialize_into(self, b: &mut WriteBuffer) {
+        (&self).serialize_into(b)
+    }
+}
+
+impl FrameIR for &DataFrame {
     fn serialize_into(self, b: &mut WriteBuffer) {
         b.write_header(self.get_header());
         if self.is_padded() {
             let pad_len: u8 = self.padding_len;
             b.extend_from_slice(&[pad_len]);
-            b.extend_from_bytes(self.data);
+            b.extend_from_bytes(self.data.clone());
             b.write_padding(pad_len);
         } else {
-            b.extend_from_bytes(self.data);
+            b.extend_from_bytes(self.data.clone());
         }
     }
 }
@@ -237,6 +252,7 @@ mod tests {
     use crate::solicit::frame::Frame;
     use crate::solicit::frame::FrameHeader;
     use crate::solicit::frame::FrameIR;
+    use crate::solicit::frame::RawFrame;
     use crate::solicit::tests::common::raw_frame_from_parts;
     use bytes::Bytes;
 
@@ -249,7 +265,7 @@ mod tests {
         // A header with the flag indicating no padding
         let header = FrameHeader::new(payload.len() as u32, 0u8, 0u8, 1u32);
 
-        let raw = raw_frame_from_parts(header.clone(), payload.to_vec());
+        let raw = raw_frame_from_parts(header, payload.to_vec());
         let frame: DataFrame = Frame::from_raw(&raw).unwrap();
 
         // The frame correctly returns the data?
@@ -277,13 +293,34 @@ mod tests {
         // A header with the flag indicating padding
         let header = FrameHeader::new(payload.len() as u32, 0u8, 8u8, 1u32);
 
-        let raw = raw_frame_from_parts(header.clone(), payload.to_vec());
+        let raw = raw_frame_from_parts(header, payload.to_vec());
         let frame: DataFrame = Frame::from_raw(&raw).unwrap();
 
         // The frame correctly returns the data?
         assert_eq!(&frame.data[..], &data[..]);
         // ...and the headers?
         assert_eq!(frame.get_header(), header);
+        assert_eq!(Some(5), frame.padding());
+        // Padding counts toward flow control
+        assert_eq!(1 + 4 + 5, frame.payload_len());
+    }
+
+    /// Tests that a padded DATA frame survives serialization and parsing.
+    #[test]
+    fn test_data_frame_padded_round_trip() {
+        let mut frame = DataFrame::with_data(3, Bytes::from_static(b"hello"));
+        frame.set_padding(7);
+        frame.set_flag(DataFlag::EndStream);
+
+        let serialized = frame.clone().serialize_into_vec();
+        let raw = RawFrame::parse(serialized).unwrap();
+        let parsed: DataFrame = Frame::from_raw(&raw).unwrap();
+
+        assert_eq!(frame, parsed);
+        assert_eq!(&b"hello"[..], &parsed.data[..]);
+        assert_eq!(Some(7), parsed.padding());
+        assert!(parsed.is_end_of_stream());
+        assert_eq!(None, DataFrame::new(3).padding());
     }
 
     /// Tests that a DATA frame with a zero-length payload is still considered
@@ -301,7 +338,7 @@ mod tests {
         // A header with the flag indicating no padding
         let header = FrameHeader::new(payload.len() as u32, 0u8, 0u8, 1u32);
 
-        let raw = raw_frame_from_parts(header.clone(), payload.to_vec());
+        let raw = raw_frame_from_parts(header, payload.to_vec());
         let frame: DataFrame = Frame::from_raw(&raw).unwrap();
 
         // The frame correctly returns the data?
@@ -349,7 +386,7 @@ mod tests {
         let payload = [];
         let header = FrameHeader::new(payload.len() as u32, 0u8, 0u8, 1u32);
 
-        let raw = raw_frame_from_parts(header.clone(), payload.to_vec());
+        let raw = raw_frame_from_parts(header, payload.to_vec());
         let frame: DataFrame = Frame::from_raw(&raw).unwrap();
 
         // The frame correctly returns the data -- i.e. an empty array?
@@ -382,7 +419,7 @@ mod tests {
         // A header with the flag indicating padding
         let header = FrameHeader::new(payload.len() as u32, 0u8, 8u8, 1u32);
 
-        let raw = raw_frame_from_parts(header.clone(), payload.to_vec());
+        let raw = raw_frame_from_parts(header, payload.to_vec());
         let frame: DataFrame = Frame::from_raw(&raw).unwrap();
 
         // The frame correctly returns the data?
@@ -462,9 +499,7 @@ mod tests {
             // Data
             res.extend(data.clone());
             // Actual padding
-            for _ in 0..5 {
-                res.push(0);
-            }
+            res.extend(&[0; 5]);
 
             res
         };
@@ -23,7 +23,7 @@ impl Flag for NoFlag {
     }
 
     fn flags() -> &'static [Self] {
-        static FLAGS: &'static [NoFlag] = &[];
+        static FLAGS: &[NoFlag] = &[];
         FLAGS
     }
 }
@@ -60,14 +60,14 @@ impl<F: Flag> Flags<F> {
 
     /// Set flag.
     pub fn with(&self, flag: F) -> Flags<F> {
-        let mut flags = self.clone();
+        let mut flags = *self;
         flags.set(flag);
         flags
     }
 
     /// Unset flag.
     pub fn without(&self, flag: F) -> Flags<F> {
-        let mut flags = self.clone();
+        let mut flags = *self;
         flags.clear(flag);
         flags
     }
@@ -85,7 +85,7 @@ impl<F: Flag> fmt::Debug for Flags<F> {
             return write!(f, "0");
         }
 
-        let mut copy: Flags<F> = (*self).clone();
+        let mut copy: Flags<F> = *self;
 
         let mut first = true;
         for &flag in F::flags() {
@@ -129,7 +129,7 @@ mod test {
         }
 
         fn flags() -> &'static [Self] {
-            static FLAGS: &'static [FakeFlag] = &[FakeFlag::Foo, FakeFlag::Bar];
+            static FLAGS: &[FakeFlag] = &[FakeFlag::Foo, FakeFlag::Bar];
             FLAGS
         }
     }
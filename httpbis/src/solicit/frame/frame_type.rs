@@ -69,7 +69,7 @@ impl RawHttpFrameType {
     pub const WINDOW_UPDATE: RawHttpFrameType = RawHttpFrameType(WINDOW_UPDATE_FRAME_TYPE);
     pub const CONTINUATION: RawHttpFrameType = RawHttpFrameType(CONTINUATION_FRAME_TYPE);
 
-    fn known(&self) -> Result<HttpFrameType, u8> {
+    pub(crate) fn known(&self) -> Result<HttpFrameType, u8> {
         HttpFrameType::ALL
             .iter()
             .find(|t| t.frame_type() == self.0)
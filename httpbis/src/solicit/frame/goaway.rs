@@ -48,9 +48,9 @@ impl GoawayFrame {
         debug_data: Bytes,
     ) -> Self {
         GoawayFrame {
-            last_stream_id: last_stream_id,
+            last_stream_id,
             error_code: error_code.into(),
-            debug_data: debug_data,
+            debug_data,
             flags: Flags::default(),
         }
     }
@@ -134,11 +134,17 @@ impl Frame for GoawayFrame {
 }
 
 impl FrameIR for GoawayFrame {
+    fn serialize_into(self, builder: &mut WriteBuffer) {
+        (&self).serialize_into(builder)
+    }
+}
+
+impl FrameIR for &GoawayFrame {
     fn serialize_into(self, builder: &mut WriteBuffer) {
         builder.write_header(self.get_header());
         builder.write_u32(self.last_stream_id);
         builder.write_u32(self.error_code.0);
-        builder.extend_from_bytes(self.debug_data);
+        builder.extend_from_bytes(self.debug_data.clone());
     }
 }
 
@@ -238,11 +244,8 @@ mod tests {
 
     #[test]
     fn test_serialize_with_debug_data() {
-        let frame = GoawayFrame::with_debug_data(
-            0,
-            ErrorCode::ProtocolError.into(),
-            Bytes::from_static(b"Hi!"),
-        );
+        let frame =
+            GoawayFrame::with_debug_data(0, ErrorCode::ProtocolError, Bytes::from_static(b"Hi!"));
         let expected: Vec<u8> = raw_frame_from_parts(
             FrameHeader::new(11, 0x7, 0, 0),
             vec![0, 0, 0, 0, 0, 0, 0, 1, b'H', b'i', b'!'],
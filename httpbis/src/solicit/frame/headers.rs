@@ -8,6 +8,7 @@ use crate::hpack::encoder::EncodeBuf;
 use crate::solicit::frame::continuation::ContinuationFlag;
 use crate::solicit::frame::flags::*;
 use crate::solicit::frame::pack_header;
+use crate::solicit::frame::push_promise::PushPromiseFlag;
 use crate::solicit::frame::HttpFrameType;
 use crate::solicit::frame::ParseFrameError;
 use crate::solicit::frame::ParseFrameResult;
@@ -46,7 +47,7 @@ impl Flag for HeadersFlag {
     }
 
     fn flags() -> &'static [Self] {
-        static FLAGS: &'static [HeadersFlag] = &[
+        static FLAGS: &[HeadersFlag] = &[
             HeadersFlag::EndStream,
             HeadersFlag::EndHeaders,
             HeadersFlag::Padded,
@@ -75,9 +76,9 @@ impl StreamDependency {
     /// exclusivity.
     pub fn new(stream_id: StreamId, weight: u8, is_exclusive: bool) -> StreamDependency {
         StreamDependency {
-            stream_id: stream_id,
-            weight: weight,
-            is_exclusive: is_exclusive,
+            stream_id,
+            weight,
+            is_exclusive,
         }
     }
 
@@ -101,9 +102,9 @@ impl StreamDependency {
         };
 
         StreamDependency {
-            stream_id: stream_id,
+            stream_id,
             weight: buf[4],
-            is_exclusive: is_exclusive,
+            is_exclusive,
         }
     }
 
@@ -178,7 +179,7 @@ impl HeadersFrame {
     ) -> HeadersFrame {
         HeadersFrame {
             header_fragment: Bytes::from(fragment),
-            stream_id: stream_id,
+            stream_id,
             stream_dep: Some(stream_dep),
             padding_len: 0,
             flags: HeadersFlag::Priority.to_flags(),
@@ -281,6 +282,9 @@ impl Frame for HeadersFrame {
         // the appropriate flag is set.
         let priority = flags.is_set(HeadersFlag::Priority);
         let (data, stream_dep) = if priority {
+            if actual.len() < 5 {
+                return Err(ParseFrameError::IncorrectFrameLength(payload_len));
+            }
             let dep = StreamDependency::parse(&actual[..5]);
             if dep.stream_id == stream_id {
                 // 5.3.1
@@ -324,6 +328,12 @@ impl Frame for HeadersFrame {
 }
 
 impl FrameIR for HeadersFrame {
+    fn serialize_into(self, b: &mut WriteBuffer) {
+        (&self).serialize_into(b)
+    }
+}
+
+impl FrameIR for &HeadersFrame {
     fn serialize_into(self, b: &mut WriteBuffer) {
         b.write_header(self.get_header());
         let padded = self.flags.is_set(HeadersFlag::Padded);
@@ -339,7 +349,7 @@ impl FrameIR for HeadersFrame {
             b.extend_from_slice(&dep_buf);
         }
         // Now the actual headers fragment
-        b.extend_from_bytes(self.header_fragment);
+        b.extend_from_bytes(self.header_fragment.clone());
         // Finally, add the trailing padding, if required
         if padded {
             b.write_padding(self.padding_len);
@@ -358,8 +368,6 @@ pub struct HeadersDecodedFrame {
     pub headers: Headers,
     /// The stream dependency information, if any.
     pub stream_dep: Option<StreamDependency>,
-    /// The length of the padding, if any.
-    pub padding_len: u8,
 }
 
 impl HeadersDecodedFrame {
@@ -382,8 +390,6 @@ pub struct HeadersMultiFrame<'a> {
     pub stream_id: StreamId,
     /// The header fragment bytes stored within the frame.
     pub headers: Headers,
-    /// The stream dependency information, if any.
-    pub stream_dep: Option<StreamDependency>,
     /// The length of the padding, if any.
     pub padding_len: u8,
 
@@ -408,6 +414,7 @@ impl<'a> fmt::Debug for HeadersMultiFrame<'a> {
 
 enum HeadersFrameType {
     Headers,
+    PushPromise,
     Continuation,
 }
 
@@ -415,6 +422,7 @@ impl HeadersFrameType {
     fn frame_type(&self) -> HttpFrameType {
         match self {
             HeadersFrameType::Headers => HttpFrameType::Headers,
+            HeadersFrameType::PushPromise => HttpFrameType::PushPromise,
             HeadersFrameType::Continuation => HttpFrameType::Continuation,
         }
     }
@@ -430,6 +438,10 @@ impl HeadersFrameType {
                 }
                 .0
             }
+            HeadersFrameType::PushPromise => match last {
+                true => PushPromiseFlag::EndHeaders.bitmask(),
+                false => 0,
+            },
             HeadersFrameType::Continuation => match last {
                 true => ContinuationFlag::EndHeaders.bitmask(),
                 false => 0,
@@ -465,15 +477,15 @@ impl<'a> EncodeBufForHeadersMultiFrame<'a> {
         let frame_length = (self.builder.remaining() - self.current_frame_offset) as u32;
         debug_assert!(frame_length >= FRAME_HEADER_LEN as u32);
         let length = frame_length - FRAME_HEADER_LEN as u32;
-        self.builder.patch_buf(
-            self.current_frame_offset,
-            &pack_header(&FrameHeader {
-                payload_len: length,
-                frame_type: self.current_frame_type.frame_type().frame_type(),
-                flags: self.current_frame_type.make_flags(self.flags, last),
-                stream_id: self.stream_id,
-            }),
-        );
+        let header = FrameHeader {
+            payload_len: length,
+            frame_type: self.current_frame_type.frame_type().frame_type(),
+            flags: self.current_frame_type.make_flags(self.flags, last),
+            stream_id: self.stream_id,
+        };
+        self.builder.frame_written(&header);
+        self.builder
+            .patch_buf(self.current_frame_offset, &pack_header(&header));
     }
 
     /// How much payload can be written into the current frame.
@@ -526,12 +538,57 @@ impl<'a> FrameIR for HeadersMultiFrame<'a> {
 
         buf.open_frame();
 
-        let headers = self
-            .headers
-            .iter()
-            .map(|h| (h.name().as_bytes(), h.value()));
+        self.encoder.encode_headers_into(&self.headers, &mut buf);
+
+        buf.finish_frame(true);
+    }
+}
+
+/// Encoder of `PUSH_PROMISE` header block, split into `CONTINUATION` frames if needed.
+pub struct PushPromiseMultiFrame<'a> {
+    /// The ID of the stream with which this frame is associated
+    pub stream_id: StreamId,
+    /// Promised Stream ID
+    pub promised_stream_id: StreamId,
+    /// Headers of the promised request.
+    pub headers: Headers,
+
+    /// Header encoder state.
+    pub encoder: &'a mut hpack::Encoder,
+    /// Current max frame size for encoding.
+    pub max_frame_size: u32,
+}
+
+impl<'a> fmt::Debug for PushPromiseMultiFrame<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PushPromiseMultiFrame")
+            .field("stream_id", &self.stream_id)
+            .field("promised_stream_id", &self.promised_stream_id)
+            .field("headers", &self.headers)
+            .field("max_frame_size", &self.max_frame_size)
+            .finish()
+    }
+}
+
+impl<'a> FrameIR for PushPromiseMultiFrame<'a> {
+    fn serialize_into(self, builder: &mut WriteBuffer) {
+        let tail_vec = builder.tail_vec();
+
+        let mut buf = EncodeBufForHeadersMultiFrame {
+            flags: Flags::new(0),
+            stream_id: self.stream_id,
+            current_frame_type: HeadersFrameType::PushPromise,
+            current_frame_offset: tail_vec.remaining(),
+            builder: tail_vec,
+            max_frame_size: self.max_frame_size,
+        };
+
+        buf.open_frame();
+
+        buf.builder
+            .extend_from_slice(&self.promised_stream_id.to_be_bytes());
 
-        self.encoder.encode_into(headers, &mut buf);
+        self.encoder.encode_headers_into(&self.headers, &mut buf);
 
         buf.finish_frame(true);
     }
@@ -544,6 +601,8 @@ mod tests {
     use crate::solicit::frame::continuation::ContinuationFlag;
     use crate::solicit::frame::flags::Flags;
     use crate::solicit::frame::headers::HeadersMultiFrame;
+    use crate::solicit::frame::headers::PushPromiseMultiFrame;
+    use crate::solicit::frame::push_promise::PushPromiseFlag;
     use crate::solicit::frame::tests::build_padded_frame_payload;
     use crate::solicit::frame::unpack_frames_for_test;
     use crate::solicit::frame::FrameHeader;
@@ -641,7 +700,7 @@ mod tests {
         let payload = data.to_vec();
         let header = FrameHeader::new(payload.len() as u32, 0x1, 0, 1);
 
-        let raw = raw_frame_from_parts(header.clone(), payload.to_vec());
+        let raw = raw_frame_from_parts(header, payload.to_vec());
         let frame: HeadersFrame = Frame::from_raw(&raw).unwrap();
 
         assert_eq!(frame.header_fragment(), &data[..]);
@@ -658,7 +717,7 @@ mod tests {
         let payload = build_padded_frame_payload(data, 6);
         let header = FrameHeader::new(payload.len() as u32, 0x1, 0x08, 1);
 
-        let raw = raw_frame_from_parts(header.clone(), payload.to_vec());
+        let raw = raw_frame_from_parts(header, payload.to_vec());
         let frame: HeadersFrame = Frame::from_raw(&raw).unwrap();
 
         assert_eq!(frame.header_fragment(), &data[..]);
@@ -676,14 +735,14 @@ mod tests {
         let dep = StreamDependency::new(0, 5, true);
         let payload = {
             let mut buf: Vec<u8> = Vec::new();
-            buf.extend(dep.serialize().to_vec().into_iter());
-            buf.extend(data.to_vec().into_iter());
+            buf.extend(dep.serialize().to_vec());
+            buf.extend(data.to_vec());
 
             buf
         };
         let header = FrameHeader::new(payload.len() as u32, 0x1, 0x20, 1);
 
-        let raw = raw_frame_from_parts(header.clone(), payload.to_vec());
+        let raw = raw_frame_from_parts(header, payload.to_vec());
         let frame: HeadersFrame = Frame::from_raw(&raw).unwrap();
 
         assert_eq!(frame.header_fragment(), &data[..]);
@@ -701,15 +760,15 @@ mod tests {
         let dep = StreamDependency::new(0, 5, true);
         let full = {
             let mut buf: Vec<u8> = Vec::new();
-            buf.extend(dep.serialize().to_vec().into_iter());
-            buf.extend(data.to_vec().into_iter());
+            buf.extend(dep.serialize().to_vec());
+            buf.extend(data.to_vec());
 
             buf
         };
         let payload = build_padded_frame_payload(&full, 4);
         let header = FrameHeader::new(payload.len() as u32, 0x1, 0x20 | 0x8, 1);
 
-        let raw = raw_frame_from_parts(header.clone(), payload.to_vec());
+        let raw = raw_frame_from_parts(header, payload.to_vec());
         let frame: HeadersFrame = Frame::from_raw(&raw).unwrap();
 
         assert_eq!(frame.header_fragment(), &data[..]);
@@ -756,8 +815,8 @@ mod tests {
         let expected = {
             let headers = pack_header(&header);
             let mut res: Vec<u8> = Vec::new();
-            res.extend(headers.to_vec().into_iter());
-            res.extend(payload.into_iter());
+            res.extend(headers.to_vec());
+            res.extend(payload);
 
             res
         };
@@ -777,8 +836,8 @@ mod tests {
         let expected = {
             let headers = pack_header(&header);
             let mut res: Vec<u8> = Vec::new();
-            res.extend(headers.to_vec().into_iter());
-            res.extend(payload.into_iter());
+            res.extend(headers.to_vec());
+            res.extend(payload);
 
             res
         };
@@ -797,8 +856,8 @@ mod tests {
         let dep = StreamDependency::new(0, 5, true);
         let payload = {
             let mut buf: Vec<u8> = Vec::new();
-            buf.extend(dep.serialize().to_vec().into_iter());
-            buf.extend(data.to_vec().into_iter());
+            buf.extend(dep.serialize().to_vec());
+            buf.extend(data.to_vec());
 
             buf
         };
@@ -806,8 +865,8 @@ mod tests {
         let expected = {
             let headers = pack_header(&header);
             let mut res: Vec<u8> = Vec::new();
-            res.extend(headers.to_vec().into_iter());
-            res.extend(payload.into_iter());
+            res.extend(headers.to_vec());
+            res.extend(payload);
 
             res
         };
@@ -826,8 +885,8 @@ mod tests {
         let dep = StreamDependency::new(0, 5, true);
         let full = {
             let mut buf: Vec<u8> = Vec::new();
-            buf.extend(dep.serialize().to_vec().into_iter());
-            buf.extend(data.to_vec().into_iter());
+            buf.extend(dep.serialize().to_vec());
+            buf.extend(data.to_vec());
 
             buf
         };
@@ -836,8 +895,8 @@ mod tests {
         let expected = {
             let headers = pack_header(&header);
             let mut res: Vec<u8> = Vec::new();
-            res.extend(headers.to_vec().into_iter());
-            res.extend(payload.into_iter());
+            res.extend(headers.to_vec());
+            res.extend(payload);
 
             res
         };
@@ -875,7 +934,6 @@ mod tests {
             flags: Flags::new(0).with(HeadersFlag::EndStream),
             stream_id: 2,
             headers,
-            stream_dep: None,
             padding_len: 0,
             encoder: &mut encoder,
             max_frame_size,
@@ -905,4 +963,54 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_push_promise_multi_frame() {
+        let mut encoder = hpack::Encoder::new();
+
+        let mut headers = Headers::new();
+        headers.add(":method", "GET");
+        headers.add(":path", "/style.css");
+        for i in 0..1000 {
+            headers.add(format!("h-{}", i), format!("v-{}", i))
+        }
+
+        let max_frame_size = 1000;
+
+        let serialized = PushPromiseMultiFrame {
+            stream_id: 3,
+            promised_stream_id: 4,
+            headers: headers.clone(),
+            encoder: &mut encoder,
+            max_frame_size,
+        }
+        .serialize_into_vec();
+
+        let frames = unpack_frames_for_test(&serialized);
+        assert!(frames.len() > 2);
+        let mut fragment = Vec::new();
+        for (i, f) in frames.iter().enumerate() {
+            match f {
+                HttpFrame::PushPromise(p) => {
+                    assert_eq!(0, i);
+                    assert_eq!(3, p.stream_id);
+                    assert_eq!(4, p.promised_stream_id);
+                    assert_eq!(max_frame_size as usize - 4, p.header_fragment.len());
+                    assert!(!p.flags.is_set(PushPromiseFlag::EndHeaders));
+                    fragment.extend_from_slice(&p.header_fragment);
+                }
+                HttpFrame::Continuation(h) => {
+                    assert_ne!(0, i);
+                    assert_eq!(3, h.stream_id);
+                    let last = i == frames.len() - 1;
+                    assert_eq!(last, h.flags.is_set(ContinuationFlag::EndHeaders));
+                    fragment.extend_from_slice(&h.header_fragment);
+                }
+                _ => panic!("wrong frame type"),
+            }
+        }
+
+        let decoded = hpack::Decoder::new().decode(fragment.into()).unwrap();
+        assert_eq!(headers.iter().count(), decoded.len());
+    }
 }
@@ -1,8 +1,7 @@
 //! The module contains the implementation of HTTP/2 frames.
 
-use std::mem;
-
 use bytes::Bytes;
+use bytes::BytesMut;
 
 use crate::solicit::frame::flags::*;
 
@@ -13,7 +12,7 @@ use crate::solicit::frame::flags::*;
 ///
 /// # Examples
 ///
-/// ```rust
+/// ```ignore
 /// let buf: [u8; 4] = [0, 0, 0, 1];
 /// assert_eq!(1u32, unpack_octets_4!(buf, 0, u32));
 /// ```
@@ -43,6 +42,7 @@ mod flags;
 mod frame_type;
 mod goaway;
 mod headers;
+mod origin;
 mod ping;
 mod priority;
 mod push_promise;
@@ -64,19 +64,27 @@ pub use self::headers::HeadersDecodedFrame;
 pub use self::headers::HeadersFlag;
 pub use self::headers::HeadersFrame;
 pub use self::headers::HeadersMultiFrame;
+pub use self::headers::PushPromiseMultiFrame;
+pub use self::headers::StreamDependency;
+pub use self::origin::OriginFrame;
+pub use self::origin::ORIGIN_FRAME_TYPE;
 pub use self::ping::PingFrame;
 pub use self::priority::PriorityFrame;
+pub use self::push_promise::PushPromiseDecodedFrame;
 pub use self::push_promise::PushPromiseFlag;
 pub use self::push_promise::PushPromiseFrame;
 pub use self::rst_stream::RstStreamFrame;
 pub use self::settings::HttpSetting;
+#[allow(deprecated)]
 pub use self::settings::HttpSettings;
+pub use self::settings::Settings;
 pub use self::settings::SettingsFlag;
 pub use self::settings::SettingsFrame;
 pub use self::window_update::WindowUpdateFrame;
 use crate::codec::write_buffer::WriteBuffer;
 use crate::solicit::frame;
 use crate::solicit::stream_id::StreamId;
+use crate::ErrorCode;
 use std::fmt;
 
 /// HTTP/2 header length is 9 bytes.
@@ -142,6 +150,23 @@ pub fn unpack_frames_for_test(mut raw: &[u8]) -> Vec<HttpFrame> {
     r
 }
 
+/// Parse a single frame from the beginning of the buffer.
+///
+/// Returns the frame and the number of bytes consumed.
+/// `HEADERS` and `PUSH_PROMISE` fragments are returned not HPACK-decoded.
+pub fn parse_frame(buf: &[u8]) -> ParseFrameResult<(HttpFrame, usize)> {
+    if buf.len() < FRAME_HEADER_LEN {
+        return Err(ParseFrameError::BufMustBeAtLeast9Bytes(buf.len()));
+    }
+    let header = unpack_header_from_slice(&buf[..FRAME_HEADER_LEN]);
+    let len = FRAME_HEADER_LEN + header.payload_len as usize;
+    if buf.len() < len {
+        return Err(ParseFrameError::IncorrectPayloadLen);
+    }
+    let raw_frame = RawFrame::from(&buf[..len]);
+    Ok((HttpFrame::from_raw(&raw_frame)?, len))
+}
+
 /// Deconstructs a `FrameHeader` into its corresponding 4 components,
 /// represented as a 4-tuple: `(length, frame_type, flags, stream_id)`.
 ///
@@ -189,7 +214,7 @@ fn parse_padded_payload(payload: Bytes, flag: bool) -> ParseFrameResult<(Bytes,
     if !flag {
         return Ok((payload, 0));
     }
-    if payload.len() == 0 {
+    if payload.is_empty() {
         // We make sure not to index the payload before we're sure how
         // large the buffer is.
         // If this is the case, the frame is invalid as no padding
@@ -226,7 +251,7 @@ pub trait FrameIR: fmt::Debug {
 }
 
 /// Parse frame errors.
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum ParseFrameError {
     /// Internal error.
     InternalError,
@@ -246,16 +271,46 @@ pub enum ParseFrameError {
     IncorrectFlags(u8),
     /// Incorrect settings push value.
     IncorrectSettingsPushValue(u32),
+    /// Incorrect settings enable connect protocol value.
+    IncorrectSettingsEnableConnectProtocolValue(u32),
     /// Incorrect settings max frame size.
     IncorrectSettingsMaxFrameSize(u32),
+    /// Settings initial window size exceeds max window size.
+    IncorrectSettingsInitialWindowSize(u32),
     /// Window size is too large.
     WindowSizeTooLarge(u32),
     /// Window update increment is invalid.
     WindowUpdateIncrementInvalid(u32),
+    /// Frame of this type is not allowed on this stream.
+    InvalidStreamId(RawHttpFrameType, StreamId),
     /// Generic error.
     ProtocolError,
 }
 
+impl ParseFrameError {
+    /// Error code of connection error to be sent to peer in `GOAWAY`.
+    pub fn error_code(&self) -> ErrorCode {
+        match *self {
+            ParseFrameError::InternalError => ErrorCode::InternalError,
+            ParseFrameError::BufMustBeAtLeast9Bytes(_)
+            | ParseFrameError::IncorrectPayloadLen
+            | ParseFrameError::IncorrectFrameLength(_) => ErrorCode::FrameSizeError,
+            ParseFrameError::IncorrectSettingsInitialWindowSize(_)
+            | ParseFrameError::WindowSizeTooLarge(_) => ErrorCode::FlowControlError,
+            ParseFrameError::StreamIdMustBeNonZero
+            | ParseFrameError::StreamIdMustBeZero(_)
+            | ParseFrameError::StreamDependencyOnItself(_)
+            | ParseFrameError::IncorrectFlags(_)
+            | ParseFrameError::IncorrectSettingsPushValue(_)
+            | ParseFrameError::IncorrectSettingsEnableConnectProtocolValue(_)
+            | ParseFrameError::IncorrectSettingsMaxFrameSize(_)
+            | ParseFrameError::WindowUpdateIncrementInvalid(_)
+            | ParseFrameError::InvalidStreamId(..)
+            | ParseFrameError::ProtocolError => ErrorCode::ProtocolError,
+        }
+    }
+}
+
 /// Alias.
 pub type ParseFrameResult<T> = Result<T, ParseFrameError>;
 
@@ -311,6 +366,14 @@ pub struct RawFrameRef<'a> {
 }
 
 impl RawFrame {
+    /// Frame with given header fields and payload.
+    pub fn from_parts(header: FrameHeader, payload: &[u8]) -> RawFrame {
+        assert_eq!(header.payload_len as usize, payload.len());
+        let mut raw = pack_header(&header).to_vec();
+        raw.extend_from_slice(payload);
+        RawFrame::from(raw)
+    }
+
     /// Parses a `RawFrame` from the bytes starting at the beginning of the given buffer.
     ///
     /// Returns the `None` variant when it is not possible to parse a raw frame from the buffer
@@ -331,7 +394,7 @@ impl RawFrame {
         let header = unpack_header(unsafe {
             assert!(buf.len() >= 9);
             // We just asserted that this transmute is safe.
-            mem::transmute(buf.as_ptr())
+            &*(buf.as_ptr() as *const [u8; 9])
         });
 
         let payload_len = header.payload_len as usize;
@@ -344,7 +407,7 @@ impl RawFrame {
     }
 
     /// As a frame ref.
-    pub fn as_frame_ref(&self) -> RawFrameRef {
+    pub fn as_frame_ref(&self) -> RawFrameRef<'_> {
         RawFrameRef {
             raw_content: &self.raw_content,
         }
@@ -357,6 +420,8 @@ impl RawFrame {
 
     /// Returns the total length of the `RawFrame`, including both headers, as well as the entire
     /// payload.
+    // Frame is never empty, it contains at least the header
+    #[allow(clippy::len_without_is_empty)]
     #[inline]
     pub fn len(&self) -> usize {
         self.raw_content.len()
@@ -371,11 +436,7 @@ impl RawFrame {
     /// Returns a `FrameHeader` instance corresponding to the headers of the
     /// `RawFrame`.
     pub fn header(&self) -> FrameHeader {
-        unpack_header(unsafe {
-            assert!(self.raw_content.len() >= 9);
-            // We just asserted that this transmute is safe.
-            mem::transmute(self.raw_content.as_ptr())
-        })
+        unpack_header_from_slice(&self.raw_content[..FRAME_HEADER_LEN])
     }
 
     /// Get frame stream id.
@@ -383,6 +444,20 @@ impl RawFrame {
         self.header().stream_id
     }
 
+    /// Check content is a complete header and exactly the payload the header declares,
+    /// so frame parsers can index a payload of `payload_len` bytes.
+    fn check_len(&self) -> ParseFrameResult<()> {
+        if self.raw_content.len() < FRAME_HEADER_LEN {
+            return Err(ParseFrameError::BufMustBeAtLeast9Bytes(
+                self.raw_content.len(),
+            ));
+        }
+        if self.raw_content.len() - FRAME_HEADER_LEN != self.header().payload_len as usize {
+            return Err(ParseFrameError::IncorrectPayloadLen);
+        }
+        Ok(())
+    }
+
     /// Returns a slice representing the payload of the `RawFrame`.
     pub fn payload(&self) -> Bytes {
         self.raw_content.slice(9..)
@@ -422,6 +497,12 @@ impl<'a> From<&'a [u8]> for RawFrame {
 
 /// `RawFrame`s can be serialized to an on-the-wire format.
 impl FrameIR for RawFrame {
+    fn serialize_into(self, b: &mut WriteBuffer) {
+        (&self).serialize_into(b)
+    }
+}
+
+impl FrameIR for &RawFrame {
     fn serialize_into(self, b: &mut WriteBuffer) {
         b.write_header(self.header());
         b.extend_from_bytes(self.payload());
@@ -430,7 +511,116 @@ impl FrameIR for RawFrame {
 
 #[cfg(test)]
 mod tests {
-    use super::{pack_header, unpack_header, FrameHeader, RawFrame};
+    use super::unpack_header_from_slice;
+    use super::{pack_header, parse_frame, unpack_header, FrameHeader, RawFrame};
+    use super::{HttpFrame, ParseFrameError, RawHttpFrameType};
+    use super::{PingFrame, RstStreamFrame, WindowUpdateFrame};
+
+    use bytes::BytesMut;
+
+    use crate::ErrorCode;
+
+    #[test]
+    fn test_parse_frame_serialize() {
+        let frames: Vec<HttpFrame> = vec![
+            PingFrame::with_data(17).into(),
+            RstStreamFrame::new(3, ErrorCode::Cancel).into(),
+            WindowUpdateFrame::for_stream(5, 100).into(),
+        ];
+
+        let mut buf = BytesMut::new();
+        for frame in &frames {
+            frame.serialize(&mut buf);
+        }
+
+        let mut rem = &buf[..];
+        for frame in &frames {
+            let (parsed, len) = parse_frame(rem).unwrap();
+            assert_eq!(*frame, parsed);
+            rem = &rem[len..];
+        }
+        assert!(rem.is_empty());
+    }
+
+    /// Valid frames of every type, with optional fields present.
+    fn all_frame_types() -> Vec<RawFrame> {
+        let frame = |frame_type, flags, stream_id, payload: &[u8]| {
+            RawFrame::from_parts(
+                FrameHeader::new(payload.len() as u32, frame_type, flags, stream_id),
+                payload,
+            )
+        };
+        vec![
+            // DATA, padded
+            frame(0x0, 0x8, 1, &[2, b'a', b'b', 0, 0]),
+            // HEADERS, padded, with priority
+            frame(0x1, 0x8 | 0x20, 1, &[2, 0, 0, 0, 3, 16, b'a', 0, 0]),
+            // PRIORITY
+            frame(0x2, 0, 1, &[0, 0, 0, 3, 16]),
+            // RST_STREAM
+            frame(0x3, 0, 1, &[0, 0, 0, 8]),
+            // SETTINGS
+            frame(0x4, 0, 0, &[0, 3, 0, 0, 0, 100]),
+            // PUSH_PROMISE, padded
+            frame(0x5, 0x8, 1, &[3, 0, 0, 0, 2, b'a', b'b', 0, 0, 0]),
+            // PING
+            frame(0x6, 0, 0, &[1, 2, 3, 4, 5, 6, 7, 8]),
+            // GOAWAY with debug data
+            frame(0x7, 0, 0, &[0, 0, 0, 1, 0, 0, 0, 0, b'x']),
+            // WINDOW_UPDATE
+            frame(0x8, 0, 1, &[0, 0, 1, 0]),
+            // CONTINUATION
+            frame(0x9, 0x4, 1, b"a"),
+        ]
+    }
+
+    #[test]
+    fn test_parse_all_frame_types() {
+        for raw in all_frame_types() {
+            let (_, len) = parse_frame(raw.as_ref()).expect("parse");
+            assert_eq!(raw.len(), len);
+        }
+    }
+
+    #[test]
+    fn test_parse_prefix_no_panic() {
+        for raw in all_frame_types() {
+            let bytes = raw.as_ref();
+            for len in 0..bytes.len() {
+                // Truncated buffer
+                assert!(parse_frame(&bytes[..len]).is_err());
+                // Truncated content, header declares full payload
+                assert!(HttpFrame::from_raw(&RawFrame::from(&bytes[..len])).is_err());
+            }
+            let header = unpack_header_from_slice(&bytes[..9]);
+            for payload_len in 0..header.payload_len {
+                // Consistent header declaring truncated payload
+                let header = FrameHeader {
+                    payload_len,
+                    ..header
+                };
+                let _ = HttpFrame::from_raw(&RawFrame::from_parts(
+                    header,
+                    &bytes[9..9 + payload_len as usize],
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_frame_incomplete() {
+        let mut buf = BytesMut::new();
+        HttpFrame::from(PingFrame::new()).serialize(&mut buf);
+
+        assert_eq!(
+            Err(ParseFrameError::BufMustBeAtLeast9Bytes(5)),
+            parse_frame(&buf[..5])
+        );
+        assert_eq!(
+            Err(ParseFrameError::IncorrectPayloadLen),
+            parse_frame(&buf[..buf.len() - 1])
+        );
+    }
 
     /// Tests that the `unpack_header` function correctly returns the
     /// components of HTTP/2 frame headers.
@@ -566,10 +756,8 @@ mod tests {
         let sz = 1 + data.len() + pad_len as usize;
         let mut payload: Vec<u8> = Vec::with_capacity(sz);
         payload.push(pad_len);
-        payload.extend(data.to_vec().into_iter());
-        for _ in 0..pad_len {
-            payload.push(0);
-        }
+        payload.extend(data.to_vec());
+        payload.resize(sz, 0);
 
         payload
     }
@@ -596,8 +784,8 @@ mod tests {
         };
         let buf = {
             let mut buf = Vec::new();
-            buf.extend(pack_header(&header).to_vec().into_iter());
-            buf.extend(data.to_vec().into_iter());
+            buf.extend(pack_header(&header).to_vec());
+            buf.extend(data.to_vec());
             buf
         };
         let raw: RawFrame = buf.clone().into();
@@ -626,6 +814,36 @@ mod tests {
             assert_eq!(buf.len(), frame.len());
         }
     }
+
+    fn raw_frame(frame_type: u8, stream_id: u32, payload: &[u8]) -> RawFrame {
+        let header = FrameHeader::new(payload.len() as u32, frame_type, 0, stream_id);
+        let mut buf = pack_header(&header).to_vec();
+        buf.extend_from_slice(payload);
+        buf.into()
+    }
+
+    #[test]
+    fn data_on_stream_zero() {
+        match HttpFrame::from_raw(&raw_frame(0x0, 0, b"abc")) {
+            Err(ParseFrameError::InvalidStreamId(RawHttpFrameType::DATA, 0)) => {}
+            r => panic!("{:?}", r),
+        }
+    }
+
+    #[test]
+    fn settings_on_non_zero_stream() {
+        match HttpFrame::from_raw(&raw_frame(0x4, 1, b"")) {
+            Err(ParseFrameError::InvalidStreamId(RawHttpFrameType::SETTINGS, 1)) => {}
+            r => panic!("{:?}", r),
+        }
+    }
+
+    #[test]
+    fn window_update_on_any_stream() {
+        let increment = [0, 0, 0, 1];
+        assert!(HttpFrame::from_raw(&raw_frame(0x8, 0, &increment)).is_ok());
+        assert!(HttpFrame::from_raw(&raw_frame(0x8, 3, &increment)).is_ok());
+    }
 }
 
 /// An enum representing all frame variants that can be returned by an `HttpConnection` can handle.
@@ -662,32 +880,35 @@ impl HttpFrame {
     /// Parse frame.
     // TODO: take by value
     pub fn from_raw(raw_frame: &RawFrame) -> ParseFrameResult<HttpFrame> {
+        raw_frame.check_len()?;
+        HttpFrame::check_stream_id(&raw_frame.header())?;
+
         let frame = match raw_frame.header().frame_type {
-            frame::data::DATA_FRAME_TYPE => HttpFrame::Data(HttpFrame::parse_frame(&raw_frame)?),
+            frame::data::DATA_FRAME_TYPE => HttpFrame::Data(HttpFrame::parse_frame(raw_frame)?),
             frame::headers::HEADERS_FRAME_TYPE => {
-                HttpFrame::Headers(HttpFrame::parse_frame(&raw_frame)?)
+                HttpFrame::Headers(HttpFrame::parse_frame(raw_frame)?)
             }
             frame::priority::PRIORITY_FRAME_TYPE => {
-                HttpFrame::Priority(HttpFrame::parse_frame(&raw_frame)?)
+                HttpFrame::Priority(HttpFrame::parse_frame(raw_frame)?)
             }
             frame::rst_stream::RST_STREAM_FRAME_TYPE => {
-                HttpFrame::RstStream(HttpFrame::parse_frame(&raw_frame)?)
+                HttpFrame::RstStream(HttpFrame::parse_frame(raw_frame)?)
             }
             frame::settings::SETTINGS_FRAME_TYPE => {
-                HttpFrame::Settings(HttpFrame::parse_frame(&raw_frame)?)
+                HttpFrame::Settings(HttpFrame::parse_frame(raw_frame)?)
             }
             frame::push_promise::PUSH_PROMISE_FRAME_TYPE => {
-                HttpFrame::PushPromise(HttpFrame::parse_frame(&raw_frame)?)
+                HttpFrame::PushPromise(HttpFrame::parse_frame(raw_frame)?)
             }
-            frame::ping::PING_FRAME_TYPE => HttpFrame::Ping(HttpFrame::parse_frame(&raw_frame)?),
+            frame::ping::PING_FRAME_TYPE => HttpFrame::Ping(HttpFrame::parse_frame(raw_frame)?),
             frame::goaway::GOAWAY_FRAME_TYPE => {
-                HttpFrame::Goaway(HttpFrame::parse_frame(&raw_frame)?)
+                HttpFrame::Goaway(HttpFrame::parse_frame(raw_frame)?)
             }
             frame::window_update::WINDOW_UPDATE_FRAME_TYPE => {
-                HttpFrame::WindowUpdate(HttpFrame::parse_frame(&raw_frame)?)
+                HttpFrame::WindowUpdate(HttpFrame::parse_frame(raw_frame)?)
             }
             frame::continuation::CONTINUATION_FRAME_TYPE => {
-                HttpFrame::Continuation(HttpFrame::parse_frame(&raw_frame)?)
+                HttpFrame::Continuation(HttpFrame::parse_frame(raw_frame)?)
             }
             _ => HttpFrame::Unknown(raw_frame.as_ref().into()),
         };
@@ -695,6 +916,35 @@ impl HttpFrame {
         Ok(frame)
     }
 
+    /// Check frame type is allowed on frame stream (RFC 7540 section 6).
+    fn check_stream_id(header: &FrameHeader) -> ParseFrameResult<()> {
+        let conn_frame = match header.frame_type {
+            frame::data::DATA_FRAME_TYPE
+            | frame::headers::HEADERS_FRAME_TYPE
+            | frame::priority::PRIORITY_FRAME_TYPE
+            | frame::rst_stream::RST_STREAM_FRAME_TYPE
+            | frame::push_promise::PUSH_PROMISE_FRAME_TYPE
+            | frame::continuation::CONTINUATION_FRAME_TYPE => false,
+            frame::settings::SETTINGS_FRAME_TYPE
+            | frame::ping::PING_FRAME_TYPE
+            | frame::goaway::GOAWAY_FRAME_TYPE => true,
+            // `WINDOW_UPDATE` is allowed on any stream, unknown frames are ignored
+            _ => return Ok(()),
+        };
+        if conn_frame != (header.stream_id == 0) {
+            let frame_type = RawHttpFrameType(header.frame_type);
+            warn!(
+                "{} frame is not allowed on stream {}",
+                frame_type, header.stream_id
+            );
+            return Err(ParseFrameError::InvalidStreamId(
+                frame_type,
+                header.stream_id,
+            ));
+        }
+        Ok(())
+    }
+
     /// A helper method that parses the given `RawFrame` into the given `Frame`
     /// implementation.
     ///
@@ -704,23 +954,23 @@ impl HttpFrame {
     /// `HttpError::InvalidFrame` error is returned.
     #[inline] // TODO: take by value
     fn parse_frame<F: Frame>(raw_frame: &RawFrame) -> ParseFrameResult<F> {
-        Frame::from_raw(&raw_frame)
+        Frame::from_raw(raw_frame)
     }
 
     /// Get stream id, zero for special frames
     pub fn get_stream_id(&self) -> StreamId {
         match self {
-            &HttpFrame::Data(ref f) => f.get_stream_id(),
-            &HttpFrame::Headers(ref f) => f.get_stream_id(),
-            &HttpFrame::Priority(ref f) => f.get_stream_id(),
-            &HttpFrame::RstStream(ref f) => f.get_stream_id(),
-            &HttpFrame::Settings(ref f) => f.get_stream_id(),
-            &HttpFrame::PushPromise(ref f) => f.get_stream_id(),
-            &HttpFrame::Ping(ref f) => f.get_stream_id(),
-            &HttpFrame::Goaway(ref f) => f.get_stream_id(),
-            &HttpFrame::WindowUpdate(ref f) => f.get_stream_id(),
-            &HttpFrame::Continuation(ref f) => f.get_stream_id(),
-            &HttpFrame::Unknown(ref f) => f.get_stream_id(),
+            HttpFrame::Data(f) => f.get_stream_id(),
+            HttpFrame::Headers(f) => f.get_stream_id(),
+            HttpFrame::Priority(f) => f.get_stream_id(),
+            HttpFrame::RstStream(f) => f.get_stream_id(),
+            HttpFrame::Settings(f) => f.get_stream_id(),
+            HttpFrame::PushPromise(f) => f.get_stream_id(),
+            HttpFrame::Ping(f) => f.get_stream_id(),
+            HttpFrame::Goaway(f) => f.get_stream_id(),
+            HttpFrame::WindowUpdate(f) => f.get_stream_id(),
+            HttpFrame::Continuation(f) => f.get_stream_id(),
+            HttpFrame::Unknown(f) => f.get_stream_id(),
         }
     }
 
@@ -737,12 +987,23 @@ impl HttpFrame {
             &HttpFrame::Goaway(..) => RawHttpFrameType::GOAWAY,
             &HttpFrame::WindowUpdate(..) => RawHttpFrameType::WINDOW_UPDATE,
             &HttpFrame::Continuation(..) => RawHttpFrameType::CONTINUATION,
-            &HttpFrame::Unknown(ref f) => RawHttpFrameType(f.frame_type()),
+            HttpFrame::Unknown(f) => RawHttpFrameType(f.frame_type()),
         }
     }
+
+    /// Append on-the-wire representation of the frame to the buffer.
+    pub fn serialize(&self, out: &mut BytesMut) {
+        out.extend_from_slice(&FrameIR::serialize_into_vec(self));
+    }
 }
 
 impl FrameIR for HttpFrame {
+    fn serialize_into(self, builder: &mut WriteBuffer) {
+        (&self).serialize_into(builder)
+    }
+}
+
+impl FrameIR for &HttpFrame {
     fn serialize_into(self, builder: &mut WriteBuffer) {
         match self {
             HttpFrame::Data(f) => f.serialize_into(builder),
@@ -834,7 +1095,7 @@ pub enum HttpFrameDecoded {
     /// `SETTINGS`
     Settings(SettingsFrame),
     /// `PUSH_PROMISE`
-    PushPromise(PushPromiseFrame),
+    PushPromise(PushPromiseDecodedFrame),
     /// `PING`
     Ping(PingFrame),
     /// `GOAWAY`
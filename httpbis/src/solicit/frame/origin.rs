@@ -0,0 +1,153 @@
+//! Implements the `ORIGIN` frame (RFC 8336).
+
+use crate::codec::write_buffer::WriteBuffer;
+use crate::solicit::frame::flags::*;
+use crate::solicit::frame::Frame;
+use crate::solicit::frame::FrameBuilder;
+use crate::solicit::frame::FrameHeader;
+use crate::solicit::frame::FrameIR;
+use crate::solicit::frame::ParseFrameError;
+use crate::solicit::frame::ParseFrameResult;
+use crate::solicit::frame::RawFrame;
+use crate::solicit::stream_id::StreamId;
+
+/// The frame type of the `ORIGIN` frame.
+pub const ORIGIN_FRAME_TYPE: u8 = 0xc;
+
+/// Origins the server is authoritative for, sent on stream zero.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OriginFrame {
+    /// ASCII serialized origins, e.g. `https://example.com`.
+    pub origins: Vec<String>,
+    /// Frame flags.
+    flags: Flags<NoFlag>,
+}
+
+impl OriginFrame {
+    /// Create a new `ORIGIN` frame.
+    pub fn new(origins: Vec<String>) -> OriginFrame {
+        OriginFrame {
+            origins,
+            flags: Flags::default(),
+        }
+    }
+
+    /// Returns the total length of the frame's payload.
+    pub fn payload_len(&self) -> u32 {
+        self.origins.iter().map(|o| 2 + o.len() as u32).sum()
+    }
+}
+
+impl Frame for OriginFrame {
+    type FlagType = NoFlag;
+
+    fn from_raw(raw_frame: &RawFrame) -> ParseFrameResult<Self> {
+        let FrameHeader {
+            frame_type,
+            flags,
+            stream_id,
+            ..
+        } = raw_frame.header();
+        if frame_type != ORIGIN_FRAME_TYPE {
+            return Err(ParseFrameError::InternalError);
+        }
+        if stream_id != 0x0 {
+            return Err(ParseFrameError::StreamIdMustBeZero(stream_id));
+        }
+
+        let payload = raw_frame.payload();
+        let mut rem = &payload[..];
+        let mut origins = Vec::new();
+        while !rem.is_empty() {
+            if rem.len() < 2 {
+                return Err(ParseFrameError::IncorrectPayloadLen);
+            }
+            let len = ((rem[0] as usize) << 8) | rem[1] as usize;
+            rem = &rem[2..];
+            if rem.len() < len {
+                return Err(ParseFrameError::IncorrectPayloadLen);
+            }
+            let origin = match String::from_utf8(rem[..len].to_vec()) {
+                Ok(origin) if origin.is_ascii() => origin,
+                _ => return Err(ParseFrameError::ProtocolError),
+            };
+            origins.push(origin);
+            rem = &rem[len..];
+        }
+
+        Ok(OriginFrame {
+            origins,
+            flags: Flags::new(flags),
+        })
+    }
+
+    fn flags(&self) -> Flags<NoFlag> {
+        self.flags
+    }
+
+    fn get_stream_id(&self) -> StreamId {
+        0
+    }
+
+    fn get_header(&self) -> FrameHeader {
+        FrameHeader {
+            payload_len: self.payload_len(),
+            frame_type: ORIGIN_FRAME_TYPE,
+            flags: self.flags.0,
+            stream_id: 0,
+        }
+    }
+}
+
+impl FrameIR for OriginFrame {
+    fn serialize_into(self, builder: &mut WriteBuffer) {
+        (&self).serialize_into(builder)
+    }
+}
+
+impl FrameIR for &OriginFrame {
+    fn serialize_into(self, builder: &mut WriteBuffer) {
+        builder.write_header(self.get_header());
+        for origin in &self.origins {
+            builder.write_slice(&(origin.len() as u16).to_be_bytes());
+            builder.write_slice(origin.as_bytes());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OriginFrame;
+
+    use crate::solicit::frame::Frame;
+    use crate::solicit::frame::FrameHeader;
+    use crate::solicit::frame::FrameIR;
+    use crate::solicit::frame::ParseFrameError;
+    use crate::solicit::tests::common::raw_frame_from_parts;
+
+    #[test]
+    fn test_serialize_parse() {
+        let frame = OriginFrame::new(vec!["https://a.example".to_owned(), "https://b".to_owned()]);
+        let raw = frame.clone().serialize_into_vec();
+        assert_eq!(&[0, 0, 30, 0xc, 0, 0, 0, 0, 0, 0, 17][..], &raw[..11]);
+        assert_eq!(frame, OriginFrame::from_raw(&raw.into()).unwrap());
+    }
+
+    #[test]
+    fn test_parse_truncated() {
+        let raw = raw_frame_from_parts(FrameHeader::new(4, 0xc, 0, 0), vec![0, 5, b'a', b'b']);
+        assert_eq!(
+            Err(ParseFrameError::IncorrectPayloadLen),
+            OriginFrame::from_raw(&raw)
+        );
+    }
+
+    #[test]
+    fn test_parse_non_zero_stream() {
+        let raw = raw_frame_from_parts(FrameHeader::new(0, 0xc, 0, 1), vec![]);
+        assert_eq!(
+            Err(ParseFrameError::StreamIdMustBeZero(1)),
+            OriginFrame::from_raw(&raw)
+        );
+    }
+}
@@ -28,7 +28,7 @@ impl Flag for PingFlag {
     }
 
     fn flags() -> &'static [Self] {
-        static FLAGS: &'static [PingFlag] = &[PingFlag::Ack];
+        static FLAGS: &[PingFlag] = &[PingFlag::Ack];
         FLAGS
     }
 }
@@ -41,6 +41,12 @@ pub struct PingFrame {
     flags: Flags<PingFlag>,
 }
 
+impl Default for PingFrame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl PingFrame {
     /// Create a new `PING` frame
     pub fn new() -> Self {
@@ -53,7 +59,7 @@ impl PingFrame {
     /// Create a new PING frame with ACK set
     pub fn new_ack(opaque_data: u64) -> Self {
         PingFrame {
-            opaque_data: opaque_data,
+            opaque_data,
             flags: PingFlag::Ack.to_flags(),
         }
     }
@@ -61,7 +67,7 @@ impl PingFrame {
     /// Create a new `PING` frame with the given opaque_data
     pub fn with_data(opaque_data: u64) -> Self {
         PingFrame {
-            opaque_data: opaque_data,
+            opaque_data,
             flags: Flags::default(),
         }
     }
@@ -125,6 +131,12 @@ impl Frame for PingFrame {
 }
 
 impl FrameIR for PingFrame {
+    fn serialize_into(self, builder: &mut WriteBuffer) {
+        (&self).serialize_into(builder)
+    }
+}
+
+impl FrameIR for &PingFrame {
     fn serialize_into(self, builder: &mut WriteBuffer) {
         builder.write_header(self.get_header());
         builder.write_u32((self.opaque_data >> 32) as u32);
@@ -146,7 +158,7 @@ mod tests {
         let raw =
             raw_frame_from_parts(FrameHeader::new(8, 0x6, 0, 0), vec![0, 0, 0, 0, 0, 0, 0, 0]);
         let frame = PingFrame::from_raw(&raw).expect("Expected successful parse");
-        assert_eq!(frame.is_ack(), false);
+        assert!(!frame.is_ack());
         assert_eq!(frame.opaque_data(), 0);
     }
 
@@ -155,7 +167,7 @@ mod tests {
         let raw =
             raw_frame_from_parts(FrameHeader::new(8, 0x6, 1, 0), vec![0, 0, 0, 0, 0, 0, 0, 0]);
         let frame = PingFrame::from_raw(&raw).expect("Expected successful parse");
-        assert_eq!(frame.is_ack(), true);
+        assert!(frame.is_ack());
         assert_eq!(frame.opaque_data(), 0);
     }
 
@@ -164,7 +176,7 @@ mod tests {
         let raw =
             raw_frame_from_parts(FrameHeader::new(8, 0x6, 1, 0), vec![1, 2, 3, 4, 5, 6, 7, 8]);
         let frame = PingFrame::from_raw(&raw).expect("Expected successful parse");
-        assert_eq!(frame.is_ack(), true);
+        assert!(frame.is_ack());
         assert_eq!(frame.opaque_data(), 0x0102030405060708);
     }
 
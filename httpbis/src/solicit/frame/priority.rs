@@ -4,11 +4,13 @@ use crate::codec::write_buffer::WriteBuffer;
 use crate::solicit::frame::flags::Flags;
 use crate::solicit::frame::flags::NoFlag;
 use crate::solicit::frame::Frame;
+use crate::solicit::frame::FrameBuilder;
 use crate::solicit::frame::FrameHeader;
 use crate::solicit::frame::FrameIR;
 use crate::solicit::frame::ParseFrameError;
 use crate::solicit::frame::ParseFrameResult;
 use crate::solicit::frame::RawFrame;
+use crate::solicit::frame::StreamDependency;
 use crate::solicit::stream_id::StreamId;
 
 /// `PRIORITY` frame.
@@ -28,6 +30,26 @@ pub struct PriorityFrame {
 
 pub const PRIORITY_FRAME_TYPE: u8 = 0x2;
 
+impl PriorityFrame {
+    /// Create a frame.
+    ///
+    /// `weight` is in the range [0, 255] representing weight [1, 256].
+    pub fn new(stream_id: StreamId, stream_dep: StreamId, exclusive: bool, weight: u8) -> Self {
+        PriorityFrame {
+            flags: Flags::default(),
+            stream_id,
+            exclusive,
+            stream_dep,
+            weight,
+        }
+    }
+
+    /// Priority as `StreamDependency`.
+    pub fn stream_dependency(&self) -> StreamDependency {
+        StreamDependency::new(self.stream_dep, self.weight, self.exclusive)
+    }
+}
+
 impl Frame for PriorityFrame {
     type FlagType = NoFlag;
 
@@ -58,9 +80,8 @@ impl Frame for PriorityFrame {
         let weight = payload.get_u8();
         assert_eq!(0, payload.remaining());
 
-        if stream_dep == stream_id {
-            return Err(ParseFrameError::StreamDependencyOnItself(stream_id));
-        }
+        // Dependency on itself is a stream error (RFC 7540 section 5.3.1),
+        // so it is checked by the connection, not by the parser.
 
         Ok(PriorityFrame {
             flags: Flags::new(flags),
@@ -90,7 +111,53 @@ impl Frame for PriorityFrame {
 }
 
 impl FrameIR for PriorityFrame {
-    fn serialize_into(self, _builder: &mut WriteBuffer) {
-        unimplemented!()
+    fn serialize_into(self, builder: &mut WriteBuffer) {
+        (&self).serialize_into(builder)
+    }
+}
+
+impl FrameIR for &PriorityFrame {
+    fn serialize_into(self, builder: &mut WriteBuffer) {
+        builder.write_header(self.get_header());
+        builder.extend_from_slice(&self.stream_dependency().serialize());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::solicit::frame::pack_header;
+
+    #[test]
+    fn parse() {
+        let mut buf = pack_header(&FrameHeader::new(5, PRIORITY_FRAME_TYPE, 0, 3)).to_vec();
+        buf.extend_from_slice(&[0x80, 0, 0, 1, 255]);
+        let frame = PriorityFrame::from_raw(&RawFrame::from(buf)).unwrap();
+        assert_eq!(PriorityFrame::new(3, 1, true, 255), frame);
+    }
+
+    #[test]
+    fn serialize_parse() {
+        let frame = PriorityFrame::new(5, 3, false, 15);
+        let raw = RawFrame::from(frame.clone().serialize_into_vec());
+        assert_eq!(frame, PriorityFrame::from_raw(&raw).unwrap());
+    }
+
+    #[test]
+    fn parse_depends_on_itself() {
+        let frame = PriorityFrame::new(5, 5, false, 15);
+        let raw = RawFrame::from(frame.clone().serialize_into_vec());
+        assert_eq!(frame, PriorityFrame::from_raw(&raw).unwrap());
+    }
+
+    #[test]
+    fn parse_incorrect_length() {
+        let mut buf = pack_header(&FrameHeader::new(4, PRIORITY_FRAME_TYPE, 0, 3)).to_vec();
+        buf.extend_from_slice(&[0, 0, 0, 1]);
+        match PriorityFrame::from_raw(&RawFrame::from(buf)) {
+            Err(ParseFrameError::IncorrectFrameLength(4)) => {}
+            r => panic!("{:?}", r),
+        }
     }
 }
@@ -14,6 +14,7 @@ use super::flags::Flag;
 use super::flags::Flags;
 use crate::codec::write_buffer::WriteBuffer;
 use crate::solicit::stream_id::StreamId;
+use crate::Headers;
 
 pub const PUSH_PROMISE_FRAME_TYPE: u8 = 0x5;
 
@@ -32,6 +33,24 @@ pub struct PushPromiseFrame {
     pub padding_len: u8,
 }
 
+/// `PUSH_PROMISE` frame with decoded headers.
+#[derive(Debug, Clone)]
+pub struct PushPromiseDecodedFrame {
+    /// The ID of the stream with which this frame is associated
+    pub stream_id: StreamId,
+    /// Promised Stream ID
+    pub promised_stream_id: StreamId,
+    /// Headers of the promised request.
+    pub headers: Headers,
+}
+
+impl PushPromiseDecodedFrame {
+    /// Get stream id
+    pub fn get_stream_id(&self) -> StreamId {
+        self.stream_id
+    }
+}
+
 /// `PUSH_PROMISE` frame flag.
 #[derive(PartialEq, Eq, Copy, Clone, Debug)]
 pub enum PushPromiseFlag {
@@ -47,8 +66,7 @@ impl Flag for PushPromiseFlag {
     }
 
     fn flags() -> &'static [PushPromiseFlag] {
-        static FLAGS: &'static [PushPromiseFlag] =
-            &[PushPromiseFlag::EndHeaders, PushPromiseFlag::Padded];
+        static FLAGS: &[PushPromiseFlag] = &[PushPromiseFlag::EndHeaders, PushPromiseFlag::Padded];
         FLAGS
     }
 }
@@ -108,12 +126,13 @@ impl Frame for PushPromiseFrame {
 
         let (payload, padding_len) = parse_padded_payload(raw_frame.payload(), padded)?;
 
-        let mut buf = &payload[..];
+        if payload.len() < 4 {
+            return Err(ParseFrameError::IncorrectFrameLength(payload_len));
+        }
 
-        let promised_stream_id = buf.get_u32();
+        let promised_stream_id = (&payload[..4]).get_u32();
 
-        let header_fragment =
-            payload.slice((payload_len as usize) - buf.remaining()..payload.len());
+        let header_fragment = payload.slice(4..);
 
         Ok(PushPromiseFrame {
             header_fragment,
@@ -143,14 +162,21 @@ impl Frame for PushPromiseFrame {
 }
 
 impl FrameIR for PushPromiseFrame {
+    fn serialize_into(self, b: &mut WriteBuffer) {
+        (&self).serialize_into(b)
+    }
+}
+
+impl FrameIR for &PushPromiseFrame {
     fn serialize_into(self, b: &mut WriteBuffer) {
         b.write_header(self.get_header());
         let padded = self.flags.is_set(PushPromiseFlag::Padded);
         if padded {
             b.extend_from_slice(&[self.padding_len]);
         }
+        b.extend_from_slice(&self.promised_stream_id.to_be_bytes());
         // Now the actual headers fragment
-        b.extend_from_bytes(self.header_fragment);
+        b.extend_from_bytes(self.header_fragment.clone());
         // Finally, add the trailing padding, if required
         if padded {
             b.write_padding(self.padding_len);
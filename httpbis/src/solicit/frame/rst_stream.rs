@@ -33,7 +33,7 @@ impl RstStreamFrame {
     pub fn new(stream_id: StreamId, error_code: ErrorCode) -> RstStreamFrame {
         RstStreamFrame {
             error_code: error_code.into(),
-            stream_id: stream_id,
+            stream_id,
             flags: Flags::default(),
         }
     }
@@ -108,6 +108,12 @@ impl Frame for RstStreamFrame {
 }
 
 impl FrameIR for RstStreamFrame {
+    fn serialize_into(self, builder: &mut WriteBuffer) {
+        (&self).serialize_into(builder)
+    }
+}
+
+impl FrameIR for &RstStreamFrame {
     fn serialize_into(self, builder: &mut WriteBuffer) {
         builder.write_header(self.get_header());
         builder.write_u32(self.error_code.0);
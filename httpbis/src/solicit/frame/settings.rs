@@ -32,6 +32,8 @@ pub enum HttpSetting {
     MaxFrameSize(u32),
     /// Setting
     MaxHeaderListSize(u32),
+    /// `SETTINGS_ENABLE_CONNECT_PROTOCOL` (RFC 8441)
+    EnableConnectProtocol(bool),
 }
 
 impl HttpSetting {
@@ -39,7 +41,7 @@ impl HttpSetting {
     /// the given setting id, based on the settings IDs defined in section
     /// 6.5.2.
     pub fn from_id(id: u16, val: u32) -> ParseFrameResult<Option<HttpSetting>> {
-        Ok(Some(match id {
+        let setting = match id {
             1 => HttpSetting::HeaderTableSize(val),
             2 => {
                 let b = match val {
@@ -50,31 +52,41 @@ impl HttpSetting {
                 HttpSetting::EnablePush(b)
             }
             3 => HttpSetting::MaxConcurrentStreams(val),
-            4 => {
-                if val > MAX_WINDOW_SIZE {
-                    // 6.5.2.  Defined SETTINGS Parameters
-                    // Values above the maximum flow-control window size of 2^31-1 MUST
-                    // be treated as a connection error (Section 5.4.1) of type
-                    // FLOW_CONTROL_ERROR.
-                    // We handle it in `process_settings_req`
-                }
-                HttpSetting::InitialWindowSize(val)
-            }
-            5 => {
-                if val < 0x4000 || val >= 0x100_0000 {
-                    // 6.5.2.  Defined SETTINGS Parameters
-                    // The initial value is 2^14 (16,384) octets.  The value advertised
-                    // by an endpoint MUST be between this initial value and the maximum
-                    // allowed frame size (2^24-1 or 16,777,215 octets), inclusive.
-                    // Values outside this range MUST be treated as a connection error
-                    // (Section 5.4.1) of type PROTOCOL_ERROR.
-                    return Err(ParseFrameError::IncorrectSettingsMaxFrameSize(val));
-                }
-                HttpSetting::MaxFrameSize(val)
-            }
+            4 => HttpSetting::InitialWindowSize(val),
+            5 => HttpSetting::MaxFrameSize(val),
             6 => HttpSetting::MaxHeaderListSize(val),
+            8 => match val {
+                0 => HttpSetting::EnableConnectProtocol(false),
+                1 => HttpSetting::EnableConnectProtocol(true),
+                _ => return Err(ParseFrameError::IncorrectSettingsEnableConnectProtocolValue(val)),
+            },
+            // 6.5.2. An endpoint that receives a SETTINGS frame with any unknown
+            // or unsupported identifier MUST ignore that setting.
             _ => return Ok(None),
-        }))
+        };
+        setting.validate()?;
+        Ok(Some(setting))
+    }
+
+    /// Check the setting value is allowed by RFC 7540 section 6.5.2.
+    pub fn validate(&self) -> ParseFrameResult<()> {
+        match *self {
+            HttpSetting::InitialWindowSize(val) if val > MAX_WINDOW_SIZE => {
+                // Values above the maximum flow-control window size of 2^31-1 MUST
+                // be treated as a connection error (Section 5.4.1) of type
+                // FLOW_CONTROL_ERROR.
+                Err(ParseFrameError::IncorrectSettingsInitialWindowSize(val))
+            }
+            HttpSetting::MaxFrameSize(val) if !(0x4000..0x100_0000).contains(&val) => {
+                // The initial value is 2^14 (16,384) octets.  The value advertised
+                // by an endpoint MUST be between this initial value and the maximum
+                // allowed frame size (2^24-1 or 16,777,215 octets), inclusive.
+                // Values outside this range MUST be treated as a connection error
+                // (Section 5.4.1) of type PROTOCOL_ERROR.
+                Err(ParseFrameError::IncorrectSettingsMaxFrameSize(val))
+            }
+            _ => Ok(()),
+        }
     }
 
     /// Creates a new `HttpSetting` by parsing the given buffer of 6 bytes,
@@ -104,6 +116,7 @@ impl HttpSetting {
             HttpSetting::InitialWindowSize(_) => 4,
             HttpSetting::MaxFrameSize(_) => 5,
             HttpSetting::MaxHeaderListSize(_) => 6,
+            HttpSetting::EnableConnectProtocol(_) => 8,
         }
     }
 
@@ -115,8 +128,8 @@ impl HttpSetting {
             | HttpSetting::InitialWindowSize(val)
             | HttpSetting::MaxFrameSize(val)
             | HttpSetting::MaxHeaderListSize(val) => val,
-            HttpSetting::EnablePush(true) => 1,
-            HttpSetting::EnablePush(false) => 0,
+            HttpSetting::EnablePush(true) | HttpSetting::EnableConnectProtocol(true) => 1,
+            HttpSetting::EnablePush(false) | HttpSetting::EnableConnectProtocol(false) => 0,
         }
     }
 
@@ -135,9 +148,14 @@ impl HttpSetting {
     }
 }
 
-/// All known setting values
+/// Values of all known settings (RFC 7540 section 6.5.2).
+///
+/// Except `SETTINGS_ENABLE_CONNECT_PROTOCOL` (RFC 8441): adding a field would break
+/// code which creates settings with struct literal, e.g. for `CommonConf::initial_settings`.
+/// It is configured with `ServerConf::enable_connect_protocol` and reported by
+/// `Client::connect_protocol_enabled` instead.
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
-pub struct HttpSettings {
+pub struct Settings {
     /// Setting
     pub header_table_size: u32,
     /// Setting
@@ -152,7 +170,11 @@ pub struct HttpSettings {
     pub max_header_list_size: u32,
 }
 
-impl HttpSettings {
+/// Former name of `Settings`.
+#[deprecated(note = "use `Settings`")]
+pub type HttpSettings = Settings;
+
+impl Settings {
     /// Apply setting to this settings.
     pub fn apply(&mut self, setting: HttpSetting) {
         match setting {
@@ -162,6 +184,8 @@ impl HttpSettings {
             HttpSetting::InitialWindowSize(s) => self.initial_window_size = s,
             HttpSetting::MaxFrameSize(s) => self.max_frame_size = s,
             HttpSetting::MaxHeaderListSize(s) => self.max_header_list_size = s,
+            // Tracked by connection, not part of `Settings`
+            HttpSetting::EnableConnectProtocol(_) => {}
         }
     }
 
@@ -171,6 +195,44 @@ impl HttpSettings {
             self.apply(*s);
         }
     }
+
+    /// All settings as a list.
+    pub fn to_settings(&self) -> Vec<HttpSetting> {
+        vec![
+            HttpSetting::HeaderTableSize(self.header_table_size),
+            HttpSetting::EnablePush(self.enable_push),
+            HttpSetting::MaxConcurrentStreams(self.max_concurrent_streams),
+            HttpSetting::InitialWindowSize(self.initial_window_size),
+            HttpSetting::MaxFrameSize(self.max_frame_size),
+            HttpSetting::MaxHeaderListSize(self.max_header_list_size),
+        ]
+    }
+
+    /// Check all values are allowed by RFC 7540 section 6.5.2.
+    ///
+    /// Fails with the error of the first invalid setting.
+    pub fn validate(&self) -> ParseFrameResult<()> {
+        HttpSetting::InitialWindowSize(self.initial_window_size).validate()?;
+        HttpSetting::MaxFrameSize(self.max_frame_size).validate()?;
+        Ok(())
+    }
+
+    /// Parse `SETTINGS` frame payload and apply it on top of these settings.
+    ///
+    /// Unknown settings are ignored, invalid value of known setting is an error.
+    pub fn parse_payload(&self, payload: &[u8]) -> ParseFrameResult<Settings> {
+        let mut settings = *self;
+        for setting in SettingsFrame::parse_payload(payload)? {
+            settings.apply(setting);
+        }
+        Ok(settings)
+    }
+
+    /// `SETTINGS` frame containing all settings, fails if any value is invalid.
+    pub fn to_frame(&self) -> ParseFrameResult<SettingsFrame> {
+        self.validate()?;
+        Ok(SettingsFrame::from_settings(self.to_settings()))
+    }
 }
 
 /// An enum representing the flags that a `SettingsFrame` can have.
@@ -191,7 +253,7 @@ impl Flag for SettingsFlag {
     }
 
     fn flags() -> &'static [Self] {
-        static FLAGS: &'static [SettingsFlag] = &[SettingsFlag::Ack];
+        static FLAGS: &[SettingsFlag] = &[SettingsFlag::Ack];
         FLAGS
     }
 }
@@ -210,9 +272,8 @@ impl Flag for SettingsFlag {
 /// - Payload length not a multiple of 6
 /// - Stream ID not zero (SETTINGS frames MUST be associated to stream 0)
 ///
-/// What is *not* treated as an error (for now) are settings values out of
-/// allowed bounds such as a EnablePush being set to something other than 0 or
-/// 1.
+/// - Setting value out of allowed bounds, e. g. EnablePush being set to something
+///   other than 0 or 1 (unknown settings are ignored)
 #[derive(PartialEq, Debug, Clone)]
 pub struct SettingsFrame {
     /// Contains all the settings that are currently set in the frame. It is
@@ -224,6 +285,12 @@ pub struct SettingsFrame {
     flags: Flags<SettingsFlag>,
 }
 
+impl Default for SettingsFrame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SettingsFrame {
     /// Creates a new empty `SettingsFrame`
     pub fn new() -> SettingsFrame {
@@ -283,10 +350,12 @@ impl SettingsFrame {
     /// Any unknown setting is ignored, as per the HTTP/2 spec requirement.
     ///
     /// If the frame is invalid (i.e. the length of the payload is not a
-    /// multiple of 6) it returns `None`.
+    /// multiple of 6) it returns an error.
     fn parse_payload(payload: &[u8]) -> ParseFrameResult<Vec<HttpSetting>> {
-        if payload.len() % 6 != 0 {
-            return Err(ParseFrameError::ProtocolError);
+        if !payload.len().is_multiple_of(6) {
+            // 6.5. A SETTINGS frame with a length other than a multiple of 6 octets
+            // MUST be treated as a connection error of type FRAME_SIZE_ERROR.
+            return Err(ParseFrameError::IncorrectFrameLength(payload.len() as u32));
         }
 
         // Iterates through chunks of the raw payload of size 6 bytes and
@@ -352,8 +421,9 @@ impl Frame for SettingsFrame {
                     flags: Flags::new(flags),
                 })
             } else {
-                // The SETTINGS flag MUST not have a payload if Ack is set
-                Err(ParseFrameError::ProtocolError)
+                // The SETTINGS flag MUST not have a payload if Ack is set,
+                // it is a connection error of type FRAME_SIZE_ERROR
+                Err(ParseFrameError::IncorrectFrameLength(payload_len))
             };
         }
 
@@ -388,6 +458,12 @@ impl Frame for SettingsFrame {
 }
 
 impl FrameIR for SettingsFrame {
+    fn serialize_into(self, b: &mut WriteBuffer) {
+        (&self).serialize_into(b)
+    }
+}
+
+impl FrameIR for &SettingsFrame {
     fn serialize_into(self, b: &mut WriteBuffer) {
         b.write_header(self.get_header());
         for setting in &self.settings {
@@ -400,8 +476,11 @@ impl FrameIR for SettingsFrame {
 mod tests {
     use super::{HttpSetting, SettingsFrame};
     use crate::solicit::frame::FrameIR;
+    use crate::solicit::frame::ParseFrameError;
     use crate::solicit::frame::{pack_header, Frame, FrameHeader};
     use crate::solicit::tests::common::raw_frame_from_parts;
+    use crate::solicit::DEFAULT_SETTINGS;
+    use crate::ErrorCode;
 
     /// Tests that a `SettingsFrame` correctly handles a SETTINGS frame with
     /// no ACK flag and only a single setting.
@@ -411,7 +490,7 @@ mod tests {
         // A header with the flag indicating no padding
         let header = FrameHeader::new(payload.len() as u32, 4, 0, 0);
 
-        let raw = raw_frame_from_parts(header.clone(), payload.to_vec());
+        let raw = raw_frame_from_parts(header, payload.to_vec());
         let frame: SettingsFrame = Frame::from_raw(&raw).unwrap();
 
         // The frame correctly interprets the settings?
@@ -432,14 +511,14 @@ mod tests {
         let payload = {
             let mut res: Vec<u8> = Vec::new();
             for s in settings.iter().map(|s| s.serialize()) {
-                res.extend(s.to_vec().into_iter());
+                res.extend(s.to_vec());
             }
 
             res
         };
         let header = FrameHeader::new(payload.len() as u32, 4, 0, 0);
 
-        let raw = raw_frame_from_parts(header.clone(), payload.to_vec());
+        let raw = raw_frame_from_parts(header, payload.to_vec());
         let frame: SettingsFrame = Frame::from_raw(&raw).unwrap();
 
         // The frame correctly interprets the settings?
@@ -462,14 +541,14 @@ mod tests {
         let payload = {
             let mut res: Vec<u8> = Vec::new();
             for s in settings.iter().map(|s| s.serialize()) {
-                res.extend(s.to_vec().into_iter());
+                res.extend(s.to_vec());
             }
 
             res
         };
         let header = FrameHeader::new(payload.len() as u32, 4, 0, 0);
 
-        let raw = raw_frame_from_parts(header.clone(), payload.to_vec());
+        let raw = raw_frame_from_parts(header, payload.to_vec());
         let frame: SettingsFrame = Frame::from_raw(&raw).unwrap();
 
         // All the settings are returned, even the duplicates
@@ -484,25 +563,25 @@ mod tests {
     /// simply ignored.
     #[test]
     fn test_settings_frame_parse_no_ack_unknown_setting() {
-        let settings = vec![
+        let settings = [
             HttpSetting::HeaderTableSize(1),
             HttpSetting::MaxHeaderListSize(5),
         ];
         let payload = {
             let mut res: Vec<u8> = Vec::new();
             for s in settings.iter().map(|s| s.serialize()) {
-                res.extend(s.to_vec().into_iter());
+                res.extend(s.to_vec());
             }
-            res.extend(vec![0, 10, 0, 0, 0, 0].into_iter());
+            res.extend(vec![0, 10, 0, 0, 0, 0]);
             for s in settings.iter().map(|s| s.serialize()) {
-                res.extend(s.to_vec().into_iter());
+                res.extend(s.to_vec());
             }
 
             res
         };
         let header = FrameHeader::new(payload.len() as u32, 4, 0, 0);
 
-        let raw = raw_frame_from_parts(header.clone(), payload.to_vec());
+        let raw = raw_frame_from_parts(header, payload.to_vec());
         let frame: SettingsFrame = Frame::from_raw(&raw).unwrap();
 
         // All the settings are returned twice, but the unkown isn't found in
@@ -526,7 +605,7 @@ mod tests {
             stream_id: 0,
         };
 
-        let raw = raw_frame_from_parts(header.clone(), payload.to_vec());
+        let raw = raw_frame_from_parts(header, payload.to_vec());
         let frame: SettingsFrame = Frame::from_raw(&raw).unwrap();
 
         // No settings there?
@@ -546,7 +625,7 @@ mod tests {
         let payload = {
             let mut res: Vec<u8> = Vec::new();
             for s in settings.iter().map(|s| s.serialize()) {
-                res.extend(s.to_vec().into_iter());
+                res.extend(s.to_vec());
             }
 
             res
@@ -602,15 +681,9 @@ mod tests {
                     flags: 0,
                     stream_id: 0,
                 })
-                .to_vec()
-                .into_iter(),
-            );
-            res.extend(
-                HttpSetting::EnablePush(false)
-                    .serialize()
-                    .to_vec()
-                    .into_iter(),
+                .to_vec(),
             );
+            res.extend(HttpSetting::EnablePush(false).serialize().to_vec());
 
             res
         };
@@ -636,21 +709,10 @@ mod tests {
                     flags: 0,
                     stream_id: 0,
                 })
-                .to_vec()
-                .into_iter(),
-            );
-            res.extend(
-                HttpSetting::EnablePush(false)
-                    .serialize()
-                    .to_vec()
-                    .into_iter(),
-            );
-            res.extend(
-                HttpSetting::MaxHeaderListSize(0)
-                    .serialize()
-                    .to_vec()
-                    .into_iter(),
+                .to_vec(),
             );
+            res.extend(HttpSetting::EnablePush(false).serialize().to_vec());
+            res.extend(HttpSetting::MaxHeaderListSize(0).serialize().to_vec());
 
             res
         };
@@ -780,4 +842,107 @@ mod tests {
             assert_eq!(buf, setting.serialize());
         }
     }
+
+    #[test]
+    fn test_setting_validate() {
+        assert!(HttpSetting::from_id(2, 1).unwrap().is_some());
+        match HttpSetting::from_id(2, 2) {
+            Err(ParseFrameError::IncorrectSettingsPushValue(2)) => {}
+            r => panic!("{:?}", r),
+        }
+
+        assert!(HttpSetting::from_id(4, 0x7fffffff).unwrap().is_some());
+        match HttpSetting::from_id(4, 0x80000000) {
+            Err(e @ ParseFrameError::IncorrectSettingsInitialWindowSize(0x80000000)) => {
+                assert_eq!(ErrorCode::FlowControlError, e.error_code());
+            }
+            r => panic!("{:?}", r),
+        }
+
+        assert!(HttpSetting::from_id(5, 16384).unwrap().is_some());
+        assert!(HttpSetting::from_id(5, 16777215).unwrap().is_some());
+        for &val in &[16383, 16777216] {
+            match HttpSetting::from_id(5, val) {
+                Err(e @ ParseFrameError::IncorrectSettingsMaxFrameSize(_)) => {
+                    assert_eq!(ErrorCode::ProtocolError, e.error_code());
+                }
+                r => panic!("{:?}", r),
+            }
+        }
+
+        assert_eq!(
+            Ok(Some(HttpSetting::EnableConnectProtocol(true))),
+            HttpSetting::from_id(8, 1)
+        );
+        match HttpSetting::from_id(8, 2) {
+            Err(ParseFrameError::IncorrectSettingsEnableConnectProtocolValue(2)) => {}
+            r => panic!("{:?}", r),
+        }
+
+        // unknown setting is ignored
+        assert!(HttpSetting::from_id(0xff, 0xffffffff).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_settings_parse_serialize() {
+        let mut settings = DEFAULT_SETTINGS;
+        settings.enable_push = false;
+        settings.max_frame_size = 20000;
+        settings.initial_window_size = 100;
+
+        let frame = settings.to_frame().unwrap();
+        let mut raw = frame.serialize_into_vec();
+        // unknown setting
+        raw.extend(&[0, 0x10, 0, 0, 0, 1]);
+
+        let parsed = DEFAULT_SETTINGS.parse_payload(&raw[9..]).unwrap();
+        assert_eq!(settings, parsed);
+
+        settings.max_frame_size = 100;
+        assert_eq!(
+            Err(ParseFrameError::IncorrectSettingsMaxFrameSize(100)),
+            settings.to_frame()
+        );
+
+        settings.max_frame_size = 16384;
+        settings.initial_window_size = 0x80000000;
+        assert_eq!(
+            Err(ParseFrameError::IncorrectSettingsInitialWindowSize(
+                0x80000000
+            )),
+            settings.validate()
+        );
+
+        // ENABLE_PUSH
+        assert_eq!(
+            Err(ParseFrameError::IncorrectSettingsPushValue(2)),
+            DEFAULT_SETTINGS.parse_payload(&[0, 2, 0, 0, 0, 2])
+        );
+    }
+
+    #[test]
+    fn test_settings_frame_parse_length_not_multiple_of_six_is_frame_size_error() {
+        for &len in &[5, 7] {
+            let payload = vec![0; len];
+            let header = FrameHeader::new(payload.len() as u32, 4, 0, 0);
+
+            let raw = raw_frame_from_parts(header, payload);
+            match SettingsFrame::from_raw(&raw) {
+                Err(e) => assert_eq!(ErrorCode::FrameSizeError, e.error_code()),
+                Ok(f) => panic!("{:?}", f),
+            }
+        }
+    }
+
+    #[test]
+    fn test_settings_frame_parse_ack_with_payload_is_frame_size_error() {
+        let payload = vec![0; 6];
+        let header = FrameHeader::new(payload.len() as u32, 4, 1, 0);
+
+        let raw = raw_frame_from_parts(header, payload);
+        match SettingsFrame::from_raw(&raw) {
+            Err(e) => assert_eq!(ErrorCode::FrameSizeError, e.error_code()),
+            Ok(f) => panic!("{:?}", f),
+        }
+    }
 }
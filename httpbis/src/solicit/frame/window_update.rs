@@ -33,7 +33,7 @@ impl WindowUpdateFrame {
     pub fn for_connection(increment: u32) -> WindowUpdateFrame {
         WindowUpdateFrame {
             stream_id: 0,
-            increment: increment,
+            increment,
             flags: Flags::default(),
         }
     }
@@ -42,8 +42,8 @@ impl WindowUpdateFrame {
     /// given increment.
     pub fn for_stream(stream_id: StreamId, increment: u32) -> WindowUpdateFrame {
         WindowUpdateFrame {
-            stream_id: stream_id,
-            increment: increment,
+            stream_id,
+            increment,
             flags: Flags::default(),
         }
     }
@@ -76,7 +76,9 @@ impl Frame for WindowUpdateFrame {
         // sender can transmit in addition to the existing flow-control window.
         // The legal range for the increment to the flow-control window is 1 to
         // 2^31-1 (2,147,483,647) octets.
-        if increment < 1 || increment > 0x7fffffff {
+        // Zero increment is parsed: it is a stream error on a stream
+        // and a connection error on stream 0, so it is handled by connection.
+        if increment > 0x7fffffff {
             return Err(ParseFrameError::WindowUpdateIncrementInvalid(increment));
         }
 
@@ -105,6 +107,12 @@ impl Frame for WindowUpdateFrame {
 }
 
 impl FrameIR for WindowUpdateFrame {
+    fn serialize_into(self, builder: &mut WriteBuffer) {
+        (&self).serialize_into(builder)
+    }
+}
+
+impl FrameIR for &WindowUpdateFrame {
     fn serialize_into(self, builder: &mut WriteBuffer) {
         builder.write_header(self.get_header());
         builder.write_u32(self.increment);
@@ -145,6 +153,13 @@ mod tests {
         assert_eq!(frame.get_stream_id(), 1);
     }
 
+    #[test]
+    fn test_parse_zero_increment() {
+        let raw = raw_frame_from_parts(FrameHeader::new(4, 0x8, 0, 1), vec![0x80, 0, 0, 0]);
+        let frame = WindowUpdateFrame::from_raw(&raw).expect("expected parsed WINDOW_UPDATE");
+        assert_eq!(frame.increment, 0);
+    }
+
     #[test]
     fn test_serialize_connection_level() {
         let frame = WindowUpdateFrame::for_connection(10);
@@ -0,0 +1,143 @@
+use bytes::Bytes;
+
+use crate::solicit::header::name::HeaderName;
+use crate::solicit::header::name::PseudoHeaderName;
+use crate::solicit::header::Header;
+use crate::solicit::header::HeaderError;
+use crate::solicit::header::HeaderResult;
+use crate::solicit::header::HeaderValue;
+use crate::solicit::header::Headers;
+
+/// Fluent `Headers` constructor.
+///
+/// Pseudo-headers must be added before regular header fields,
+/// the first misplaced or invalid header is reported by `build`.
+///
+/// # Examples
+///
+/// ```
+/// # use httpbis::*;
+/// let headers = Headers::builder()
+///     .pseudo(":status", "200")
+///     .field("content-type", "application/json")
+///     .build()
+///     .unwrap();
+/// assert_eq!(200, headers.status());
+/// ```
+#[derive(Default, Debug)]
+pub struct HeadersBuilder {
+    headers: Headers,
+    error: Option<HeaderError>,
+}
+
+impl HeadersBuilder {
+    /// Construct empty builder.
+    pub fn new() -> HeadersBuilder {
+        Default::default()
+    }
+
+    fn add(mut self, name: HeaderResult<HeaderName>, value: &[u8]) -> HeadersBuilder {
+        if self.error.is_some() {
+            return self;
+        }
+        let value = HeaderValue::from_bytes(Bytes::copy_from_slice(value)).map_err(|(e, _)| e);
+        match (name, value) {
+            (Ok(name), Ok(value)) => self.headers.add_header(Header::new(name, value)),
+            (Err(e), _) | (_, Err(e)) => self.error = Some(e),
+        }
+        self
+    }
+
+    /// Add a pseudo-header, e.g. `:status`.
+    pub fn pseudo(self, name: &str, value: impl AsRef<[u8]>) -> HeadersBuilder {
+        let name = match PseudoHeaderName::parse(name.as_bytes()) {
+            Ok(_) if self.headers.pseudo_count != self.headers.headers.len() => {
+                Err(HeaderError::PseudoHeadersAfterRegularHeaders)
+            }
+            Ok(name) => Ok(HeaderName::pseudo(name)),
+            Err(e) => Err(e),
+        };
+        self.add(name, value.as_ref())
+    }
+
+    /// Add a regular header field, name is converted to lower case.
+    pub fn field(self, name: &str, value: impl AsRef<[u8]>) -> HeadersBuilder {
+        let name = match HeaderName::from_bytes_lowercasing(Bytes::copy_from_slice(name.as_bytes()))
+        {
+            Ok(name) if name.is_pseudo() => Err(HeaderError::UnexpectedPseudoHeader(
+                name.pseudo_header_name().unwrap(),
+            )),
+            Ok(name) => Ok(name),
+            Err((e, _)) => Err(e),
+        };
+        self.add(name, value.as_ref())
+    }
+
+    /// Finish building, returning the first error encountered.
+    pub fn build(self) -> HeaderResult<Headers> {
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(self.headers),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn build() {
+        let headers = HeadersBuilder::new()
+            .pseudo(":status", "200")
+            .field("Content-Type", "application/json")
+            .build()
+            .unwrap();
+        assert_eq!(200, headers.status());
+        assert_eq!(Some("application/json"), headers.get_opt("content-type"));
+    }
+
+    #[test]
+    fn misplaced() {
+        assert_eq!(
+            Err(HeaderError::PseudoHeadersAfterRegularHeaders),
+            HeadersBuilder::new()
+                .field("content-type", "text/plain")
+                .pseudo(":status", "200")
+                .build()
+        );
+        assert_eq!(
+            Err(HeaderError::UnexpectedPseudoHeader(PseudoHeaderName::Path)),
+            HeadersBuilder::new().field(":path", "/").build()
+        );
+        assert_eq!(
+            Err(HeaderError::UnknownPseudoHeader),
+            HeadersBuilder::new()
+                .pseudo("content-type", "text/plain")
+                .build()
+        );
+    }
+
+    #[test]
+    fn invalid_value() {
+        assert_eq!(
+            Err(HeaderError::ObsFold),
+            HeadersBuilder::new().field("x-a", "a\r\n b").build()
+        );
+        assert_eq!(
+            Err(HeaderError::IncorrectCharInValue(1)),
+            HeadersBuilder::new()
+                .pseudo(":path", b"/\0".as_ref())
+                .build()
+        );
+        // First error is reported
+        assert_eq!(
+            Err(HeaderError::IncorrectCharInValue(0)),
+            HeadersBuilder::new()
+                .field("x-a", "\0")
+                .field("x-b", "b")
+                .field("x-c", "\r")
+                .build()
+        );
+    }
+}
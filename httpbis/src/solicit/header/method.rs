@@ -1,10 +1,6 @@
-use bytes::Bytes;
-
 use crate::HeaderValue;
 
 /// String `"GET"`.
-pub const METHOD_GET: HeaderValue =
-    unsafe { HeaderValue::from_bytes_unchecked(Bytes::from_static(b"GET")) };
+pub const METHOD_GET: HeaderValue = HeaderValue::from_static("GET");
 /// String `"POST"`.
-pub const METHOD_POST: HeaderValue =
-    unsafe { HeaderValue::from_bytes_unchecked(Bytes::from_static(b"POST")) };
+pub const METHOD_POST: HeaderValue = HeaderValue::from_static("POST");
@@ -1,10 +1,13 @@
 //! HTTP/2 headers model.
 
 use std::fmt;
+use std::hash::Hash;
+use std::hash::Hasher;
 use std::iter::FromIterator;
 use std::result;
 use std::str;
 use std::str::FromStr;
+use std::time::Duration;
 
 use crate::headers_place::HeadersPlace;
 use crate::req_resp::RequestOrResponse;
@@ -13,6 +16,7 @@ use crate::assert_types::*;
 
 use bytes::Bytes;
 
+use crate::solicit::header::builder::HeadersBuilder;
 use crate::solicit::header::method::METHOD_GET;
 use crate::solicit::header::method::METHOD_POST;
 use crate::solicit::header::name::HeaderName;
@@ -21,17 +25,36 @@ use crate::solicit::header::name::PseudoHeaderNameSet;
 use crate::solicit::header::status::status_to_header_value;
 use crate::HeaderValue;
 
+pub(crate) mod builder;
 pub(crate) mod method;
 pub(crate) mod name;
 pub(crate) mod status;
 pub(crate) mod value;
 
 /// HTTP/2 header, regular or pseudo-header
-#[derive(PartialEq, Eq, Hash, Clone)]
+#[derive(Clone)]
 pub struct Header {
     name: HeaderName,
     /// Header value.
     pub value: HeaderValue,
+    /// Encode with HPACK never indexed literal representation.
+    sensitive: bool,
+}
+
+// Sensitivity is an encoding hint, it does not participate in comparison.
+impl PartialEq for Header {
+    fn eq(&self, other: &Header) -> bool {
+        self.name == other.name && self.value == other.value
+    }
+}
+
+impl Eq for Header {}
+
+impl Hash for Header {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        self.value.hash(state);
+    }
 }
 
 impl fmt::Debug for Header {
@@ -49,7 +72,7 @@ fn _assert_header_sync_send() {
 }
 
 /// Header error.
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 pub enum HeaderError {
     /// Unknown pseudo-header.
     UnknownPseudoHeader,
@@ -59,8 +82,12 @@ pub enum HeaderError {
     EmptyValue(PseudoHeaderName),
     /// Incorrect character in header name.
     IncorrectCharInName,
-    /// Incorrect character in header value.
-    IncorrectCharInValue,
+    /// Uppercase character in header name.
+    UppercaseHeaderName,
+    /// Incorrect character in header value at given zero-based offset.
+    IncorrectCharInValue(usize),
+    /// CR or LF in header value (possibly obsolete line folding).
+    ObsFold,
     /// Header name is not ASCII.
     HeaderNameNotAscii,
     /// Header value is not ASCII.
@@ -79,11 +106,31 @@ pub enum HeaderError {
     ConnectionSpecificHeader(&'static str),
     /// RE can only contain trailers.
     TeCanOnlyContainTrailer,
+    /// Header value is not a decimal number.
+    InvalidNumber,
+    /// Multiple `content-length` headers with different values.
+    ConflictingContentLength,
+    /// `:status` is not a three digit code in 100..=599.
+    InvalidStatus,
+    /// `:method` is not a token.
+    InvalidMethod,
+    /// `:path` contains whitespace.
+    InvalidPath,
 }
 
 /// Type alias.
 pub type HeaderResult<T> = result::Result<T, HeaderError>;
 
+fn parse_status(value: &HeaderValue) -> HeaderResult<u32> {
+    if value.as_slice().len() != 3 {
+        return Err(HeaderError::InvalidStatus);
+    }
+    match value.parse_u64() {
+        Ok(status @ 100..=599) => Ok(status as u32),
+        _ => Err(HeaderError::InvalidStatus),
+    }
+}
+
 impl Header {
     /// Create a new `Header` object with exact values of `name` and `value`.
     ///
@@ -91,9 +138,11 @@ impl Header {
     /// header name must be lower case.
     pub fn new_validate(name: Bytes, value: Bytes) -> HeaderResult<Header> {
         let name = HeaderName::new_validate(name).map_err(|(e, _)| e)?;
+        let value = HeaderValue::from_bytes(value).map_err(|(e, _)| e)?;
         Ok(Header {
             name,
-            value: HeaderValue::from(value),
+            value,
+            sensitive: false,
         })
     }
 
@@ -107,6 +156,7 @@ impl Header {
         Header {
             name: name.into(),
             value: value.into(),
+            sensitive: false,
         }
     }
 
@@ -139,13 +189,29 @@ impl Header {
         self.value.as_slice()
     }
 
+    /// Mark header as sensitive: it will be encoded with HPACK never indexed
+    /// literal representation and never added to the dynamic table.
+    ///
+    /// Headers received with never indexed representation are marked
+    /// sensitive, so proxies forwarding them preserve the representation.
+    pub fn set_sensitive(&mut self, sensitive: bool) {
+        self.sensitive = sensitive;
+    }
+
+    /// Is this header sensitive?
+    pub fn is_sensitive(&self) -> bool {
+        self.sensitive
+    }
+
+    /// Size of the header entry as defined for `SETTINGS_MAX_HEADER_LIST_SIZE`:
+    /// uncompressed name and value lengths plus an overhead of 32 octets.
+    pub fn list_size(&self) -> usize {
+        self.name().len() + self.value.as_slice().len() + 32
+    }
+
     /// name: value
     pub fn format(&self) -> String {
-        format!(
-            "{}: {}",
-            self.name(),
-            String::from_utf8_lossy(self.value.as_slice())
-        )
+        format!("{}: {}", self.name(), self.value)
     }
 
     /// Header is pseudo header?
@@ -199,6 +265,11 @@ impl Headers {
         Default::default()
     }
 
+    /// Construct headers with `HeadersBuilder`
+    pub fn builder() -> HeadersBuilder {
+        HeadersBuilder::new()
+    }
+
     /// Construct headers from a vec of individual headers
     pub fn from_vec(mut headers: Vec<Header>) -> Headers {
         headers.sort_by_key(|h| !h.is_preudo_header());
@@ -210,27 +281,41 @@ impl Headers {
     }
 
     pub(crate) fn from_vec_pseudo_first(headers: Vec<Header>) -> Result<Headers, HeaderError> {
+        let pseudo_count = headers.iter().filter(|h| h.is_preudo_header()).count();
+        let headers = Headers {
+            headers,
+            pseudo_count,
+        };
+        headers.validate_pseudo_ordering()?;
+        Ok(headers)
+    }
+
+    /// Check that all pseudo-headers precede regular headers.
+    ///
+    /// Unknown pseudo-headers cannot be present, because they are rejected
+    /// on `HeaderName` construction.
+    pub fn validate_pseudo_ordering(&self) -> Result<(), HeaderError> {
+        // All pseudo-header fields MUST appear in the header block before
+        // regular header fields. Any request or response that contains
+        // a pseudo-header field that appears in a header block after
+        // a regular header field MUST be treated as malformed (Section 8.1.2.6).
         let mut saw_regular_header = false;
-        let mut pseudo_count = 0;
-        for header in &headers {
+        for header in &self.headers {
             if header.is_preudo_header() {
                 if saw_regular_header {
                     return Err(HeaderError::PseudoHeadersAfterRegularHeaders);
                 }
-                pseudo_count += 1;
             } else {
                 saw_regular_header = true;
             }
         }
-        return Ok(Headers {
-            headers,
-            pseudo_count,
-        });
+        Ok(())
     }
 
     /// Return an iterator over headers.
     ///
-    /// Pseudo headers returned first.
+    /// Pseudo headers returned first, regular headers are returned
+    /// in insertion order, duplicates included.
     pub fn iter(&self) -> impl Iterator<Item = &Header> {
         self.headers.iter()
     }
@@ -243,12 +328,22 @@ impl Headers {
         &self.headers[self.pseudo_count..]
     }
 
+    /// Uncompressed header list size (RFC 7540 section 6.5.2).
+    pub fn list_size(&self) -> usize {
+        self.headers.iter().map(Header::list_size).sum()
+    }
+
+    /// Header list size does not exceed peer's `SETTINGS_MAX_HEADER_LIST_SIZE`.
+    pub fn fits_within(&self, max_list_size: u32) -> bool {
+        self.list_size() as u64 <= max_list_size as u64
+    }
+
     /// Dump all headers as multiline string.
     pub fn dump(&self) -> String {
         let mut r = String::new();
         for h in &self.headers {
             r.push_str(&h.format());
-            r.push_str("\n");
+            r.push('\n');
         }
         r
     }
@@ -290,6 +385,19 @@ impl Headers {
         headers
     }
 
+    /// Validate outgoing trailers: no pseudo-headers, names are lowercase.
+    pub(crate) fn validate_trailers(&self) -> HeaderResult<()> {
+        if !self.pseudo_headers().is_empty() {
+            return Err(HeaderError::PseudoHeadersInTrailers);
+        }
+        for header in self.regular_headers() {
+            if header.name().bytes().any(|b| b.is_ascii_uppercase()) {
+                return Err(HeaderError::UppercaseHeaderName);
+            }
+        }
+        Ok(())
+    }
+
     pub(crate) fn validate(
         &self,
         req_or_resp: RequestOrResponse,
@@ -312,10 +420,26 @@ impl Headers {
                 return Err(HeaderError::MoreThanOnePseudoHeader(header_name));
             }
 
+            if header_name == PseudoHeaderName::Method && !header.value.as_ref_view().is_token() {
+                return Err(HeaderError::InvalidMethod);
+            }
+
             if header_name == PseudoHeaderName::Path {
                 if header.value.as_slice().is_empty() {
                     return Err(HeaderError::EmptyValue(header_name));
                 }
+                if header
+                    .value
+                    .as_slice()
+                    .iter()
+                    .any(|b| b.is_ascii_whitespace())
+                {
+                    return Err(HeaderError::InvalidPath);
+                }
+            }
+
+            if header_name == PseudoHeaderName::Status {
+                parse_status(&header.value)?;
             }
         }
 
@@ -325,7 +449,44 @@ impl Headers {
         }
 
         if headers_place == HeadersPlace::Initial {
+            self.parse_content_length()?;
+
+            let connect = req_or_resp == RequestOrResponse::Request
+                && self.get_opt(":method") == Some("CONNECT");
+            let extended_connect = pseudo_headers_met.contains(PseudoHeaderName::Protocol);
+
+            // On requests that contain the ":protocol" pseudo-header field,
+            // the ":method" pseudo-header field MUST be CONNECT (RFC 8441 section 4).
+            if extended_connect && !connect {
+                return Err(HeaderError::UnexpectedPseudoHeader(
+                    PseudoHeaderName::Protocol,
+                ));
+            }
+
+            if connect && !extended_connect {
+                // The ":scheme" and ":path" pseudo-header fields MUST be omitted
+                // (Section 8.3).
+                for &omitted in &[PseudoHeaderName::Scheme, PseudoHeaderName::Path] {
+                    if pseudo_headers_met.contains(omitted) {
+                        return Err(HeaderError::UnexpectedPseudoHeader(omitted));
+                    }
+                }
+            }
+
             let required_headers = match req_or_resp {
+                // The ":authority" pseudo-header field MUST be present
+                // in a CONNECT request (Section 8.3).
+                RequestOrResponse::Request if connect && !extended_connect => {
+                    &[PseudoHeaderName::Method, PseudoHeaderName::Authority][..]
+                }
+                // Extended CONNECT requests MUST include ":scheme", ":path"
+                // and ":authority" pseudo-header fields (RFC 8441 section 4).
+                RequestOrResponse::Request if extended_connect => &[
+                    PseudoHeaderName::Method,
+                    PseudoHeaderName::Scheme,
+                    PseudoHeaderName::Authority,
+                    PseudoHeaderName::Path,
+                ][..],
                 // All HTTP/2 requests MUST include exactly one valid value for the
                 // ":method", ":scheme", and ":path" pseudo-header fields, unless it is
                 // a CONNECT request (Section 8.3).  An HTTP request that omits
@@ -354,17 +515,38 @@ impl Headers {
 
     /// Lookup header.
     pub fn get_opt<'a>(&'a self, name: &str) -> Option<&'a str> {
-        let headers = if name.starts_with(':') {
-            self.pseudo_headers()
-        } else {
-            self.regular_headers()
-        };
-        headers
+        self.headers_for_name(name)
             .iter()
             .find(|h| h.name() == name)
             .and_then(|h| str::from_utf8(h.value()).ok())
     }
 
+    /// Lookup header by case-insensitive name.
+    ///
+    /// Header names are stored lowercase, so `"Content-Type"` finds `content-type`.
+    pub fn get_ci(&self, name: &str) -> Option<&HeaderValue> {
+        self.headers_for_name(name)
+            .iter()
+            .find(|h| h.name().eq_ignore_ascii_case(name))
+            .map(|h| &h.value)
+    }
+
+    /// Lookup all values of header by case-insensitive name.
+    pub fn get_all_ci<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a HeaderValue> {
+        self.headers_for_name(name)
+            .iter()
+            .filter(move |h| h.name().eq_ignore_ascii_case(name))
+            .map(|h| &h.value)
+    }
+
+    fn headers_for_name(&self, name: &str) -> &[Header] {
+        if name.starts_with(':') {
+            self.pseudo_headers()
+        } else {
+            self.regular_headers()
+        }
+    }
+
     /// Lookup header.
     ///
     /// # Panics
@@ -396,14 +578,38 @@ impl Headers {
     }
 
     /// Content-length header.
+    ///
+    /// `None` if header is absent, invalid or conflicting.
     pub fn content_length(&self) -> Option<u64> {
-        match self.get_opt("content-length") {
-            Some(v) => v.parse().ok(),
-            None => None,
+        self.parse_content_length().ok().and_then(|l| l)
+    }
+
+    /// Parse `content-length` header.
+    ///
+    /// Repeated headers with the same value are accepted,
+    /// different values are an error (RFC 7230 section 3.3.2).
+    pub fn parse_content_length(&self) -> HeaderResult<Option<u64>> {
+        let mut r = None;
+        for value in self.get_all_ci("content-length") {
+            let length = value.parse_u64()?;
+            match r {
+                Some(prev) if prev != length => return Err(HeaderError::ConflictingContentLength),
+                _ => r = Some(length),
+            }
         }
+        Ok(r)
+    }
+
+    /// Parse `grpc-timeout` header, `None` if header is absent or malformed.
+    pub fn grpc_timeout(&self) -> Option<Duration> {
+        self.get_opt("grpc-timeout").and_then(parse_grpc_timeout)
     }
 
     /// Add a header
+    ///
+    /// # Panics
+    ///
+    /// If name or value is not valid, use `HeadersBuilder` to handle invalid headers.
     pub fn add(&mut self, name: impl Into<HeaderName>, value: impl Into<HeaderValue>) {
         self.add_header(Header::new(name, value));
     }
@@ -428,16 +634,56 @@ impl Headers {
     }
 }
 
+/// Parse `grpc-timeout` value: up to 8 ASCII digits followed by unit.
+fn parse_grpc_timeout(value: &str) -> Option<Duration> {
+    if value.len() < 2 || value.len() > 9 || !value.is_ascii() {
+        return None;
+    }
+    let (digits, unit) = value.split_at(value.len() - 1);
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let n: u64 = digits.parse().ok()?;
+    Some(match unit {
+        "H" => Duration::from_secs(n * 60 * 60),
+        "M" => Duration::from_secs(n * 60),
+        "S" => Duration::from_secs(n),
+        "m" => Duration::from_millis(n),
+        "u" => Duration::from_micros(n),
+        "n" => Duration::from_nanos(n),
+        _ => return None,
+    })
+}
+
 impl FromIterator<Header> for Headers {
     fn from_iter<T: IntoIterator<Item = Header>>(iter: T) -> Headers {
         Headers::from_vec(iter.into_iter().collect())
     }
 }
 
+/// Collect headers from name/value pairs.
+///
+/// # Panics
+///
+/// If name or value is not valid, like `Headers::add`.
+impl<N: Into<HeaderName>, V: Into<HeaderValue>> FromIterator<(N, V)> for Headers {
+    fn from_iter<T: IntoIterator<Item = (N, V)>>(iter: T) -> Headers {
+        iter.into_iter().map(Header::from).collect()
+    }
+}
+
 #[cfg(test)]
 mod test {
+    use std::time::Duration;
 
+    use bytes::Bytes;
+
+    use crate::headers_place::HeadersPlace;
+    use crate::req_resp::RequestOrResponse;
     use crate::solicit::header::Header;
+    use crate::solicit::header::HeaderError;
+    use crate::solicit::header::Headers;
+    use crate::solicit::header::PseudoHeaderName;
 
     #[test]
     fn test_partial_eq_of_headers() {
@@ -461,4 +707,274 @@ mod test {
             format!("{:?}", Header::new(&b":method"[..], &b"\t"[..]))
         );
     }
+
+    #[test]
+    fn new_validate() {
+        let header = Header::new_validate(
+            Bytes::from_static(b"content-type"),
+            Bytes::from_static(b"text/plain"),
+        )
+        .unwrap();
+        assert_eq!(b"text/plain", header.value());
+
+        match Header::new_validate(Bytes::from_static(b"x"), Bytes::from_static(b"a\r\nb")) {
+            Err(HeaderError::ObsFold) => {}
+            r => panic!("{:?}", r),
+        }
+        match Header::new_validate(Bytes::from_static(b"x"), Bytes::from_static(b"a\x01b")) {
+            Err(HeaderError::IncorrectCharInValue(1)) => {}
+            r => panic!("{:?}", r),
+        }
+    }
+
+    #[test]
+    fn validate_pseudo_ordering() {
+        let misordered = vec![
+            Header::new("content-type", "text/plain"),
+            Header::new(":method", "GET"),
+        ];
+        match Headers::from_vec_pseudo_first(misordered) {
+            Err(HeaderError::PseudoHeadersAfterRegularHeaders) => {}
+            r => panic!("{:?}", r),
+        }
+
+        let headers = Headers::from_vec_pseudo_first(vec![
+            Header::new(":method", "GET"),
+            Header::new(":path", "/"),
+            Header::new("content-type", "text/plain"),
+        ])
+        .unwrap();
+        assert_eq!(2, headers.pseudo_count);
+        headers.validate_pseudo_ordering().unwrap();
+
+        // `from_vec` reorders
+        Headers::from_vec(vec![
+            Header::new("content-type", "text/plain"),
+            Header::new(":method", "GET"),
+        ])
+        .validate_pseudo_ordering()
+        .unwrap();
+    }
+
+    #[test]
+    fn validate_trailers() {
+        let mut trailers = Headers::new();
+        trailers.add("grpc-status", "0");
+        trailers.validate_trailers().unwrap();
+
+        match Headers::new_status(200).validate_trailers() {
+            Err(HeaderError::PseudoHeadersInTrailers) => {}
+            r => panic!("{:?}", r),
+        }
+    }
+
+    #[test]
+    fn get_ci() {
+        let mut headers = Headers::new_status(200);
+        headers.add("content-type", "text/plain");
+        headers.add("vary", "accept");
+        headers.add("vary", "accept-encoding");
+
+        assert_eq!(
+            Some(b"text/plain".as_ref()),
+            headers.get_ci("Content-Type").map(|v| v.as_slice())
+        );
+        assert_eq!(
+            Some(b"200".as_ref()),
+            headers.get_ci(":status").map(|v| v.as_slice())
+        );
+        assert!(headers.get_ci("x-missing").is_none());
+        assert_eq!(
+            vec![b"accept".as_ref(), b"accept-encoding".as_ref()],
+            headers
+                .get_all_ci("VARY")
+                .map(|v| v.as_slice())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn duplicate_headers_order() {
+        let mut headers = Headers::ok_200();
+        headers.add("set-cookie", "a=1");
+        headers.add("via", "1.1 first");
+        headers.add("set-cookie", "b=2");
+        headers.add("via", "1.1 second");
+        let pairs: Vec<_> = headers
+            .iter()
+            .map(|h| (h.name(), h.value.as_slice()))
+            .collect();
+        assert_eq!(
+            vec![
+                (":status", &b"200"[..]),
+                ("set-cookie", b"a=1"),
+                ("via", b"1.1 first"),
+                ("set-cookie", b"b=2"),
+                ("via", b"1.1 second"),
+            ],
+            pairs
+        );
+    }
+
+    #[test]
+    fn validate_status() {
+        let validate = |status: &str| {
+            let mut headers = Headers::new();
+            headers.add(":status", status);
+            headers.validate(RequestOrResponse::Response, HeadersPlace::Initial)
+        };
+        assert_eq!(Ok(()), validate("200"));
+        assert_eq!(Ok(()), validate("100"));
+        assert_eq!(Ok(()), validate("599"));
+        assert_eq!(Err(HeaderError::InvalidStatus), validate("abc"));
+        assert_eq!(Err(HeaderError::InvalidStatus), validate("99"));
+        assert_eq!(Err(HeaderError::InvalidStatus), validate("099"));
+        assert_eq!(Err(HeaderError::InvalidStatus), validate("600"));
+        assert_eq!(Err(HeaderError::InvalidStatus), validate("2000"));
+    }
+
+    #[test]
+    fn from_iter_pairs() {
+        let headers: Headers = vec![("content-type", "text/plain"), (":status", "200")]
+            .into_iter()
+            .collect();
+        assert_eq!(1, headers.pseudo_headers().len());
+        assert_eq!(200, headers.status());
+        assert_eq!(Some("text/plain"), headers.get_opt("content-type"));
+    }
+
+    #[test]
+    fn validate_request_pseudo_headers() {
+        let validate = |headers: &[(&str, &str)]| {
+            let mut h = Headers::new();
+            for &(n, v) in headers {
+                h.add(n, v);
+            }
+            h.validate(RequestOrResponse::Request, HeadersPlace::Initial)
+        };
+        let get = [(":method", "GET"), (":scheme", "http")];
+        assert_eq!(Ok(()), validate(&[get[0], get[1], (":path", "/a?b")]));
+        assert_eq!(
+            Err(HeaderError::EmptyValue(PseudoHeaderName::Path)),
+            validate(&[get[0], get[1], (":path", "")])
+        );
+        assert_eq!(
+            Err(HeaderError::InvalidPath),
+            validate(&[get[0], get[1], (":path", "/a b")])
+        );
+        assert_eq!(
+            Err(HeaderError::MissingPseudoHeader(PseudoHeaderName::Scheme)),
+            validate(&[get[0], (":path", "/")])
+        );
+        assert_eq!(
+            Err(HeaderError::InvalidMethod),
+            validate(&[(":method", "GE(T"), get[1], (":path", "/")])
+        );
+        assert_eq!(
+            Ok(()),
+            validate(&[(":method", "CONNECT"), (":authority", "example.com:443")])
+        );
+        assert_eq!(
+            Err(HeaderError::MissingPseudoHeader(
+                PseudoHeaderName::Authority
+            )),
+            validate(&[(":method", "CONNECT")])
+        );
+        assert_eq!(
+            Err(HeaderError::UnexpectedPseudoHeader(PseudoHeaderName::Path)),
+            validate(&[
+                (":method", "CONNECT"),
+                (":authority", "example.com:443"),
+                (":path", "/")
+            ])
+        );
+
+        let extended_connect = [
+            (":method", "CONNECT"),
+            (":protocol", "websocket"),
+            (":scheme", "https"),
+            (":path", "/chat"),
+            (":authority", "example.com"),
+        ];
+        assert_eq!(Ok(()), validate(&extended_connect));
+        assert_eq!(
+            Err(HeaderError::MissingPseudoHeader(
+                PseudoHeaderName::Authority
+            )),
+            validate(&extended_connect[..3])
+        );
+        assert_eq!(
+            Err(HeaderError::UnexpectedPseudoHeader(
+                PseudoHeaderName::Protocol
+            )),
+            validate(&[
+                (":method", "GET"),
+                extended_connect[1],
+                get[1],
+                (":path", "/")
+            ])
+        );
+    }
+
+    #[test]
+    fn parse_content_length() {
+        let content_length = |values: &[&str]| {
+            let mut headers = Headers::new();
+            for v in values {
+                headers.add("content-length", *v);
+            }
+            headers.parse_content_length()
+        };
+        assert_eq!(Ok(None), content_length(&[]));
+        assert_eq!(Ok(Some(10)), content_length(&["10"]));
+        assert_eq!(Ok(Some(10)), content_length(&["10", "10"]));
+        assert_eq!(
+            Err(HeaderError::ConflictingContentLength),
+            content_length(&["10", "11"])
+        );
+        assert_eq!(Err(HeaderError::InvalidNumber), content_length(&["+10"]));
+        assert_eq!(Err(HeaderError::InvalidNumber), content_length(&["-1"]));
+        assert_eq!(Err(HeaderError::InvalidNumber), content_length(&["12a"]));
+    }
+
+    #[test]
+    fn list_size() {
+        assert_eq!(0, Headers::new().list_size());
+        let mut headers = Headers::new_status(200);
+        headers.add("content-type", "text/plain");
+        assert_eq!((7 + 3 + 32) + (12 + 10 + 32), headers.list_size());
+        assert!(headers.fits_within(96));
+        assert!(!headers.fits_within(95));
+        assert!(Headers::new().fits_within(0));
+    }
+
+    #[test]
+    fn sensitive_does_not_affect_eq() {
+        let mut header = Header::new("authorization", "secret");
+        assert!(!header.is_sensitive());
+        header.set_sensitive(true);
+        assert!(header.is_sensitive());
+        assert_eq!(Header::new("authorization", "secret"), header);
+    }
+
+    #[test]
+    fn test_grpc_timeout() {
+        let timeout = |v: &str| {
+            let mut headers = Headers::new();
+            headers.add("grpc-timeout", v.to_owned());
+            headers.grpc_timeout()
+        };
+        assert_eq!(Some(Duration::from_secs(7200)), timeout("2H"));
+        assert_eq!(Some(Duration::from_secs(180)), timeout("3M"));
+        assert_eq!(Some(Duration::from_secs(10)), timeout("10S"));
+        assert_eq!(Some(Duration::from_millis(100)), timeout("100m"));
+        assert_eq!(Some(Duration::from_micros(5)), timeout("5u"));
+        assert_eq!(Some(Duration::from_nanos(99999999)), timeout("99999999n"));
+        assert_eq!(None, timeout("100"));
+        assert_eq!(None, timeout("m"));
+        assert_eq!(None, timeout("-1S"));
+        assert_eq!(None, timeout("100x"));
+        assert_eq!(None, timeout("123456789S"));
+        assert_eq!(None, Headers::new().grpc_timeout());
+    }
 }
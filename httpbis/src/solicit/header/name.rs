@@ -1,4 +1,5 @@
 use crate::ascii::Ascii;
+use crate::hpack::static_table::static_name_index;
 use crate::req_resp::RequestOrResponse;
 use crate::solicit::header::{HeaderError, HeaderResult};
 use bytes::Bytes;
@@ -21,6 +22,10 @@ pub enum PseudoHeaderName {
     // 8.1.2.4 Response Pseudo-Header Fields
     /// `:status`
     Status = 4,
+
+    // RFC 8441 4. The Extended CONNECT Method
+    /// `:protocol`
+    Protocol = 5,
 }
 
 impl PseudoHeaderName {
@@ -32,6 +37,7 @@ impl PseudoHeaderName {
             PseudoHeaderName::Authority => ":authority",
             PseudoHeaderName::Path => ":path",
             PseudoHeaderName::Status => ":status",
+            PseudoHeaderName::Protocol => ":protocol",
         }
     }
 
@@ -43,6 +49,7 @@ impl PseudoHeaderName {
             b":authority" => Ok(PseudoHeaderName::Authority),
             b":path" => Ok(PseudoHeaderName::Path),
             b":status" => Ok(PseudoHeaderName::Status),
+            b":protocol" => Ok(PseudoHeaderName::Protocol),
             _ => Err(HeaderError::UnknownPseudoHeader),
         }
     }
@@ -55,6 +62,7 @@ impl PseudoHeaderName {
             PseudoHeaderName::Authority => RequestOrResponse::Request,
             PseudoHeaderName::Path => RequestOrResponse::Request,
             PseudoHeaderName::Status => RequestOrResponse::Response,
+            PseudoHeaderName::Protocol => RequestOrResponse::Request,
         }
     }
 
@@ -70,6 +78,7 @@ impl PseudoHeaderName {
             PseudoHeaderName::Scheme,
             PseudoHeaderName::Authority,
             PseudoHeaderName::Path,
+            PseudoHeaderName::Protocol,
         ];
         static RESPONSE_HEADERS: &[PseudoHeaderName] = &[PseudoHeaderName::Status];
         match request_or_response {
@@ -86,14 +95,15 @@ impl PseudoHeaderName {
             PseudoHeaderName::Authority,
             PseudoHeaderName::Path,
             PseudoHeaderName::Status,
+            PseudoHeaderName::Protocol,
         ];
         ALL_HEADERS
     }
 }
 
-impl Into<Bytes> for PseudoHeaderName {
-    fn into(self) -> Bytes {
-        self.name_bytes()
+impl From<PseudoHeaderName> for Bytes {
+    fn from(name: PseudoHeaderName) -> Self {
+        name.name_bytes()
     }
 }
 
@@ -159,7 +169,7 @@ impl RegularHeaderName {
                 return Err((HeaderError::IncorrectCharInName, bs));
             }
             if b.is_ascii_uppercase() {
-                return Err((HeaderError::IncorrectCharInName, bs));
+                return Err((HeaderError::UppercaseHeaderName, bs));
             }
             let bad_chars = b"()<>@,;:\\\"/[]?={} \t";
             if bad_chars.contains(&b) {
@@ -174,9 +184,9 @@ impl RegularHeaderName {
     }
 }
 
-impl Into<Bytes> for RegularHeaderName {
-    fn into(self) -> Bytes {
-        self.0.into()
+impl From<RegularHeaderName> for Bytes {
+    fn from(name: RegularHeaderName) -> Self {
+        name.0.into()
     }
 }
 
@@ -252,9 +262,9 @@ impl From<Bytes> for HeaderName {
     }
 }
 
-impl Into<Bytes> for HeaderName {
-    fn into(self) -> Bytes {
-        match self.0 {
+impl From<HeaderName> for Bytes {
+    fn from(name: HeaderName) -> Self {
+        match name.0 {
             HeaderNameEnum::Pseudo(n) => n.into(),
             HeaderNameEnum::Regular(n) => n.into(),
         }
@@ -307,14 +317,31 @@ impl HeaderName {
     /// HeaderName::new("");
     /// ```
     pub fn new(name: impl Into<Bytes>) -> HeaderName {
-        let mut name = name.into();
-        make_ascii_lowercase(&mut name);
-        match HeaderName::new_validate(name) {
+        match HeaderName::from_bytes_lowercasing(name.into()) {
             Ok(h) => h,
             Err((e, name)) => panic!("incorrect header name: {:?}: {:?}", name, e),
         }
     }
 
+    /// Convert header name to lower case and validate it.
+    ///
+    /// Useful to normalize outgoing header names.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate httpbis;
+    /// # extern crate bytes;
+    /// # use httpbis::*;
+    /// # use bytes::*;
+    /// let name = HeaderName::from_bytes_lowercasing(Bytes::from("Content-Type")).unwrap();
+    /// assert_eq!("content-type", name.name());
+    /// ```
+    pub fn from_bytes_lowercasing(mut name: Bytes) -> Result<HeaderName, (HeaderError, Bytes)> {
+        make_ascii_lowercase(&mut name);
+        HeaderName::new_validate(name)
+    }
+
     /// Construct a header from given sequence of bytes
     ///
     /// Returns error if header name is not valid,
@@ -331,10 +358,19 @@ impl HeaderName {
     /// assert!(HeaderName::new_validate(Bytes::from("Content-Type")).is_err());
     /// ```
     pub fn new_validate(name: Bytes) -> Result<HeaderName, (HeaderError, Bytes)> {
-        if name.len() == 0 {
+        if name.is_empty() {
             return Err((HeaderError::EmptyName, name));
         }
 
+        // Just as in HTTP/1.x, header field names are strings of ASCII
+        // characters that are compared in a case-insensitive fashion.  However,
+        // header field names MUST be converted to lowercase prior to their
+        // encoding in HTTP/2.  A request or response containing uppercase
+        // header field names MUST be treated as malformed (Section 8.1.2.6).
+        if name.iter().any(|b| b.is_ascii_uppercase()) {
+            return Err((HeaderError::UppercaseHeaderName, name));
+        }
+
         Ok(if name[0] == b':' {
             HeaderName(HeaderNameEnum::Pseudo(
                 PseudoHeaderName::parse(&name).map_err(|e| (e, name))?,
@@ -381,6 +417,20 @@ impl HeaderName {
         }
     }
 
+    /// 1-based index of this name in HPACK static table.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use httpbis::*;
+    /// assert_eq!(Some(2), HeaderName::new(":method").static_index());
+    /// assert_eq!(Some(31), HeaderName::new("content-type").static_index());
+    /// assert_eq!(None, HeaderName::new("x-foo").static_index());
+    /// ```
+    pub fn static_index(&self) -> Option<u8> {
+        static_name_index(self.name().as_bytes())
+    }
+
     /// Obtain pseudo header name from this header name
     pub fn pseudo_header_name(&self) -> Option<PseudoHeaderName> {
         match self.0 {
@@ -420,6 +470,43 @@ mod test {
         assert_eq!("content-type", HeaderName::new("Content-Type").name());
     }
 
+    #[test]
+    fn header_name_new_validate_uppercase() {
+        match HeaderName::new_validate(Bytes::from("Content-Type")) {
+            Err((HeaderError::UppercaseHeaderName, _)) => {}
+            r => panic!("{:?}", r),
+        }
+        match HeaderName::new_validate(Bytes::from(":Method")) {
+            Err((HeaderError::UppercaseHeaderName, _)) => {}
+            r => panic!("{:?}", r),
+        }
+        assert_eq!(
+            "content-type",
+            HeaderName::new_validate(Bytes::from("content-type"))
+                .unwrap()
+                .name()
+        );
+        assert_eq!(
+            "content-type",
+            HeaderName::from_bytes_lowercasing(Bytes::from("Content-Type"))
+                .unwrap()
+                .name()
+        );
+    }
+
+    #[test]
+    fn header_name_static_index() {
+        assert_eq!(Some(1), HeaderName::new(":authority").static_index());
+        assert_eq!(Some(8), HeaderName::new(":status").static_index());
+        assert_eq!(Some(61), HeaderName::new("www-authenticate").static_index());
+        assert_eq!(None, HeaderName::new("x-fgfg").static_index());
+        for name in PseudoHeaderName::all_names() {
+            // `:protocol` is defined after HPACK
+            let expected = *name != PseudoHeaderName::Protocol;
+            assert_eq!(expected, HeaderName::pseudo(*name).static_index().is_some());
+        }
+    }
+
     #[test]
     fn header_name_display() {
         assert_eq!(
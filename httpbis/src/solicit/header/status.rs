@@ -2,12 +2,9 @@ use bytes::Bytes;
 
 use crate::HeaderValue;
 
-pub const STATUS_200: HeaderValue =
-    unsafe { HeaderValue::from_bytes_unchecked(Bytes::from_static(b"200")) };
-pub const STATUS_404: HeaderValue =
-    unsafe { HeaderValue::from_bytes_unchecked(Bytes::from_static(b"404")) };
-pub const STATUS_500: HeaderValue =
-    unsafe { HeaderValue::from_bytes_unchecked(Bytes::from_static(b"500")) };
+pub const STATUS_200: HeaderValue = HeaderValue::from_static("200");
+pub const STATUS_404: HeaderValue = HeaderValue::from_static("404");
+pub const STATUS_500: HeaderValue = HeaderValue::from_static("500");
 
 pub fn status_to_header_value(code: u32) -> HeaderValue {
     match code {
@@ -1,11 +1,67 @@
-use crate::ascii::Ascii;
 use crate::solicit::header::HeaderError;
 use bytes::Bytes;
+use bytes::BytesMut;
+use std::borrow::Cow;
 use std::fmt;
+use std::ops;
+use std::str;
+
+/// Which bytes are accepted in header value.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum HeaderValueValidation {
+    /// Visible ASCII characters, space and tab.
+    #[default]
+    Strict,
+    /// Additionally accept obs-text (0x80-0xFF).
+    AllowObsText,
+    /// Accept any byte except NUL, CR and LF.
+    AllowControlChars,
+}
+
+impl HeaderValueValidation {
+    fn check_byte(self, i: usize, b: u8) -> Result<(), HeaderError> {
+        // Reported separately from other control characters
+        // to make request smuggling attempts visible.
+        if b == b'\r' || b == b'\n' {
+            return Err(HeaderError::ObsFold);
+        }
+
+        match self {
+            HeaderValueValidation::Strict => {
+                if !b.is_ascii() {
+                    return Err(HeaderError::HeaderValueNotAscii);
+                }
+                if !(b' '..=b'~').contains(&b) && b != b'\t' {
+                    return Err(HeaderError::IncorrectCharInValue(i));
+                }
+            }
+            HeaderValueValidation::AllowObsText => {
+                if (b < b' ' && b != b'\t') || b == 0x7f {
+                    return Err(HeaderError::IncorrectCharInValue(i));
+                }
+            }
+            HeaderValueValidation::AllowControlChars => {
+                if b == 0 {
+                    return Err(HeaderError::IncorrectCharInValue(i));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn check(self, bs: &[u8]) -> Result<(), HeaderError> {
+        for (i, &b) in bs.iter().enumerate() {
+            self.check_byte(i, b)?;
+        }
+        Ok(())
+    }
+}
 
 /// A convenience struct representing a header value.
+///
+/// Value is ASCII unless constructed with relaxed `HeaderValueValidation`.
 #[derive(Eq, PartialEq, Hash, Clone)]
-pub struct HeaderValue(Ascii);
+pub struct HeaderValue(Bytes);
 
 impl HeaderValue {
     /// Validate and create header value from bytes.
@@ -34,38 +90,276 @@ impl HeaderValue {
         // SHOULD limit their field values to US‑ASCII octets. A recipient SHOULD
         // treat other octets in field content (obs‑text) as opaque data.
 
-        for &b in &bs {
-            if !b.is_ascii() {
-                return Err((HeaderError::HeaderValueNotAscii, bs));
-            }
+        HeaderValue::from_bytes_with(bs, HeaderValueValidation::Strict)
+    }
 
-            if (b < b' ' || b > b'~') && b != b'\t' {
-                return Err((HeaderError::IncorrectCharInValue, bs));
-            }
+    /// Validate and create header value from bytes with specified validation mode.
+    pub fn from_bytes_with(
+        bs: Bytes,
+        validation: HeaderValueValidation,
+    ) -> Result<HeaderValue, (HeaderError, Bytes)> {
+        match validation.check(&bs) {
+            Ok(()) => Ok(HeaderValue(bs)),
+            Err(e) => Err((e, bs)),
         }
+    }
+
+    /// Strip leading and trailing OWS (SP and HTAB), then validate
+    /// and create header value from remaining bytes.
+    ///
+    /// Trimmed value shares storage with the original `Bytes`.
+    pub fn from_bytes_trimmed(bs: Bytes) -> Result<HeaderValue, (HeaderError, Bytes)> {
+        let range = ows_trimmed_range(&bs);
+        let start = range.start;
+        HeaderValue::from_bytes(bs.slice(range)).map_err(|(e, _)| {
+            let e = match e {
+                HeaderError::IncorrectCharInValue(i) => {
+                    HeaderError::IncorrectCharInValue(start + i)
+                }
+                e => e,
+            };
+            (e, bs)
+        })
+    }
 
-        Ok(HeaderValue(unsafe { Ascii::from_bytes_unchecked(bs) }))
+    /// Validate and create header value from ISO-8859-1 bytes.
+    ///
+    /// Unlike `from_bytes`, obs-text (0x80-0xFF) is accepted,
+    /// but control characters are still rejected.
+    pub fn from_bytes_latin1(bs: Bytes) -> Result<HeaderValue, (HeaderError, Bytes)> {
+        HeaderValue::from_bytes_with(bs, HeaderValueValidation::AllowObsText)
     }
 
     /// Into underlying storage object.
     pub fn into_inner(self) -> Bytes {
-        self.0.into_bytes()
+        self.0
     }
 
     /// As bytes.
     pub fn as_slice(&self) -> &[u8] {
-        self.0.as_bytes()
+        &self.0
+    }
+
+    /// Borrowed view of this value.
+    pub fn as_ref_view(&self) -> HeaderValueRef<'_> {
+        HeaderValueRef(&self.0)
+    }
+
+    /// As string if the value is ASCII.
+    pub fn as_str(&self) -> Option<&str> {
+        self.as_ref_view().as_str()
+    }
+
+    /// Decode value as ISO-8859-1, which is identity for ASCII values.
+    pub fn as_str_lossy(&self) -> Cow<'_, str> {
+        self.as_ref_view().as_str_lossy()
+    }
+
+    /// Create header value from static string without allocation.
+    ///
+    /// Validation cannot be done at compile time in release mode,
+    /// so the value is validated with `debug_assert!` only
+    /// (when used to initialize a `const`, invalid value fails the debug build).
+    pub const fn from_static(s: &'static str) -> HeaderValue {
+        debug_assert!(is_valid_strict(s.as_bytes()), "invalid header value");
+        HeaderValue(Bytes::from_static(s.as_bytes()))
+    }
+
+    /// Join values with separator (e. g. cookie crumbs with `"; "`).
+    ///
+    /// Separator is validated strictly, joined value is valid
+    /// because each part is valid. Single value is returned without copying.
+    pub fn concat(values: &[HeaderValue], separator: &[u8]) -> Result<HeaderValue, HeaderError> {
+        HeaderValueValidation::Strict.check(separator)?;
+
+        match values {
+            [] => Ok(HeaderValue(Bytes::new())),
+            [value] => Ok(value.clone()),
+            values => {
+                let len = values.iter().map(|v| v.0.len()).sum::<usize>()
+                    + separator.len() * (values.len() - 1);
+                let mut r = BytesMut::with_capacity(len);
+                for (i, value) in values.iter().enumerate() {
+                    if i != 0 {
+                        r.extend_from_slice(separator);
+                    }
+                    r.extend_from_slice(&value.0);
+                }
+                Ok(HeaderValue(r.freeze()))
+            }
+        }
+    }
+
+    /// Compare values ignoring leading and trailing OWS.
+    ///
+    /// Unlike `==`, `"gzip"` and `" gzip\t"` are equal.
+    pub fn eq_ignore_ows(&self, other: &HeaderValue) -> bool {
+        self.as_ref_view().eq_ignore_ows(&other.as_ref_view())
+    }
+
+    /// Value is a single RFC 7230 token (e. g. `chunked`, but not `chunked, gzip`).
+    pub fn is_token(&self) -> bool {
+        self.as_ref_view().is_token()
+    }
+
+    /// Parse value as decimal number (like `content-length` or `grpc-status`).
+    ///
+    /// Sign, whitespace and any non-digit byte are rejected.
+    pub fn parse_u64(&self) -> Result<u64, HeaderError> {
+        self.as_ref_view().parse_u64()
+    }
+
+    /// Parse value as decimal number without leading zeros.
+    pub fn parse_u64_strict(&self) -> Result<u64, HeaderError> {
+        self.as_ref_view().parse_u64_strict()
+    }
+
+    /// Split comma-separated list value (like `accept-encoding`)
+    /// into trimmed non-empty elements.
+    ///
+    /// Non-ASCII elements (possible only with relaxed validation) are skipped.
+    pub fn split_comma(&self) -> impl Iterator<Item = &str> {
+        self.as_ref_view().split_comma()
     }
 
     /// Unsafe no-validation `const` constructor.
+    ///
+    /// # Safety
+    ///
+    /// `bytes` must be a valid header value.
     pub const unsafe fn from_bytes_unchecked(bytes: Bytes) -> HeaderValue {
-        HeaderValue(Ascii::from_bytes_unchecked(bytes))
+        HeaderValue(bytes)
+    }
+}
+
+fn is_ows(b: u8) -> bool {
+    b == b' ' || b == b'\t'
+}
+
+/// Range of bytes without leading and trailing OWS.
+fn ows_trimmed_range(bs: &[u8]) -> ops::Range<usize> {
+    let start = bs.iter().position(|&b| !is_ows(b)).unwrap_or(bs.len());
+    let end = bs
+        .iter()
+        .rposition(|&b| !is_ows(b))
+        .map_or(start, |i| i + 1);
+    start..end
+}
+
+/// Same checks as `HeaderValue::from_bytes`, usable in `const fn`.
+const fn is_valid_strict(bs: &[u8]) -> bool {
+    let mut i = 0;
+    while i < bs.len() {
+        let b = bs[i];
+        if (b < b' ' || b > b'~') && b != b'\t' {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Borrowed header value, to validate and read header value without allocation.
+#[derive(Eq, PartialEq, Hash, Clone, Copy)]
+pub struct HeaderValueRef<'a>(&'a [u8]);
+
+impl<'a> HeaderValueRef<'a> {
+    /// Validate (same as `HeaderValue::from_bytes`) and create a header value view.
+    pub fn from_slice(bs: &'a [u8]) -> Result<HeaderValueRef<'a>, HeaderError> {
+        HeaderValueValidation::Strict.check(bs)?;
+        Ok(HeaderValueRef(bs))
+    }
+
+    /// As bytes.
+    pub fn as_slice(&self) -> &'a [u8] {
+        self.0
+    }
+
+    /// As string if the value is ASCII.
+    pub fn as_str(&self) -> Option<&'a str> {
+        if self.0.is_ascii() {
+            Some(unsafe { str::from_utf8_unchecked(self.0) })
+        } else {
+            None
+        }
+    }
+
+    /// Decode value as ISO-8859-1, which is identity for ASCII values.
+    pub fn as_str_lossy(&self) -> Cow<'a, str> {
+        match self.as_str() {
+            Some(s) => Cow::Borrowed(s),
+            None => Cow::Owned(self.0.iter().map(|&b| b as char).collect()),
+        }
+    }
+
+    /// Compare values ignoring leading and trailing OWS.
+    pub fn eq_ignore_ows(&self, other: &HeaderValueRef) -> bool {
+        self.0[ows_trimmed_range(self.0)] == other.0[ows_trimmed_range(other.0)]
+    }
+
+    /// Value is a single RFC 7230 token.
+    pub fn is_token(&self) -> bool {
+        // token          = 1*tchar
+        // tchar          = "!" / "#" / "$" / "%" / "&" / "'" / "*"
+        //                / "+" / "-" / "." / "^" / "_" / "`" / "|" / "~"
+        //                / DIGIT / ALPHA
+        //                ; any VCHAR, except delimiters
+        !self.0.is_empty()
+            && self
+                .0
+                .iter()
+                .all(|&b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+    }
+
+    /// Parse value as decimal number: one or more ASCII digits,
+    /// no sign, no whitespace.
+    pub fn parse_u64(&self) -> Result<u64, HeaderError> {
+        if self.0.is_empty() || !self.0.iter().all(|b| b.is_ascii_digit()) {
+            return Err(HeaderError::InvalidNumber);
+        }
+        self.0.iter().try_fold(0u64, |r, &b| {
+            r.checked_mul(10)
+                .and_then(|r| r.checked_add((b - b'0') as u64))
+                .ok_or(HeaderError::InvalidNumber)
+        })
+    }
+
+    /// Same as `parse_u64`, but leading zeros are rejected too (`"0"` is accepted).
+    pub fn parse_u64_strict(&self) -> Result<u64, HeaderError> {
+        if self.0.len() > 1 && self.0[0] == b'0' {
+            return Err(HeaderError::InvalidNumber);
+        }
+        self.parse_u64()
+    }
+
+    /// Split comma-separated list value into trimmed non-empty elements.
+    ///
+    /// Non-ASCII elements are skipped.
+    pub fn split_comma(&self) -> impl Iterator<Item = &'a str> {
+        self.0
+            .split(|&b| b == b',')
+            .map(|e| &e[ows_trimmed_range(e)])
+            .filter(|e| !e.is_empty() && e.is_ascii())
+            .map(|e| unsafe { str::from_utf8_unchecked(e) })
+    }
+}
+
+impl<'a> fmt::Debug for HeaderValueRef<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.as_str_lossy(), fmt)
     }
 }
 
 impl fmt::Debug for HeaderValue {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        fmt::Debug::fmt(&self.0, fmt)
+        fmt::Debug::fmt(&self.as_str_lossy(), fmt)
+    }
+}
+
+/// Value as is; ISO-8859-1 values are decoded.
+impl fmt::Display for HeaderValue {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.as_str_lossy(), fmt)
     }
 }
 
@@ -99,9 +393,9 @@ impl<'a> From<&'a str> for HeaderValue {
     }
 }
 
-impl Into<Bytes> for HeaderValue {
-    fn into(self) -> Bytes {
-        self.0.into()
+impl From<HeaderValue> for Bytes {
+    fn from(value: HeaderValue) -> Self {
+        value.0
     }
 }
 
@@ -111,8 +405,277 @@ impl AsRef<[u8]> for HeaderValue {
     }
 }
 
+/// Panics if value is not ASCII, which is possible only for values
+/// created with `from_bytes_latin1` or relaxed `HeaderValueValidation`,
+/// use `as_str` or `as_str_lossy` for these.
 impl AsRef<str> for HeaderValue {
     fn as_ref(&self) -> &str {
-        self.0.as_ref()
+        self.as_str().expect("header value is not ASCII")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn from_bytes_incorrect_char_offset() {
+        match HeaderValue::from_bytes(Bytes::from_static(b"ok\x01bad")) {
+            Err((HeaderError::IncorrectCharInValue(2), _)) => {}
+            r => panic!("{:?}", r),
+        }
+        match HeaderValue::from_bytes_latin1(Bytes::from_static(b"\xe9\x00")) {
+            Err((HeaderError::IncorrectCharInValue(1), _)) => {}
+            r => panic!("{:?}", r),
+        }
+    }
+
+    #[test]
+    fn from_bytes_trimmed() {
+        let bs = Bytes::from_static(b" \t gzip ");
+        let v = HeaderValue::from_bytes_trimmed(bs.clone()).unwrap();
+        assert_eq!(b"gzip", v.as_slice());
+        assert_eq!(bs[3..].as_ptr(), v.as_slice().as_ptr());
+
+        assert_eq!(
+            b"a b",
+            HeaderValue::from_bytes_trimmed(Bytes::from_static(b"a b"))
+                .unwrap()
+                .as_slice()
+        );
+        assert_eq!(
+            b"",
+            HeaderValue::from_bytes_trimmed(Bytes::from_static(b" \t "))
+                .unwrap()
+                .as_slice()
+        );
+        assert_eq!(
+            b"",
+            HeaderValue::from_bytes_trimmed(Bytes::new())
+                .unwrap()
+                .as_slice()
+        );
+
+        match HeaderValue::from_bytes_trimmed(Bytes::from_static(b"  a\x01")) {
+            Err((HeaderError::IncorrectCharInValue(3), bs)) => assert_eq!(b"  a\x01", &bs[..]),
+            r => panic!("{:?}", r),
+        }
+    }
+
+    #[test]
+    fn split_comma() {
+        fn split(s: &str) -> Vec<String> {
+            HeaderValue::from(s)
+                .split_comma()
+                .map(|e| e.to_owned())
+                .collect()
+        }
+
+        assert_eq!(Vec::<String>::new(), split(""));
+        assert_eq!(Vec::<String>::new(), split(" , ,"));
+        assert_eq!(vec!["gzip"], split("gzip"));
+        assert_eq!(vec!["a", "b"], split("a,,b"));
+        assert_eq!(vec!["a", "b"], split("a,b,"));
+        assert_eq!(vec!["gzip", "deflate br"], split(" gzip ,\tdeflate br "));
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!("a\tb", format!("{}", HeaderValue::from("a\tb")));
+        assert_eq!("\"a\\tb\"", format!("{:?}", HeaderValue::from("a\tb")));
+        let latin1 = HeaderValue::from_bytes_latin1(Bytes::from_static(b"\xe9")).unwrap();
+        assert_eq!("é", format!("{}", latin1));
+    }
+
+    #[test]
+    fn from_bytes_with() {
+        fn check(bs: &'static [u8], validation: HeaderValueValidation) -> bool {
+            HeaderValue::from_bytes_with(Bytes::from_static(bs), validation).is_ok()
+        }
+
+        use HeaderValueValidation::*;
+
+        assert!(check(b"a b\t", Strict));
+        assert!(!check(b"\xe9", Strict));
+        assert!(!check(b"\x01", Strict));
+
+        assert!(check(b"\xe9", AllowObsText));
+        assert!(!check(b"\x01", AllowObsText));
+        assert!(!check(b"\x7f", AllowObsText));
+
+        assert!(check(b"\xe9\x01\x7f\x1b", AllowControlChars));
+        assert!(!check(b"\x00", AllowControlChars));
+        assert!(!check(b"\r", AllowControlChars));
+        assert!(!check(b"\n", AllowControlChars));
+
+        match HeaderValue::from_bytes(Bytes::from_static(b"\xe9")) {
+            Err((HeaderError::HeaderValueNotAscii, _)) => {}
+            r => panic!("{:?}", r),
+        }
+    }
+
+    #[test]
+    fn concat() {
+        let crumbs = [
+            HeaderValue::from("a=b"),
+            HeaderValue::from("c=d"),
+            HeaderValue::from("e=f"),
+        ];
+        let joined = HeaderValue::concat(&crumbs, b"; ").unwrap();
+        assert_eq!(b"a=b; c=d; e=f", joined.as_slice());
+
+        let single = HeaderValue::concat(&crumbs[..1], b"; ").unwrap();
+        assert_eq!(crumbs[0].as_slice().as_ptr(), single.as_slice().as_ptr());
+
+        assert_eq!(b"", HeaderValue::concat(&[], b"; ").unwrap().as_slice());
+
+        match HeaderValue::concat(&crumbs, b";\x01") {
+            Err(HeaderError::IncorrectCharInValue(1)) => {}
+            r => panic!("{:?}", r),
+        }
+    }
+
+    #[test]
+    fn eq_ignore_ows() {
+        let gzip = HeaderValue::from("gzip");
+        assert!(gzip.eq_ignore_ows(&HeaderValue::from(" gzip ")));
+        assert!(gzip.eq_ignore_ows(&HeaderValue::from("\tgzip")));
+        assert!(HeaderValue::from(" gzip").eq_ignore_ows(&HeaderValue::from("gzip\t")));
+        assert!(!gzip.eq_ignore_ows(&HeaderValue::from("g zip")));
+        assert_ne!(gzip, HeaderValue::from(" gzip "));
+
+        assert!(HeaderValue::from(" \t").eq_ignore_ows(&HeaderValue::from("")));
+        assert!(HeaderValue::from("  ").eq_ignore_ows(&HeaderValue::from("\t")));
+        assert!(!HeaderValue::from("  ").eq_ignore_ows(&gzip));
+    }
+
+    #[test]
+    fn obs_fold() {
+        for &bs in &[&b"a\r\n b"[..], b"a\nb", b"a\r"] {
+            for &validation in &[
+                HeaderValueValidation::Strict,
+                HeaderValueValidation::AllowObsText,
+                HeaderValueValidation::AllowControlChars,
+            ] {
+                match HeaderValue::from_bytes_with(Bytes::from_static(bs), validation) {
+                    Err((HeaderError::ObsFold, _)) => {}
+                    r => panic!("{:?}", r),
+                }
+            }
+        }
+
+        match HeaderValue::from_bytes(Bytes::from_static(b"a\x0b")) {
+            Err((HeaderError::IncorrectCharInValue(1), _)) => {}
+            r => panic!("{:?}", r),
+        }
+    }
+
+    #[test]
+    fn header_value_ref() {
+        let v = HeaderValue::from(" gzip, br ");
+        let r = v.as_ref_view();
+        assert_eq!(v.as_slice().as_ptr(), r.as_slice().as_ptr());
+        assert_eq!(vec!["gzip", "br"], r.split_comma().collect::<Vec<_>>());
+        assert!(r.eq_ignore_ows(&HeaderValueRef::from_slice(b"gzip, br").unwrap()));
+        assert_eq!("\" gzip, br \"", format!("{:?}", r));
+
+        assert!(HeaderValueRef::from_slice(b"a\x01").is_err());
+        match HeaderValueRef::from_slice(b"a\r\n b") {
+            Err(HeaderError::ObsFold) => {}
+            r => panic!("{:?}", r),
+        }
+    }
+
+    #[test]
+    fn is_token() {
+        assert!(HeaderValue::from("chunked").is_token());
+        assert!(HeaderValue::from("x-Custom_1.0~!#$%&'*+^`|").is_token());
+        assert!(!HeaderValue::from("").is_token());
+        assert!(!HeaderValue::from("chunked gzip").is_token());
+        assert!(!HeaderValue::from("chunked\t").is_token());
+        for &d in b"()<>@,;:\\\"/[]?={}" {
+            let v = HeaderValue::from(vec![b'a', d, b'b']);
+            assert!(!v.is_token(), "{:?}", v);
+        }
+        let latin1 = HeaderValue::from_bytes_latin1(Bytes::from_static(b"caf\xe9")).unwrap();
+        assert!(!latin1.is_token());
+    }
+
+    #[test]
+    fn from_bytes_latin1() {
+        let v = HeaderValue::from_bytes_latin1(Bytes::from_static(b"caf\xe9\tok")).unwrap();
+        assert_eq!(b"caf\xe9\tok", v.as_slice());
+        assert_eq!(None, v.as_str());
+        assert_eq!("café\tok", v.as_str_lossy());
+
+        assert!(HeaderValue::from_bytes(Bytes::from_static(b"caf\xe9")).is_err());
+        assert!(HeaderValue::from_bytes_latin1(Bytes::from_static(b"a\x01")).is_err());
+        assert!(HeaderValue::from_bytes_latin1(Bytes::from_static(b"a\r\n")).is_err());
+        assert!(HeaderValue::from_bytes_latin1(Bytes::from_static(b"a\x7f")).is_err());
+    }
+
+    #[test]
+    fn from_static() {
+        const V: HeaderValue = HeaderValue::from_static("application/grpc");
+        assert_eq!(b"application/grpc", V.as_slice());
+        assert!(is_valid_strict(b"a\tb"));
+        assert!(!is_valid_strict(b"a\nb"));
+        assert!(!is_valid_strict("ю".as_bytes()));
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic]
+    fn from_static_invalid() {
+        HeaderValue::from_static("a\r\nb");
+    }
+
+    #[test]
+    fn parse_u64() {
+        assert_eq!(Ok(0), HeaderValue::from("0").parse_u64());
+        assert_eq!(Ok(1234), HeaderValue::from("1234").parse_u64());
+        assert_eq!(Ok(7), HeaderValue::from("007").parse_u64());
+        assert_eq!(
+            Ok(u64::MAX),
+            HeaderValue::from("18446744073709551615").parse_u64()
+        );
+        for invalid in &["", "-1", "+1", "12a", " 1", "1 ", "18446744073709551616"] {
+            assert_eq!(
+                Err(HeaderError::InvalidNumber),
+                HeaderValue::from(*invalid).parse_u64(),
+                "{:?}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn parse_u64_strict() {
+        assert_eq!(Ok(0), HeaderValue::from("0").parse_u64_strict());
+        assert_eq!(Ok(200), HeaderValue::from("200").parse_u64_strict());
+        assert_eq!(
+            Err(HeaderError::InvalidNumber),
+            HeaderValue::from("007").parse_u64_strict()
+        );
+        assert_eq!(
+            Err(HeaderError::InvalidNumber),
+            HeaderValue::from("-1").parse_u64_strict()
+        );
+    }
+
+    #[test]
+    fn as_str_lossy_ascii_borrowed() {
+        let v = HeaderValue::from("gzip");
+        assert_eq!(Some("gzip"), v.as_str());
+        match v.as_str_lossy() {
+            Cow::Borrowed("gzip") => {}
+            r => panic!("{:?}", r),
+        }
+    }
+
+    #[test]
+    fn as_ref_str() {
+        let v = HeaderValue::from("gzip");
+        assert_eq!("gzip", AsRef::<str>::as_ref(&v));
     }
 }
@@ -35,18 +35,12 @@ impl StreamState {
 
     /// Returns whether the stream is closed locally.
     pub fn is_closed_local(&self) -> bool {
-        match *self {
-            StreamState::HalfClosedLocal | StreamState::Closed => true,
-            _ => false,
-        }
+        matches!(*self, StreamState::HalfClosedLocal | StreamState::Closed)
     }
 
     /// Returns whether the remote peer has closed the stream. This includes a fully closed stream.
     pub fn is_closed_remote(&self) -> bool {
-        match *self {
-            StreamState::HalfClosedRemote | StreamState::Closed => true,
-            _ => false,
-        }
+        matches!(*self, StreamState::HalfClosedRemote | StreamState::Closed)
     }
 }
 
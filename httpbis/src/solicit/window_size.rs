@@ -1,6 +1,10 @@
 //! Window size related types and constants
 
+use std::cmp;
 use std::fmt;
+use std::mem;
+use std::time::Duration;
+use std::time::Instant;
 
 /// A sender MUST NOT allow a flow-control window to exceed 231-1 octets. If a sender receives
 /// a WINDOW_UPDATE that causes a flow-control window to exceed this maximum,
@@ -11,8 +15,7 @@ pub const MAX_WINDOW_SIZE: u32 = 0x7fffffff;
 
 #[test]
 fn test_max_window_size_is_i32_max() {
-    use std::i32;
-    assert_eq!(i32::max_value(), MAX_WINDOW_SIZE as i32);
+    assert_eq!(i32::MAX, MAX_WINDOW_SIZE as i32);
 }
 
 /// Effective reachable min window size.
@@ -29,7 +32,9 @@ pub const MAX_WINDOW_SIZE_INC: u32 = 0x7fffffff;
 ///
 /// It exposes methods that allow the manipulation of window sizes, such that they can never
 /// overflow the spec-mandated upper bound.
-#[derive(Debug, Copy, Clone, PartialEq)]
+///
+/// Windows are ordered by size, so `stream.min(conn)` is the window limiting what can be sent.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct WindowSize(i32);
 impl WindowSize {
     /// Add or subtract window size, check for overflow
@@ -54,6 +59,41 @@ impl WindowSize {
         self.try_add(delta as i32)
     }
 
+    /// Increases the window size by the given delta, clamping the result to `MAX_WINDOW_SIZE`.
+    ///
+    /// This is a lenient version of `try_increase` for peers which send increments
+    /// overflowing the window. Returns the number of octets dropped by clamping,
+    /// or an error if the delta is zero (which is a no-op and a protocol violation).
+    pub fn saturating_increase(&mut self, delta: u32) -> Result<u32, ()> {
+        if delta == 0 {
+            return Err(());
+        }
+
+        let new = self.0 as i64 + delta as i64;
+        if new > MAX_WINDOW_SIZE as i64 {
+            self.0 = MAX_WINDOW_SIZE as i32;
+            Ok((new - MAX_WINDOW_SIZE as i64) as u32)
+        } else {
+            self.0 = new as i32;
+            Ok(0)
+        }
+    }
+
+    /// Adjust the window after peer changed SETTINGS_INITIAL_WINDOW_SIZE from `old` to `new`.
+    ///
+    /// Window is allowed to become negative (saturating at `i32::MIN`);
+    /// the only error is the window exceeding `MAX_WINDOW_SIZE`,
+    /// which must be treated as FLOW_CONTROL_ERROR.
+    pub fn apply_initial_window_delta(&mut self, old: u32, new: u32) -> Result<(), ()> {
+        let delta = new as i64 - old as i64;
+        let size = self.0 as i64 + delta;
+        if size > MAX_WINDOW_SIZE as i64 {
+            return Err(());
+        }
+        self.0 = cmp::max(size, i32::MIN as i64) as i32;
+        Ok(())
+    }
+
     /// Tries to decrease the size of the window by the given delta.
     ///
     /// There are situations where the window size should legitimately be allowed to become
@@ -69,6 +109,19 @@ impl WindowSize {
         }
     }
 
+    /// Try decrease window size, fail if decreases to negative.
+    ///
+    /// On failure returns the number of octets by which the delta exceeds the window.
+    pub fn try_decrease_checked(&mut self, delta: i32) -> Result<(), u32> {
+        debug_assert!(delta >= 0);
+        let new = self.0 as i64 - delta as i64;
+        if new < 0 {
+            return Err((-new) as u32);
+        }
+        self.0 = new as i32;
+        Ok(())
+    }
+
     /// Try decrease windows size, fail if decreases to negative.
     pub fn try_decrease_to_non_negative(&mut self, delta: i32) -> Result<(), ()> {
         match self.0.checked_sub(delta) {
@@ -92,6 +145,22 @@ impl WindowSize {
         self.0
     }
 
+    /// Number of octets which can be sent before the window is exhausted.
+    ///
+    /// Negative window is reported as zero.
+    pub fn available(&self) -> u32 {
+        if self.0 > 0 {
+            self.0 as u32
+        } else {
+            0
+        }
+    }
+
+    /// Nothing can be sent until the window is increased.
+    pub fn is_exhausted(&self) -> bool {
+        self.0 <= 0
+    }
+
     /// Window size when it's know to be non-negative
     ///
     /// Panics if windows size if negative
@@ -108,6 +177,161 @@ impl fmt::Display for WindowSize {
     }
 }
 
+/// Serialized transparently as the inner `i32`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for WindowSize {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_i32(self.0)
+    }
+}
+
+/// Values outside of `MIN_WINDOW_SIZE..=MAX_WINDOW_SIZE` are rejected.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for WindowSize {
+    fn deserialize<D>(deserializer: D) -> Result<WindowSize, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let size = <i64 as serde::Deserialize>::deserialize(deserializer)?;
+        if size > MAX_WINDOW_SIZE as i64 || size < MIN_WINDOW_SIZE as i64 {
+            return Err(<D::Error as serde::de::Error>::custom(format!(
+                "window size out of range: {}",
+                size
+            )));
+        }
+        Ok(WindowSize(size as i32))
+    }
+}
+
+/// Receive window which grows when it limits the throughput.
+///
+/// Consumed octets are accounted over a sampling period. When the peer manages
+/// to send the whole window within one period, the window is the bottleneck
+/// (bandwidth-delay product is larger than the window), so it is doubled,
+/// up to the configured maximum.
+#[derive(Debug, Clone)]
+pub struct AutoTuningWindow {
+    window: u32,
+    max: u32,
+    period: Duration,
+    period_start: Option<Instant>,
+    consumed_in_period: u64,
+    pending_update: u32,
+}
+
+impl AutoTuningWindow {
+    /// Create a window with given initial size.
+    ///
+    /// `max` is clamped to `MAX_WINDOW_SIZE`.
+    pub fn new(initial: u32, max: u32, period: Duration) -> AutoTuningWindow {
+        let max = cmp::min(max, MAX_WINDOW_SIZE);
+        AutoTuningWindow {
+            window: cmp::min(initial, max),
+            max,
+            period,
+            period_start: None,
+            consumed_in_period: 0,
+            pending_update: 0,
+        }
+    }
+
+    /// Current target window size.
+    pub fn window(&self) -> u32 {
+        self.window
+    }
+
+    /// Record octets consumed by the application.
+    pub fn record_consumed(&mut self, bytes: u32, now: Instant) {
+        self.pending_update = self.pending_update.saturating_add(bytes);
+
+        match self.period_start {
+            Some(start) if now.duration_since(start) < self.period => {}
+            _ => {
+                self.period_start = Some(now);
+                self.consumed_in_period = 0;
+            }
+        }
+
+        self.consumed_in_period += bytes as u64;
+
+        if self.consumed_in_period >= self.window as u64 && self.window < self.max {
+            let new_window = cmp::min(self.window.saturating_mul(2), self.max);
+            debug!("auto-tuning window: {} -> {}", self.window, new_window);
+            self.pending_update = self.pending_update.saturating_add(new_window - self.window);
+            self.window = new_window;
+            self.period_start = Some(now);
+            self.consumed_in_period = 0;
+        }
+    }
+
+    /// WINDOW_UPDATE increment which should be sent to the peer,
+    /// including consumed octets and window growth.
+    pub fn recommended_update(&mut self) -> Option<u32> {
+        if self.pending_update == 0 {
+            return None;
+        }
+        let update = cmp::min(self.pending_update, MAX_WINDOW_SIZE_INC);
+        self.pending_update -= update;
+        Some(update)
+    }
+}
+
+/// Accumulator of consumed octets used to coalesce WINDOW_UPDATE frames.
+///
+/// Instead of sending WINDOW_UPDATE for each consumed DATA frame,
+/// consumed octets are accumulated until they reach a fraction of initial window.
+#[derive(Debug, Clone)]
+pub struct PendingWindowUpdate {
+    pending: u32,
+    threshold_fraction: f32,
+}
+
+impl Default for PendingWindowUpdate {
+    fn default() -> PendingWindowUpdate {
+        PendingWindowUpdate::new(0.5)
+    }
+}
+
+impl PendingWindowUpdate {
+    /// Update is surfaced when accumulated octets reach `initial * threshold_fraction`.
+    pub fn new(threshold_fraction: f32) -> PendingWindowUpdate {
+        assert!(threshold_fraction > 0.0 && threshold_fraction < 1.0);
+        PendingWindowUpdate {
+            pending: 0,
+            threshold_fraction,
+        }
+    }
+
+    /// Octets accumulated so far.
+    pub fn pending(&self) -> u32 {
+        self.pending
+    }
+
+    /// Accumulate consumed octets, fail if accumulator would exceed `MAX_WINDOW_SIZE_INC`.
+    pub fn add(&mut self, consumed: u32) -> Result<(), ()> {
+        match self.pending.checked_add(consumed) {
+            Some(pending) if pending <= MAX_WINDOW_SIZE_INC => {
+                self.pending = pending;
+                Ok(())
+            }
+            _ => Err(()),
+        }
+    }
+
+    /// Take accumulated octets if they crossed the threshold.
+    pub fn take_if_threshold(&mut self, initial: u32) -> Option<u32> {
+        let threshold = cmp::max((initial as f64 * self.threshold_fraction as f64) as u32, 1);
+        if self.pending >= threshold {
+            Some(mem::replace(&mut self.pending, 0))
+        } else {
+            None
+        }
+    }
+}
+
 /// In window size cannot be negative.
 pub(crate) struct NonNegativeWindowSize(WindowSize);
 
@@ -121,11 +345,159 @@ impl NonNegativeWindowSize {
         self.0.size()
     }
 
-    pub fn try_decrease_to_non_negative(&mut self, delta: i32) -> Result<(), ()> {
-        self.0.try_decrease_to_non_negative(delta)
+    pub fn try_decrease_checked(&mut self, delta: i32) -> Result<(), u32> {
+        self.0.try_decrease_checked(delta)
     }
 
     pub fn try_increase(&mut self, delta: u32) -> Result<(), ()> {
         self.0.try_increase(delta)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn saturating_increase() {
+        let mut w = WindowSize::new(10);
+        assert_eq!(Ok(0), w.saturating_increase(5));
+        assert_eq!(15, w.size());
+
+        let mut w = WindowSize::new(MAX_WINDOW_SIZE as i32 - 10);
+        assert_eq!(Ok(20), w.saturating_increase(30));
+        assert_eq!(MAX_WINDOW_SIZE as i32, w.size());
+
+        let mut w = WindowSize::new(MIN_WINDOW_SIZE);
+        assert_eq!(Ok(1), w.saturating_increase(u32::MAX));
+        assert_eq!(MAX_WINDOW_SIZE as i32, w.size());
+
+        let mut w = WindowSize::new(7);
+        assert_eq!(Err(()), w.saturating_increase(0));
+        assert_eq!(7, w.size());
+    }
+
+    #[test]
+    fn try_decrease_checked() {
+        let mut w = WindowSize::new(100);
+        assert_eq!(Err(50), w.try_decrease_checked(150));
+        assert_eq!(100, w.size());
+        assert_eq!(Ok(()), w.try_decrease_checked(100));
+        assert_eq!(0, w.size());
+
+        let mut w = WindowSize::new(-10);
+        assert_eq!(Err(15), w.try_decrease_checked(5));
+        assert_eq!(-10, w.size());
+    }
+
+    #[test]
+    fn available() {
+        assert_eq!(10, WindowSize::new(10).available());
+        assert!(!WindowSize::new(10).is_exhausted());
+        assert_eq!(0, WindowSize::new(0).available());
+        assert!(WindowSize::new(0).is_exhausted());
+        assert_eq!(0, WindowSize::new(-10).available());
+        assert!(WindowSize::new(-10).is_exhausted());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        use serde_test::assert_de_tokens_error;
+        use serde_test::assert_tokens;
+        use serde_test::Token;
+
+        assert_tokens(&WindowSize(-5), &[Token::I32(-5)]);
+        assert_tokens(
+            &WindowSize(MAX_WINDOW_SIZE as i32),
+            &[Token::I32(MAX_WINDOW_SIZE as i32)],
+        );
+        assert_de_tokens_error::<WindowSize>(
+            &[Token::I64(MAX_WINDOW_SIZE as i64 + 1)],
+            "window size out of range: 2147483648",
+        );
+    }
+
+    #[test]
+    fn apply_initial_window_delta() {
+        let mut w = WindowSize::new(-10);
+        w.apply_initial_window_delta(100, 50).unwrap();
+        assert_eq!(-60, w.size());
+        w.apply_initial_window_delta(50, 200).unwrap();
+        assert_eq!(90, w.size());
+
+        let mut w = WindowSize::new(MIN_WINDOW_SIZE);
+        w.apply_initial_window_delta(MAX_WINDOW_SIZE, 0).unwrap();
+        assert_eq!(i32::MIN, w.size());
+
+        let mut w = WindowSize::new(10);
+        assert_eq!(Err(()), w.apply_initial_window_delta(0, MAX_WINDOW_SIZE));
+        assert_eq!(10, w.size());
+    }
+
+    #[test]
+    fn ord() {
+        assert!(WindowSize::new(-1) < WindowSize::new(0));
+        assert_eq!(
+            WindowSize::new(3),
+            WindowSize::new(10).min(WindowSize::new(3))
+        );
+        assert_eq!(0, WindowSize::new(10).min(WindowSize::new(-3)).available());
+    }
+
+    #[test]
+    fn pending_window_update() {
+        let mut p = PendingWindowUpdate::default();
+        assert_eq!(None, p.take_if_threshold(100));
+        p.add(30).unwrap();
+        assert_eq!(None, p.take_if_threshold(100));
+        p.add(30).unwrap();
+        assert_eq!(Some(60), p.take_if_threshold(100));
+        assert_eq!(0, p.pending());
+
+        let mut p = PendingWindowUpdate::new(0.25);
+        p.add(25).unwrap();
+        assert_eq!(Some(25), p.take_if_threshold(100));
+
+        let mut p = PendingWindowUpdate::default();
+        p.add(MAX_WINDOW_SIZE_INC).unwrap();
+        assert_eq!(Err(()), p.add(1));
+        assert_eq!(MAX_WINDOW_SIZE_INC, p.pending());
+    }
+
+    #[test]
+    fn auto_tuning_window_grows() {
+        let start = Instant::now();
+        let mut w = AutoTuningWindow::new(100, 350, Duration::from_secs(1));
+        assert_eq!(None, w.recommended_update());
+
+        w.record_consumed(60, start);
+        assert_eq!(Some(60), w.recommended_update());
+        assert_eq!(100, w.window());
+
+        // whole window consumed within the period
+        w.record_consumed(40, start + Duration::from_millis(500));
+        assert_eq!(200, w.window());
+        assert_eq!(Some(40 + 100), w.recommended_update());
+
+        w.record_consumed(200, start + Duration::from_millis(600));
+        assert_eq!(350, w.window());
+        assert_eq!(Some(200 + 150), w.recommended_update());
+
+        // capped at max
+        w.record_consumed(350, start + Duration::from_millis(700));
+        assert_eq!(350, w.window());
+        assert_eq!(Some(350), w.recommended_update());
+    }
+
+    #[test]
+    fn auto_tuning_window_slow_consumer() {
+        let start = Instant::now();
+        let mut w = AutoTuningWindow::new(100, 1000, Duration::from_secs(1));
+        for i in 0..10 {
+            w.record_consumed(60, start + Duration::from_secs(i * 2));
+            assert_eq!(Some(60), w.recommended_update());
+        }
+        assert_eq!(100, w.window());
+    }
+}
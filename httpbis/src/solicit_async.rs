@@ -1,5 +1,6 @@
 use std::io;
 use std::io::Read;
+use std::mem;
 
 use bytes::Bytes;
 
@@ -9,11 +10,13 @@ use futures::stream::Stream;
 use std::future::Future;
 
 use tokio::io::AsyncRead;
+use tokio::io::AsyncReadExt;
 use tokio::io::AsyncWrite;
 use tokio::io::AsyncWriteExt;
 
 use crate::error;
 use crate::error::Error;
+use crate::error::PrefaceError;
 use crate::result;
 use crate::result::Result;
 
@@ -24,8 +27,11 @@ use crate::solicit::frame::RawFrameRef;
 use crate::solicit::frame::SettingsFrame;
 use crate::solicit::frame::FRAME_HEADER_LEN;
 
-use crate::misc::BsDebug;
 use crate::net::socket::SocketStream;
+use crate::server::h2c::parse_upgrade_request;
+use crate::server::h2c::H2cUpgrade;
+use crate::server::h2c::MAX_REQUEST_HEAD_SIZE;
+use crate::server::h2c::SWITCHING_PROTOCOLS_RESPONSE;
 use std::pin::Pin;
 use std::task::Context;
 
@@ -80,7 +86,7 @@ where
     Ok(())
 }
 
-static PREFACE: &'static [u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+pub(crate) static PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
 
 async fn send_settings<W: AsyncWrite + Unpin + Send + 'static>(
     conn: &mut W,
@@ -102,20 +108,52 @@ pub async fn client_handshake<I: SocketStream>(
 }
 
 /// Response to be sent when request is sent over HTTP/1
-const HTTP_1_500_RESPONSE: &'static [u8] = b"\
+const HTTP_1_500_RESPONSE: &[u8] = b"\
 HTTP/1.1 500 Internal Server Error\r\n\
 Server: httpbis\r\n\
 \r\n\
 Request is made using HTTP/1, server only supports HTTP/2\r\n\
 ";
 
+const HTTP_1_METHODS: &[&[u8]] = &[b"GET ", b"POST ", b"HEAD ", b"OPTIONS "];
+
 /// Buf content looks like a start of HTTP/1 request
 fn looks_like_http_1(buf: &[u8]) -> bool {
-    buf.starts_with(b"GET ") || buf.starts_with(b"POST ") || buf.starts_with(b"HEAD ")
+    HTTP_1_METHODS.iter().any(|m| buf.starts_with(m))
+}
+
+/// Buf content may become a start of HTTP/1 request when more bytes are read
+fn may_be_http_1(buf: &[u8]) -> bool {
+    HTTP_1_METHODS
+        .iter()
+        .any(|m| m.starts_with(buf) || buf.starts_with(m))
 }
 
-/// Recv HTTP/2 preface, or sent HTTP/1 500 and return error is input looks like HTTP/1 request
-async fn recv_preface_or_handle_http_1<I>(conn: &mut I) -> result::Result<()>
+/// Read the rest of HTTP/1 request head after request line.
+async fn recv_http_1_head<I>(conn: &mut I, head: &mut Vec<u8>) -> result::Result<()>
+where
+    I: AsyncRead + Unpin,
+{
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() >= MAX_REQUEST_HEAD_SIZE {
+            return Err(error::Error::InvalidFrame(
+                "HTTP/1 request head is too large".to_owned(),
+            ));
+        }
+        head.push(conn.read_u8().await?);
+    }
+    Ok(())
+}
+
+/// Recv HTTP/2 preface, or sent HTTP/1 500 and return error is input looks like HTTP/1 request.
+///
+/// When `allow_h2c_upgrade` is set, HTTP/1 request with `Upgrade: h2c`
+/// is answered with `101 Switching Protocols` and returned.
+async fn recv_preface_or_handle_http_1<I>(
+    conn: &mut I,
+    allow_prior_knowledge: bool,
+    allow_h2c_upgrade: bool,
+) -> result::Result<Option<H2cUpgrade>>
 where
     I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
 {
@@ -128,7 +166,8 @@ where
     where
         I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
     {
-        type Output = result::Result<bool>;
+        /// HTTP/1 request line if request looks like HTTP/1
+        type Output = result::Result<Option<Vec<u8>>>;
 
         fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
             loop {
@@ -148,59 +187,83 @@ where
 
                 let c = buf[0];
 
-                if self.collected.len() == 0 && c == 0x16 {
-                    return Poll::Ready(Err(Error::InvalidFrame(format!(
-                        "wrong fitst byte, likely TLS"
-                    ))));
-                }
-
                 self.collected.push(c);
 
                 if self.collected == PREFACE {
-                    return Poll::Ready(Ok(false));
+                    return Poll::Ready(Ok(None));
                 }
 
-                // TODO: check only for first \n
-                if c == b'\n' {
-                    if looks_like_http_1(&self.collected) {
-                        return Poll::Ready(Ok(true));
-                    }
+                if PREFACE.starts_with(&self.collected) {
+                    continue;
                 }
 
-                if self.collected.len() == PREFACE.len() {
-                    return Poll::Ready(Err(error::Error::InvalidFrame(format!(
-                        "wrong preface, likely TLS: {:?}",
-                        BsDebug(&self.collected)
+                // Fail on first byte which is neither preface nor HTTP/1 request line
+                if !may_be_http_1(&self.collected) || self.collected.len() >= MAX_REQUEST_HEAD_SIZE
+                {
+                    return Poll::Ready(Err(Error::PrefaceError(PrefaceError::new(
+                        &self.collected,
                     ))));
                 }
+
+                if c == b'\n' && looks_like_http_1(&self.collected) {
+                    return Poll::Ready(Ok(Some(mem::take(&mut self.collected))));
+                }
             }
         }
     }
 
-    let need_500 = Intermediate {
+    let http_1_head = Intermediate {
         conn,
         collected: Vec::new(),
     }
     .await?;
 
-    if need_500 {
+    if let Some(mut head) = http_1_head {
+        if allow_h2c_upgrade {
+            recv_http_1_head(conn, &mut head).await?;
+            if let Some(upgrade) = parse_upgrade_request(&head) {
+                debug!("h2c upgrade");
+                conn.write_all(SWITCHING_PROTOCOLS_RESPONSE).await?;
+                return Ok(Some(upgrade));
+            }
+        }
+
         conn.write_all(HTTP_1_500_RESPONSE).await?;
 
         return Err(error::Error::RequestIsMadeUsingHttp1);
     }
 
-    Ok(())
+    if !allow_prior_knowledge {
+        return Err(error::Error::PriorKnowledgeNotAllowed);
+    }
+
+    Ok(None)
 }
 
-pub async fn server_handshake<I>(conn: &mut I, settings: SettingsFrame) -> result::Result<()>
+/// Server side of connection preface.
+///
+/// Return upgrade request if connection was upgraded from HTTP/1.1.
+pub(crate) async fn server_handshake<I>(
+    conn: &mut I,
+    settings: SettingsFrame,
+    allow_prior_knowledge: bool,
+    allow_h2c_upgrade: bool,
+) -> result::Result<Option<H2cUpgrade>>
 where
     I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
 {
-    let mut preface_buf = Vec::with_capacity(PREFACE.len());
-    preface_buf.resize(PREFACE.len(), 0);
-
-    recv_preface_or_handle_http_1(conn).await?;
+    let upgrade =
+        recv_preface_or_handle_http_1(conn, allow_prior_knowledge, allow_h2c_upgrade).await?;
     send_settings(conn, settings).await?;
 
-    Ok(())
+    if upgrade.is_some() {
+        // 3.2: client sends preface after 101 response
+        let mut preface = [0; PREFACE.len()];
+        conn.read_exact(&mut preface).await?;
+        if preface != PREFACE {
+            return Err(error::Error::PrefaceError(PrefaceError::new(&preface)));
+        }
+    }
+
+    Ok(upgrade)
 }
@@ -6,7 +6,7 @@ use crate::solicit::frame::HttpFrame;
 use crate::solicit::frame::HttpFrameDecoded;
 use crate::solicit::frame::PingFrame;
 use crate::solicit::frame::PriorityFrame;
-use crate::solicit::frame::PushPromiseFrame;
+use crate::solicit::frame::PushPromiseDecodedFrame;
 use crate::solicit::frame::RawFrame;
 use crate::solicit::frame::RstStreamFrame;
 use crate::solicit::frame::SettingsFrame;
@@ -20,7 +20,7 @@ pub enum HttpFrameStream {
     Headers(HeadersDecodedFrame),
     Priority(PriorityFrame),
     RstStream(RstStreamFrame),
-    PushPromise(PushPromiseFrame),
+    PushPromise(PushPromiseDecodedFrame),
     WindowUpdate(WindowUpdateFrame),
 }
 
@@ -40,20 +40,20 @@ impl HttpFrameStream {
     #[allow(dead_code)]
     pub fn get_stream_id(&self) -> StreamId {
         match self {
-            &HttpFrameStream::Data(ref f) => f.get_stream_id(),
-            &HttpFrameStream::Headers(ref f) => f.get_stream_id(),
-            &HttpFrameStream::Priority(ref f) => f.get_stream_id(),
-            &HttpFrameStream::WindowUpdate(ref f) => f.get_stream_id(),
-            &HttpFrameStream::RstStream(ref f) => f.get_stream_id(),
-            &HttpFrameStream::PushPromise(ref f) => f.get_stream_id(),
+            HttpFrameStream::Data(f) => f.get_stream_id(),
+            HttpFrameStream::Headers(f) => f.get_stream_id(),
+            HttpFrameStream::Priority(f) => f.get_stream_id(),
+            HttpFrameStream::WindowUpdate(f) => f.get_stream_id(),
+            HttpFrameStream::RstStream(f) => f.get_stream_id(),
+            HttpFrameStream::PushPromise(f) => f.get_stream_id(),
         }
     }
 
     #[allow(dead_code)]
     pub fn is_end_of_stream(&self) -> bool {
         match self {
-            &HttpFrameStream::Headers(ref f) => f.is_end_of_stream(),
-            &HttpFrameStream::Data(ref f) => f.is_end_of_stream(),
+            HttpFrameStream::Headers(f) => f.is_end_of_stream(),
+            HttpFrameStream::Data(f) => f.is_end_of_stream(),
             &HttpFrameStream::Priority(..) => false,
             &HttpFrameStream::WindowUpdate(..) => false,
             &HttpFrameStream::RstStream(..) => true,
@@ -38,7 +38,9 @@ fn found(client: Client) {
     assert_eq!(BytesDeque::from("200 200 200"), r.body);
 }
 
-const TESTS: &'static [(&'static str, fn(Client))] = &[("not_found", not_found), ("found", found)];
+type TestCase = (&'static str, fn(Client));
+
+const TESTS: &[TestCase] = &[("not_found", not_found), ("found", found)];
 
 fn find_test_case(name: &str) -> Option<fn(Client)> {
     for &(next_name, test) in TESTS {
@@ -70,7 +72,7 @@ fn new_http_client() -> Client {
 }
 
 fn run_test_case(name: &str) {
-    let f = find_test_case(name).expect(&format!("test case not found: {}", name));
+    let f = find_test_case(name).unwrap_or_else(|| panic!("test case not found: {}", name));
     f(new_http_client());
 }
 